@@ -0,0 +1,72 @@
+//! Query times of plain A* against the hierarchical pathfinder on generated maps.
+//!
+//! Run with `cargo bench --bench pathfinding`.
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+
+use frontier_outpost::pathfinding::{HierarchicalPathfinder, NavGrid, PathResult, Pathfinder};
+use frontier_outpost::rng::Rng;
+use frontier_outpost::worldgen::{WorldGenerator, tiles};
+
+const SEED: u64 = 0x5eed;
+const SIZES: [u32; 2] = [256, 1024];
+const QUERIES: usize = 32;
+
+/// Connected start and goal pairs at least half the map apart, the queries that hurt most.
+fn queries(grid: &NavGrid, pathfinder: &mut Pathfinder) -> Vec<([i32; 2], [i32; 2])> {
+	let mut rng = Rng::new(SEED);
+	let size = grid.width();
+	let mut queries = Vec::new();
+	while queries.len() < QUERIES {
+		let start = [rng.below(size) as i32, rng.below(size) as i32];
+		let goal = [rng.below(size) as i32, rng.below(size) as i32];
+		let distance = start[0].abs_diff(goal[0]).max(start[1].abs_diff(goal[1]));
+		if distance >= size / 2 && grid.is_passable(start) && pathfinder.regions().connected(start, goal) {
+			queries.push((start, goal));
+		}
+	}
+	queries
+}
+
+fn pathfinding(c: &mut Criterion) {
+	let mut group = c.benchmark_group("pathfinding");
+	group.sample_size(10);
+
+	for size in SIZES {
+		let map = WorldGenerator::new(SEED).generate(size, size).to_tilemap();
+		let grid = NavGrid::from_tilemap(&map, tiles::movement_cost);
+		// Without a cache every query is a full search.
+		let mut flat = Pathfinder::with_cache_capacity(grid.clone(), 0);
+		let queries = queries(&grid, &mut flat);
+
+		group.bench_with_input(BenchmarkId::new("build hierarchy", size), &grid, |b, grid| {
+			b.iter(|| HierarchicalPathfinder::new(grid.clone()));
+		});
+
+		let mut hierarchical = HierarchicalPathfinder::new(grid.clone());
+		let mut next = queries.iter().cycle();
+		group.bench_function(BenchmarkId::new("flat A*", size), |b| {
+			b.iter(|| {
+				let &(start, goal) = next.next().unwrap();
+				assert!(matches!(flat.find_path(start, goal), PathResult::Found(_)));
+			});
+		});
+		group.bench_function(BenchmarkId::new("hierarchical", size), |b| {
+			b.iter(|| {
+				let &(start, goal) = next.next().unwrap();
+				assert!(matches!(hierarchical.find_path(start, goal), PathResult::Found(_)));
+			});
+		});
+		group.bench_function(BenchmarkId::new("unreachable", size), |b| {
+			let goal = [0, 0];
+			let start = (0..size as i32).map(|x| [x, size as i32 / 2])
+				.find(|&start| grid.is_passable(start) && !flat.regions().connected(start, goal))
+				.unwrap_or([0, 0]);
+			b.iter(|| hierarchical.find_path(start, goal));
+		});
+	}
+	group.finish();
+}
+
+criterion_group!(benches, pathfinding);
+criterion_main!(benches);
@@ -0,0 +1,112 @@
+use crate::building::{BuildingCatalog, BuildingId, Placement, Rotation};
+use crate::game::Game;
+use crate::input::{Action, Input};
+use crate::renderer::{Camera2D, Renderer, Sprite};
+
+/// Layer the placement preview is drawn on, above everything else in the world.
+pub const GHOST_LAYER: i32 = 100;
+
+/// Opacity of the placement preview.
+const GHOST_ALPHA: f32 = 0.5;
+
+/// Tint of preview tiles the building cannot go on.
+const INVALID_TINT: [f32; 4] = [1.0, 0.15, 0.15, GHOST_ALPHA];
+
+/// Placing buildings with the mouse: [`Action::NextBuilding`] picks what to build,
+/// [`Action::Rotate`] turns it, [`Action::Build`] places it under the cursor and
+/// [`Action::Cancel`] puts it away.
+#[derive(Clone, Debug, Default)]
+pub struct BuildTool {
+	selected: Option<BuildingId>,
+	rotation: Rotation,
+	/// Where the building would go, checked against the game as of the last update.
+	preview: Option<Placement>,
+}
+
+impl BuildTool {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn selected(&self) -> Option<BuildingId> {
+		self.selected
+	}
+
+	pub fn select(&mut self, building: Option<BuildingId>) {
+		self.selected = building;
+	}
+
+	pub fn rotation(&self) -> Rotation {
+		self.rotation
+	}
+
+	/// Reacts to the frame's input, placing the selected building when asked to.
+	pub fn update(&mut self, input: &Input, camera: &Camera2D, game: &mut Game) {
+		if input.pressed(Action::NextBuilding) {
+			let count = game.world.resource::<BuildingCatalog>().map_or(0, BuildingCatalog::len) as u16;
+			self.selected = match self.selected {
+				None if count > 0 => Some(BuildingId(0)),
+				Some(BuildingId(index)) if index + 1 < count => Some(BuildingId(index + 1)),
+				_ => None,
+			};
+		}
+		if input.pressed(Action::Cancel) {
+			self.selected = None;
+		}
+		if input.pressed(Action::Rotate) {
+			self.rotation = self.rotation.clockwise();
+		}
+
+		let Some(kind) = self.selected else {
+			self.preview = None;
+			return;
+		};
+		let size = game.world.resource::<BuildingCatalog>()
+			.and_then(|catalog| catalog.get(kind))
+			.map_or([1, 1], |def| def.size());
+		let size = match self.rotation {
+			Rotation::North | Rotation::South => size,
+			Rotation::East | Rotation::West => [size[1], size[0]],
+		};
+		// Centre the footprint on the cursor rather than hanging it off one corner.
+		let [x, y] = camera.screen_to_world(input.cursor());
+		let origin = [
+			(x - size[0] as f32 / 2.0 + 0.5).floor() as i32,
+			(y - size[1] as f32 / 2.0 + 0.5).floor() as i32,
+		];
+
+		if input.pressed(Action::Build) {
+			match game.place_building(kind, origin, self.rotation) {
+				Ok(site) => log::debug!("placed construction site {site:?}"),
+				Err(error) => log::info!("cannot build here: {error}"),
+			}
+		}
+		self.preview = Some(game.check_placement(kind, origin, self.rotation));
+	}
+
+	/// Queues a translucent copy of the selected building under the cursor, with the tiles it
+	/// cannot go on in red. Every tile is red when the building breaks a rule as a whole.
+	pub fn draw(&self, renderer: &mut Renderer, game: &Game) {
+		let Some(preview) = &self.preview else {
+			return;
+		};
+		let color = game.world.resource::<BuildingCatalog>()
+			.and_then(|catalog| catalog.get(preview.kind))
+			.map_or([255; 4], |def| def.color);
+		let [red, green, blue, _] = color.map(|channel| channel as f32 / 255.0);
+		let valid_tint = [red, green, blue, GHOST_ALPHA];
+
+		for (position, error) in &preview.tiles {
+			let tint = if error.is_some() || !preview.errors.is_empty() { INVALID_TINT } else { valid_tint };
+			renderer.draw_sprite(Sprite {
+				tint,
+				layer: GHOST_LAYER,
+				..Sprite::new(
+					renderer.white_texture(),
+					[position[0] as f32 + 0.5, position[1] as f32 + 0.5],
+					[1.0, 1.0],
+				)
+			});
+		}
+	}
+}
@@ -0,0 +1,468 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::sync::Arc;
+
+use crate::content::Content;
+use crate::ecs::{Entity, World};
+use crate::pathfinding::Pathfinder;
+use crate::save::{Persist, Reader, SaveError, Writer};
+use crate::tilemap::{Tile, Tilemap};
+use crate::worldgen::tiles;
+
+/// Handle to a [`BuildingDef`] in the [`BuildingCatalog`].
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct BuildingId(pub u16);
+
+/// Which way a building faces. Footprints are defined facing north and turned clockwise for the
+/// other directions.
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Rotation {
+	#[default]
+	North,
+	East,
+	South,
+	West,
+}
+
+impl Rotation {
+	pub const ALL: [Rotation; 4] = [Rotation::North, Rotation::East, Rotation::South, Rotation::West];
+
+	/// The next direction clockwise.
+	pub fn clockwise(self) -> Self {
+		Self::ALL[(self as usize + 1) % 4]
+	}
+
+	/// The next direction counter clockwise.
+	pub fn counter_clockwise(self) -> Self {
+		Self::ALL[(self as usize + 3) % 4]
+	}
+
+	/// Step to the neighbouring tile this way, north being up the map.
+	pub fn offset(self) -> [i32; 2] {
+		match self {
+			Rotation::North => [0, 1],
+			Rotation::East => [1, 0],
+			Rotation::South => [0, -1],
+			Rotation::West => [-1, 0],
+		}
+	}
+
+	/// Angle to turn a north facing sprite by, counter clockwise in radians like
+	/// [`Sprite::rotation`](crate::renderer::Sprite::rotation).
+	pub fn radians(self) -> f32 {
+		-(self as u8 as f32) * std::f32::consts::FRAC_PI_2
+	}
+
+	/// Turns `offset` within a `size` footprint, keeping the turned footprint's bottom left
+	/// corner at the origin.
+	fn apply(self, offset: [i32; 2], size: [i32; 2]) -> [i32; 2] {
+		let [x, y] = offset;
+		let [width, height] = size;
+		match self {
+			Rotation::North => [x, y],
+			Rotation::East => [y, width - 1 - x],
+			Rotation::South => [width - 1 - x, height - 1 - y],
+			Rotation::West => [height - 1 - y, x],
+		}
+	}
+}
+
+/// Something a building needs from the tiles around its footprint, the ones sharing an edge
+/// with it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AdjacencyRule {
+	/// At least one neighbouring tile is one of these, like a pump that has to be by water.
+	NextToTerrain(Vec<Tile>),
+	/// At least one neighbouring tile is covered by this kind of building.
+	NextToBuilding(BuildingId),
+	/// No neighbouring tile is covered by this kind of building.
+	AwayFromBuilding(BuildingId),
+}
+
+/// What a kind of building looks like to the placement rules.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BuildingDef {
+	pub name: String,
+	/// Tiles covered when facing north, as offsets from the bottom left of the footprint. None
+	/// are negative.
+	pub footprint: Vec<[i32; 2]>,
+	/// Terrain every covered tile has to be, any terrain when empty.
+	pub terrain: Vec<Tile>,
+	pub adjacency: Vec<AdjacencyRule>,
+	/// Drawn as a block of this colour until there is building art.
+	pub color: [u8; 4],
+}
+
+impl BuildingDef {
+	/// A building covering a `width` by `height` rectangle, placeable anywhere.
+	pub fn new(name: impl Into<String>, size: [u32; 2]) -> Self {
+		let [width, height] = size.map(|side| side as i32);
+		Self {
+			name: name.into(),
+			footprint: (0..height).flat_map(|y| (0..width).map(move |x| [x, y])).collect(),
+			terrain: Vec::new(),
+			adjacency: Vec::new(),
+			color: [255; 4],
+		}
+	}
+
+	/// Width and height of the footprint's bounding box when facing north.
+	pub fn size(&self) -> [i32; 2] {
+		self.footprint.iter().fold([0, 0], |[width, height], &[x, y]| [width.max(x + 1), height.max(y + 1)])
+	}
+
+	/// Tiles covered when placed with the bottom left of its footprint at `origin`.
+	pub fn tiles(&self, origin: [i32; 2], rotation: Rotation) -> Vec<[i32; 2]> {
+		let size = self.size();
+		self.footprint.iter()
+			.map(|&offset| {
+				let [x, y] = rotation.apply(offset, size);
+				[origin[0] + x, origin[1] + y]
+			})
+			.collect()
+	}
+}
+
+/// Every kind of building that can be placed, stored as a world resource.
+#[derive(Clone, Debug, Default)]
+pub struct BuildingCatalog {
+	defs: Vec<BuildingDef>,
+}
+
+impl BuildingCatalog {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn add(&mut self, def: BuildingDef) -> BuildingId {
+		self.defs.push(def);
+		BuildingId(self.defs.len() as u16 - 1)
+	}
+
+	pub fn get(&self, id: BuildingId) -> Option<&BuildingDef> {
+		self.defs.get(id.0 as usize)
+	}
+
+	pub fn len(&self) -> usize {
+		self.defs.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.defs.is_empty()
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = (BuildingId, &BuildingDef)> {
+		self.defs.iter().enumerate().map(|(index, def)| (BuildingId(index as u16), def))
+	}
+}
+
+/// A placed building, finished or still a [`ConstructionSite`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Building {
+	pub kind: BuildingId,
+	pub origin: [i32; 2],
+	pub rotation: Rotation,
+	/// Tiles it covers, kept so they can be reserved again without the catalog.
+	pub tiles: Vec<[i32; 2]>,
+}
+
+impl Persist for Building {
+	fn save(&self, writer: &mut Writer) {
+		writer.varint(self.kind.0 as u64);
+		writer.i32(self.origin[0]);
+		writer.i32(self.origin[1]);
+		writer.u8(self.rotation as u8);
+		writer.varint(self.tiles.len() as u64);
+		for &[x, y] in &self.tiles {
+			writer.i32(x);
+			writer.i32(y);
+		}
+	}
+
+	fn load(reader: &mut Reader) -> Result<Self, SaveError> {
+		let kind = u16::try_from(reader.varint()?)
+			.map_err(|_| SaveError::Corrupt("building kind out of range".into()))?;
+		let origin = [reader.i32()?, reader.i32()?];
+		let rotation = *Rotation::ALL.get(reader.u8()? as usize)
+			.ok_or_else(|| SaveError::Corrupt("unknown building rotation".into()))?;
+		let mut tiles = Vec::new();
+		for _ in 0..reader.varint()? {
+			tiles.push([reader.i32()?, reader.i32()?]);
+		}
+		Ok(Self {
+			kind: BuildingId(kind),
+			origin,
+			rotation,
+			tiles,
+		})
+	}
+}
+
+/// A building that has been placed but not built yet.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ConstructionSite {
+	/// How much of the work is done, from 0 to 1.
+	pub progress: f32,
+}
+
+impl Persist for ConstructionSite {
+	fn save(&self, writer: &mut Writer) {
+		writer.f32(self.progress);
+	}
+
+	fn load(reader: &mut Reader) -> Result<Self, SaveError> {
+		Ok(Self { progress: reader.f32()? })
+	}
+}
+
+/// Which entity, if any, has reserved each tile of the map. Stored as a world resource and
+/// derived from the [`Building`] components.
+#[derive(Clone, Debug)]
+pub struct Occupancy {
+	width: u32,
+	height: u32,
+	tiles: Vec<Option<Entity>>,
+}
+
+impl Occupancy {
+	pub fn new(width: u32, height: u32) -> Self {
+		Self {
+			width,
+			height,
+			tiles: vec![None; (width * height) as usize],
+		}
+	}
+
+	/// Occupancy of the buildings in `world` on a `width` by `height` map.
+	pub fn from_world(world: &World, width: u32, height: u32) -> Self {
+		let mut occupancy = Self::new(width, height);
+		for (entity, building) in world.query::<Building>() {
+			occupancy.reserve(&building.tiles, entity);
+		}
+		occupancy
+	}
+
+	fn index(&self, position: [i32; 2]) -> Option<usize> {
+		let [x, y] = position;
+		(x >= 0 && y >= 0 && (x as u32) < self.width && (y as u32) < self.height)
+			.then(|| (y as u32 * self.width + x as u32) as usize)
+	}
+
+	/// Entity that reserved `position`, `None` for free tiles and those outside the map.
+	pub fn get(&self, position: [i32; 2]) -> Option<Entity> {
+		self.tiles[self.index(position)?]
+	}
+
+	/// Reserves `tiles` for `entity`, replacing whatever reserved them before. Tiles outside the
+	/// map are ignored.
+	pub fn reserve(&mut self, tiles: &[[i32; 2]], entity: Entity) {
+		for &position in tiles {
+			if let Some(index) = self.index(position) {
+				self.tiles[index] = Some(entity);
+			}
+		}
+	}
+
+	/// Frees those of `tiles` reserved by `entity`.
+	pub fn release(&mut self, tiles: &[[i32; 2]], entity: Entity) {
+		for &position in tiles {
+			if let Some(index) = self.index(position) {
+				if self.tiles[index] == Some(entity) {
+					self.tiles[index] = None;
+				}
+			}
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PlacementError {
+	UnknownBuilding(BuildingId),
+	OutsideMap([i32; 2]),
+	WrongTerrain([i32; 2], Tile),
+	Occupied([i32; 2], Entity),
+	Adjacency(AdjacencyRule),
+}
+
+impl Display for PlacementError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			PlacementError::UnknownBuilding(id) => write!(f, "there is no building {}", id.0),
+			PlacementError::OutsideMap([x, y]) => write!(f, "({x}, {y}) is outside the map"),
+			PlacementError::WrongTerrain([x, y], tile) => write!(f, "cannot build on tile {} at ({x}, {y})", tile.0),
+			PlacementError::Occupied([x, y], _) => write!(f, "({x}, {y}) is already taken"),
+			PlacementError::Adjacency(AdjacencyRule::NextToTerrain(tiles)) => {
+				let tiles = tiles.iter().map(|tile| tile.0.to_string()).collect::<Vec<_>>();
+				write!(f, "has to be next to one of the tiles {}", tiles.join(", "))
+			}
+			PlacementError::Adjacency(AdjacencyRule::NextToBuilding(id)) => write!(f, "has to be next to building {}", id.0),
+			PlacementError::Adjacency(AdjacencyRule::AwayFromBuilding(id)) => write!(f, "cannot be next to building {}", id.0),
+		}
+	}
+}
+
+impl Error for PlacementError {}
+
+/// Whether a building fits somewhere, tile by tile so a preview can show which tiles are wrong.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Placement {
+	pub kind: BuildingId,
+	pub origin: [i32; 2],
+	pub rotation: Rotation,
+	/// Tiles the footprint covers, each with what is wrong with it, if anything.
+	pub tiles: Vec<([i32; 2], Option<PlacementError>)>,
+	/// Problems with the building as a whole, like broken adjacency rules.
+	pub errors: Vec<PlacementError>,
+}
+
+impl Placement {
+	/// Checks every placement rule for a `kind` building with the bottom left of its footprint at
+	/// `origin`, against the [`BuildingCatalog`] and [`Occupancy`] resources of `world`.
+	pub fn check(world: &World, tilemap: &Tilemap, kind: BuildingId, origin: [i32; 2], rotation: Rotation) -> Self {
+		let mut placement = Self {
+			kind,
+			origin,
+			rotation,
+			tiles: Vec::new(),
+			errors: Vec::new(),
+		};
+		let Some(def) = world.resource::<BuildingCatalog>().and_then(|catalog| catalog.get(kind)) else {
+			placement.errors.push(PlacementError::UnknownBuilding(kind));
+			return placement;
+		};
+		let occupancy = world.resource::<Occupancy>();
+		let occupant = |position| occupancy.and_then(|occupancy| occupancy.get(position));
+
+		let tiles = def.tiles(origin, rotation);
+		for &position in &tiles {
+			let error = match tilemap.get(position[0], position[1]) {
+				None => Some(PlacementError::OutsideMap(position)),
+				Some(tile) if !def.terrain.is_empty() && !def.terrain.contains(&tile) => {
+					Some(PlacementError::WrongTerrain(position, tile))
+				}
+				Some(_) => occupant(position).map(|entity| PlacementError::Occupied(position, entity)),
+			};
+			placement.tiles.push((position, error));
+		}
+
+		let mut neighbours = Vec::new();
+		for &[x, y] in &tiles {
+			for next in [[x + 1, y], [x - 1, y], [x, y + 1], [x, y - 1]] {
+				if !tiles.contains(&next) && !neighbours.contains(&next) {
+					neighbours.push(next);
+				}
+			}
+		}
+		let building_at = |position| occupant(position)
+			.and_then(|entity| world.get::<Building>(entity))
+			.map(|building| building.kind);
+
+		for rule in &def.adjacency {
+			let satisfied = match rule {
+				AdjacencyRule::NextToTerrain(terrain) => neighbours.iter()
+					.any(|&[x, y]| tilemap.get(x, y).is_some_and(|tile| terrain.contains(&tile))),
+				AdjacencyRule::NextToBuilding(other) => neighbours.iter().any(|&next| building_at(next) == Some(*other)),
+				AdjacencyRule::AwayFromBuilding(other) => neighbours.iter().all(|&next| building_at(next) != Some(*other)),
+			};
+			if !satisfied {
+				placement.errors.push(PlacementError::Adjacency(rule.clone()));
+			}
+		}
+		placement
+	}
+
+	pub fn is_valid(&self) -> bool {
+		self.error().is_none()
+	}
+
+	/// The first problem found, if any.
+	pub fn error(&self) -> Option<&PlacementError> {
+		self.tiles.iter().find_map(|(_, error)| error.as_ref()).or(self.errors.first())
+	}
+}
+
+/// Places a `kind` building if every rule allows it, reserving its tiles and spawning a
+/// [`ConstructionSite`] entity for it.
+pub fn place(
+	world: &mut World,
+	tilemap: &Tilemap,
+	kind: BuildingId,
+	origin: [i32; 2],
+	rotation: Rotation,
+) -> Result<Entity, PlacementError> {
+	let placement = Placement::check(world, tilemap, kind, origin, rotation);
+	if let Some(error) = placement.error() {
+		return Err(error.clone());
+	}
+
+	let tiles = placement.tiles.into_iter().map(|(position, _)| position).collect::<Vec<_>>();
+	let site = world.spawn();
+	match world.resource_mut::<Occupancy>() {
+		Some(occupancy) => occupancy.reserve(&tiles, site),
+		None => {
+			let mut occupancy = Occupancy::new(tilemap.width(), tilemap.height());
+			occupancy.reserve(&tiles, site);
+			world.insert_resource(occupancy);
+		}
+	}
+	if let Some(pathfinder) = world.resource_mut::<Pathfinder>() {
+		for &position in &tiles {
+			pathfinder.set_cost(position, None);
+		}
+	}
+	world.insert(site, Building {
+		kind,
+		origin,
+		rotation,
+		tiles,
+	});
+	world.insert(site, ConstructionSite::default());
+	Ok(site)
+}
+
+/// Removes a building or construction site, freeing its tiles. Returns `false` if `entity` is
+/// not a building.
+pub fn demolish(world: &mut World, tilemap: &Tilemap, entity: Entity) -> bool {
+	let Some(building) = world.remove::<Building>(entity) else {
+		return false;
+	};
+	if let Some(occupancy) = world.resource_mut::<Occupancy>() {
+		occupancy.release(&building.tiles, entity);
+	}
+	let costs = building.tiles.iter().map(|&position| (position, path_cost(world, tilemap, position))).collect::<Vec<_>>();
+	if let Some(pathfinder) = world.resource_mut::<Pathfinder>() {
+		for (position, cost) in costs {
+			pathfinder.set_cost(position, cost);
+		}
+	}
+	world.despawn(entity)
+}
+
+/// What walking over `position` costs: `None` under a building, or else the cost of its
+/// terrain by the loaded [`Content`], or the built in tiles without it.
+pub(crate) fn path_cost(world: &World, tilemap: &Tilemap, position: [i32; 2]) -> Option<u8> {
+	let covered = world.resource::<Occupancy>()
+		.and_then(|occupancy| occupancy.get(position))
+		.is_some_and(|entity| world.has::<Building>(entity));
+	let tile = tilemap.get(position[0], position[1]).filter(|_| !covered)?;
+	terrain_cost(world, tile)
+}
+
+/// Cost of walking over `tile`, from the loaded content if there is any.
+pub(crate) fn terrain_cost(world: &World, tile: Tile) -> Option<u8> {
+	match world.resource::<Arc<Content>>() {
+		Some(content) => content.movement_cost(tile),
+		None => tiles::movement_cost(tile),
+	}
+}
+
+/// Makes every tile covered by a building impassable to the [`Pathfinder`], which is built from
+/// the terrain alone.
+pub(crate) fn block_paths(world: &mut World) {
+	let tiles = world.query::<Building>().flat_map(|(_, building)| building.tiles.clone()).collect::<Vec<_>>();
+	if let Some(pathfinder) = world.resource_mut::<Pathfinder>() {
+		for position in tiles {
+			pathfinder.set_cost(position, None);
+		}
+	}
+}
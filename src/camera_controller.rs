@@ -0,0 +1,31 @@
+use std::time::Duration;
+
+use crate::input::{Action, Input};
+use crate::renderer::Camera2D;
+
+/// Keyboard panning speed in screen pixels per second.
+const PAN_SPEED: f32 = 600.0;
+
+/// Zoom multiplier for one notch of the mouse wheel.
+const ZOOM_STEP: f32 = 1.1;
+
+/// Moves `camera` for a frame lasting `elapsed`: the wheel zooms towards the cursor,
+/// [`Action::DragCamera`] drags the view and the pan actions scroll it.
+pub fn update(input: &Input, elapsed: Duration, camera: &mut Camera2D) {
+	if input.wheel() != 0.0 {
+		camera.zoom_at(ZOOM_STEP.powf(input.wheel()), input.cursor());
+	}
+
+	if input.held(Action::DragCamera) {
+		camera.drag(input.cursor_delta());
+	}
+
+	let axis = |negative: Action, positive: Action| {
+		input.held(positive) as i32 as f32 - input.held(negative) as i32 as f32
+	};
+	let distance = PAN_SPEED * elapsed.as_secs_f32();
+	camera.pan([
+		axis(Action::PanLeft, Action::PanRight) * distance,
+		axis(Action::PanDown, Action::PanUp) * distance,
+	]);
+}
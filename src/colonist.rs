@@ -0,0 +1,898 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::building::{Building, ConstructionSite, Occupancy};
+use crate::components::Position;
+use crate::content::{Content, RecipeId};
+use crate::crafting::Crafter;
+use crate::economy::{Resource, Stockpile};
+use crate::ecs::{Entity, World};
+use crate::logistics::{self, Pickup};
+use crate::pathfinding::{PathRequest, PathResult, Pathfinder};
+use crate::save::{Persist, Reader, SaveError, Sections, Writer};
+use crate::tilemap::Tile;
+
+/// Need level below which a colonist eats, if there is food in a stockpile.
+pub const HUNGRY: f32 = 0.25;
+/// Rest level below which a colonist drops what it is doing and sleeps.
+pub const TIRED: f32 = 0.15;
+/// Morale below which a colonist works at half speed.
+pub const LOW_MORALE: f32 = 0.3;
+/// Highest skill level.
+pub const MAX_SKILL: u8 = 10;
+/// Work a colonist without any skill does each tick. Every skill level adds one.
+pub const BASE_WORK: u32 = 10;
+/// Tiles of walking one skill level is worth when choosing who does a job.
+pub const SKILL_WEIGHT: i32 = 5;
+
+const HUNGER_PER_SECOND: f32 = 1.0 / 480.0;
+const REST_PER_SECOND: f32 = 1.0 / 720.0;
+const SLEEP_PER_SECOND: f32 = 1.0 / 120.0;
+const MORALE_PER_SECOND: f32 = 1.0 / 300.0;
+/// Hunger a unit of food satisfies.
+const MEAL: f32 = 0.6;
+
+/// How satisfied a colonist is, each from 0, desperate, to 1, content.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Needs {
+	/// Falls over time, restored by eating food from a stockpile.
+	pub hunger: f32,
+	/// Falls while awake, restored by sleeping.
+	pub rest: f32,
+	/// Drifts towards the average of the other needs.
+	pub morale: f32,
+}
+
+impl Default for Needs {
+	fn default() -> Self {
+		Self {
+			hunger: 1.0,
+			rest: 1.0,
+			morale: 1.0,
+		}
+	}
+}
+
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Skill {
+	Construction,
+	Mining,
+	Crafting,
+	Hauling,
+}
+
+impl Skill {
+	pub const ALL: [Skill; 4] = [Skill::Construction, Skill::Mining, Skill::Crafting, Skill::Hauling];
+}
+
+/// Level of every [`Skill`], from 0 to [`MAX_SKILL`].
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Skills([u8; Skill::ALL.len()]);
+
+impl Skills {
+	pub fn get(&self, skill: Skill) -> u8 {
+		self.0[skill as usize]
+	}
+
+	/// Sets a level, capped at [`MAX_SKILL`].
+	pub fn set(&mut self, skill: Skill, level: u8) {
+		self.0[skill as usize] = level.min(MAX_SKILL);
+	}
+}
+
+/// Handle to a job posted to the [`JobBoard`].
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct JobId(pub u64);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum JobKind {
+	/// Work on a [`ConstructionSite`] until the building is finished.
+	Build(Entity),
+	/// Dig out a tile, leaving the given tile in its place.
+	Mine([i32; 2], Tile),
+	/// Craft a recipe by hand from the [`Stockpile`] on an entity, putting the outputs back.
+	Craft(RecipeId, Entity),
+	/// Collect every pickup in turn, then deliver the lot to `to`. The job's work is the number
+	/// of pickups. Posted by the logistics planner, see [`logistics::plan`].
+	Haul { pickups: Vec<Pickup>, to: Entity },
+}
+
+impl JobKind {
+	pub fn skill(&self) -> Skill {
+		match self {
+			JobKind::Build(_) => Skill::Construction,
+			JobKind::Mine(..) => Skill::Mining,
+			JobKind::Craft(..) => Skill::Crafting,
+			JobKind::Haul { .. } => Skill::Hauling,
+		}
+	}
+
+	/// Tile the work starts at, `None` once the entity it is for is gone.
+	pub fn site(&self, world: &World) -> Option<[i32; 2]> {
+		match *self {
+			JobKind::Build(site) => world.get::<Building>(site).map(|building| building.origin),
+			JobKind::Mine(tile, _) => Some(tile),
+			JobKind::Craft(_, stockpile) => entity_tile(world, stockpile),
+			JobKind::Haul { ref pickups, to } => entity_tile(world, pickups.first().map_or(to, |pickup| pickup.from)),
+		}
+	}
+}
+
+/// Tile an entity stands on: the origin of its building, or else the tile under its position.
+pub fn entity_tile(world: &World, entity: Entity) -> Option<[i32; 2]> {
+	if let Some(building) = world.get::<Building>(entity) {
+		return Some(building.origin);
+	}
+	world.get::<Position>(entity).map(|position| tile_at(position.current))
+}
+
+/// Tile to walk to for work at `goal`: the goal itself if it can be walked on, or else the
+/// walkable tile next to the building covering it that is nearest to `from`.
+pub(crate) fn approach(world: &World, from: [i32; 2], goal: [i32; 2]) -> [i32; 2] {
+	let Some(grid) = world.resource::<Pathfinder>().map(Pathfinder::grid) else {
+		return goal;
+	};
+	if grid.is_passable(goal) {
+		return goal;
+	}
+	let covered = world.resource::<Occupancy>()
+		.and_then(|occupancy| occupancy.get(goal))
+		.and_then(|entity| world.get::<Building>(entity))
+		.map_or_else(|| vec![goal], |building| building.tiles.clone());
+	covered.iter()
+		.flat_map(|&[x, y]| [[x + 1, y], [x - 1, y], [x, y + 1], [x, y - 1]])
+		.filter(|next| !covered.contains(next) && grid.is_passable(*next))
+		.min_by_key(|&[x, y]| ((x - from[0]).abs() + (y - from[1]).abs(), [x, y]))
+		.unwrap_or(goal)
+}
+
+fn tile_at(position: [f32; 2]) -> [i32; 2] {
+	[position[0].floor() as i32, position[1].floor() as i32]
+}
+
+/// A work order on the [`JobBoard`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Job {
+	pub kind: JobKind,
+	/// Jobs with a higher priority are handed out first.
+	pub priority: u8,
+	/// Units of work the job takes, see [`BASE_WORK`].
+	pub work: u32,
+	/// Units of work done so far, kept when the job is interrupted.
+	pub done: u32,
+	claimed_by: Option<Entity>,
+}
+
+impl Job {
+	pub fn new(kind: JobKind, priority: u8, work: u32) -> Self {
+		Self {
+			kind,
+			priority,
+			work,
+			done: 0,
+			claimed_by: None,
+		}
+	}
+
+	/// Colonist doing the job, `None` while it waits for one.
+	pub fn claimed_by(&self) -> Option<Entity> {
+		self.claimed_by
+	}
+}
+
+/// The outpost's queue of jobs, stored as a world resource. Jobs stay on the board until they
+/// are done or cancelled, and are handed out again if the colonist doing one is interrupted.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct JobBoard {
+	jobs: BTreeMap<JobId, Job>,
+	next_id: u64,
+	/// Tiles mined out since the game last applied them to the map.
+	mined: Vec<([i32; 2], Tile)>,
+}
+
+impl JobBoard {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn post(&mut self, job: Job) -> JobId {
+		let id = JobId(self.next_id);
+		self.next_id += 1;
+		self.jobs.insert(id, Job { claimed_by: None, ..job });
+		id
+	}
+
+	/// Takes a job off the board. The colonist doing it, if any, stops on its next tick.
+	pub fn cancel(&mut self, id: JobId) -> Option<Job> {
+		self.jobs.remove(&id)
+	}
+
+	pub fn get(&self, id: JobId) -> Option<&Job> {
+		self.jobs.get(&id)
+	}
+
+	pub fn len(&self) -> usize {
+		self.jobs.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.jobs.is_empty()
+	}
+
+	/// Jobs in the order they were posted.
+	pub fn iter(&self) -> impl Iterator<Item = (JobId, &Job)> {
+		self.jobs.iter().map(|(&id, job)| (id, job))
+	}
+
+	/// Tiles mined out since the last call, with the tile to leave in their place.
+	pub fn take_mined(&mut self) -> Vec<([i32; 2], Tile)> {
+		std::mem::take(&mut self.mined)
+	}
+}
+
+/// What a colonist is up to.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Activity {
+	#[default]
+	Idle,
+	/// On the way to where the job's next step happens, along `path`, or waiting for the
+	/// pathfinder to answer `request`.
+	Walking {
+		job: JobId,
+		path: VecDeque<[i32; 2]>,
+		request: Option<PathRequest>,
+	},
+	Working(JobId),
+	Sleeping,
+}
+
+impl Activity {
+	/// Job being walked to or worked on.
+	pub fn job(&self) -> Option<JobId> {
+		match *self {
+			Activity::Walking { job, .. } | Activity::Working(job) => Some(job),
+			Activity::Idle | Activity::Sleeping => None,
+		}
+	}
+}
+
+/// A colonist, walking to jobs on the [`JobBoard`] and working them over ticks. Moves its
+/// entity's [`Position`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Colonist {
+	pub name: String,
+	pub needs: Needs,
+	pub skills: Skills,
+	/// Tiles walked per second.
+	pub speed: f32,
+	/// Resources held for the job in progress, returned if it is interrupted.
+	pub carrying: Vec<(Resource, u64)>,
+	activity: Activity,
+}
+
+impl Colonist {
+	pub fn new(name: impl Into<String>) -> Self {
+		Self {
+			name: name.into(),
+			needs: Needs::default(),
+			skills: Skills::default(),
+			speed: 2.0,
+			carrying: Vec::new(),
+			activity: Activity::Idle,
+		}
+	}
+
+	pub fn with_skill(mut self, skill: Skill, level: u8) -> Self {
+		self.skills.set(skill, level);
+		self
+	}
+
+	pub fn activity(&self) -> &Activity {
+		&self.activity
+	}
+
+	/// Work done each tick on a job needing `skill`.
+	pub fn work_rate(&self, skill: Skill) -> u32 {
+		let rate = BASE_WORK + self.skills.get(skill) as u32;
+		if self.needs.morale < LOW_MORALE { rate / 2 } else { rate }
+	}
+}
+
+/// Stops whatever `colonist` is doing, handing its job back to the board and returning what it
+/// carries. Returns `false` if it was not doing a job.
+pub fn interrupt(world: &mut World, colonist: Entity) -> bool {
+	let Some(mut state) = world.get::<Colonist>(colonist).cloned() else {
+		return false;
+	};
+	let released = release(world, colonist, &mut state);
+	state.activity = Activity::Idle;
+	world.insert(colonist, state);
+	released
+}
+
+/// Hands the job of `entity` back, if it still holds it, and returns what it carries, leaving
+/// its activity as it was.
+fn release(world: &mut World, entity: Entity, colonist: &mut Colonist) -> bool {
+	if let Activity::Walking { request: Some(request), .. } = colonist.activity {
+		if let Some(pathfinder) = world.resource_mut::<Pathfinder>() {
+			pathfinder.cancel(request);
+		}
+	}
+	let Some(id) = colonist.activity.job() else {
+		return false;
+	};
+	let Some(mut board) = world.remove_resource::<JobBoard>() else {
+		return false;
+	};
+	if let Some(job) = board.jobs.get_mut(&id) {
+		// A job handed to someone else since is theirs to keep, progress and all.
+		if job.claimed_by == Some(entity) {
+			job.claimed_by = None;
+			// What was taken is taken afresh by whoever picks the job up next.
+			if matches!(job.kind, JobKind::Haul { .. } | JobKind::Craft(..)) {
+				job.done = 0;
+			}
+		}
+		match job.kind {
+			JobKind::Haul { ref pickups, .. } => {
+				for (pickup, &(resource, amount)) in pickups.iter().zip(&colonist.carrying) {
+					logistics::put_back(world, pickup.from, resource, amount);
+				}
+			}
+			JobKind::Craft(_, stockpile) => {
+				if let Some(stockpile) = world.get_mut::<Stockpile>(stockpile) {
+					for &(resource, amount) in &colonist.carrying {
+						stockpile.add(resource, amount);
+					}
+				}
+			}
+			JobKind::Build(_) | JobKind::Mine(..) => {}
+		}
+	}
+	colonist.carrying.clear();
+	world.insert_resource(board);
+	true
+}
+
+/// Lowers needs over `dt`, feeding hungry colonists from the first stockpile with food.
+pub(crate) fn update_needs(world: &mut World, dt: Duration) {
+	let seconds = dt.as_secs_f32();
+	let food = world.resource::<Arc<Content>>().and_then(|content| content.item_id("food")).map(Resource::Item);
+	let colonists = world.query::<Colonist>().map(|(entity, _)| entity).collect::<Vec<_>>();
+	for entity in colonists {
+		let colonist = world.get_mut::<Colonist>(entity).expect("queried above");
+		let needs = &mut colonist.needs;
+		needs.hunger = (needs.hunger - HUNGER_PER_SECOND * seconds).max(0.0);
+		needs.rest = match colonist.activity {
+			Activity::Sleeping => (needs.rest + SLEEP_PER_SECOND * seconds).min(1.0),
+			_ => (needs.rest - REST_PER_SECOND * seconds).max(0.0),
+		};
+		let target = (needs.hunger + needs.rest) / 2.0;
+		let step = MORALE_PER_SECOND * seconds;
+		needs.morale = if needs.morale < target { (needs.morale + step).min(target) } else { (needs.morale - step).max(target) };
+		if needs.hunger >= HUNGRY {
+			continue;
+		}
+
+		let Some(food) = food else {
+			continue;
+		};
+		let pantry = world.query::<Stockpile>().find(|(_, stockpile)| stockpile.get(food) > 0).map(|(entity, _)| entity);
+		if let Some(pantry) = pantry {
+			world.get_mut::<Stockpile>(pantry).expect("found above").remove(food, 1);
+			let needs = &mut world.get_mut::<Colonist>(entity).expect("queried above").needs;
+			needs.hunger = (needs.hunger + MEAL).min(1.0);
+		}
+	}
+}
+
+/// Whether `job` can be started now, beyond its site still existing.
+fn can_start(world: &World, job: &Job) -> bool {
+	match job.kind {
+		JobKind::Build(site) => world.has::<ConstructionSite>(site),
+		JobKind::Mine(..) => true,
+		JobKind::Craft(recipe, stockpile) => {
+			let content = world.resource::<Arc<Content>>();
+			let recipe = content.and_then(|content| content.recipe(recipe));
+			let stockpile = world.get::<Stockpile>(stockpile);
+			match (recipe, stockpile) {
+				(Some(recipe), Some(stockpile)) => recipe.inputs.iter()
+					.all(|input| stockpile.get(Resource::Item(input.item)) >= input.count as u64),
+				_ => false,
+			}
+		}
+		JobKind::Haul { to, .. } => world.has::<Stockpile>(to) || world.has::<Crafter>(to),
+	}
+}
+
+/// Hands waiting jobs, highest priority first, to the idle colonist best suited to each: the
+/// one with the most skill for it less the distance to walk, counting a skill level as
+/// [`SKILL_WEIGHT`] tiles. Colonists who cannot reach a job are passed over.
+pub(crate) fn assign_jobs(world: &mut World) {
+	let Some(mut board) = world.remove_resource::<JobBoard>() else {
+		return;
+	};
+	let mut idle = world.query2::<Colonist, Position>()
+		.filter(|(_, colonist, _)| colonist.activity == Activity::Idle && colonist.needs.rest >= TIRED)
+		.map(|(entity, colonist, position)| (entity, colonist.skills, tile_at(position.current)))
+		.collect::<Vec<_>>();
+	let mut waiting = board.jobs.iter().filter(|(_, job)| job.claimed_by.is_none()).map(|(&id, job)| (id, job.priority)).collect::<Vec<_>>();
+	waiting.sort_by_key(|&(id, priority)| (std::cmp::Reverse(priority), id));
+
+	for (id, _) in waiting {
+		if idle.is_empty() {
+			break;
+		}
+		let job = &board.jobs[&id];
+		let Some(site) = job.kind.site(world).filter(|_| can_start(world, job)) else {
+			continue;
+		};
+		let skill = job.kind.skill();
+		let mut best = None;
+		for (index, &(_, skills, tile)) in idle.iter().enumerate() {
+			let goal = approach(world, tile, site);
+			let reachable = match world.resource_mut::<Pathfinder>() {
+				Some(pathfinder) => tile == goal || pathfinder.regions().connected(tile, goal),
+				None => true,
+			};
+			if !reachable {
+				continue;
+			}
+			let distance = (tile[0] - site[0]).abs() + (tile[1] - site[1]).abs();
+			let score = skills.get(skill) as i32 * SKILL_WEIGHT - distance;
+			if best.is_none_or(|(_, best)| score > best) {
+				best = Some((index, score));
+			}
+		}
+		let Some((index, _)) = best else {
+			continue;
+		};
+		let (colonist, ..) = idle.remove(index);
+		board.jobs.get_mut(&id).expect("listed above").claimed_by = Some(colonist);
+		world.get_mut::<Colonist>(colonist).expect("listed above").activity = Activity::Walking {
+			job: id,
+			path: VecDeque::new(),
+			request: None,
+		};
+	}
+	world.insert_resource(board);
+}
+
+/// Moves `position` up to `distance` tiles along `path`, dropping the tiles it reaches.
+fn walk(position: &mut [f32; 2], path: &mut VecDeque<[i32; 2]>, distance: f32) {
+	let mut left = distance;
+	while let Some(&tile) = path.front() {
+		let target = [tile[0] as f32 + 0.5, tile[1] as f32 + 0.5];
+		let offset = [target[0] - position[0], target[1] - position[1]];
+		let length = offset[0].hypot(offset[1]);
+		if length > left {
+			position[0] += offset[0] / length * left;
+			position[1] += offset[1] / length * left;
+			return;
+		}
+		*position = target;
+		left -= length;
+		path.pop_front();
+	}
+}
+
+/// What came of a tick of work.
+enum Step {
+	Continue,
+	/// Done with this site, walk to the next one.
+	WalkTo,
+	Finished,
+	/// The job cannot go on, hand it back.
+	Abandon,
+}
+
+/// Runs every colonist's activity for a tick: sleeping when tired, walking to jobs and working
+/// them.
+pub(crate) fn work(world: &mut World, dt: Duration) {
+	let colonists = world.query2::<Colonist, Position>().map(|(entity, ..)| entity).collect::<Vec<_>>();
+	for entity in colonists {
+		let mut colonist = world.get::<Colonist>(entity).expect("queried above").clone();
+		let mut position = world.get::<Position>(entity).expect("queried above").current;
+		update(world, entity, &mut colonist, &mut position, dt);
+		world.get_mut::<Position>(entity).expect("queried above").current = position;
+		world.insert(entity, colonist);
+	}
+}
+
+fn update(world: &mut World, entity: Entity, colonist: &mut Colonist, position: &mut [f32; 2], dt: Duration) {
+	if colonist.activity == Activity::Sleeping {
+		if colonist.needs.rest >= 1.0 {
+			colonist.activity = Activity::Idle;
+		}
+		return;
+	}
+	if colonist.needs.rest < TIRED {
+		release(world, entity, colonist);
+		colonist.activity = Activity::Sleeping;
+		return;
+	}
+
+	let Some(id) = colonist.activity.job() else {
+		return;
+	};
+	let job = world.resource::<JobBoard>().and_then(|board| board.get(id)).filter(|job| job.claimed_by == Some(entity));
+	let Some(job) = job.cloned() else {
+		// Cancelled, or taken away.
+		release(world, entity, colonist);
+		colonist.activity = Activity::Idle;
+		return;
+	};
+
+	match &mut colonist.activity {
+		Activity::Walking { path, request, .. } if path.is_empty() => {
+			if world.resource::<Pathfinder>().is_none() {
+				colonist.activity = Activity::Working(id);
+				return;
+			}
+			let Some(pending) = request.take() else {
+				let goal = match job.kind {
+					JobKind::Haul { ref pickups, to } => {
+						entity_tile(world, pickups.get(job.done as usize).map_or(to, |pickup| pickup.from))
+					}
+					_ => job.kind.site(world),
+				};
+				let Some(goal) = goal else {
+					release(world, entity, colonist);
+					colonist.activity = Activity::Idle;
+					return;
+				};
+				let goal = approach(world, tile_at(*position), goal);
+				let pathfinder = world.resource_mut::<Pathfinder>().expect("checked above");
+				*request = Some(pathfinder.request(tile_at(*position), goal));
+				return;
+			};
+			match world.resource_mut::<Pathfinder>().expect("checked above").result(pending) {
+				None => *request = Some(pending),
+				Some(PathResult::Found(found)) => {
+					path.extend(found.tiles.iter().skip(1));
+					if path.is_empty() {
+						colonist.activity = Activity::Working(id);
+					}
+				}
+				Some(PathResult::Unreachable) => {
+					release(world, entity, colonist);
+					colonist.activity = Activity::Idle;
+				}
+			}
+		}
+		Activity::Walking { path, .. } => {
+			walk(position, path, colonist.speed * dt.as_secs_f32());
+			if path.is_empty() {
+				colonist.activity = Activity::Working(id);
+			}
+		}
+		Activity::Working(_) => match perform(world, colonist, id, &job) {
+			Step::Continue => {}
+			Step::WalkTo => {
+				colonist.activity = Activity::Walking {
+					job: id,
+					path: VecDeque::new(),
+					request: None,
+				};
+			}
+			Step::Finished => {
+				let board = world.resource_mut::<JobBoard>().expect("job found above");
+				board.jobs.remove(&id);
+				if let JobKind::Mine(tile, leaves) = job.kind {
+					board.mined.push((tile, leaves));
+				}
+				colonist.activity = Activity::Idle;
+			}
+			Step::Abandon => {
+				release(world, entity, colonist);
+				colonist.activity = Activity::Idle;
+			}
+		},
+		Activity::Idle | Activity::Sleeping => {}
+	}
+}
+
+/// Adds `rate` units of work to a job, returning how much is done.
+fn add_work(world: &mut World, id: JobId, rate: u32) -> u32 {
+	let job = world.resource_mut::<JobBoard>().and_then(|board| board.jobs.get_mut(&id)).expect("job is on the board");
+	job.done = (job.done + rate).min(job.work);
+	job.done
+}
+
+/// Does a tick of work on `job` at its site.
+fn perform(world: &mut World, colonist: &mut Colonist, id: JobId, job: &Job) -> Step {
+	let rate = colonist.work_rate(job.kind.skill());
+	match job.kind {
+		JobKind::Haul { ref pickups, to } => {
+			// `done` counts the pickups made, and `carrying` holds what each of them found.
+			if let Some(pickup) = pickups.get(job.done as usize) {
+				let taken = logistics::pick_up(world, pickup.from, pickup.resource, pickup.amount);
+				colonist.carrying.push((pickup.resource, taken));
+				add_work(world, id, 1);
+				return Step::WalkTo;
+			}
+			// Whatever does not fit goes back where it came from.
+			for (pickup, (resource, amount)) in pickups.iter().zip(colonist.carrying.drain(..)) {
+				let delivered = logistics::deliver(world, to, resource, amount);
+				logistics::put_back(world, pickup.from, resource, amount - delivered);
+			}
+			Step::Finished
+		}
+		JobKind::Craft(recipe, stockpile) => {
+			let Some(recipe) = world.resource::<Arc<Content>>().and_then(|content| content.recipe(recipe)).cloned() else {
+				return Step::Abandon;
+			};
+			if job.done == 0 && colonist.carrying.is_empty() {
+				if !can_start(world, job) {
+					return Step::Abandon;
+				}
+				let stockpile = world.get_mut::<Stockpile>(stockpile).expect("checked by can_start");
+				for input in &recipe.inputs {
+					let resource = Resource::Item(input.item);
+					colonist.carrying.push((resource, stockpile.remove(resource, input.count as u64)));
+				}
+			}
+			if add_work(world, id, rate) < job.work {
+				return Step::Continue;
+			}
+			colonist.carrying.clear();
+			if let Some(stockpile) = world.get_mut::<Stockpile>(stockpile) {
+				for output in &recipe.outputs {
+					stockpile.add(Resource::Item(output.item), output.count as u64);
+				}
+			}
+			Step::Finished
+		}
+		JobKind::Build(site) => {
+			if !world.has::<ConstructionSite>(site) {
+				return Step::Finished;
+			}
+			let done = add_work(world, id, rate);
+			if done == job.work {
+				world.remove::<ConstructionSite>(site);
+				return Step::Finished;
+			}
+			world.get_mut::<ConstructionSite>(site).expect("checked above").progress = done as f32 / job.work.max(1) as f32;
+			Step::Continue
+		}
+		JobKind::Mine(..) => {
+			if add_work(world, id, rate) == job.work { Step::Finished } else { Step::Continue }
+		}
+	}
+}
+
+fn save_job_kind(kind: &JobKind, writer: &mut Writer) {
+	match *kind {
+		JobKind::Build(site) => {
+			writer.u8(0);
+			writer.entity(site);
+		}
+		JobKind::Mine([x, y], leaves) => {
+			writer.u8(1);
+			writer.i32(x);
+			writer.i32(y);
+			writer.varint(leaves.0 as u64);
+		}
+		JobKind::Craft(recipe, stockpile) => {
+			writer.u8(2);
+			writer.varint(recipe.0 as u64);
+			writer.entity(stockpile);
+		}
+		JobKind::Haul { ref pickups, to } => {
+			writer.u8(3);
+			writer.varint(pickups.len() as u64);
+			for pickup in pickups {
+				writer.entity(pickup.from);
+				pickup.resource.save(writer);
+				writer.varint(pickup.amount);
+			}
+			writer.entity(to);
+		}
+	}
+}
+
+fn load_u16(reader: &mut Reader, what: &str) -> Result<u16, SaveError> {
+	u16::try_from(reader.varint()?).map_err(|_| SaveError::Corrupt(format!("{what} out of range")))
+}
+
+fn load_job_kind(reader: &mut Reader) -> Result<JobKind, SaveError> {
+	Ok(match reader.u8()? {
+		0 => JobKind::Build(reader.entity()?),
+		1 => JobKind::Mine([reader.i32()?, reader.i32()?], Tile(load_u16(reader, "tile")?)),
+		2 => JobKind::Craft(RecipeId(load_u16(reader, "recipe")?), reader.entity()?),
+		3 => {
+			let mut pickups = Vec::new();
+			for _ in 0..reader.varint()? {
+				pickups.push(Pickup {
+					from: reader.entity()?,
+					resource: Resource::load(reader)?,
+					amount: reader.varint()?,
+				});
+			}
+			JobKind::Haul { pickups, to: reader.entity()? }
+		}
+		tag => return Err(SaveError::Corrupt(format!("unknown job kind {tag}"))),
+	})
+}
+
+/// Save migration to version 2, where a haul lists several pickups instead of taking one amount
+/// from one place.
+///
+/// Version 1 hauls did not count their pickup in `done`, so one under way cannot be told apart
+/// from one not yet started. Hauls are handed back instead: their colonist finds the job is no
+/// longer its own on the first tick and puts back what it carries, and the job is assigned afresh.
+pub(crate) fn migrate_hauls_to_pickup_lists(sections: &mut Sections) -> Result<(), SaveError> {
+	const SECTION: &str = "resource.job_board";
+	let Some(data) = sections.get(SECTION) else {
+		return Ok(());
+	};
+	let mut reader = Reader::new(data);
+	let mut writer = Writer::new();
+	writer.varint(reader.varint()?);
+	let count = reader.varint()?;
+	writer.varint(count);
+	for _ in 0..count {
+		writer.varint(reader.varint()?);
+		let tag = reader.u8()?;
+		writer.u8(tag);
+		let haul = tag == 3;
+		match tag {
+			0 => writer.entity(reader.entity()?),
+			1 => {
+				writer.i32(reader.i32()?);
+				writer.i32(reader.i32()?);
+				writer.varint(reader.varint()?);
+			}
+			2 => {
+				writer.varint(reader.varint()?);
+				writer.entity(reader.entity()?);
+			}
+			3 => {
+				let from = reader.entity()?;
+				let to = reader.entity()?;
+				let resource = Resource::load(&mut reader)?;
+				writer.varint(1);
+				writer.entity(from);
+				resource.save(&mut writer);
+				writer.varint(reader.varint()?);
+				writer.entity(to);
+			}
+			tag => return Err(SaveError::Corrupt(format!("unknown job kind {tag}"))),
+		}
+		writer.u8(reader.u8()?);
+		let work = reader.varint()?;
+		let done = reader.varint()?;
+		let claimed_by = if reader.bool()? { Some(reader.entity()?) } else { None };
+		// One stop, none made yet.
+		writer.varint(if haul { 1 } else { work });
+		writer.varint(if haul { 0 } else { done });
+		match claimed_by.filter(|_| !haul) {
+			Some(colonist) => {
+				writer.bool(true);
+				writer.entity(colonist);
+			}
+			None => writer.bool(false),
+		}
+	}
+	if !reader.is_empty() {
+		return Err(SaveError::Corrupt("job_board section: unread data at the end".into()));
+	}
+	sections.insert(SECTION, writer.into_bytes());
+	Ok(())
+}
+
+impl Persist for JobBoard {
+	fn save(&self, writer: &mut Writer) {
+		writer.varint(self.next_id);
+		writer.varint(self.jobs.len() as u64);
+		for (id, job) in &self.jobs {
+			writer.varint(id.0);
+			save_job_kind(&job.kind, writer);
+			writer.u8(job.priority);
+			writer.varint(job.work as u64);
+			writer.varint(job.done as u64);
+			writer.bool(job.claimed_by.is_some());
+			if let Some(colonist) = job.claimed_by {
+				writer.entity(colonist);
+			}
+		}
+	}
+
+	fn load(reader: &mut Reader) -> Result<Self, SaveError> {
+		let next_id = reader.varint()?;
+		let mut jobs = BTreeMap::new();
+		for _ in 0..reader.varint()? {
+			let id = JobId(reader.varint()?);
+			let job = Job {
+				kind: load_job_kind(reader)?,
+				priority: reader.u8()?,
+				work: reader.varint_u32()?,
+				done: reader.varint_u32()?,
+				claimed_by: if reader.bool()? { Some(reader.entity()?) } else { None },
+			};
+			jobs.insert(id, job);
+		}
+		Ok(Self {
+			jobs,
+			next_id,
+			mined: Vec::new(),
+		})
+	}
+}
+
+impl Persist for Colonist {
+	fn save(&self, writer: &mut Writer) {
+		writer.str(&self.name);
+		writer.f32(self.needs.hunger);
+		writer.f32(self.needs.rest);
+		writer.f32(self.needs.morale);
+		for skill in Skill::ALL {
+			writer.u8(self.skills.get(skill));
+		}
+		writer.f32(self.speed);
+		writer.varint(self.carrying.len() as u64);
+		for &(resource, amount) in &self.carrying {
+			resource.save(writer);
+			writer.varint(amount);
+		}
+		// Path requests are not saved, a loaded colonist asks again for its path.
+		match &self.activity {
+			Activity::Idle => writer.u8(0),
+			Activity::Walking { job, path, .. } => {
+				writer.u8(1);
+				writer.varint(job.0);
+				writer.varint(path.len() as u64);
+				for &[x, y] in path {
+					writer.i32(x);
+					writer.i32(y);
+				}
+			}
+			Activity::Working(job) => {
+				writer.u8(2);
+				writer.varint(job.0);
+			}
+			Activity::Sleeping => writer.u8(3),
+		}
+	}
+
+	fn load(reader: &mut Reader) -> Result<Self, SaveError> {
+		let name = reader.str()?.to_owned();
+		let needs = Needs {
+			hunger: reader.f32()?,
+			rest: reader.f32()?,
+			morale: reader.f32()?,
+		};
+		let mut skills = Skills::default();
+		for skill in Skill::ALL {
+			skills.set(skill, reader.u8()?);
+		}
+		let speed = reader.f32()?;
+		let mut carrying = Vec::new();
+		for _ in 0..reader.varint()? {
+			carrying.push((Resource::load(reader)?, reader.varint()?));
+		}
+		let activity = match reader.u8()? {
+			0 => Activity::Idle,
+			1 => {
+				let job = JobId(reader.varint()?);
+				let mut path = VecDeque::new();
+				for _ in 0..reader.varint()? {
+					path.push_back([reader.i32()?, reader.i32()?]);
+				}
+				Activity::Walking { job, path, request: None }
+			}
+			2 => Activity::Working(JobId(reader.varint()?)),
+			3 => Activity::Sleeping,
+			tag => return Err(SaveError::Corrupt(format!("unknown colonist activity {tag}"))),
+		};
+		Ok(Self {
+			name,
+			needs,
+			skills,
+			speed,
+			carrying,
+			activity,
+		})
+	}
+}
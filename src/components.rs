@@ -0,0 +1,84 @@
+use crate::renderer::{TextureId, UvRect};
+use crate::save::{Persist, Reader, SaveError, Writer};
+
+/// Where an entity is in world units, along with where it was at the previous tick so drawing can
+/// interpolate between the two.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Position {
+	pub current: [f32; 2],
+	pub previous: [f32; 2],
+}
+
+impl Position {
+	pub fn new(position: [f32; 2]) -> Self {
+		Self {
+			current: position,
+			previous: position,
+		}
+	}
+
+	/// Position `alpha` of the way from the previous tick to the current one.
+	pub fn interpolated(&self, alpha: f32) -> [f32; 2] {
+		[
+			self.previous[0] + (self.current[0] - self.previous[0]) * alpha,
+			self.previous[1] + (self.current[1] - self.previous[1]) * alpha,
+		]
+	}
+
+	/// Moves without interpolating, for jumps that should not be seen sliding across the map.
+	pub fn teleport(&mut self, position: [f32; 2]) {
+		*self = Self::new(position);
+	}
+}
+
+impl Persist for Position {
+	fn save(&self, writer: &mut Writer) {
+		self.current.into_iter().chain(self.previous).for_each(|value| writer.f32(value));
+	}
+
+	fn load(reader: &mut Reader) -> Result<Self, SaveError> {
+		Ok(Self {
+			current: [reader.f32()?, reader.f32()?],
+			previous: [reader.f32()?, reader.f32()?],
+		})
+	}
+}
+
+/// Movement in world units per second.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Velocity(pub [f32; 2]);
+
+impl Persist for Velocity {
+	fn save(&self, writer: &mut Writer) {
+		self.0.into_iter().for_each(|value| writer.f32(value));
+	}
+
+	fn load(reader: &mut Reader) -> Result<Self, SaveError> {
+		Ok(Self([reader.f32()?, reader.f32()?]))
+	}
+}
+
+/// How an entity is drawn, as a sprite centred on its [`Position`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Renderable {
+	pub texture: TextureId,
+	pub uv_rect: UvRect,
+	/// Size in world units.
+	pub size: [f32; 2],
+	pub rotation: f32,
+	pub tint: [f32; 4],
+	pub layer: i32,
+}
+
+impl Renderable {
+	pub fn new(texture: TextureId, size: [f32; 2]) -> Self {
+		Self {
+			texture,
+			uv_rect: UvRect::FULL,
+			size,
+			rotation: 0.0,
+			tint: [1.0; 4],
+			layer: 0,
+		}
+	}
+}
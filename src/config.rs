@@ -0,0 +1,325 @@
+use std::error::Error;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::Deserialize;
+use wgpu::{Backends, PowerPreference};
+use wgpu::util::parse_backends_from_comma_list;
+
+use crate::game::DEFAULT_MAP_SIZE;
+
+/// Config file read from the working directory unless `--config` says otherwise.
+pub const DEFAULT_CONFIG_PATH: &str = "frontier-outpost.toml";
+
+/// Backends in the order they are tried when several are allowed.
+const BACKEND_PRIORITY: &[Backends] = &[
+	Backends::VULKAN,
+	Backends::METAL,
+	Backends::DX12,
+	Backends::DX11,
+	Backends::GL,
+	Backends::BROWSER_WEBGPU,
+];
+
+#[derive(Clone, Debug)]
+pub struct GraphicsConfig {
+	pub backends: Backends,
+	pub power_preference: PowerPreference,
+	pub force_fallback_adapter: bool,
+}
+
+impl Default for GraphicsConfig {
+	fn default() -> Self {
+		Self {
+			backends: Backends::all(),
+			power_preference: PowerPreference::HighPerformance,
+			force_fallback_adapter: false,
+		}
+	}
+}
+
+impl GraphicsConfig {
+	/// Settings for offscreen rendering on machines without a GPU.
+	pub fn headless() -> Self {
+		Self {
+			backends: Backends::all(),
+			power_preference: PowerPreference::LowPower,
+			force_fallback_adapter: true,
+		}
+	}
+
+	/// Splits the allowed backend set into single backends, most preferred first.
+	pub fn backend_attempts(&self) -> impl Iterator<Item = Backends> + '_ {
+		BACKEND_PRIORITY.iter().copied().filter(|backend| self.backends.contains(*backend))
+	}
+
+	/// The backends to request an adapter from, each paired with whether to ask for the fallback
+	/// adapter: every backend without it first, then every backend with it. Only the latter
+	/// when the fallback adapter is forced.
+	pub fn adapter_attempts(&self) -> impl Iterator<Item = (Backends, bool)> + '_ {
+		let fallback_attempts: &[bool] = if self.force_fallback_adapter { &[true] } else { &[false, true] };
+		fallback_attempts.iter()
+			.flat_map(move |&fallback| self.backend_attempts().map(move |backend| (backend, fallback)))
+	}
+
+	fn apply(&mut self, file: GraphicsFile) -> Result<(), Box<dyn Error>> {
+		if let Some(value) = file.backends {
+			self.backends = parse_backends(&value)?;
+		}
+		if let Some(value) = file.power_preference {
+			self.power_preference = parse_power_preference(&value)?;
+		}
+		if let Some(value) = file.force_fallback_adapter {
+			self.force_fallback_adapter = value;
+		}
+		Ok(())
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct SimulationConfig {
+	pub ticks_per_second: u32,
+	/// Most ticks run for one rendered frame before the simulation is allowed to fall behind
+	/// real time.
+	pub max_ticks_per_frame: u32,
+}
+
+impl Default for SimulationConfig {
+	fn default() -> Self {
+		Self {
+			ticks_per_second: 60,
+			max_ticks_per_frame: 5,
+		}
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct WorldConfig {
+	/// Seed for generating a new map. A random one is picked when unset.
+	pub seed: Option<u64>,
+	/// Width and height of a new map in tiles.
+	pub size: u32,
+}
+
+impl Default for WorldConfig {
+	fn default() -> Self {
+		Self {
+			seed: None,
+			size: DEFAULT_MAP_SIZE,
+		}
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct SaveConfig {
+	/// Simulated time between autosaves, or `None` to never autosave.
+	pub autosave_interval: Option<Duration>,
+	pub autosave_path: PathBuf,
+	/// Save to continue instead of generating a new map.
+	pub load: Option<PathBuf>,
+}
+
+impl Default for SaveConfig {
+	fn default() -> Self {
+		Self {
+			autosave_interval: Some(Duration::from_secs(5 * 60)),
+			autosave_path: PathBuf::from("autosave.sav"),
+			load: None,
+		}
+	}
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+	pub graphics: GraphicsConfig,
+	pub simulation: SimulationConfig,
+	pub world: WorldConfig,
+	pub save: SaveConfig,
+	/// Run the simulation without opening a window.
+	pub headless: bool,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+	graphics: GraphicsFile,
+	simulation: SimulationFile,
+	world: WorldFile,
+	save: SaveFile,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct GraphicsFile {
+	backends: Option<String>,
+	power_preference: Option<String>,
+	force_fallback_adapter: Option<bool>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SimulationFile {
+	ticks_per_second: Option<u32>,
+	max_ticks_per_frame: Option<u32>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct WorldFile {
+	seed: Option<u64>,
+	size: Option<u32>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SaveFile {
+	/// Seconds of simulated time, 0 turns autosaving off.
+	autosave_interval: Option<u64>,
+	autosave_path: Option<PathBuf>,
+}
+
+impl Config {
+	/// Builds the config from, in increasing priority, built in defaults, the config file, the
+	/// `WGPU_BACKEND` and `WGPU_POWER_PREF` environment variables, and command line flags.
+	pub fn load(args: impl IntoIterator<Item = String>) -> Result<Self, Box<dyn Error>> {
+		let mut args = args.into_iter();
+		let mut config_path = None;
+		let mut backends = None;
+		let mut power_preference = None;
+		let mut force_fallback_adapter = None;
+		let mut ticks_per_second = None;
+		let mut seed = None;
+		let mut load = None;
+		let mut headless = false;
+
+		while let Some(arg) = args.next() {
+			let mut value = |name: &str| args.next().ok_or(format!("{name} requires a value"));
+			match arg.as_str() {
+				"--config" => config_path = Some(PathBuf::from(value("--config")?)),
+				"--backend" => backends = Some(parse_backends(&value("--backend")?)?),
+				"--power-preference" => power_preference = Some(parse_power_preference(&value("--power-preference")?)?),
+				"--fallback-adapter" => force_fallback_adapter = Some(true),
+				"--ticks-per-second" => ticks_per_second = Some(parse_ticks_per_second(&value("--ticks-per-second")?)?),
+				"--seed" => seed = Some(parse_seed(&value("--seed")?)?),
+				"--load" => load = Some(PathBuf::from(value("--load")?)),
+				"--headless" => headless = true,
+				_ => return Err(format!("unknown argument {arg}").into()),
+			}
+		}
+
+		let mut config = Config::default();
+
+		let path = config_path.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_PATH));
+		match fs::read_to_string(&path) {
+			Ok(contents) => {
+				let file: ConfigFile = toml::from_str(&contents)
+					.map_err(|error| format!("{}: {error}", path.display()))?;
+				config.graphics.apply(file.graphics)
+					.and_then(|()| config.simulation.apply(file.simulation))
+					.and_then(|()| config.world.apply(file.world))
+					.map(|()| config.save.apply(file.save))
+					.map_err(|error| format!("{}: {error}", path.display()))?;
+			}
+			// Only a config file that was asked for by name has to exist.
+			Err(error) if error.kind() == ErrorKind::NotFound && config_path.is_none() => {}
+			Err(error) => return Err(format!("{}: {error}", path.display()).into()),
+		}
+
+		if let Ok(value) = std::env::var("WGPU_BACKEND") {
+			config.graphics.backends = parse_backends(&value)?;
+		}
+		if let Ok(value) = std::env::var("WGPU_POWER_PREF") {
+			config.graphics.power_preference = parse_power_preference(&value)?;
+		}
+
+		if let Some(value) = backends {
+			config.graphics.backends = value;
+		}
+		if let Some(value) = power_preference {
+			config.graphics.power_preference = value;
+		}
+		if let Some(value) = force_fallback_adapter {
+			config.graphics.force_fallback_adapter = value;
+		}
+		if let Some(value) = ticks_per_second {
+			config.simulation.ticks_per_second = value;
+		}
+		if seed.is_some() {
+			config.world.seed = seed;
+		}
+		config.save.load = load;
+		config.headless = headless;
+
+		Ok(config)
+	}
+}
+
+impl SimulationConfig {
+	fn apply(&mut self, file: SimulationFile) -> Result<(), Box<dyn Error>> {
+		if let Some(value) = file.ticks_per_second {
+			if value == 0 {
+				return Err("ticks_per_second must be positive".into());
+			}
+			self.ticks_per_second = value;
+		}
+		if let Some(value) = file.max_ticks_per_frame {
+			self.max_ticks_per_frame = value.max(1);
+		}
+		Ok(())
+	}
+}
+
+impl WorldConfig {
+	fn apply(&mut self, file: WorldFile) -> Result<(), Box<dyn Error>> {
+		if file.seed.is_some() {
+			self.seed = file.seed;
+		}
+		if let Some(value) = file.size {
+			if value == 0 {
+				return Err("world size must be positive".into());
+			}
+			self.size = value;
+		}
+		Ok(())
+	}
+}
+
+impl SaveConfig {
+	fn apply(&mut self, file: SaveFile) {
+		if let Some(seconds) = file.autosave_interval {
+			self.autosave_interval = (seconds > 0).then(|| Duration::from_secs(seconds));
+		}
+		if let Some(path) = file.autosave_path {
+			self.autosave_path = path;
+		}
+	}
+}
+
+fn parse_backends(value: &str) -> Result<Backends, Box<dyn Error>> {
+	let backends = parse_backends_from_comma_list(value);
+	if backends.is_empty() {
+		return Err(format!("no known graphics backend in \"{value}\"").into());
+	}
+	Ok(backends)
+}
+
+fn parse_power_preference(value: &str) -> Result<PowerPreference, Box<dyn Error>> {
+	match value.to_lowercase().as_str() {
+		"low" => Ok(PowerPreference::LowPower),
+		"high" => Ok(PowerPreference::HighPerformance),
+		_ => Err(format!("unknown power preference \"{value}\", expected low or high").into()),
+	}
+}
+
+fn parse_seed(value: &str) -> Result<u64, Box<dyn Error>> {
+	value.parse().map_err(|_| format!("invalid seed \"{value}\", expected a whole number").into())
+}
+
+fn parse_ticks_per_second(value: &str) -> Result<u32, Box<dyn Error>> {
+	match value.parse() {
+		Ok(0) | Err(_) => Err(format!("invalid tick rate \"{value}\", expected a positive whole number").into()),
+		Ok(ticks_per_second) => Ok(ticks_per_second),
+	}
+}
@@ -0,0 +1,533 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+
+use crate::building::{AdjacencyRule, BuildingCatalog, BuildingDef, BuildingId};
+use crate::tilemap::Tile;
+use crate::worldgen::tiles;
+
+mod raw;
+
+/// Directory the content files are read from, relative to the working directory.
+pub const DEFAULT_CONTENT_DIR: &str = "assets/content";
+
+pub const TERRAIN_FILE: &str = "terrain.toml";
+pub const ITEMS_FILE: &str = "items.toml";
+pub const BUILDINGS_FILE: &str = "buildings.toml";
+pub const RECIPES_FILE: &str = "recipes.toml";
+pub const RESEARCH_FILE: &str = "research.toml";
+
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ItemId(pub u16);
+
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct RecipeId(pub u16);
+
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ResearchId(pub u16);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TerrainDef {
+	pub id: String,
+	pub name: String,
+	pub color: [u8; 4],
+	/// How slow the terrain is to walk through, `None` if it cannot be walked on.
+	pub movement_cost: Option<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ItemDef {
+	pub id: String,
+	pub name: String,
+	/// Most of the item that fits in one stack.
+	pub stack_size: u32,
+}
+
+/// An amount of one item.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ItemCount {
+	pub item: ItemId,
+	pub count: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecipeDef {
+	pub id: String,
+	pub name: String,
+	pub inputs: Vec<ItemCount>,
+	pub outputs: Vec<ItemCount>,
+	/// Time taken to craft the outputs once.
+	pub duration: Duration,
+	/// Watts drawn while crafting.
+	pub power: f32,
+	/// Building the recipe is crafted in, `None` for recipes crafted by hand.
+	pub building: Option<BuildingId>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResearchDef {
+	pub id: String,
+	pub name: String,
+	/// Research points needed to complete it.
+	pub cost: u32,
+	/// Research that has to be completed first.
+	pub prerequisites: Vec<ResearchId>,
+	/// Buildings and recipes that become available once it is completed.
+	pub buildings: Vec<BuildingId>,
+	pub recipes: Vec<RecipeId>,
+}
+
+#[derive(Debug)]
+pub enum ContentError {
+	Io(PathBuf, io::Error),
+	Parse(PathBuf, toml::de::Error),
+	/// A value that parsed but makes no sense, like a reference to something that does not exist.
+	/// `field` is the path to it within the file, like `building[2].terrain[0]`.
+	Invalid {
+		file: PathBuf,
+		field: String,
+		message: String,
+	},
+}
+
+impl Display for ContentError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			ContentError::Io(path, error) => write!(f, "failed to read {}: {error}", path.display()),
+			ContentError::Parse(path, error) => write!(f, "{}: {error}", path.display()),
+			ContentError::Invalid { file, field, message } => write!(f, "{}: {field}: {message}", file.display()),
+		}
+	}
+}
+
+impl Error for ContentError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			ContentError::Io(_, error) => Some(error),
+			ContentError::Parse(_, error) => Some(error),
+			ContentError::Invalid { .. } => None,
+		}
+	}
+}
+
+/// String IDs of every definition, each mapped to its index in its file.
+#[derive(Clone, Debug, Default)]
+struct Ids {
+	terrain: HashMap<String, u16>,
+	items: HashMap<String, u16>,
+	buildings: HashMap<String, u16>,
+	recipes: HashMap<String, u16>,
+	research: HashMap<String, u16>,
+}
+
+/// Every building, item, recipe, terrain type and research topic in the game, read from the
+/// content files. Definitions refer to each other by string ID in the files and by numeric
+/// handle once loaded.
+#[derive(Clone, Debug)]
+pub struct Content {
+	terrain: Vec<TerrainDef>,
+	items: Vec<ItemDef>,
+	buildings: BuildingCatalog,
+	recipes: Vec<RecipeDef>,
+	research: Vec<ResearchDef>,
+	ids: Ids,
+}
+
+impl Content {
+	/// Reads and checks the content files in `dir`.
+	pub fn load(dir: &Path) -> Result<Self, ContentError> {
+		let terrain_path = dir.join(TERRAIN_FILE);
+		let items_path = dir.join(ITEMS_FILE);
+		let buildings_path = dir.join(BUILDINGS_FILE);
+		let recipes_path = dir.join(RECIPES_FILE);
+		let research_path = dir.join(RESEARCH_FILE);
+		let terrain: raw::TerrainFile = read(&terrain_path)?;
+		let items: raw::ItemFile = read(&items_path)?;
+		let buildings: raw::BuildingFile = read(&buildings_path)?;
+		let recipes: raw::RecipeFile = read(&recipes_path)?;
+		let research: raw::ResearchFile = read(&research_path)?;
+
+		// Every ID is known before any reference is resolved, so definitions can refer to ones
+		// further down or in other files.
+		let ids = Ids {
+			terrain: collect_ids(&terrain_path, "terrain", terrain.terrain.iter().map(|def| &def.id))?,
+			items: collect_ids(&items_path, "item", items.item.iter().map(|def| &def.id))?,
+			buildings: collect_ids(&buildings_path, "building", buildings.building.iter().map(|def| &def.id))?,
+			recipes: collect_ids(&recipes_path, "recipe", recipes.recipe.iter().map(|def| &def.id))?,
+			research: collect_ids(&research_path, "research", research.research.iter().map(|def| &def.id))?,
+		};
+
+		let terrain = Resolver::new(&ids, &terrain_path).terrain(terrain)?;
+		let items = Resolver::new(&ids, &items_path).items(items)?;
+		let buildings = Resolver::new(&ids, &buildings_path).buildings(buildings)?;
+		let recipes = Resolver::new(&ids, &recipes_path).recipes(recipes)?;
+		let research = Resolver::new(&ids, &research_path).research(research)?;
+		Ok(Self {
+			terrain,
+			items,
+			buildings,
+			recipes,
+			research,
+			ids,
+		})
+	}
+
+	pub fn terrain(&self, tile: Tile) -> Option<&TerrainDef> {
+		self.terrain.get((tile.0 as usize).checked_sub(1)?)
+	}
+
+	pub fn terrain_id(&self, id: &str) -> Option<Tile> {
+		self.ids.terrain.get(id).map(|&index| Tile(index + 1))
+	}
+
+	/// Terrain in tile order, starting at `Tile(1)`.
+	pub fn terrain_defs(&self) -> &[TerrainDef] {
+		&self.terrain
+	}
+
+	/// How slow `tile` is to walk through, `None` for tiles that cannot be walked on. Empty and
+	/// unknown tiles are open ground.
+	pub fn movement_cost(&self, tile: Tile) -> Option<u8> {
+		self.terrain(tile).map_or(Some(1), |terrain| terrain.movement_cost)
+	}
+
+	/// Colour of `tile`, transparent for empty and unknown tiles.
+	pub fn color(&self, tile: Tile) -> [u8; 4] {
+		self.terrain(tile).map_or([0; 4], |terrain| terrain.color)
+	}
+
+	pub fn item(&self, item: ItemId) -> Option<&ItemDef> {
+		self.items.get(item.0 as usize)
+	}
+
+	pub fn item_id(&self, id: &str) -> Option<ItemId> {
+		self.ids.items.get(id).copied().map(ItemId)
+	}
+
+	pub fn items(&self) -> impl Iterator<Item = (ItemId, &ItemDef)> {
+		self.items.iter().enumerate().map(|(index, def)| (ItemId(index as u16), def))
+	}
+
+	pub fn buildings(&self) -> &BuildingCatalog {
+		&self.buildings
+	}
+
+	pub fn building_id(&self, id: &str) -> Option<BuildingId> {
+		self.ids.buildings.get(id).copied().map(BuildingId)
+	}
+
+	pub fn recipe(&self, recipe: RecipeId) -> Option<&RecipeDef> {
+		self.recipes.get(recipe.0 as usize)
+	}
+
+	pub fn recipe_id(&self, id: &str) -> Option<RecipeId> {
+		self.ids.recipes.get(id).copied().map(RecipeId)
+	}
+
+	pub fn recipes(&self) -> impl Iterator<Item = (RecipeId, &RecipeDef)> {
+		self.recipes.iter().enumerate().map(|(index, def)| (RecipeId(index as u16), def))
+	}
+
+	pub fn research(&self, research: ResearchId) -> Option<&ResearchDef> {
+		self.research.get(research.0 as usize)
+	}
+
+	pub fn research_id(&self, id: &str) -> Option<ResearchId> {
+		self.ids.research.get(id).copied().map(ResearchId)
+	}
+}
+
+fn read<T: DeserializeOwned>(path: &Path) -> Result<T, ContentError> {
+	let contents = fs::read_to_string(path).map_err(|error| ContentError::Io(path.to_owned(), error))?;
+	toml::from_str(&contents).map_err(|error| ContentError::Parse(path.to_owned(), error))
+}
+
+fn invalid(file: &Path, field: impl Into<String>, message: impl Into<String>) -> ContentError {
+	ContentError::Invalid {
+		file: file.to_owned(),
+		field: field.into(),
+		message: message.into(),
+	}
+}
+
+fn collect_ids<'a>(
+	file: &Path,
+	section: &str,
+	ids: impl Iterator<Item = &'a String>,
+) -> Result<HashMap<String, u16>, ContentError> {
+	let mut map = HashMap::new();
+	for (index, id) in ids.enumerate() {
+		let field = format!("{section}[{index}].id");
+		if id.is_empty() {
+			return Err(invalid(file, field, "is empty"));
+		}
+		// One handle is kept free so terrain, which starts at 1, fits as well.
+		let index = u16::try_from(index).ok().filter(|&index| index < u16::MAX)
+			.ok_or_else(|| invalid(file, &field, format!("more than {} definitions", u16::MAX - 1)))?;
+		if map.insert(id.clone(), index).is_some() {
+			return Err(invalid(file, field, format!("\"{id}\" is defined more than once")));
+		}
+	}
+	Ok(map)
+}
+
+/// Turns the definitions of one file into their loaded form, naming the file in every error.
+struct Resolver<'a> {
+	ids: &'a Ids,
+	file: &'a Path,
+}
+
+impl<'a> Resolver<'a> {
+	fn new(ids: &'a Ids, file: &'a Path) -> Self {
+		Self { ids, file }
+	}
+
+	fn invalid(&self, field: impl Into<String>, message: impl Into<String>) -> ContentError {
+		invalid(self.file, field, message)
+	}
+
+	fn lookup(&self, ids: &HashMap<String, u16>, kind: &str, field: String, id: &str) -> Result<u16, ContentError> {
+		ids.get(id).copied().ok_or_else(|| self.invalid(field, format!("unknown {kind} \"{id}\"")))
+	}
+
+	fn terrain_id(&self, field: String, id: &str) -> Result<Tile, ContentError> {
+		self.lookup(&self.ids.terrain, "terrain", field, id).map(|index| Tile(index + 1))
+	}
+
+	fn item_id(&self, field: String, id: &str) -> Result<ItemId, ContentError> {
+		self.lookup(&self.ids.items, "item", field, id).map(ItemId)
+	}
+
+	fn building_id(&self, field: String, id: &str) -> Result<BuildingId, ContentError> {
+		self.lookup(&self.ids.buildings, "building", field, id).map(BuildingId)
+	}
+
+	fn recipe_id(&self, field: String, id: &str) -> Result<RecipeId, ContentError> {
+		self.lookup(&self.ids.recipes, "recipe", field, id).map(RecipeId)
+	}
+
+	fn research_id(&self, field: String, id: &str) -> Result<ResearchId, ContentError> {
+		self.lookup(&self.ids.research, "research", field, id).map(ResearchId)
+	}
+
+	/// Resolves every ID in the list at `field`.
+	fn list<T>(
+		&self,
+		field: String,
+		ids: &[String],
+		resolve: impl Fn(&Self, String, &str) -> Result<T, ContentError>,
+	) -> Result<Vec<T>, ContentError> {
+		ids.iter().enumerate().map(|(index, id)| resolve(self, format!("{field}[{index}]"), id)).collect()
+	}
+
+	fn terrain(&self, file: raw::TerrainFile) -> Result<Vec<TerrainDef>, ContentError> {
+		// The map generator places its tiles by index, so they have to be where it expects.
+		for (index, id) in tiles::NAMES.into_iter().enumerate() {
+			if self.ids.terrain.get(id) != Some(&(index as u16)) {
+				return Err(self.invalid(
+					format!("terrain[{index}]"),
+					format!("has to be \"{id}\", the map generator places it as tile {}", index + 1),
+				));
+			}
+		}
+
+		file.terrain.into_iter().enumerate()
+			.map(|(index, terrain)| {
+				if terrain.movement_cost == Some(0) {
+					return Err(self.invalid(
+						format!("terrain[{index}].movement_cost"),
+						"has to be at least 1, leave it out for terrain that cannot be walked on",
+					));
+				}
+				Ok(TerrainDef {
+					id: terrain.id,
+					name: terrain.name,
+					color: terrain.color,
+					movement_cost: terrain.movement_cost,
+				})
+			})
+			.collect()
+	}
+
+	fn items(&self, file: raw::ItemFile) -> Result<Vec<ItemDef>, ContentError> {
+		file.item.into_iter().enumerate()
+			.map(|(index, item)| {
+				if item.stack_size == 0 {
+					return Err(self.invalid(format!("item[{index}].stack_size"), "has to be at least 1"));
+				}
+				Ok(ItemDef {
+					id: item.id,
+					name: item.name,
+					stack_size: item.stack_size,
+				})
+			})
+			.collect()
+	}
+
+	fn buildings(&self, file: raw::BuildingFile) -> Result<BuildingCatalog, ContentError> {
+		let mut catalog = BuildingCatalog::new();
+		for (index, building) in file.building.into_iter().enumerate() {
+			let field = |name: &str| format!("building[{index}].{name}");
+
+			let footprint = match (building.size, building.footprint) {
+				(Some([width, height]), None) => {
+					if width == 0 || height == 0 {
+						return Err(self.invalid(field("size"), "has to be at least 1 by 1"));
+					}
+					BuildingDef::new("", [width, height]).footprint
+				}
+				(None, Some(footprint)) => {
+					if footprint.is_empty() {
+						return Err(self.invalid(field("footprint"), "is empty"));
+					}
+					for (tile, &offset) in footprint.iter().enumerate() {
+						if offset[0] < 0 || offset[1] < 0 {
+							return Err(self.invalid(format!("{}[{tile}]", field("footprint")), "cannot be negative"));
+						}
+						if footprint[..tile].contains(&offset) {
+							return Err(self.invalid(format!("{}[{tile}]", field("footprint")), "is listed twice"));
+						}
+					}
+					footprint
+				}
+				(Some(_), Some(_)) => return Err(self.invalid(field("size"), "cannot be given along with a footprint")),
+				(None, None) => return Err(self.invalid(field("size"), "is missing, give either a size or a footprint")),
+			};
+
+			let terrain = self.list(field("terrain"), &building.terrain, Self::terrain_id)?;
+
+			let mut adjacency = Vec::new();
+			for (rule, raw) in building.adjacency.iter().enumerate() {
+				let field = format!("{}[{rule}]", field("adjacency"));
+				adjacency.push(match raw {
+					raw::Adjacency::NextToTerrain(ids) => {
+						AdjacencyRule::NextToTerrain(self.list(format!("{field}.next_to_terrain"), ids, Self::terrain_id)?)
+					}
+					raw::Adjacency::NextToBuilding(id) => {
+						AdjacencyRule::NextToBuilding(self.building_id(format!("{field}.next_to_building"), id)?)
+					}
+					raw::Adjacency::AwayFromBuilding(id) => {
+						AdjacencyRule::AwayFromBuilding(self.building_id(format!("{field}.away_from_building"), id)?)
+					}
+				});
+			}
+
+			catalog.add(BuildingDef {
+				name: building.name,
+				footprint,
+				terrain,
+				adjacency,
+				color: building.color,
+			});
+		}
+		Ok(catalog)
+	}
+
+	fn item_counts(&self, field: String, counts: &[raw::ItemCount]) -> Result<Vec<ItemCount>, ContentError> {
+		counts.iter().enumerate()
+			.map(|(index, count)| {
+				let field = format!("{field}[{index}]");
+				if count.count == 0 {
+					return Err(self.invalid(format!("{field}.count"), "has to be at least 1"));
+				}
+				Ok(ItemCount {
+					item: self.item_id(format!("{field}.item"), &count.item)?,
+					count: count.count,
+				})
+			})
+			.collect()
+	}
+
+	fn recipes(&self, file: raw::RecipeFile) -> Result<Vec<RecipeDef>, ContentError> {
+		file.recipe.into_iter().enumerate()
+			.map(|(index, recipe)| {
+				let field = |name: &str| format!("recipe[{index}].{name}");
+				if recipe.outputs.is_empty() {
+					return Err(self.invalid(field("outputs"), "is empty"));
+				}
+				if recipe.duration.is_nan() || recipe.duration <= 0.0 {
+					return Err(self.invalid(field("duration"), "has to be a positive number of seconds"));
+				}
+				let duration = Duration::try_from_secs_f32(recipe.duration)
+					.map_err(|_| self.invalid(field("duration"), "is too long to count in seconds"))?;
+				if !recipe.power.is_finite() {
+					return Err(self.invalid(field("power"), "has to be a finite number of watts"));
+				}
+				if recipe.power < 0.0 {
+					return Err(self.invalid(field("power"), "cannot be negative"));
+				}
+				Ok(RecipeDef {
+					inputs: self.item_counts(field("inputs"), &recipe.inputs)?,
+					outputs: self.item_counts(field("outputs"), &recipe.outputs)?,
+					duration,
+					power: recipe.power,
+					building: recipe.building.as_deref()
+						.map(|id| self.building_id(field("building"), id))
+						.transpose()?,
+					id: recipe.id,
+					name: recipe.name,
+				})
+			})
+			.collect()
+	}
+
+	fn research(&self, file: raw::ResearchFile) -> Result<Vec<ResearchDef>, ContentError> {
+		let research = file.research.into_iter().enumerate()
+			.map(|(index, research)| {
+				let field = |name: &str| format!("research[{index}].{name}");
+				Ok(ResearchDef {
+					cost: research.cost,
+					prerequisites: self.list(field("prerequisites"), &research.prerequisites, Self::research_id)?,
+					buildings: self.list(field("buildings"), &research.buildings, Self::building_id)?,
+					recipes: self.list(field("recipes"), &research.recipes, Self::recipe_id)?,
+					id: research.id,
+					name: research.name,
+				})
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+
+		if let Some(index) = find_cycle(&research) {
+			return Err(self.invalid(
+				format!("research[{index}].prerequisites"),
+				format!("\"{}\" ends up depending on itself", research[index].id),
+			));
+		}
+		Ok(research)
+	}
+}
+
+/// Index of a research topic whose prerequisites lead back to it, if there is one.
+fn find_cycle(research: &[ResearchDef]) -> Option<usize> {
+	#[derive(Copy, Clone, PartialEq)]
+	enum State {
+		Unvisited,
+		/// On the path currently being followed.
+		Visiting,
+		Done,
+	}
+
+	fn visit(research: &[ResearchDef], states: &mut [State], index: usize) -> Option<usize> {
+		match states[index] {
+			State::Visiting => return Some(index),
+			State::Done => return None,
+			State::Unvisited => {}
+		}
+		states[index] = State::Visiting;
+		for prerequisite in &research[index].prerequisites {
+			if let Some(cycle) = visit(research, states, prerequisite.0 as usize) {
+				return Some(cycle);
+			}
+		}
+		states[index] = State::Done;
+		None
+	}
+
+	let mut states = vec![State::Unvisited; research.len()];
+	(0..research.len()).find_map(|index| visit(research, &mut states, index))
+}
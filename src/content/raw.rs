@@ -0,0 +1,111 @@
+//! Content files as written, before string IDs are resolved and values checked.
+
+use serde::Deserialize;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TerrainFile {
+	pub terrain: Vec<Terrain>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Terrain {
+	pub id: String,
+	pub name: String,
+	pub color: [u8; 4],
+	/// Left out for terrain that cannot be walked on.
+	pub movement_cost: Option<u8>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ItemFile {
+	pub item: Vec<Item>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Item {
+	pub id: String,
+	pub name: String,
+	pub stack_size: u32,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BuildingFile {
+	pub building: Vec<Building>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Building {
+	pub id: String,
+	pub name: String,
+	/// Rectangular footprint, for buildings without a `footprint`.
+	pub size: Option<[u32; 2]>,
+	pub footprint: Option<Vec<[i32; 2]>>,
+	#[serde(default)]
+	pub terrain: Vec<String>,
+	#[serde(default)]
+	pub adjacency: Vec<Adjacency>,
+	pub color: [u8; 4],
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Adjacency {
+	NextToTerrain(Vec<String>),
+	NextToBuilding(String),
+	AwayFromBuilding(String),
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecipeFile {
+	pub recipe: Vec<Recipe>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Recipe {
+	pub id: String,
+	pub name: String,
+	#[serde(default)]
+	pub inputs: Vec<ItemCount>,
+	pub outputs: Vec<ItemCount>,
+	/// Seconds.
+	pub duration: f32,
+	/// Watts drawn while crafting.
+	#[serde(default)]
+	pub power: f32,
+	pub building: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ItemCount {
+	pub item: String,
+	pub count: u32,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ResearchFile {
+	pub research: Vec<Research>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Research {
+	pub id: String,
+	pub name: String,
+	pub cost: u32,
+	#[serde(default)]
+	pub prerequisites: Vec<String>,
+	#[serde(default)]
+	pub buildings: Vec<String>,
+	#[serde(default)]
+	pub recipes: Vec<String>,
+}
@@ -0,0 +1,746 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::building::{self, Occupancy, PlacementError, Rotation};
+use crate::content::{Content, ItemId};
+use crate::crafting::Crafter;
+use crate::economy::{self, Resource, Stockpile};
+use crate::ecs::{Entity, World};
+use crate::logistics;
+use crate::save::{Persist, Reader, SaveError, Writer};
+use crate::tilemap::Tilemap;
+
+/// Steps a belt tile is divided into for placing stacks along it.
+pub const SUBTILES: u32 = 240;
+/// Tiles a belt moves its stacks per second.
+pub const BELT_SPEED: u32 = 2;
+/// Closest two stacks sit on a belt, in [`SUBTILES`], so four fit on a tile.
+pub const STACK_SPACING: u32 = SUBTILES / 4;
+/// Time an inserter takes to move one stack.
+pub const INSERTER_SWING: Duration = Duration::from_millis(750);
+
+fn neighbour([x, y]: [i32; 2], direction: Rotation) -> [i32; 2] {
+	let [dx, dy] = direction.offset();
+	[x + dx, y + dy]
+}
+
+/// Some of one item travelling on a belt.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Stack {
+	pub item: ItemId,
+	pub amount: u64,
+}
+
+impl Stack {
+	fn save(self, writer: &mut Writer) {
+		writer.varint(self.item.0 as u64);
+		writer.varint(self.amount);
+	}
+
+	fn load(reader: &mut Reader) -> Result<Self, SaveError> {
+		let item = u16::try_from(reader.varint()?).map_err(|_| SaveError::Corrupt("item out of range".into()))?;
+		Ok(Self {
+			item: ItemId(item),
+			amount: reader.varint()?,
+		})
+	}
+}
+
+/// What a belt tile does with the stacks reaching its end.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum BeltKind {
+	/// Moves stacks forward. Takes them from the belt behind it, or turns a corner when a single
+	/// belt feeds it from the side instead.
+	#[default]
+	Straight,
+	/// Takes stacks from behind and hands them out to its left and right in turn, or to
+	/// whichever side has room.
+	Splitter,
+	/// Takes stacks from behind, its left and its right in turn and moves them forward.
+	Merger,
+}
+
+impl BeltKind {
+	const ALL: [BeltKind; 3] = [BeltKind::Straight, BeltKind::Splitter, BeltKind::Merger];
+}
+
+/// One tile of conveyor belt, a directed lane moving stacks along at [`BELT_SPEED`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Belt {
+	pub tile: [i32; 2],
+	/// Way stacks move along it.
+	pub direction: Rotation,
+	pub kind: BeltKind,
+}
+
+impl Belt {
+	pub fn new(tile: [i32; 2], direction: Rotation, kind: BeltKind) -> Self {
+		Self { tile, direction, kind }
+	}
+
+	/// Tiles it hands stacks on to.
+	fn outputs(&self) -> Vec<[i32; 2]> {
+		match self.kind {
+			BeltKind::Splitter => vec![
+				neighbour(self.tile, self.direction.counter_clockwise()),
+				neighbour(self.tile, self.direction.clockwise()),
+			],
+			BeltKind::Straight | BeltKind::Merger => vec![neighbour(self.tile, self.direction)],
+		}
+	}
+}
+
+impl Persist for Belt {
+	fn save(&self, writer: &mut Writer) {
+		writer.i32(self.tile[0]);
+		writer.i32(self.tile[1]);
+		writer.u8(self.direction as u8);
+		writer.u8(self.kind as u8);
+	}
+
+	fn load(reader: &mut Reader) -> Result<Self, SaveError> {
+		let tile = [reader.i32()?, reader.i32()?];
+		let direction = *Rotation::ALL.get(reader.u8()? as usize)
+			.ok_or_else(|| SaveError::Corrupt("unknown belt direction".into()))?;
+		let kind = *BeltKind::ALL.get(reader.u8()? as usize)
+			.ok_or_else(|| SaveError::Corrupt("unknown belt kind".into()))?;
+		Ok(Self { tile, direction, kind })
+	}
+}
+
+/// Moves stacks from the tile behind it to the tile in front, one every [`INSERTER_SWING`].
+/// Either side can be a belt or a building with a [`Crafter`] or [`Stockpile`], which it takes
+/// from the output buffer of and delivers into the input buffer of.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Inserter {
+	pub tile: [i32; 2],
+	/// Way stacks are moved.
+	pub direction: Rotation,
+	/// Most it moves in one swing.
+	pub stack: u64,
+	/// Ticks until it can swing again.
+	cooldown: u64,
+}
+
+impl Inserter {
+	/// An inserter moving one item a swing.
+	pub fn new(tile: [i32; 2], direction: Rotation) -> Self {
+		Self {
+			tile,
+			direction,
+			stack: 1,
+			cooldown: 0,
+		}
+	}
+
+	/// Moves up to `stack` of an item a swing.
+	pub fn with_stack(mut self, stack: u64) -> Self {
+		self.stack = stack.max(1);
+		self
+	}
+}
+
+impl Persist for Inserter {
+	fn save(&self, writer: &mut Writer) {
+		writer.i32(self.tile[0]);
+		writer.i32(self.tile[1]);
+		writer.u8(self.direction as u8);
+		writer.varint(self.stack);
+		writer.varint(self.cooldown);
+	}
+
+	fn load(reader: &mut Reader) -> Result<Self, SaveError> {
+		let tile = [reader.i32()?, reader.i32()?];
+		let direction = *Rotation::ALL.get(reader.u8()? as usize)
+			.ok_or_else(|| SaveError::Corrupt("unknown inserter direction".into()))?;
+		Ok(Self {
+			tile,
+			direction,
+			stack: reader.varint()?,
+			cooldown: reader.varint()?,
+		})
+	}
+}
+
+/// Belts moving stacks in step: a run of straight belts each feeding the next, or a single
+/// splitter or merger.
+#[derive(Clone, Debug)]
+struct Segment {
+	kind: BeltKind,
+	/// From the start of the run to its end.
+	belts: Vec<Entity>,
+	/// Front first, each with its distance to the stack ahead, or to the end for the first. Only
+	/// the gaps change as stacks move, and only up to the first one with room to close.
+	stacks: VecDeque<(Stack, u32)>,
+	/// Segments stacks reaching the end are handed to.
+	outputs: Vec<usize>,
+	/// Segments handing it stacks, behind first.
+	inputs: Vec<usize>,
+	/// Output a splitter hands the next stack to, or input a merger takes it from.
+	turn: usize,
+}
+
+impl Segment {
+	fn length(&self) -> u32 {
+		self.belts.len() as u32 * SUBTILES
+	}
+
+	/// Distance from the end to the last stack.
+	fn tail(&self) -> u32 {
+		self.stacks.iter().map(|&(_, gap)| gap).sum()
+	}
+
+	fn has_room(&self) -> bool {
+		self.stacks.is_empty() || self.length().saturating_sub(self.tail()) >= STACK_SPACING
+	}
+
+	/// Whether a stack is at the end, waiting to be handed on.
+	fn is_waiting(&self) -> bool {
+		matches!(self.stacks.front(), Some(&(_, 0)))
+	}
+
+	/// Stacks, front first, with their distance from the end.
+	fn distances(&self) -> impl Iterator<Item = (Stack, u32)> + '_ {
+		self.stacks.iter().scan(0, |distance, &(stack, gap)| {
+			*distance += gap;
+			Some((stack, *distance))
+		})
+	}
+
+	/// Belt a stack `distance` from the end is on, and its distance from the end of that belt.
+	fn locate(&self, distance: u32) -> (usize, u32) {
+		let last = self.belts.len() - 1;
+		let index = last - (distance / SUBTILES).min(last as u32) as usize;
+		(index, distance - (last - index) as u32 * SUBTILES)
+	}
+
+	/// Distances from the end the `index`th belt covers. The first also covers anything pushed
+	/// back past the start.
+	fn span(&self, index: usize) -> (u32, u32) {
+		let start = (self.belts.len() - 1 - index) as u32 * SUBTILES;
+		(start, if index == 0 { u32::MAX } else { start + SUBTILES })
+	}
+
+	/// Puts `stack` at `distance` from the end, behind any stacks at the same distance.
+	fn place(&mut self, stack: Stack, distance: u32) {
+		let (mut ahead, mut index) = (0, 0);
+		while let Some(&(_, gap)) = self.stacks.get(index) {
+			if ahead + gap > distance {
+				break;
+			}
+			ahead += gap;
+			index += 1;
+		}
+		if let Some((_, gap)) = self.stacks.get_mut(index) {
+			*gap = ahead + *gap - distance;
+		}
+		self.stacks.insert(index, (stack, distance - ahead));
+	}
+
+	/// Moves every stack up to `step` towards the end, as far as the stack ahead lets it.
+	fn advance(&mut self, step: u32) {
+		// How far the stack ahead moved. Once one moves the whole step, so does everything behind.
+		let mut moved = 0;
+		for (index, (_, gap)) in self.stacks.iter_mut().enumerate() {
+			let closest = if index == 0 { 0 } else { STACK_SPACING };
+			let distance = step.min((*gap + moved).saturating_sub(closest));
+			*gap = *gap + moved - distance;
+			if distance == step {
+				break;
+			}
+			moved = distance;
+		}
+	}
+}
+
+/// Every belt on the map grouped into [`Segment`]s, with the stacks on them. Stored as a world
+/// resource and regrouped whenever a belt is laid or removed.
+///
+/// Only the gaps between stacks are stored, so moving a run of belts costs as much as the
+/// stacks at its front that are queued up against the end, however long it is.
+#[derive(Clone, Debug, Default)]
+pub struct BeltNetwork {
+	segments: Vec<Segment>,
+	/// Segment of every belt, and its place from the segment's start.
+	belts: BTreeMap<Entity, (usize, usize)>,
+	tiles: BTreeMap<[i32; 2], Entity>,
+	/// Stacks waiting to be put back on a segment, with their belt and distance from the end of
+	/// it.
+	loose: Vec<(Entity, u32, Stack)>,
+	/// Turns of splitters and mergers waiting to be put back, by belt.
+	turns: BTreeMap<Entity, usize>,
+	/// Whether it has to be regrouped before the next tick.
+	stale: bool,
+}
+
+impl BeltNetwork {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Regroups on the next tick, for when [`Belt`] components were changed directly.
+	pub fn invalidate(&mut self) {
+		self.stale = true;
+	}
+
+	pub fn belt_at(&self, tile: [i32; 2]) -> Option<Entity> {
+		self.tiles.get(&tile).copied()
+	}
+
+	/// Index of the segment `belt` is grouped into.
+	pub fn segment_of(&self, belt: Entity) -> Option<usize> {
+		self.belts.get(&belt).map(|&(segment, _)| segment)
+	}
+
+	pub fn segment_count(&self) -> usize {
+		self.segments.len()
+	}
+
+	/// Stacks on the belt at `tile`, front first.
+	pub fn stacks_at(&self, tile: [i32; 2]) -> Vec<Stack> {
+		let Some((segment, (start, end))) = self.span_at(tile) else {
+			return Vec::new();
+		};
+		segment.distances()
+			.filter(|&(_, distance)| (start..end).contains(&distance))
+			.map(|(stack, _)| stack)
+			.collect()
+	}
+
+	/// Where every stack is drawn, in world units.
+	pub fn stack_positions(&self, world: &World) -> Vec<([f32; 2], Stack)> {
+		let mut positions = Vec::new();
+		for segment in &self.segments {
+			for (stack, distance) in segment.distances() {
+				let (index, to_end) = segment.locate(distance);
+				let Some(belt) = world.get::<Belt>(segment.belts[index]) else {
+					continue;
+				};
+				let along = 0.5 - to_end.min(SUBTILES) as f32 / SUBTILES as f32;
+				let [dx, dy] = belt.direction.offset();
+				let [x, y] = belt.tile;
+				positions.push(([x as f32 + 0.5 + dx as f32 * along, y as f32 + 0.5 + dy as f32 * along], stack));
+			}
+		}
+		positions
+	}
+
+	/// Puts `stack` in the middle of the belt at `tile`, if no other stack is too close.
+	pub fn insert(&mut self, tile: [i32; 2], stack: Stack) -> bool {
+		let Some((segment, distance)) = self.insertion_point(tile) else {
+			return false;
+		};
+		self.segments[segment].place(stack, distance);
+		true
+	}
+
+	/// Takes up to `most` of the stack nearest the end of the belt at `tile`.
+	pub fn take(&mut self, tile: [i32; 2], most: u64) -> Option<Stack> {
+		let (segment, (start, end)) = self.span_at(tile)?;
+		let index = segment.distances().position(|(_, distance)| (start..end).contains(&distance))?;
+		let segment = &mut self.segments[self.belts[&self.tiles[&tile]].0];
+		let (stack, _) = &mut segment.stacks[index];
+		if stack.amount > most {
+			stack.amount -= most;
+			return Some(Stack { amount: most, ..*stack });
+		}
+		let (stack, gap) = segment.stacks.remove(index).expect("found above");
+		if let Some((_, next)) = segment.stacks.get_mut(index) {
+			*next += gap;
+		}
+		Some(stack)
+	}
+
+	/// The stack [`take`](Self::take) would take from.
+	pub fn peek(&self, tile: [i32; 2]) -> Option<Stack> {
+		self.stacks_at(tile).first().copied()
+	}
+
+	fn span_at(&self, tile: [i32; 2]) -> Option<(&Segment, (u32, u32))> {
+		let &(segment, index) = self.belts.get(self.tiles.get(&tile)?)?;
+		let segment = &self.segments[segment];
+		Some((segment, segment.span(index)))
+	}
+
+	/// Segment and distance from its end of the middle of the belt at `tile`, if a stack fits
+	/// there.
+	fn insertion_point(&self, tile: [i32; 2]) -> Option<(usize, u32)> {
+		let &(index, belt) = self.belts.get(self.tiles.get(&tile)?)?;
+		let segment = &self.segments[index];
+		let distance = segment.span(belt).0 + SUBTILES / 2;
+		let clear = segment.distances().all(|(_, other)| other.abs_diff(distance) >= STACK_SPACING);
+		clear.then_some((index, distance))
+	}
+
+	/// Takes every stack and turn off the segments, leaving them to be put back by
+	/// [`rebuild`](Self::rebuild).
+	fn unload(&mut self) {
+		for segment in self.segments.drain(..) {
+			for (stack, distance) in segment.distances() {
+				let (index, to_end) = segment.locate(distance);
+				self.loose.push((segment.belts[index], to_end, stack));
+			}
+			if segment.kind != BeltKind::Straight {
+				self.turns.insert(segment.belts[0], segment.turn);
+			}
+		}
+		self.belts.clear();
+		self.tiles.clear();
+	}
+
+	/// Groups the [`Belt`]s of `world` into segments and puts the stacks back on them.
+	pub(crate) fn rebuild(&mut self, world: &World) {
+		self.unload();
+		let belts = world.query::<Belt>().map(|(entity, &belt)| (entity, belt)).collect::<BTreeMap<_, _>>();
+		self.tiles = belts.iter().map(|(&entity, belt)| (belt.tile, entity)).collect();
+
+		// Belts each one takes stacks from, behind first.
+		let feeds = |belt: &Belt, side: Rotation| {
+			let &entity = self.tiles.get(&neighbour(belt.tile, side))?;
+			belts[&entity].outputs().contains(&belt.tile).then_some(entity)
+		};
+		let mut inputs = BTreeMap::new();
+		for (&entity, belt) in &belts {
+			let behind = feeds(belt, belt.direction.clockwise().clockwise());
+			let sides = [belt.direction.counter_clockwise(), belt.direction.clockwise()]
+				.into_iter()
+				.filter_map(|side| feeds(belt, side))
+				.collect::<Vec<_>>();
+			let accepted = match belt.kind {
+				BeltKind::Merger => behind.into_iter().chain(sides).collect(),
+				BeltKind::Straight if behind.is_none() && sides.len() == 1 => sides,
+				BeltKind::Straight | BeltKind::Splitter => behind.into_iter().collect::<Vec<_>>(),
+			};
+			inputs.insert(entity, accepted);
+		}
+		let targets = |entity: Entity| {
+			belts[&entity].outputs().iter()
+				.filter_map(|tile| self.tiles.get(tile).copied())
+				.filter(|target| inputs[target].contains(&entity))
+				.collect::<Vec<_>>()
+		};
+
+		// Straight belts feeding straight belts move in step.
+		let mut next = BTreeMap::new();
+		for (&entity, belt) in &belts {
+			if let (BeltKind::Straight, [target]) = (belt.kind, targets(entity).as_slice()) {
+				if belts[target].kind == BeltKind::Straight {
+					next.insert(entity, *target);
+				}
+			}
+		}
+		let fed = next.values().copied().collect::<BTreeSet<_>>();
+		// Runs from their first belt, then loops from their earliest belt.
+		let starts = belts.keys().filter(|entity| !fed.contains(entity)).chain(belts.keys());
+		let mut runs = Vec::new();
+		for &start in starts {
+			if self.belts.contains_key(&start) {
+				continue;
+			}
+			let mut run = vec![start];
+			self.belts.insert(start, (runs.len(), 0));
+			while let Some(&belt) = next.get(run.last().expect("never empty")) {
+				if self.belts.contains_key(&belt) {
+					break;
+				}
+				self.belts.insert(belt, (runs.len(), run.len()));
+				run.push(belt);
+			}
+			runs.push(run);
+		}
+
+		for belts_in_run in runs {
+			let (first, last) = (belts_in_run[0], *belts_in_run.last().expect("never empty"));
+			self.segments.push(Segment {
+				kind: belts[&first].kind,
+				outputs: targets(last).iter().map(|target| self.belts[target].0).collect(),
+				inputs: inputs[&first].iter().map(|input| self.belts[input].0).collect(),
+				turn: self.turns.remove(&first).unwrap_or(0),
+				belts: belts_in_run,
+				stacks: VecDeque::new(),
+			});
+		}
+		self.turns.clear();
+
+		for (belt, to_end, stack) in std::mem::take(&mut self.loose) {
+			let Some(&(segment, index)) = self.belts.get(&belt) else {
+				continue;
+			};
+			let segment = &mut self.segments[segment];
+			let distance = segment.span(index).0 + to_end;
+			segment.place(stack, distance);
+		}
+		for segment in &mut self.segments {
+			for (_, gap) in segment.stacks.iter_mut().skip(1) {
+				*gap = (*gap).max(STACK_SPACING);
+			}
+		}
+		self.stale = false;
+	}
+
+	/// Moves every stack on by one tick and hands those at the end of a segment on.
+	pub(crate) fn advance(&mut self, dt: Duration) {
+		let per_second = (BELT_SPEED * SUBTILES) as u128;
+		let step = ((per_second * dt.as_nanos() + 500_000_000) / 1_000_000_000) as u32;
+		for segment in &mut self.segments {
+			segment.advance(step);
+		}
+		for index in 0..self.segments.len() {
+			self.hand_on(index);
+		}
+	}
+
+	/// Hands the stack waiting at the end of the `from` segment to the next segment with room.
+	fn hand_on(&mut self, from: usize) {
+		let source = &self.segments[from];
+		if !source.is_waiting() {
+			return;
+		}
+		let count = source.outputs.len();
+		let first = if source.kind == BeltKind::Splitter { source.turn } else { 0 };
+		for choice in (first..first + count).map(|choice| choice % count) {
+			let to = self.segments[from].outputs[choice];
+			if !self.accepts(to, from) {
+				continue;
+			}
+			let source = &mut self.segments[from];
+			let (stack, _) = source.stacks.pop_front().expect("waiting");
+			if source.kind == BeltKind::Splitter {
+				source.turn = (choice + 1) % count;
+			}
+			let target = &mut self.segments[to];
+			let distance = target.length();
+			target.place(stack, distance);
+			if target.kind == BeltKind::Merger {
+				if let Some(input) = target.inputs.iter().position(|&input| input == from) {
+					target.turn = (input + 1) % target.inputs.len();
+				}
+			}
+			return;
+		}
+	}
+
+	/// Whether the `to` segment takes a stack from `from` now. A merger waits for the input
+	/// whose turn it is, unless that has nothing to hand it.
+	fn accepts(&self, to: usize, from: usize) -> bool {
+		let target = &self.segments[to];
+		if !target.has_room() {
+			return false;
+		}
+		if target.kind != BeltKind::Merger || target.inputs.is_empty() {
+			return true;
+		}
+		let turn = target.inputs[target.turn % target.inputs.len()];
+		let waiting = self.segments[turn].is_waiting() && self.segments[turn].outputs.contains(&to);
+		turn == from || !waiting
+	}
+}
+
+impl Persist for BeltNetwork {
+	fn save(&self, writer: &mut Writer) {
+		let mut network = self.clone();
+		network.unload();
+		writer.varint(network.loose.len() as u64);
+		for &(belt, to_end, stack) in &network.loose {
+			writer.entity(belt);
+			writer.varint(to_end as u64);
+			stack.save(writer);
+		}
+		writer.varint(network.turns.len() as u64);
+		for (&belt, &turn) in &network.turns {
+			writer.entity(belt);
+			writer.varint(turn as u64);
+		}
+	}
+
+	fn load(reader: &mut Reader) -> Result<Self, SaveError> {
+		let mut network = Self::new();
+		for _ in 0..reader.varint()? {
+			let belt = reader.entity()?;
+			let to_end = reader.varint_u32()?;
+			network.loose.push((belt, to_end, Stack::load(reader)?));
+		}
+		for _ in 0..reader.varint()? {
+			network.turns.insert(reader.entity()?, reader.varint()? as usize);
+		}
+		network.stale = true;
+		Ok(network)
+	}
+}
+
+/// Either side of an inserter.
+#[derive(Copy, Clone)]
+enum End {
+	Belt([i32; 2]),
+	Building(Entity),
+}
+
+fn end_at(world: &World, network: &BeltNetwork, tile: [i32; 2]) -> Option<End> {
+	if network.belt_at(tile).is_some() {
+		return Some(End::Belt(tile));
+	}
+	let entity = world.resource::<Occupancy>()?.get(tile)?;
+	(world.has::<Crafter>(entity) || world.has::<Stockpile>(entity)).then_some(End::Building(entity))
+}
+
+/// How much of `item` an inserter can put down at `end`, as [`logistics::deliver`] would take
+/// it into a building.
+fn room(world: &World, network: &BeltNetwork, content: Option<&Content>, end: End, item: ItemId) -> u64 {
+	match end {
+		End::Belt(tile) => if network.insertion_point(tile).is_some() { u64::MAX } else { 0 },
+		End::Building(entity) => match world.get::<Crafter>(entity) {
+			Some(crafter) => content.and_then(|content| content.recipe(crafter.recipe))
+				.map_or(0, |recipe| crafter.room_for(recipe, item)),
+			None => world.get::<Stockpile>(entity).map_or(0, Stockpile::free),
+		},
+	}
+}
+
+/// Moves up to `most` of one item from `from` to `to`. Returns whether anything moved.
+fn swing(world: &mut World, network: &mut BeltNetwork, content: Option<&Content>, from: End, to: End, most: u64) -> bool {
+	let (item, amount) = match from {
+		End::Belt(tile) => match network.peek(tile) {
+			Some(stack) => (stack.item, stack.amount),
+			None => return false,
+		},
+		End::Building(entity) => {
+			let offered = match world.get::<Crafter>(entity) {
+				Some(crafter) => &crafter.output,
+				None => world.get::<Stockpile>(entity).expect("buildings at an end have one"),
+			};
+			let found = offered.iter().find_map(|(resource, amount)| match resource {
+				Resource::Item(item) if room(world, network, content, to, item) > 0 => Some((item, amount)),
+				_ => None,
+			});
+			match found {
+				Some(found) => found,
+				None => return false,
+			}
+		}
+	};
+	let amount = amount.min(most).min(room(world, network, content, to, item));
+	if amount == 0 {
+		return false;
+	}
+	let taken = match from {
+		End::Belt(tile) => network.take(tile, amount).map_or(0, |stack| stack.amount),
+		End::Building(entity) => logistics::pick_up(world, entity, Resource::Item(item), amount),
+	};
+	match to {
+		End::Belt(tile) => network.insert(tile, Stack { item, amount: taken }),
+		End::Building(entity) => logistics::deliver(world, entity, Resource::Item(item), taken) == taken,
+	}
+}
+
+/// Regroups the network if it is stale, moves every stack along and swings every inserter that
+/// is ready, in entity order.
+pub(crate) fn tick(world: &mut World, dt: Duration) {
+	let Some(mut network) = world.remove_resource::<BeltNetwork>() else {
+		return;
+	};
+	if network.stale {
+		network.rebuild(world);
+	}
+	network.advance(dt);
+
+	let content = world.resource::<Arc<Content>>().cloned();
+	let inserters = world.query::<Inserter>().map(|(entity, _)| entity).collect::<Vec<_>>();
+	for entity in inserters {
+		let inserter = world.get_mut::<Inserter>(entity).expect("queried above");
+		if inserter.cooldown > 0 {
+			inserter.cooldown -= 1;
+			continue;
+		}
+		let Inserter { tile, direction, stack, .. } = *inserter;
+		let from = end_at(world, &network, neighbour(tile, direction.clockwise().clockwise()));
+		let to = end_at(world, &network, neighbour(tile, direction));
+		let (Some(from), Some(to)) = (from, to) else {
+			continue;
+		};
+		if swing(world, &mut network, content.as_deref(), from, to, stack) {
+			world.get_mut::<Inserter>(entity).expect("queried above").cooldown = economy::ticks_in(INSERTER_SWING, dt) - 1;
+		}
+	}
+	world.insert_resource(network);
+}
+
+/// Spawns an entity reserving `tile`, if it is on the map, free and walkable ground.
+///
+/// Colonists step over belts and inserters, so unlike buildings they leave the path cost of
+/// their tile as it is.
+fn reserve(world: &mut World, tilemap: &Tilemap, tile: [i32; 2]) -> Result<Entity, PlacementError> {
+	let Some(terrain) = tilemap.get(tile[0], tile[1]) else {
+		return Err(PlacementError::OutsideMap(tile));
+	};
+	if building::terrain_cost(world, terrain).is_none() {
+		return Err(PlacementError::WrongTerrain(tile, terrain));
+	}
+	if let Some(other) = world.resource::<Occupancy>().and_then(|occupancy| occupancy.get(tile)) {
+		return Err(PlacementError::Occupied(tile, other));
+	}
+	let entity = world.spawn();
+	match world.resource_mut::<Occupancy>() {
+		Some(occupancy) => occupancy.reserve(&[tile], entity),
+		None => {
+			let mut occupancy = Occupancy::new(tilemap.width(), tilemap.height());
+			occupancy.reserve(&[tile], entity);
+			world.insert_resource(occupancy);
+		}
+	}
+	Ok(entity)
+}
+
+/// Lays `belt` on its tile and regroups the [`BeltNetwork`] around it.
+pub fn place_belt(world: &mut World, tilemap: &Tilemap, belt: Belt) -> Result<Entity, PlacementError> {
+	let entity = reserve(world, tilemap, belt.tile)?;
+	world.insert(entity, belt);
+	let mut network = world.remove_resource::<BeltNetwork>().unwrap_or_default();
+	network.rebuild(world);
+	world.insert_resource(network);
+	Ok(entity)
+}
+
+/// Places `inserter` on its tile.
+pub fn place_inserter(world: &mut World, tilemap: &Tilemap, inserter: Inserter) -> Result<Entity, PlacementError> {
+	let entity = reserve(world, tilemap, inserter.tile)?;
+	world.insert(entity, inserter);
+	Ok(entity)
+}
+
+/// Removes a belt or inserter, freeing its tile. Returns the stacks that were on it, `None` if
+/// `entity` is neither.
+pub fn remove(world: &mut World, entity: Entity) -> Option<Vec<Stack>> {
+	let tile = match world.remove::<Belt>(entity) {
+		Some(belt) => belt.tile,
+		None => world.remove::<Inserter>(entity)?.tile,
+	};
+	if let Some(occupancy) = world.resource_mut::<Occupancy>() {
+		occupancy.release(&[tile], entity);
+	}
+	let mut stacks = Vec::new();
+	if let Some(mut network) = world.remove_resource::<BeltNetwork>() {
+		network.unload();
+		network.loose.retain(|&(belt, _, stack)| {
+			if belt == entity {
+				stacks.push(stack);
+			}
+			belt != entity
+		});
+		network.rebuild(world);
+		world.insert_resource(network);
+	}
+	world.despawn(entity);
+	Some(stacks)
+}
+
+/// Reserves the tiles of every belt and inserter in `world`, which [`Occupancy::from_world`]
+/// leaves out.
+pub(crate) fn occupy(world: &World, occupancy: &mut Occupancy) {
+	for (entity, belt) in world.query::<Belt>() {
+		occupancy.reserve(&[belt.tile], entity);
+	}
+	for (entity, inserter) in world.query::<Inserter>() {
+		occupancy.reserve(&[inserter.tile], entity);
+	}
+}
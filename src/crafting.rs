@@ -0,0 +1,189 @@
+use std::time::Duration;
+
+use crate::content::{Content, ItemCount, ItemId, RecipeDef, RecipeId};
+use crate::economy::{self, Ledger, Resource, Stockpile};
+use crate::ecs::{Entity, World};
+use crate::save::{Persist, Reader, SaveError, Writer};
+
+/// Crafts' worth of inputs and outputs a [`Crafter`]'s buffers hold.
+pub const BUFFER_CRAFTS: u64 = 2;
+
+/// Energy in kilojoules, the unit of [`Resource::Power`], a recipe draws over one craft.
+pub fn energy(recipe: &RecipeDef) -> u64 {
+	// Rounded to whole joules first, so durations that are not exact in binary do not add a
+	// kilojoule.
+	let joules = (recipe.power as f64 * recipe.duration.as_secs_f64()).round() as u64;
+	joules.div_ceil(1000)
+}
+
+fn total(counts: &[ItemCount]) -> u64 {
+	counts.iter().map(|count| count.count as u64).sum()
+}
+
+/// How a [`Crafter`] fared on its last tick.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum CraftingStatus {
+	/// Has not run yet, or its recipe is not in the loaded content.
+	#[default]
+	Idle,
+	Crafting,
+	/// Waiting to start a craft until its input buffer has enough of something, or its
+	/// entity's [`Stockpile`] enough power.
+	Starved(Resource),
+	/// Done with a craft but waiting for room in its output buffer.
+	Blocked(Resource),
+}
+
+/// A production building running one recipe from the loaded [`Content`] over and over.
+///
+/// Each craft takes the recipe's inputs from the input buffer and its energy from the
+/// [`Stockpile`] on the same entity when it starts, and puts the outputs in the output buffer
+/// once the recipe's duration has passed. Outputs are moved on to the building `output_to`,
+/// so buildings can be chained, ore to plates to gears.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Crafter {
+	pub recipe: RecipeId,
+	pub input: Stockpile,
+	pub output: Stockpile,
+	/// Crafter or stockpile entity the outputs are delivered to.
+	pub output_to: Option<Entity>,
+	pub status: CraftingStatus,
+	/// Ticks into the craft in progress, `None` between crafts.
+	progress: Option<u64>,
+}
+
+impl Crafter {
+	/// A crafter for `recipe` with buffers sized to [`BUFFER_CRAFTS`] crafts, `None` if the
+	/// recipe is unknown.
+	pub fn new(content: &Content, recipe: RecipeId) -> Option<Self> {
+		let def = content.recipe(recipe)?;
+		Some(Self {
+			recipe,
+			input: Stockpile::new(total(&def.inputs) * BUFFER_CRAFTS),
+			output: Stockpile::new(total(&def.outputs) * BUFFER_CRAFTS),
+			output_to: None,
+			status: CraftingStatus::Idle,
+			progress: None,
+		})
+	}
+
+	/// Delivers outputs to `target`.
+	pub fn output_to(mut self, target: Entity) -> Self {
+		self.output_to = Some(target);
+		self
+	}
+
+	/// Ticks into the craft in progress, `None` between crafts.
+	pub fn progress(&self) -> Option<u64> {
+		self.progress
+	}
+
+	/// How much of `item` the input buffer takes, at most [`BUFFER_CRAFTS`] crafts' worth of
+	/// each input so one input cannot crowd out the others.
+	pub fn room_for(&self, recipe: &RecipeDef, item: ItemId) -> u64 {
+		let needed = recipe.inputs.iter().filter(|input| input.item == item).map(|input| input.count as u64).sum::<u64>();
+		(needed * BUFFER_CRAFTS).saturating_sub(self.input.get(Resource::Item(item))).min(self.input.free())
+	}
+
+	/// Runs one tick of `recipe`, drawing power from `power`, noting what moved in `ledger`.
+	pub(crate) fn tick(&mut self, recipe: &RecipeDef, power: Option<&mut Stockpile>, ledger: &mut Ledger, dt: Duration) {
+		if let Some(progress) = &mut self.progress {
+			let ticks = economy::ticks_in(recipe.duration, dt);
+			*progress = (*progress + 1).min(ticks);
+			if *progress < ticks {
+				self.status = CraftingStatus::Crafting;
+				return;
+			}
+			if total(&recipe.outputs) > self.output.free() {
+				self.status = CraftingStatus::Blocked(Resource::Item(recipe.outputs[0].item));
+				return;
+			}
+			for output in &recipe.outputs {
+				self.output.add(Resource::Item(output.item), output.count as u64);
+				ledger.record_income(Resource::Item(output.item), output.count as u64);
+			}
+			self.progress = None;
+		}
+
+		// The next craft starts on the tick the last one finished, so none is lost in between.
+		if let Some(input) = recipe.inputs.iter().find(|input| self.input.get(Resource::Item(input.item)) < input.count as u64) {
+			self.status = CraftingStatus::Starved(Resource::Item(input.item));
+			return;
+		}
+		let energy = energy(recipe);
+		let stored = power.as_ref().map_or(0, |power| power.get(Resource::Power));
+		if stored < energy {
+			self.status = CraftingStatus::Starved(Resource::Power);
+			return;
+		}
+		for input in &recipe.inputs {
+			self.input.remove(Resource::Item(input.item), input.count as u64);
+			ledger.record_expense(Resource::Item(input.item), input.count as u64);
+		}
+		if let Some(power) = power {
+			power.remove(Resource::Power, energy);
+			ledger.record_expense(Resource::Power, energy);
+		}
+		self.progress = Some(0);
+		self.status = CraftingStatus::Crafting;
+	}
+}
+
+/// Moves what every [`Crafter`] with an `output_to` has made into the input buffer of the
+/// crafter there, as far as it needs it, or into the [`Stockpile`] there.
+pub(crate) fn deliver_outputs(world: &mut World, content: &Content) {
+	let links = world.query::<Crafter>()
+		.filter_map(|(entity, crafter)| Some((entity, crafter.output_to?)))
+		.collect::<Vec<_>>();
+	for (source, target) in links {
+		let outputs = world.get::<Crafter>(source).map(|crafter| crafter.output.iter().collect::<Vec<_>>()).unwrap_or_default();
+		for (resource, amount) in outputs {
+			let room = match (world.get::<Crafter>(target), resource) {
+				(Some(crafter), Resource::Item(item)) => content.recipe(crafter.recipe).map_or(0, |recipe| crafter.room_for(recipe, item)),
+				(Some(_), Resource::Power) => 0,
+				(None, _) => world.get::<Stockpile>(target).map_or(0, Stockpile::free),
+			};
+			let moved = amount.min(room);
+			if moved == 0 {
+				continue;
+			}
+			world.get_mut::<Crafter>(source).expect("source is a crafter").output.remove(resource, moved);
+			match world.get_mut::<Crafter>(target) {
+				Some(crafter) => crafter.input.add(resource, moved),
+				None => world.get_mut::<Stockpile>(target).expect("target has room").add(resource, moved),
+			};
+		}
+	}
+}
+
+impl Persist for Crafter {
+	fn save(&self, writer: &mut Writer) {
+		writer.varint(self.recipe.0 as u64);
+		self.input.save(writer);
+		self.output.save(writer);
+		writer.bool(self.output_to.is_some());
+		if let Some(target) = self.output_to {
+			writer.entity(target);
+		}
+		writer.bool(self.progress.is_some());
+		if let Some(progress) = self.progress {
+			writer.varint(progress);
+		}
+	}
+
+	fn load(reader: &mut Reader) -> Result<Self, SaveError> {
+		let recipe = u16::try_from(reader.varint()?).map_err(|_| SaveError::Corrupt("recipe out of range".into()))?;
+		let input = Stockpile::load(reader)?;
+		let output = Stockpile::load(reader)?;
+		let output_to = if reader.bool()? { Some(reader.entity()?) } else { None };
+		let progress = if reader.bool()? { Some(reader.varint()?) } else { None };
+		Ok(Self {
+			recipe: RecipeId(recipe),
+			input,
+			output,
+			output_to,
+			status: CraftingStatus::Idle,
+			progress,
+		})
+	}
+}
@@ -0,0 +1,406 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+
+use crate::content::ItemId;
+use crate::save::{Persist, Reader, SaveError, Writer};
+
+/// Minutes of income and expense kept by the [`Ledger`].
+pub const HISTORY_MINUTES: usize = 120;
+
+/// Something the economy counts: stacks of an item, or stored power in kilojoules.
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Resource {
+	Item(ItemId),
+	Power,
+}
+
+impl Resource {
+	pub(crate) fn save(self, writer: &mut Writer) {
+		match self {
+			Resource::Item(item) => {
+				writer.u8(0);
+				writer.varint(item.0 as u64);
+			}
+			Resource::Power => writer.u8(1),
+		}
+	}
+
+	pub(crate) fn load(reader: &mut Reader) -> Result<Self, SaveError> {
+		match reader.u8()? {
+			0 => u16::try_from(reader.varint()?)
+				.map(|item| Resource::Item(ItemId(item)))
+				.map_err(|_| SaveError::Corrupt("item out of range".into())),
+			1 => Ok(Resource::Power),
+			tag => Err(SaveError::Corrupt(format!("unknown resource {tag}"))),
+		}
+	}
+}
+
+fn save_amounts(amounts: &BTreeMap<Resource, u64>, writer: &mut Writer) {
+	writer.varint(amounts.len() as u64);
+	for (&resource, &amount) in amounts {
+		resource.save(writer);
+		writer.varint(amount);
+	}
+}
+
+fn load_amounts(reader: &mut Reader) -> Result<BTreeMap<Resource, u64>, SaveError> {
+	let mut amounts = BTreeMap::new();
+	for _ in 0..reader.varint()? {
+		amounts.insert(Resource::load(reader)?, reader.varint()?);
+	}
+	Ok(amounts)
+}
+
+/// Resources held by one entity, up to a total capacity shared between all of them.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Stockpile {
+	amounts: BTreeMap<Resource, u64>,
+	capacity: u64,
+}
+
+impl Stockpile {
+	pub fn new(capacity: u64) -> Self {
+		Self {
+			amounts: BTreeMap::new(),
+			capacity,
+		}
+	}
+
+	pub fn capacity(&self) -> u64 {
+		self.capacity
+	}
+
+	/// Changes the capacity. Anything already stored above it is kept.
+	pub fn set_capacity(&mut self, capacity: u64) {
+		self.capacity = capacity;
+	}
+
+	pub fn get(&self, resource: Resource) -> u64 {
+		self.amounts.get(&resource).copied().unwrap_or(0)
+	}
+
+	/// Amount of every resource held together.
+	pub fn total(&self) -> u64 {
+		self.amounts.values().sum()
+	}
+
+	/// Room left before the stockpile is full.
+	pub fn free(&self) -> u64 {
+		self.capacity.saturating_sub(self.total())
+	}
+
+	/// Stores as much of `amount` as fits, returning how much that was.
+	pub fn add(&mut self, resource: Resource, amount: u64) -> u64 {
+		let added = amount.min(self.free());
+		if added > 0 {
+			*self.amounts.entry(resource).or_default() += added;
+		}
+		added
+	}
+
+	/// Takes out as much of `amount` as there is, returning how much that was.
+	pub fn remove(&mut self, resource: Resource, amount: u64) -> u64 {
+		let Some(held) = self.amounts.get_mut(&resource) else {
+			return 0;
+		};
+		let removed = amount.min(*held);
+		*held -= removed;
+		if *held == 0 {
+			self.amounts.remove(&resource);
+		}
+		removed
+	}
+
+	/// Resources held, in a fixed order, leaving out those there are none of.
+	pub fn iter(&self) -> impl Iterator<Item = (Resource, u64)> + '_ {
+		self.amounts.iter().map(|(&resource, &amount)| (resource, amount))
+	}
+}
+
+impl Persist for Stockpile {
+	fn save(&self, writer: &mut Writer) {
+		writer.varint(self.capacity);
+		save_amounts(&self.amounts, writer);
+	}
+
+	fn load(reader: &mut Reader) -> Result<Self, SaveError> {
+		Ok(Self {
+			capacity: reader.varint()?,
+			amounts: load_amounts(reader)?,
+		})
+	}
+}
+
+/// `amount` units every `period` of simulated time. The period is rounded to whole ticks, so
+/// rates are exact however long a tick is.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Rate {
+	pub amount: u64,
+	pub period: Duration,
+}
+
+impl Rate {
+	pub fn per_second(amount: u64) -> Self {
+		Self { amount, period: Duration::from_secs(1) }
+	}
+
+	pub fn per_minute(amount: u64) -> Self {
+		Self { amount, period: Duration::from_secs(60) }
+	}
+}
+
+/// Number of ticks of length `dt` closest to `period`, at least one.
+pub(crate) fn ticks_in(period: Duration, dt: Duration) -> u64 {
+	let dt = dt.as_nanos().max(1);
+	((period.as_nanos() + dt / 2) / dt).max(1) as u64
+}
+
+/// One resource a building makes or uses, with the part of a unit it is owed so far.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Flow {
+	pub resource: Resource,
+	pub rate: Rate,
+	/// Accumulated `amount` per tick, a unit is due for every period's worth of ticks.
+	progress: u64,
+}
+
+impl Flow {
+	pub fn new(resource: Resource, rate: Rate) -> Self {
+		Self {
+			resource,
+			rate,
+			progress: 0,
+		}
+	}
+
+	/// Whole units due after one more tick, and the progress left over once they are handed out.
+	fn advance(&self, dt: Duration) -> (u64, u64) {
+		let ticks = ticks_in(self.rate.period, dt);
+		let progress = self.progress + self.rate.amount;
+		(progress / ticks, progress % ticks)
+	}
+}
+
+/// How a [`Producer`] fared on its last tick.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum ProducerStatus {
+	#[default]
+	Running,
+	/// Paused because its stockpile ran out of something it consumes.
+	Starved(Resource),
+	/// Its stockpile had no room for everything it made, the rest was lost.
+	Blocked(Resource),
+}
+
+/// A building that steadily consumes resources from the [`Stockpile`] on the same entity and
+/// produces others into it.
+///
+/// Production only runs on ticks where every consumed resource due could be taken, so a
+/// building without its inputs pauses rather than producing for free.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Producer {
+	pub consumes: Vec<Flow>,
+	pub produces: Vec<Flow>,
+	pub status: ProducerStatus,
+}
+
+impl Producer {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn consuming(mut self, resource: Resource, rate: Rate) -> Self {
+		self.consumes.push(Flow::new(resource, rate));
+		self
+	}
+
+	pub fn producing(mut self, resource: Resource, rate: Rate) -> Self {
+		self.produces.push(Flow::new(resource, rate));
+		self
+	}
+
+	/// Runs one tick against `stockpile`, noting what moved in `ledger`.
+	pub(crate) fn tick(&mut self, stockpile: &mut Stockpile, ledger: &mut Ledger, dt: Duration) {
+		let consumed = self.consumes.iter().map(|flow| flow.advance(dt)).collect::<Vec<_>>();
+		if let Some(flow) = self.consumes.iter().zip(&consumed).find(|(flow, (due, _))| stockpile.get(flow.resource) < *due) {
+			self.status = ProducerStatus::Starved(flow.0.resource);
+			return;
+		}
+		self.status = ProducerStatus::Running;
+
+		for (flow, (due, progress)) in self.consumes.iter_mut().zip(consumed) {
+			stockpile.remove(flow.resource, due);
+			ledger.record_expense(flow.resource, due);
+			flow.progress = progress;
+		}
+		for flow in &mut self.produces {
+			let (due, progress) = flow.advance(dt);
+			let added = stockpile.add(flow.resource, due);
+			ledger.record_income(flow.resource, added);
+			if added < due {
+				self.status = ProducerStatus::Blocked(flow.resource);
+			}
+			flow.progress = progress;
+		}
+	}
+}
+
+fn save_flows(flows: &[Flow], writer: &mut Writer) {
+	writer.varint(flows.len() as u64);
+	for flow in flows {
+		flow.resource.save(writer);
+		writer.varint(flow.rate.amount);
+		writer.u64(flow.rate.period.as_nanos() as u64);
+		writer.varint(flow.progress);
+	}
+}
+
+fn load_flows(reader: &mut Reader) -> Result<Vec<Flow>, SaveError> {
+	let mut flows = Vec::new();
+	for _ in 0..reader.varint()? {
+		flows.push(Flow {
+			resource: Resource::load(reader)?,
+			rate: Rate {
+				amount: reader.varint()?,
+				period: Duration::from_nanos(reader.u64()?),
+			},
+			progress: reader.varint()?,
+		});
+	}
+	Ok(flows)
+}
+
+impl Persist for Producer {
+	fn save(&self, writer: &mut Writer) {
+		save_flows(&self.consumes, writer);
+		save_flows(&self.produces, writer);
+	}
+
+	fn load(reader: &mut Reader) -> Result<Self, SaveError> {
+		Ok(Self {
+			consumes: load_flows(reader)?,
+			produces: load_flows(reader)?,
+			status: ProducerStatus::Running,
+		})
+	}
+}
+
+/// What came into and went out of the stockpiles during one minute, and what they held at its
+/// end.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MinuteRecord {
+	pub income: BTreeMap<Resource, u64>,
+	pub expense: BTreeMap<Resource, u64>,
+	pub stock: BTreeMap<Resource, u64>,
+}
+
+impl MinuteRecord {
+	pub fn income(&self, resource: Resource) -> u64 {
+		self.income.get(&resource).copied().unwrap_or(0)
+	}
+
+	pub fn expense(&self, resource: Resource) -> u64 {
+		self.expense.get(&resource).copied().unwrap_or(0)
+	}
+
+	pub fn stock(&self, resource: Resource) -> u64 {
+		self.stock.get(&resource).copied().unwrap_or(0)
+	}
+
+	/// Income less expense.
+	pub fn net(&self, resource: Resource) -> i64 {
+		self.income(resource) as i64 - self.expense(resource) as i64
+	}
+}
+
+/// Income and expense of the whole outpost, kept minute by minute for graphs. Stored as a world
+/// resource and updated by the [`economy`](crate::systems::economy) system.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Ledger {
+	/// The minute in progress.
+	current: MinuteRecord,
+	/// Ticks into the current minute.
+	ticks: u64,
+	/// Completed minutes, oldest first, at most [`HISTORY_MINUTES`] of them.
+	history: VecDeque<MinuteRecord>,
+}
+
+impl Ledger {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn record_income(&mut self, resource: Resource, amount: u64) {
+		if amount > 0 {
+			*self.current.income.entry(resource).or_default() += amount;
+		}
+	}
+
+	pub fn record_expense(&mut self, resource: Resource, amount: u64) {
+		if amount > 0 {
+			*self.current.expense.entry(resource).or_default() += amount;
+		}
+	}
+
+	/// The minute in progress, without its stock.
+	pub fn current(&self) -> &MinuteRecord {
+		&self.current
+	}
+
+	/// Completed minutes, oldest first.
+	pub fn history(&self) -> impl ExactSizeIterator<Item = &MinuteRecord> + DoubleEndedIterator {
+		self.history.iter()
+	}
+
+	/// Net change of `resource` in each completed minute, oldest first, for plotting.
+	pub fn net_history(&self, resource: Resource) -> impl Iterator<Item = i64> + '_ {
+		self.history.iter().map(move |record| record.net(resource))
+	}
+
+	/// Counts a tick of length `dt`, closing the minute with the stock held in `stockpiles` once
+	/// it is over.
+	pub(crate) fn end_tick<'a>(&mut self, dt: Duration, stockpiles: impl Iterator<Item = &'a Stockpile>) {
+		self.ticks += 1;
+		if self.ticks < ticks_in(Duration::from_secs(60), dt) {
+			return;
+		}
+		let mut record = std::mem::take(&mut self.current);
+		for stockpile in stockpiles {
+			for (resource, amount) in stockpile.iter() {
+				*record.stock.entry(resource).or_default() += amount;
+			}
+		}
+		if self.history.len() == HISTORY_MINUTES {
+			self.history.pop_front();
+		}
+		self.history.push_back(record);
+		self.ticks = 0;
+	}
+}
+
+impl Persist for Ledger {
+	fn save(&self, writer: &mut Writer) {
+		writer.varint(self.ticks);
+		writer.varint(self.history.len() as u64 + 1);
+		for record in self.history.iter().chain([&self.current]) {
+			save_amounts(&record.income, writer);
+			save_amounts(&record.expense, writer);
+			save_amounts(&record.stock, writer);
+		}
+	}
+
+	fn load(reader: &mut Reader) -> Result<Self, SaveError> {
+		let ticks = reader.varint()?;
+		let mut history = VecDeque::new();
+		for _ in 0..reader.varint()? {
+			history.push_back(MinuteRecord {
+				income: load_amounts(reader)?,
+				expense: load_amounts(reader)?,
+				stock: load_amounts(reader)?,
+			});
+		}
+		let current = history.pop_back().ok_or_else(|| SaveError::Corrupt("ledger has no current minute".into()))?;
+		Ok(Self { current, ticks, history })
+	}
+}
@@ -0,0 +1,257 @@
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, VecDeque};
+
+pub use self::schedule::{Schedule, System};
+pub use self::storage::Storage;
+
+use self::storage::AnyStorage;
+
+mod schedule;
+mod storage;
+
+/// Handle to a simulation object. Indices are reused once an entity is despawned, the generation
+/// tells the new entity apart from stale handles to the old one.
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Entity {
+	index: u32,
+	generation: u32,
+}
+
+impl Entity {
+	/// The handle with these parts, for loading saved references. It only refers to a live
+	/// entity if the world has one at `index` with this `generation`.
+	pub(crate) fn from_parts(index: u32, generation: u32) -> Self {
+		Self { index, generation }
+	}
+
+	pub fn index(self) -> u32 {
+		self.index
+	}
+
+	pub fn generation(self) -> u32 {
+		self.generation
+	}
+}
+
+/// Which entity indices are in use and at what generation, saved so a loaded [`World`] hands
+/// out the same handles as the one that was saved.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EntitySlots {
+	pub generations: Vec<u32>,
+	pub alive: Vec<bool>,
+	/// Despawned indices in the order they will be reused.
+	pub free: Vec<u32>,
+}
+
+/// Entities, their components and the resources shared by all systems.
+///
+/// Components are any `'static` type, each stored in its own [`Storage`]. Resources are single
+/// values looked up by type, for state that belongs to no entity in particular.
+#[derive(Default)]
+pub struct World {
+	generations: Vec<u32>,
+	alive: Vec<bool>,
+	/// Indices of despawned entities, reused oldest first so spawning stays deterministic.
+	free: VecDeque<u32>,
+	storages: HashMap<TypeId, Box<dyn AnyStorage>>,
+	resources: HashMap<TypeId, Box<dyn Any>>,
+}
+
+impl World {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Replaces the entities of a world that has none with those of `slots`, keeping its
+	/// resources. Returns `false`, changing nothing, if the world has entities or `slots` is
+	/// inconsistent.
+	pub fn restore_slots(&mut self, slots: EntitySlots) -> bool {
+		if !self.is_empty() || slots.generations.len() != slots.alive.len() {
+			return false;
+		}
+		let mut free = vec![false; slots.alive.len()];
+		for &index in &slots.free {
+			match free.get_mut(index as usize) {
+				Some(seen) if !*seen => *seen = true,
+				_ => return false,
+			}
+		}
+		// Every index is either alive or waiting to be reused, never both.
+		if slots.alive.iter().zip(&free).any(|(alive, free)| alive == free) {
+			return false;
+		}
+
+		self.generations = slots.generations;
+		self.alive = slots.alive;
+		self.free = slots.free.into();
+		true
+	}
+
+	pub fn slots(&self) -> EntitySlots {
+		EntitySlots {
+			generations: self.generations.clone(),
+			alive: self.alive.clone(),
+			free: self.free.iter().copied().collect(),
+		}
+	}
+
+	pub fn spawn(&mut self) -> Entity {
+		match self.free.pop_front() {
+			Some(index) => {
+				self.alive[index as usize] = true;
+				Entity {
+					index,
+					generation: self.generations[index as usize],
+				}
+			}
+			None => {
+				self.generations.push(0);
+				self.alive.push(true);
+				Entity {
+					index: self.generations.len() as u32 - 1,
+					generation: 0,
+				}
+			}
+		}
+	}
+
+	/// Removes `entity` and all of its components. Returns `false` if it was already gone.
+	pub fn despawn(&mut self, entity: Entity) -> bool {
+		if !self.is_alive(entity) {
+			return false;
+		}
+		for storage in self.storages.values_mut() {
+			storage.remove_entity(entity);
+		}
+
+		let index = entity.index as usize;
+		self.alive[index] = false;
+		self.generations[index] = self.generations[index].wrapping_add(1);
+		self.free.push_back(entity.index);
+		true
+	}
+
+	pub fn is_alive(&self, entity: Entity) -> bool {
+		let index = entity.index as usize;
+		self.alive.get(index).copied().unwrap_or(false) && self.generations[index] == entity.generation
+	}
+
+	/// The living entity with this index, if there is one.
+	pub fn entity(&self, index: u32) -> Option<Entity> {
+		let entity = Entity {
+			index,
+			generation: *self.generations.get(index as usize)?,
+		};
+		self.is_alive(entity).then_some(entity)
+	}
+
+	/// Number of living entities.
+	pub fn len(&self) -> usize {
+		self.alive.len() - self.free.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Every living entity, in index order.
+	pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+		self.alive.iter().enumerate().filter(|(_, alive)| **alive).map(|(index, _)| Entity {
+			index: index as u32,
+			generation: self.generations[index],
+		})
+	}
+
+	pub fn storage<T: 'static>(&self) -> Option<&Storage<T>> {
+		let storage = self.storages.get(&TypeId::of::<T>())?;
+		storage.as_any().downcast_ref()
+	}
+
+	pub fn storage_mut<T: 'static>(&mut self) -> &mut Storage<T> {
+		self.storages
+			.entry(TypeId::of::<T>())
+			.or_insert_with(|| Box::new(Storage::<T>::new()))
+			.as_any_mut()
+			.downcast_mut()
+			.expect("storage is keyed by its component type")
+	}
+
+	/// Adds or replaces a component of `entity`, returning the one it replaced.
+	///
+	/// Panics if `entity` has been despawned.
+	pub fn insert<T: 'static>(&mut self, entity: Entity, component: T) -> Option<T> {
+		assert!(self.is_alive(entity), "{entity:?} has been despawned");
+		self.storage_mut().insert(entity, component)
+	}
+
+	pub fn remove<T: 'static>(&mut self, entity: Entity) -> Option<T> {
+		self.storage_mut().remove(entity)
+	}
+
+	pub fn get<T: 'static>(&self, entity: Entity) -> Option<&T> {
+		self.storage()?.get(entity)
+	}
+
+	pub fn get_mut<T: 'static>(&mut self, entity: Entity) -> Option<&mut T> {
+		self.storage_mut().get_mut(entity)
+	}
+
+	pub fn has<T: 'static>(&self, entity: Entity) -> bool {
+		self.storage::<T>().is_some_and(|storage| storage.contains(entity))
+	}
+
+	/// Every entity with a `T`, along with it.
+	pub fn query<T: 'static>(&self) -> impl Iterator<Item = (Entity, &T)> {
+		self.storage::<T>().into_iter().flat_map(Storage::iter)
+	}
+
+	pub fn query_mut<T: 'static>(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+		self.storage_mut::<T>().iter_mut()
+	}
+
+	/// Every entity with both an `A` and a `B`, in the order of the `A` storage.
+	pub fn query2<A: 'static, B: 'static>(&self) -> impl Iterator<Item = (Entity, &A, &B)> {
+		let b = self.storage::<B>();
+		self.query::<A>().filter_map(move |(entity, a)| Some((entity, a, b?.get(entity)?)))
+	}
+
+	/// Calls `f` for every entity with both an `A` and a `B`, in the order of the `A` storage.
+	///
+	/// Panics if `A` and `B` are the same type.
+	pub fn query2_mut<A: 'static, B: 'static>(&mut self, mut f: impl FnMut(Entity, &mut A, &mut B)) {
+		assert_ne!(TypeId::of::<A>(), TypeId::of::<B>(), "query2_mut needs two different component types");
+		// Taking one storage out of the map leaves the other free to borrow mutably.
+		let Some(mut a) = self.storages.remove(&TypeId::of::<A>()) else {
+			return;
+		};
+		{
+			let a = a.as_any_mut().downcast_mut::<Storage<A>>().expect("storage is keyed by its component type");
+			let b = self.storage_mut::<B>();
+			for (entity, a) in a.iter_mut() {
+				if let Some(b) = b.get_mut(entity) {
+					f(entity, a, b);
+				}
+			}
+		}
+		self.storages.insert(TypeId::of::<A>(), a);
+	}
+
+	/// Adds or replaces the resource of type `T`, returning the one it replaced.
+	pub fn insert_resource<T: 'static>(&mut self, resource: T) -> Option<T> {
+		let previous = self.resources.insert(TypeId::of::<T>(), Box::new(resource))?;
+		Some(*previous.downcast().expect("resource is keyed by its type"))
+	}
+
+	pub fn remove_resource<T: 'static>(&mut self) -> Option<T> {
+		let resource = self.resources.remove(&TypeId::of::<T>())?;
+		Some(*resource.downcast().expect("resource is keyed by its type"))
+	}
+
+	pub fn resource<T: 'static>(&self) -> Option<&T> {
+		self.resources.get(&TypeId::of::<T>())?.downcast_ref()
+	}
+
+	pub fn resource_mut<T: 'static>(&mut self) -> Option<&mut T> {
+		self.resources.get_mut(&TypeId::of::<T>())?.downcast_mut()
+	}
+}
@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use super::World;
+
+/// Game logic run on the [`World`] once per simulation tick.
+pub trait System {
+	fn run(&mut self, world: &mut World, dt: Duration);
+}
+
+impl<F: FnMut(&mut World, Duration)> System for F {
+	fn run(&mut self, world: &mut World, dt: Duration) {
+		self(world, dt)
+	}
+}
+
+/// Systems run one after another in a fixed order, so every tick sees the effects of earlier
+/// systems in the same tick.
+#[derive(Default)]
+pub struct Schedule {
+	systems: Vec<(&'static str, Box<dyn System>)>,
+}
+
+impl Schedule {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Adds a system that runs after all systems added so far.
+	pub fn add(&mut self, name: &'static str, system: impl System + 'static) {
+		assert!(!self.contains(name), "a system named {name} is already scheduled");
+		self.systems.push((name, Box::new(system)));
+	}
+
+	/// Adds a system that runs just before the system named `before`.
+	pub fn add_before(&mut self, name: &'static str, before: &str, system: impl System + 'static) {
+		assert!(!self.contains(name), "a system named {name} is already scheduled");
+		let index = self.position(before);
+		self.systems.insert(index, (name, Box::new(system)));
+	}
+
+	/// Adds a system that runs just after the system named `after`.
+	pub fn add_after(&mut self, name: &'static str, after: &str, system: impl System + 'static) {
+		assert!(!self.contains(name), "a system named {name} is already scheduled");
+		let index = self.position(after) + 1;
+		self.systems.insert(index, (name, Box::new(system)));
+	}
+
+	fn position(&self, name: &str) -> usize {
+		self.systems
+			.iter()
+			.position(|(existing, _)| *existing == name)
+			.unwrap_or_else(|| panic!("no system named {name} is scheduled"))
+	}
+
+	pub fn contains(&self, name: &str) -> bool {
+		self.systems.iter().any(|(existing, _)| *existing == name)
+	}
+
+	/// Names of the systems in the order they run.
+	pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+		self.systems.iter().map(|(name, _)| *name)
+	}
+
+	pub fn run(&mut self, world: &mut World, dt: Duration) {
+		for (_, system) in &mut self.systems {
+			system.run(world, dt);
+		}
+	}
+}
@@ -0,0 +1,121 @@
+use std::any::Any;
+
+use super::Entity;
+
+/// Components of one type, packed densely for iteration with a sparse index from entity to slot.
+///
+/// Removal swaps the last component into the gap, so iteration order depends only on the order of
+/// insertions and removals and is the same on every run.
+pub struct Storage<T> {
+	components: Vec<T>,
+	entities: Vec<Entity>,
+	/// Slot of each entity index in `components`.
+	slots: Vec<Option<u32>>,
+}
+
+impl<T> Storage<T> {
+	pub fn new() -> Self {
+		Self {
+			components: Vec::new(),
+			entities: Vec::new(),
+			slots: Vec::new(),
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.components.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.components.is_empty()
+	}
+
+	fn slot(&self, entity: Entity) -> Option<usize> {
+		let slot = (*self.slots.get(entity.index as usize)?)? as usize;
+		(self.entities[slot] == entity).then_some(slot)
+	}
+
+	pub fn contains(&self, entity: Entity) -> bool {
+		self.slot(entity).is_some()
+	}
+
+	pub fn get(&self, entity: Entity) -> Option<&T> {
+		self.slot(entity).map(|slot| &self.components[slot])
+	}
+
+	pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+		self.slot(entity).map(|slot| &mut self.components[slot])
+	}
+
+	/// Adds or replaces the component of `entity`, returning the one it replaced.
+	pub fn insert(&mut self, entity: Entity, component: T) -> Option<T> {
+		let index = entity.index as usize;
+		if index >= self.slots.len() {
+			self.slots.resize(index + 1, None);
+		}
+
+		if let Some(slot) = self.slots[index] {
+			let slot = slot as usize;
+			if self.entities[slot] == entity {
+				return Some(std::mem::replace(&mut self.components[slot], component));
+			}
+			// Left behind by an earlier entity with the same index.
+			self.remove_slot(slot);
+		}
+
+		self.slots[index] = Some(self.components.len() as u32);
+		self.components.push(component);
+		self.entities.push(entity);
+		None
+	}
+
+	pub fn remove(&mut self, entity: Entity) -> Option<T> {
+		let slot = self.slot(entity)?;
+		Some(self.remove_slot(slot))
+	}
+
+	fn remove_slot(&mut self, slot: usize) -> T {
+		let entity = self.entities.swap_remove(slot);
+		let component = self.components.swap_remove(slot);
+		self.slots[entity.index as usize] = None;
+		if let Some(moved) = self.entities.get(slot) {
+			self.slots[moved.index as usize] = Some(slot as u32);
+		}
+		component
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = (Entity, &T)> {
+		self.entities.iter().copied().zip(&self.components)
+	}
+
+	pub fn iter_mut(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+		self.entities.iter().copied().zip(&mut self.components)
+	}
+}
+
+impl<T> Default for Storage<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// A [`Storage`] of any component type, so a despawned entity can be removed from all of them.
+pub(super) trait AnyStorage {
+	fn remove_entity(&mut self, entity: Entity);
+	fn as_any(&self) -> &dyn Any;
+	fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: 'static> AnyStorage for Storage<T> {
+	fn remove_entity(&mut self, entity: Entity) {
+		self.remove(entity);
+	}
+
+	fn as_any(&self) -> &dyn Any {
+		self
+	}
+
+	fn as_any_mut(&mut self) -> &mut dyn Any {
+		self
+	}
+}
@@ -0,0 +1,186 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::building::{self, Building, BuildingCatalog, BuildingId, ConstructionSite, Occupancy, Placement, PlacementError, Rotation};
+use crate::colonist::{Colonist, JobBoard};
+use crate::components::{Position, Velocity};
+use crate::content::Content;
+use crate::conveyor::{self, Belt, BeltNetwork, Inserter, Stack};
+use crate::crafting::Crafter;
+use crate::economy::{Ledger, Producer, Stockpile};
+use crate::ecs::{Entity, Schedule, World};
+use crate::logistics::{Demand, LogisticsReport};
+use crate::pathfinding::{NavGrid, Pathfinder};
+use crate::renderer::Renderer;
+use crate::rng::Rng;
+use crate::save::Registry;
+use crate::simulation::Simulation;
+use crate::systems;
+use crate::tilemap::{Tile, Tilemap};
+use crate::worldgen::{WorldGenerator, tiles};
+
+/// Width and height of a new map in tiles.
+pub const DEFAULT_MAP_SIZE: u32 = 256;
+
+/// Simulated time from one dawn to the next.
+pub const DAY_LENGTH: Duration = Duration::from_secs(10 * 60);
+
+/// Everything that makes up a running outpost.
+pub struct Game {
+	/// Seed the map was generated from.
+	pub seed: u64,
+	/// Simulated time since the game started.
+	pub time: Duration,
+	pub tilemap: Tilemap,
+	/// Buildings, colonists, items and everything else that exists on the map.
+	pub world: World,
+	/// Systems run on `world` every tick.
+	pub schedule: Schedule,
+	/// Source of every random decision made while simulating, so a saved game plays out the same
+	/// after loading.
+	pub rng: Rng,
+	/// Components and resources of `world` that are kept in saves.
+	pub persistence: Registry,
+}
+
+impl Game {
+	/// A game on an empty map.
+	pub fn new() -> Self {
+		Self::with_tilemap(0, Tilemap::new(DEFAULT_MAP_SIZE, DEFAULT_MAP_SIZE))
+	}
+
+	/// A game on a new `size` by `size` map generated from `seed`.
+	pub fn generate(seed: u64, size: u32) -> Self {
+		let map = WorldGenerator::new(seed).generate(size, size);
+		Self::with_tilemap(seed, map.to_tilemap())
+	}
+
+	pub(crate) fn with_tilemap(seed: u64, tilemap: Tilemap) -> Self {
+		let mut schedule = Schedule::new();
+		schedule.add("store_previous_positions", systems::store_previous_positions);
+		schedule.add("pathfinding", systems::pathfinding);
+		schedule.add("movement", systems::movement);
+		schedule.add("economy", systems::economy);
+		schedule.add("crafting", systems::crafting);
+		schedule.add("conveyors", systems::conveyors);
+		schedule.add("logistics", systems::logistics);
+		schedule.add("colonists", systems::colonists);
+
+		let mut persistence = Registry::new();
+		persistence.register_component::<Position>("position");
+		persistence.register_component::<Velocity>("velocity");
+		persistence.register_component::<Building>("building");
+		persistence.register_component::<ConstructionSite>("construction_site");
+		persistence.register_component::<Stockpile>("stockpile");
+		persistence.register_component::<Producer>("producer");
+		persistence.register_component::<Crafter>("crafter");
+		persistence.register_component::<Colonist>("colonist");
+		persistence.register_component::<Demand>("demand");
+		persistence.register_component::<Belt>("belt");
+		persistence.register_component::<Inserter>("inserter");
+		persistence.register_resource::<Ledger>("ledger");
+		persistence.register_resource::<JobBoard>("job_board");
+		persistence.register_resource::<BeltNetwork>("belt_network");
+
+		// Derived from the tiles, so it is rebuilt rather than saved.
+		let mut world = World::new();
+		world.insert_resource(Pathfinder::new(NavGrid::from_tilemap(&tilemap, tiles::movement_cost)));
+		world.insert_resource(Occupancy::new(tilemap.width(), tilemap.height()));
+		world.insert_resource(BuildingCatalog::new());
+		world.insert_resource(Ledger::new());
+		world.insert_resource(JobBoard::new());
+		world.insert_resource(LogisticsReport::default());
+		world.insert_resource(BeltNetwork::new());
+
+		Self {
+			seed,
+			time: Duration::ZERO,
+			tilemap,
+			world,
+			schedule,
+			rng: Rng::new(seed),
+			persistence,
+		}
+	}
+
+	/// Plays with the buildings and terrain of `content`, stored as a world resource, instead of
+	/// the built in tiles. Everything derived from them is rebuilt.
+	pub fn set_content(&mut self, content: Arc<Content>) {
+		let grid = NavGrid::from_tilemap(&self.tilemap, |tile| content.movement_cost(tile));
+		self.world.insert_resource(Pathfinder::new(grid));
+		self.world.insert_resource(content.buildings().clone());
+		self.world.insert_resource(content);
+		building::block_paths(&mut self.world);
+	}
+
+	/// Changes a tile and everything derived from it, returning the previous tile.
+	pub fn set_tile(&mut self, x: i32, y: i32, tile: Tile) -> Option<Tile> {
+		let previous = self.tilemap.set(x, y, tile)?;
+		let cost = building::path_cost(&self.world, &self.tilemap, [x, y]);
+		if let Some(pathfinder) = self.world.resource_mut::<Pathfinder>() {
+			pathfinder.set_cost([x, y], cost);
+		}
+		Some(previous)
+	}
+
+	/// Whether a `kind` building fits with the bottom left of its footprint at `origin`.
+	pub fn check_placement(&self, kind: BuildingId, origin: [i32; 2], rotation: Rotation) -> Placement {
+		Placement::check(&self.world, &self.tilemap, kind, origin, rotation)
+	}
+
+	/// Reserves the tiles for a `kind` building and spawns its construction site.
+	pub fn place_building(&mut self, kind: BuildingId, origin: [i32; 2], rotation: Rotation) -> Result<Entity, PlacementError> {
+		building::place(&mut self.world, &self.tilemap, kind, origin, rotation)
+	}
+
+	/// Removes a building or construction site, freeing its tiles.
+	pub fn demolish(&mut self, entity: Entity) -> bool {
+		building::demolish(&mut self.world, &self.tilemap, entity)
+	}
+
+	/// Lays a belt tile, if its tile is free.
+	pub fn place_belt(&mut self, belt: Belt) -> Result<Entity, PlacementError> {
+		conveyor::place_belt(&mut self.world, &self.tilemap, belt)
+	}
+
+	/// Places an inserter, if its tile is free.
+	pub fn place_inserter(&mut self, inserter: Inserter) -> Result<Entity, PlacementError> {
+		conveyor::place_inserter(&mut self.world, &self.tilemap, inserter)
+	}
+
+	/// Removes a belt or inserter, freeing its tile and returning the stacks that were on it.
+	pub fn remove_conveyor(&mut self, entity: Entity) -> Option<Vec<Stack>> {
+		conveyor::remove(&mut self.world, entity)
+	}
+
+	/// How far through the current day the game is, from 0 at dawn towards 1.
+	pub fn time_of_day(&self) -> f32 {
+		(self.time.as_nanos() % DAY_LENGTH.as_nanos()) as f32 / DAY_LENGTH.as_nanos() as f32
+	}
+
+	/// Queues the current state for the next frame, `alpha` of the way from the last tick
+	/// towards the next.
+	pub fn draw(&self, renderer: &mut Renderer, alpha: f32) {
+		renderer.draw_tilemap(&self.tilemap);
+		systems::extract_buildings(&self.world, renderer);
+		systems::extract_conveyors(&self.world, renderer);
+		systems::extract_sprites(&self.world, renderer, alpha);
+	}
+}
+
+impl Simulation for Game {
+	fn tick(&mut self, dt: Duration) {
+		self.time += dt;
+		self.schedule.run(&mut self.world, dt);
+		let mined = self.world.resource_mut::<JobBoard>().map(JobBoard::take_mined).unwrap_or_default();
+		for ([x, y], tile) in mined {
+			self.set_tile(x, y, tile);
+		}
+	}
+}
+
+impl Default for Game {
+	fn default() -> Self {
+		Self::new()
+	}
+}
@@ -0,0 +1,264 @@
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use winit::event::{
+	ElementState, KeyboardInput, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent,
+};
+
+/// Bindings file read from the working directory.
+pub const DEFAULT_BINDINGS_PATH: &str = "bindings.toml";
+
+/// Something the player can do, independent of which key or button does it.
+#[derive(Copy, Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum Action {
+	PanLeft,
+	PanRight,
+	PanUp,
+	PanDown,
+	DragCamera,
+	Build,
+	NextBuilding,
+	Cancel,
+	Rotate,
+	ToggleOverlay,
+	ExportProfile,
+	QuickSave,
+	QuickLoad,
+}
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Binding {
+	Key(VirtualKeyCode),
+	Mouse(MouseButton),
+}
+
+/// The keys and mouse buttons that trigger one action.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ActionBindings {
+	pub keys: Vec<VirtualKeyCode>,
+	pub mouse: Vec<MouseButton>,
+}
+
+impl ActionBindings {
+	fn contains(&self, binding: Binding) -> bool {
+		match binding {
+			Binding::Key(key) => self.keys.contains(&key),
+			Binding::Mouse(button) => self.mouse.contains(&button),
+		}
+	}
+
+	fn bindings(&self) -> impl Iterator<Item = Binding> + '_ {
+		self.keys.iter().map(|&key| Binding::Key(key))
+			.chain(self.mouse.iter().map(|&button| Binding::Mouse(button)))
+	}
+}
+
+/// Which keys and buttons trigger each [`Action`], as stored in the bindings file:
+///
+/// ```toml
+/// [PanLeft]
+/// keys = ["A", "Left"]
+///
+/// [Build]
+/// mouse = ["Left"]
+/// ```
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct Bindings {
+	actions: BTreeMap<Action, ActionBindings>,
+}
+
+impl Default for Bindings {
+	fn default() -> Self {
+		use VirtualKeyCode::*;
+
+		let bind = |keys: &[VirtualKeyCode], mouse: &[MouseButton]| ActionBindings {
+			keys: keys.to_vec(),
+			mouse: mouse.to_vec(),
+		};
+
+		Self {
+			actions: BTreeMap::from([
+				(Action::PanLeft, bind(&[A, Left], &[])),
+				(Action::PanRight, bind(&[D, Right], &[])),
+				(Action::PanUp, bind(&[W, Up], &[])),
+				(Action::PanDown, bind(&[S, Down], &[])),
+				(Action::DragCamera, bind(&[], &[MouseButton::Middle, MouseButton::Right])),
+				(Action::Build, bind(&[], &[MouseButton::Left])),
+				(Action::NextBuilding, bind(&[Tab], &[])),
+				(Action::Cancel, bind(&[Escape], &[])),
+				(Action::Rotate, bind(&[R], &[])),
+				(Action::ToggleOverlay, bind(&[F3], &[])),
+				(Action::ExportProfile, bind(&[F4], &[])),
+				(Action::QuickSave, bind(&[F5], &[])),
+				(Action::QuickLoad, bind(&[F9], &[])),
+			]),
+		}
+	}
+}
+
+impl Bindings {
+	/// Reads bindings from `path`. Actions missing from the file keep their default bindings, and
+	/// a missing file gives the defaults.
+	pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+		let mut bindings = Self::default();
+		match fs::read_to_string(path) {
+			Ok(contents) => {
+				let file: Bindings = toml::from_str(&contents)
+					.map_err(|error| format!("{}: {error}", path.display()))?;
+				bindings.actions.extend(file.actions);
+			}
+			Err(error) if error.kind() == ErrorKind::NotFound => {}
+			Err(error) => return Err(format!("{}: {error}", path.display()).into()),
+		}
+		Ok(bindings)
+	}
+
+	pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+		fs::write(path, toml::to_string(self)?)?;
+		Ok(())
+	}
+
+	pub fn get(&self, action: Action) -> Option<&ActionBindings> {
+		self.actions.get(&action)
+	}
+
+	/// Replaces the bindings of `action`.
+	pub fn rebind(&mut self, action: Action, bindings: ActionBindings) {
+		self.actions.insert(action, bindings);
+	}
+}
+
+/// Key and mouse state for the current frame, read through [`Action`]s.
+pub struct Input {
+	bindings: Bindings,
+	held: HashSet<Binding>,
+	pressed: HashSet<Binding>,
+	released: HashSet<Binding>,
+	/// Unknown until the first cursor event.
+	cursor: Option<[f32; 2]>,
+	cursor_delta: [f32; 2],
+	wheel: f32,
+}
+
+impl Input {
+	pub fn new(bindings: Bindings) -> Self {
+		Self {
+			bindings,
+			held: HashSet::new(),
+			pressed: HashSet::new(),
+			released: HashSet::new(),
+			cursor: None,
+			cursor_delta: [0.0, 0.0],
+			wheel: 0.0,
+		}
+	}
+
+	pub fn bindings(&self) -> &Bindings {
+		&self.bindings
+	}
+
+	pub fn bindings_mut(&mut self) -> &mut Bindings {
+		&mut self.bindings
+	}
+
+	pub fn handle_event(&mut self, event: &WindowEvent) {
+		match event {
+			WindowEvent::KeyboardInput {
+				input: KeyboardInput { state, virtual_keycode: Some(key), .. },
+				..
+			} => self.set(Binding::Key(*key), *state),
+			WindowEvent::MouseInput { state, button, .. } => self.set(Binding::Mouse(*button), *state),
+			WindowEvent::CursorMoved { position, .. } => {
+				let cursor = [position.x as f32, position.y as f32];
+				// The first position only says where the cursor is, not how far it moved.
+				if let Some(previous) = self.cursor {
+					self.cursor_delta[0] += cursor[0] - previous[0];
+					self.cursor_delta[1] += cursor[1] - previous[1];
+				}
+				self.cursor = Some(cursor);
+			}
+			WindowEvent::MouseWheel { delta, .. } => {
+				self.wheel += match delta {
+					MouseScrollDelta::LineDelta(_, y) => *y,
+					// Roughly one line per notch on common touchpads.
+					MouseScrollDelta::PixelDelta(position) => position.y as f32 / 100.0,
+				};
+			}
+			// Releases are not reported while unfocused, so nothing can be assumed to stay held.
+			WindowEvent::Focused(false) => {
+				self.released.extend(self.held.drain());
+			}
+			_ => {}
+		}
+	}
+
+	fn set(&mut self, binding: Binding, state: ElementState) {
+		match state {
+			ElementState::Pressed => {
+				// Key repeat sends further presses while held, which are not new presses.
+				if self.held.insert(binding) {
+					self.pressed.insert(binding);
+				}
+			}
+			ElementState::Released => {
+				if self.held.remove(&binding) {
+					self.released.insert(binding);
+				}
+			}
+		}
+	}
+
+	/// Clears the per frame state. Call once the frame's input has been acted on.
+	pub fn end_frame(&mut self) {
+		self.pressed.clear();
+		self.released.clear();
+		self.cursor_delta = [0.0, 0.0];
+		self.wheel = 0.0;
+	}
+
+	fn any(&self, action: Action, set: &HashSet<Binding>) -> bool {
+		self.bindings.get(action).is_some_and(|bindings| bindings.bindings().any(|binding| set.contains(&binding)))
+	}
+
+	/// Whether `action` started this frame.
+	pub fn pressed(&self, action: Action) -> bool {
+		self.any(action, &self.pressed)
+	}
+
+	/// Whether `action` is active.
+	pub fn held(&self, action: Action) -> bool {
+		self.any(action, &self.held)
+	}
+
+	/// Whether `action` stopped this frame, with none of its other bindings still held.
+	pub fn released(&self, action: Action) -> bool {
+		self.any(action, &self.released) && !self.held(action)
+	}
+
+	/// Cursor position in physical pixels from the top left of the window, the top left corner
+	/// until the cursor has moved over the window.
+	pub fn cursor(&self) -> [f32; 2] {
+		self.cursor.unwrap_or([0.0, 0.0])
+	}
+
+	/// How far the cursor moved this frame.
+	pub fn cursor_delta(&self) -> [f32; 2] {
+		self.cursor_delta
+	}
+
+	/// Mouse wheel movement this frame in notches, positive away from the user.
+	pub fn wheel(&self) -> f32 {
+		self.wheel
+	}
+
+	/// Whether `binding` triggers `action`, for showing bindings in menus.
+	pub fn is_bound(&self, action: Action, binding: Binding) -> bool {
+		self.bindings.get(action).is_some_and(|bindings| bindings.contains(binding))
+	}
+}
@@ -0,0 +1,24 @@
+pub mod build_tool;
+pub mod building;
+pub mod camera_controller;
+pub mod colonist;
+pub mod components;
+pub mod config;
+pub mod content;
+pub mod conveyor;
+pub mod crafting;
+pub mod economy;
+pub mod ecs;
+pub mod game;
+pub mod input;
+pub mod logistics;
+pub mod overlay;
+pub mod pathfinding;
+pub mod profiler;
+pub mod renderer;
+pub mod rng;
+pub mod save;
+pub mod simulation;
+pub mod systems;
+pub mod tilemap;
+pub mod worldgen;
@@ -0,0 +1,308 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::colonist::{self, Job, JobBoard, JobKind};
+use crate::content::Content;
+use crate::crafting::Crafter;
+use crate::economy::{Resource, Stockpile};
+use crate::ecs::{Entity, World};
+use crate::pathfinding::Pathfinder;
+use crate::save::{Persist, Reader, SaveError, Writer};
+
+/// Stacks a hauler carries on one trip, of any mix of items.
+pub const CARRY_STACKS: u64 = 2;
+/// Priority of the haul jobs posted for a crafter's inputs.
+pub const CRAFTER_PRIORITY: u8 = 1;
+
+/// Resources an entity wants delivered into its [`Stockpile`], such as the materials for a
+/// construction site. Each delivery lowers what is still wanted.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Demand {
+	pub wants: BTreeMap<Resource, u64>,
+	/// Priority of the haul jobs posted for it.
+	pub priority: u8,
+}
+
+impl Demand {
+	pub fn new(priority: u8) -> Self {
+		Self {
+			wants: BTreeMap::new(),
+			priority,
+		}
+	}
+
+	pub fn want(mut self, resource: Resource, amount: u64) -> Self {
+		*self.wants.entry(resource).or_default() += amount;
+		self
+	}
+
+	/// Whether everything wanted has been delivered.
+	pub fn is_met(&self) -> bool {
+		self.wants.is_empty()
+	}
+}
+
+impl Persist for Demand {
+	fn save(&self, writer: &mut Writer) {
+		writer.u8(self.priority);
+		writer.varint(self.wants.len() as u64);
+		for (&resource, &amount) in &self.wants {
+			resource.save(writer);
+			writer.varint(amount);
+		}
+	}
+
+	fn load(reader: &mut Reader) -> Result<Self, SaveError> {
+		let priority = reader.u8()?;
+		let mut wants = BTreeMap::new();
+		for _ in 0..reader.varint()? {
+			wants.insert(Resource::load(reader)?, reader.varint()?);
+		}
+		Ok(Self { wants, priority })
+	}
+}
+
+/// One stop on a haul: what to take, and from where.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Pickup {
+	pub from: Entity,
+	pub resource: Resource,
+	pub amount: u64,
+}
+
+/// A demand the planner could not cover from the supplies that can be reached.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Shortage {
+	pub entity: Entity,
+	pub resource: Resource,
+	/// How much is wanted beyond what is on its way.
+	pub missing: u64,
+}
+
+/// Demands left unmet by the last planning pass, stored as a world resource for the UI.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct LogisticsReport {
+	pub shortages: Vec<Shortage>,
+}
+
+/// Takes up to `amount` from what `entity` offers: the output buffer of a crafter, or else its
+/// stockpile. Returns how much was taken.
+pub(crate) fn pick_up(world: &mut World, entity: Entity, resource: Resource, amount: u64) -> u64 {
+	if let Some(crafter) = world.get_mut::<Crafter>(entity) {
+		return crafter.output.remove(resource, amount);
+	}
+	world.get_mut::<Stockpile>(entity).map_or(0, |stockpile| stockpile.remove(resource, amount))
+}
+
+/// Returns what [`pick_up`] took, ignoring capacity so nothing is lost.
+pub(crate) fn put_back(world: &mut World, entity: Entity, resource: Resource, amount: u64) {
+	let stockpile = match world.get_mut::<Crafter>(entity) {
+		Some(crafter) => &mut crafter.output,
+		None => match world.get_mut::<Stockpile>(entity) {
+			Some(stockpile) => stockpile,
+			None => return,
+		},
+	};
+	let capacity = stockpile.capacity();
+	stockpile.set_capacity(u64::MAX);
+	stockpile.add(resource, amount);
+	stockpile.set_capacity(capacity);
+}
+
+/// Hands up to `amount` to `entity`: into the input buffer of a crafter, as far as its recipe
+/// needs it, or else its stockpile, lowering its [`Demand`]. Returns how much was taken.
+pub(crate) fn deliver(world: &mut World, entity: Entity, resource: Resource, amount: u64) -> u64 {
+	let content = world.resource::<Arc<Content>>().cloned();
+	if let Some(crafter) = world.get_mut::<Crafter>(entity) {
+		let room = match (resource, content.as_ref().and_then(|content| content.recipe(crafter.recipe))) {
+			(Resource::Item(item), Some(recipe)) => crafter.room_for(recipe, item),
+			_ => 0,
+		};
+		return crafter.input.add(resource, amount.min(room));
+	}
+	let Some(stockpile) = world.get_mut::<Stockpile>(entity) else {
+		return 0;
+	};
+	let added = stockpile.add(resource, amount);
+	if let Some(demand) = world.get_mut::<Demand>(entity) {
+		if let Some(wanted) = demand.wants.get_mut(&resource) {
+			*wanted = wanted.saturating_sub(added);
+			if *wanted == 0 {
+				demand.wants.remove(&resource);
+			}
+		}
+	}
+	added
+}
+
+/// Amounts per entity and resource.
+type Amounts = BTreeMap<(Entity, Resource), u64>;
+
+/// What the haul jobs on the board have spoken for: stock still to be picked up at each
+/// source, and everything on its way to each destination.
+fn reservations(board: &JobBoard) -> (Amounts, Amounts) {
+	let (mut reserved, mut incoming) = (Amounts::new(), Amounts::new());
+	for (_, job) in board.iter() {
+		let JobKind::Haul { pickups, to } = &job.kind else {
+			continue;
+		};
+		for (index, pickup) in pickups.iter().enumerate() {
+			if index as u32 >= job.done {
+				*reserved.entry((pickup.from, pickup.resource)).or_default() += pickup.amount;
+			}
+			*incoming.entry((*to, pickup.resource)).or_default() += pickup.amount;
+		}
+	}
+	(reserved, incoming)
+}
+
+/// A destination still wanting resources, and how much of each.
+struct Need {
+	entity: Entity,
+	tile: [i32; 2],
+	priority: u8,
+	wants: Vec<(Resource, u64)>,
+}
+
+/// Matches what crafters and [`Demand`]s want against what stockpiles and crafter outputs hold
+/// beyond their reservations, posting haul jobs for it, and reports what cannot be covered.
+///
+/// A trip carries up to [`CARRY_STACKS`] stacks to one destination, picked up from the
+/// nearest sources first, so several small stocks are gathered in one go. What a trip takes
+/// is reserved until the job is done or cancelled, so no two haulers count on the same items.
+pub fn plan(world: &mut World) {
+	let Some(content) = world.resource::<Arc<Content>>().cloned() else {
+		return;
+	};
+	let Some(mut board) = world.remove_resource::<JobBoard>() else {
+		return;
+	};
+	let (reserved, incoming) = reservations(&board);
+	let in_flight = |entity, resource| incoming.get(&(entity, resource)).copied().unwrap_or(0);
+
+	let mut needs = Vec::new();
+	for (entity, crafter) in world.query::<Crafter>() {
+		let (Some(recipe), Some(tile)) = (content.recipe(crafter.recipe), colonist::entity_tile(world, entity)) else {
+			continue;
+		};
+		let wants = recipe.inputs.iter()
+			.map(|input| (Resource::Item(input.item), crafter.room_for(recipe, input.item)))
+			.map(|(resource, room)| (resource, room.saturating_sub(in_flight(entity, resource))))
+			.filter(|&(_, amount)| amount > 0)
+			.collect::<Vec<_>>();
+		needs.push(Need { entity, tile, priority: CRAFTER_PRIORITY, wants });
+	}
+	for (entity, demand) in world.query::<Demand>() {
+		let (Some(stockpile), Some(tile)) = (world.get::<Stockpile>(entity), colonist::entity_tile(world, entity)) else {
+			continue;
+		};
+		let mut room = stockpile.free();
+		let mut wants = Vec::new();
+		for (&resource, &amount) in &demand.wants {
+			let amount = amount.saturating_sub(in_flight(entity, resource)).min(room);
+			room -= amount;
+			if amount > 0 {
+				wants.push((resource, amount));
+			}
+		}
+		needs.push(Need { entity, tile, priority: demand.priority, wants });
+	}
+	needs.sort_by_key(|need| std::cmp::Reverse(need.priority));
+
+	// Sources and what they can spare.
+	let mut supplies = Amounts::new();
+	let mut tiles = BTreeMap::new();
+	let offers = world.query::<Crafter>().map(|(entity, crafter)| (entity, &crafter.output))
+		.chain(world.query::<Stockpile>().filter(|&(entity, _)| !world.has::<Crafter>(entity) && !world.has::<Demand>(entity)));
+	for (entity, stockpile) in offers {
+		let Some(tile) = colonist::entity_tile(world, entity) else {
+			continue;
+		};
+		tiles.insert(entity, tile);
+		for (resource, amount) in stockpile.iter() {
+			let spare = amount.saturating_sub(reserved.get(&(entity, resource)).copied().unwrap_or(0));
+			if spare > 0 {
+				supplies.insert((entity, resource), spare);
+			}
+		}
+	}
+
+	let mut shortages = Vec::new();
+	for need in needs {
+		let mut remaining = need.wants.clone();
+		loop {
+			let trip = plan_trip(world, &content, &need, &mut remaining, &mut supplies, &tiles);
+			if trip.is_empty() {
+				break;
+			}
+			let stops = trip.len() as u32;
+			board.post(Job::new(JobKind::Haul { pickups: trip, to: need.entity }, need.priority, stops));
+		}
+		shortages.extend(remaining.into_iter().filter(|&(_, missing)| missing > 0).map(|(resource, missing)| Shortage {
+			entity: need.entity,
+			resource,
+			missing,
+		}));
+	}
+	world.insert_resource(board);
+	world.insert_resource(LogisticsReport { shortages });
+}
+
+/// Pickups for one trip towards `need`, taken out of `remaining` and `supplies`.
+fn plan_trip(
+	world: &mut World,
+	content: &Content,
+	need: &Need,
+	remaining: &mut [(Resource, u64)],
+	supplies: &mut Amounts,
+	tiles: &BTreeMap<Entity, [i32; 2]>,
+) -> Vec<Pickup> {
+	let mut stacks = CARRY_STACKS;
+	let mut pickups = Vec::new();
+	for (resource, wanted) in remaining.iter_mut() {
+		let Resource::Item(item) = *resource else {
+			continue;
+		};
+		let stack_size = content.item(item).map_or(1, |item| item.stack_size.max(1) as u64);
+		let mut carried = 0;
+		// Nearest first, ties going to the earlier entity.
+		let mut sources = supplies.iter()
+			.filter(|&(&(entity, offered), _)| offered == *resource && entity != need.entity)
+			.map(|(&(entity, _), &spare)| {
+				let [x, y] = tiles[&entity];
+				(((x - need.tile[0]).abs() + (y - need.tile[1]).abs()), entity, spare)
+			})
+			.collect::<Vec<_>>();
+		sources.sort();
+		for (_, from, spare) in sources {
+			let limit = (stacks * stack_size).saturating_sub(carried);
+			let amount = spare.min(*wanted).min(limit);
+			if amount == 0 {
+				break;
+			}
+			// Buildings are walked up to, not onto.
+			let start = colonist::approach(world, tiles[&from], tiles[&from]);
+			let goal = colonist::approach(world, need.tile, need.tile);
+			let reachable = match world.resource_mut::<Pathfinder>() {
+				Some(pathfinder) => pathfinder.regions().connected(start, goal),
+				None => true,
+			};
+			if !reachable {
+				continue;
+			}
+			pickups.push(Pickup { from, resource: *resource, amount });
+			carried += amount;
+			*wanted -= amount;
+			let spare = supplies.get_mut(&(from, *resource)).expect("listed above");
+			*spare -= amount;
+			if *spare == 0 {
+				supplies.remove(&(from, *resource));
+			}
+		}
+		stacks = stacks.saturating_sub(carried.div_ceil(stack_size));
+		if stacks == 0 {
+			break;
+		}
+	}
+	pickups
+}
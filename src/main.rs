@@ -1,211 +1,172 @@
 use std::error::Error;
-use std::iter;
-use std::mem::size_of;
-use std::time::{Duration, Instant};
-
-use bytemuck::{Pod, Zeroable};
-use wgpu::{
-	Backends, BlendState, BufferAddress, BufferUsages, Color, ColorTargetState, ColorWrites,
-	CommandEncoderDescriptor, CompositeAlphaMode, Device, DeviceDescriptor, Face, Features,
-	FragmentState, FrontFace, include_wgsl, IndexFormat, Instance, Limits, LoadOp, MultisampleState,
-	Operations, PipelineLayoutDescriptor, PolygonMode, PowerPreference, PresentMode, PrimitiveState,
-	PrimitiveTopology, RenderPassColorAttachment, RenderPassDescriptor, RenderPipelineDescriptor,
-	RequestAdapterOptions, Surface, SurfaceConfiguration, TextureUsages, TextureViewDescriptor,
-	VertexAttribute, VertexBufferLayout, VertexFormat, VertexState, VertexStepMode,
-};
-use wgpu::util::{BufferInitDescriptor, DeviceExt};
-use winit::dpi::PhysicalSize;
+use std::path::Path;
+use std::process;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use frontier_outpost::build_tool::BuildTool;
+use frontier_outpost::camera_controller;
+use frontier_outpost::config::Config;
+use frontier_outpost::content::{Content, DEFAULT_CONTENT_DIR};
+use frontier_outpost::game::Game;
+use frontier_outpost::input::{Action, Bindings, DEFAULT_BINDINGS_PATH, Input};
+use frontier_outpost::overlay::{OVERLAY_FONT_PATH, PerformanceOverlay};
+use frontier_outpost::profiler::{DEFAULT_CSV_PATH, FrameSample, Profiler};
+use frontier_outpost::renderer::{AtlasBuilder, Image, Renderer, TextureError, TileSet};
+use frontier_outpost::rng::split_mix;
+use frontier_outpost::save::{self, Autosave, DEFAULT_SAVE_PATH};
+use frontier_outpost::simulation::{self, FixedTimestep};
 use winit::event::{Event, WindowEvent};
 use winit::event_loop::{ControlFlow, EventLoop};
 use winit::window::WindowBuilder;
 
-#[repr(C)]
-#[derive(Copy, Clone, Debug, Pod, Zeroable)]
-struct Vertex {
-	position: [f32; 2],
-}
+#[async_std::main]
+async fn main() {
+	env_logger::init();
 
-impl Vertex {
-	fn descriptor<'a>() -> VertexBufferLayout<'a> {
-		VertexBufferLayout {
-			array_stride: size_of::<Vertex>() as BufferAddress,
-			step_mode: VertexStepMode::Vertex,
-			attributes: &[
-				VertexAttribute {
-					offset: 0,
-					shader_location: 0,
-					format: VertexFormat::Float32x2,
-				}
-			],
-		}
+	if let Err(error) = run().await {
+		eprintln!("Frontier Outpost failed to start: {error}");
+		process::exit(1);
 	}
 }
 
-const VERTICES: &[Vertex] = &[
-	Vertex { position: [-0.5, -0.5] },
-	Vertex { position: [0.5, -0.5] },
-	Vertex { position: [0.5, 0.5] },
-	Vertex { position: [-0.5, 0.5] },
-];
-
-const INDICES: &[u16] = &[
-	0, 1, 2,
-	0, 2, 3,
-];
+async fn run() -> Result<(), Box<dyn Error>> {
+	let config = Config::load(std::env::args().skip(1))?;
+	let content = Arc::new(Content::load(Path::new(DEFAULT_CONTENT_DIR))?);
+	let mut game = match &config.save.load {
+		Some(path) => {
+			log::info!("loading {}", path.display());
+			save::load(path)?
+		}
+		None => {
+			let seed = config.world.seed.unwrap_or_else(random_seed);
+			log::info!("generating a {size}x{size} map from seed {seed}", size = config.world.size);
+			Game::generate(seed, config.world.size)
+		}
+	};
+	game.set_content(content.clone());
+	let mut autosave = config.save.autosave_interval.map(|interval| Autosave::new(interval, game.time));
 
-#[async_std::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-	env_logger::init();
+	if config.headless {
+		simulation::run_headless(&mut game, &config.simulation, |game, _| {
+			if autosave.as_mut().is_some_and(|autosave| autosave.due(game.time)) {
+				write_save(&config.save.autosave_path, game, &content);
+			}
+			true
+		});
+		return Ok(());
+	}
 
 	let event_loop = EventLoop::new();
 	let window = WindowBuilder::new()
 		.with_title("Frontier Outpost")
 		.build(&event_loop)?;
 
-	let instance = Instance::new(Backends::VULKAN);
-	let surface = unsafe { instance.create_surface(&window) };
-
-	let adapter = instance.request_adapter(&RequestAdapterOptions {
-		power_preference: PowerPreference::HighPerformance,
-		compatible_surface: Some(&surface),
-		force_fallback_adapter: false,
-	}).await.unwrap();
-
-	let (device, queue) = adapter.request_device(&DeviceDescriptor {
-		features: Features::empty(),
-		limits: Limits::default(),
-		label: None,
-	}, None).await?;
-
-	let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
-		label: None,
-		contents: bytemuck::cast_slice(VERTICES),
-		usage: BufferUsages::VERTEX,
-	});
-
-	let index_buffer = device.create_buffer_init(&BufferInitDescriptor {
-		label: None,
-		contents: bytemuck::cast_slice(INDICES),
-		usage: BufferUsages::INDEX,
-	});
-
-	let mut config = SurfaceConfiguration {
-		usage: TextureUsages::RENDER_ATTACHMENT,
-		format: surface.get_supported_formats(&adapter)[0],
-		width: 0,
-		height: 0,
-		present_mode: PresentMode::AutoVsync,
-		alpha_mode: CompositeAlphaMode::Auto,
-	};
-
-	let configure = move |
-		config: &mut SurfaceConfiguration,
-	    new_size: PhysicalSize<u32>,
-	    surface: &Surface,
-	    device: &Device
-	| {
-		config.width = new_size.width;
-		config.height = new_size.height;
-		surface.configure(device, &config);
-	};
-
-	configure(&mut config, window.inner_size(), &surface, &device);
-
-	let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
-
-	let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-		label: None,
-		bind_group_layouts: &[],
-		push_constant_ranges: &[],
-	});
-
-	let render_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
-		label: None,
-		layout: Some(&render_pipeline_layout),
-		vertex: VertexState {
-			module: &shader,
-			entry_point: "vertex_main",
-			buffers: &[Vertex::descriptor()],
-		},
-		fragment: Some(FragmentState {
-			module: &shader,
-			entry_point: "fragment_main",
-			targets: &[Some(ColorTargetState {
-				format: config.format,
-				blend: Some(BlendState::REPLACE),
-				write_mask: ColorWrites::ALL,
-			})],
-		}),
-		primitive: PrimitiveState {
-			topology: PrimitiveTopology::TriangleList,
-			strip_index_format: None,
-			front_face: FrontFace::Ccw,
-			cull_mode: Some(Face::Back),
-			polygon_mode: PolygonMode::Fill,
-			unclipped_depth: false,
-			conservative: false,
-		},
-		depth_stencil: None,
-		multisample: MultisampleState {
-			count: 1,
-			mask: !0,
-			alpha_to_coverage_enabled: false,
-		},
-		multiview: None,
-	});
-
-	let mut frame_time = Duration::ZERO;
-	let mut last_time = Instant::now();
-	let mut frames = 0;
+	let mut renderer = Renderer::new_windowed(&window, &config.graphics).await?;
+	let tileset = placeholder_tileset(&mut renderer, &content)?;
+	renderer.set_tileset(tileset);
+	let centre = config.world.size as f32 / 2.0;
+	renderer.camera_mut().position = [centre, centre];
+	let mut input = Input::new(Bindings::load(Path::new(DEFAULT_BINDINGS_PATH))?);
+	let mut timestep = FixedTimestep::new(&config.simulation);
+	let mut last_update = Instant::now();
+	let mut profiler = Profiler::default();
+	let mut overlay = PerformanceOverlay::new(renderer.load_font(Path::new(OVERLAY_FONT_PATH))?);
+	let mut build_tool = BuildTool::new();
 
 	event_loop.run(move |event, _, control_flow| match event {
 		Event::MainEventsCleared => window.request_redraw(),
 		Event::RedrawRequested(window_id) if window_id == window.id() => {
-			let output = surface.get_current_texture().unwrap();
-
-			frames += 1;
-			if last_time.elapsed() >= Duration::from_secs(1) {
-				println!("{} FPS {:.2}ms Avg", frames, frame_time.as_millis() as f64 / frames as f64);
-				frame_time = Duration::ZERO;
-				last_time = Instant::now();
-				frames = 0;
+			let now = Instant::now();
+			let elapsed = now - last_update;
+			last_update = now;
+
+			if input.pressed(Action::ToggleOverlay) {
+				overlay.toggle();
+			}
+			if input.pressed(Action::ExportProfile) {
+				match profiler.export_csv(Path::new(DEFAULT_CSV_PATH)) {
+					Ok(()) => log::info!("wrote frame samples to {DEFAULT_CSV_PATH}"),
+					Err(error) => log::error!("failed to write {DEFAULT_CSV_PATH}: {error}"),
+				}
 			}
 
-			let frame_start_time = Instant::now();
-
-			let view = output.texture.create_view(&TextureViewDescriptor::default());
-			let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
-
-			{
-				let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
-					label: None,
-					color_attachments: &[Some(RenderPassColorAttachment {
-						view: &view,
-						resolve_target: None,
-						ops: Operations {
-							load: LoadOp::Clear(Color::BLACK),
-							store: true,
-						},
-					})],
-					depth_stencil_attachment: None,
-				});
-
-				render_pass.set_pipeline(&render_pipeline);
-				render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-				render_pass.set_index_buffer(index_buffer.slice(..), IndexFormat::Uint16);
-				render_pass.draw_indexed(0..INDICES.len() as u32, 0, 0..1);
+			if input.pressed(Action::QuickSave) {
+				write_save(Path::new(DEFAULT_SAVE_PATH), &game, &content);
+			}
+			if input.pressed(Action::QuickLoad) {
+				match save::load(Path::new(DEFAULT_SAVE_PATH)) {
+					Ok(loaded) => {
+						log::info!("loaded {DEFAULT_SAVE_PATH}");
+						game = loaded;
+						game.set_content(content.clone());
+						autosave = config.save.autosave_interval.map(|interval| Autosave::new(interval, game.time));
+					}
+					Err(error) => log::error!("failed to load {DEFAULT_SAVE_PATH}: {error}"),
+				}
 			}
 
-			queue.submit(iter::once(encoder.finish()));
-			output.present();
+			let alpha = timestep.update(elapsed, &mut game);
+			if autosave.as_mut().is_some_and(|autosave| autosave.due(game.time)) {
+				write_save(&config.save.autosave_path, &game, &content);
+			}
+			camera_controller::update(&input, elapsed, renderer.camera_mut());
+			build_tool.update(&input, renderer.camera(), &mut game);
+			game.draw(&mut renderer, alpha);
+			build_tool.draw(&mut renderer, &game);
+			overlay.draw(&mut renderer, &profiler);
+
+			match renderer.render() {
+				Ok(()) => {}
+				Err(error) if error.is_recoverable() => log::debug!("skipped frame: {error}"),
+				Err(error) => {
+					eprintln!("Frontier Outpost stopped rendering: {error}");
+					*control_flow = ControlFlow::ExitWithCode(1);
+				}
+			}
 
-			frame_time += frame_start_time.elapsed();
+			let stats = renderer.stats();
+			profiler.record_with_passes(FrameSample {
+				frame_time: elapsed,
+				cpu_time: now.elapsed(),
+				gpu_time: None,
+				draw_calls: stats.draw_calls,
+				vertices: stats.vertices,
+			}, renderer.gpu_timings().unwrap_or_default());
+			input.end_frame();
 		}
 		Event::WindowEvent { ref event, window_id } if window_id == window.id() => match event {
-			WindowEvent::Resized(new_size) => configure(&mut config, *new_size, &surface, &device),
+			WindowEvent::Resized(new_size) => renderer.resize(*new_size),
 			WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
-			_ => {}
+			event => input.handle_event(event),
 		}
 		_ => {}
 	});
 }
+
+fn random_seed() -> u64 {
+	let mut nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64;
+	split_mix(&mut nanos)
+}
+
+fn write_save(path: &Path, game: &Game, content: &Content) {
+	let thumbnail = save::thumbnail(&game.tilemap, |tile| content.color(tile));
+	match save::save(path, game, &thumbnail) {
+		Ok(()) => log::info!("saved to {}", path.display()),
+		Err(error) => log::error!("failed to save: {error}"),
+	}
+}
+
+/// Solid colour tiles, until there is tile art.
+fn placeholder_tileset(renderer: &mut Renderer, content: &Content) -> Result<TileSet, TextureError> {
+	let mut builder = AtlasBuilder::default();
+	for terrain in content.terrain_defs() {
+		builder.add(&terrain.id, Image {
+			width: 4,
+			height: 4,
+			pixels: terrain.color.repeat(16),
+		})?;
+	}
+	let atlas = builder.build(renderer);
+	TileSet::from_atlas(&atlas, content.terrain_defs().iter().map(|terrain| terrain.id.as_str()))
+}
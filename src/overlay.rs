@@ -0,0 +1,115 @@
+use std::time::Duration;
+
+use crate::profiler::{milliseconds, Profiler};
+use crate::renderer::{FontId, Renderer, Sprite, TextSpan, TextStyle};
+
+/// Font the overlay is drawn with, relative to the working directory.
+pub const OVERLAY_FONT_PATH: &str = "assets/fonts/DejaVuSansMono.ttf";
+
+/// Frames the statistics and graph cover.
+const SUMMARY_FRAMES: usize = 120;
+
+const FONT_SIZE: f32 = 14.0;
+const MARGIN: f32 = 8.0;
+const PADDING: f32 = 6.0;
+const BAR_WIDTH: f32 = 2.0;
+const GRAPH_HEIGHT: f32 = 48.0;
+/// Frame time shown at the top of the graph. Longer frames are clipped.
+const GRAPH_MAX: Duration = Duration::from_micros(33_333);
+/// A 60 Hz frame with a little slack for vsync jitter.
+const TARGET_FRAME_TIME: Duration = Duration::from_micros(17_000);
+
+const TEXT_COLOR: [f32; 4] = [1.0; 4];
+const BACKGROUND_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+const FAST_COLOR: [f32; 4] = [0.2, 0.9, 0.2, 1.0];
+const SLOW_COLOR: [f32; 4] = [0.9, 0.8, 0.2, 1.0];
+const VERY_SLOW_COLOR: [f32; 4] = [0.9, 0.2, 0.2, 1.0];
+
+/// Frame timing statistics and a frame time graph drawn in the top left corner of the screen.
+pub struct PerformanceOverlay {
+	font: FontId,
+	visible: bool,
+}
+
+impl PerformanceOverlay {
+	pub fn new(font: FontId) -> Self {
+		Self {
+			font,
+			visible: false,
+		}
+	}
+
+	pub fn visible(&self) -> bool {
+		self.visible
+	}
+
+	pub fn toggle(&mut self) {
+		self.visible = !self.visible;
+	}
+
+	/// Queues the overlay for the next frame, if it is visible.
+	pub fn draw(&self, renderer: &mut Renderer, profiler: &Profiler) {
+		if !self.visible {
+			return;
+		}
+
+		let summary = profiler.summary(SUMMARY_FRAMES);
+		let latest = profiler.latest().copied().unwrap_or_default();
+		let gpu_time = match latest.gpu_time {
+			Some(time) => {
+				let passes = profiler.gpu_passes()
+					.iter()
+					.map(|pass| format!("{} {:.2}", pass.name, milliseconds(pass.duration)))
+					.collect::<Vec<_>>();
+				format!("{:.2} ms ({})", milliseconds(time), passes.join(", "))
+			}
+			None => "n/a".to_owned(),
+		};
+		let text = format!(
+			"FPS {:.1}\nframe min {:.2} avg {:.2} max {:.2} p99 {:.2} ms\nCPU {:.2} ms  GPU {gpu_time}\ndraw calls {}  vertices {}",
+			summary.fps,
+			milliseconds(summary.min),
+			milliseconds(summary.avg),
+			milliseconds(summary.max),
+			milliseconds(summary.p99),
+			milliseconds(latest.cpu_time),
+			latest.draw_calls,
+			latest.vertices,
+		);
+		let layout = renderer.layout_text(&[TextSpan::new(&text, TEXT_COLOR)], &TextStyle::new(self.font, FONT_SIZE));
+
+		let graph_width = SUMMARY_FRAMES as f32 * BAR_WIDTH;
+		let width = layout.width().max(graph_width) + PADDING * 2.0;
+		let height = layout.height() + GRAPH_HEIGHT + PADDING * 3.0;
+		let white = renderer.white_texture();
+		renderer.draw_ui_sprite(Sprite {
+			tint: BACKGROUND_COLOR,
+			layer: -1,
+			..Sprite::new(white, [MARGIN + width / 2.0, MARGIN + height / 2.0], [width, height])
+		});
+		renderer.draw_text(&layout, [MARGIN + PADDING, MARGIN + PADDING]);
+
+		// Newest frame on the right, bars growing up from the bottom of the panel.
+		let graph_bottom = MARGIN + height - PADDING;
+		let graph_right = MARGIN + PADDING + graph_width;
+		for (index, sample) in profiler.samples().rev().take(SUMMARY_FRAMES).enumerate() {
+			let fraction = (sample.frame_time.as_secs_f32() / GRAPH_MAX.as_secs_f32()).min(1.0);
+			let bar_height = (fraction * GRAPH_HEIGHT).max(1.0);
+			let x = graph_right - (index as f32 + 0.5) * BAR_WIDTH;
+			renderer.draw_ui_sprite(Sprite {
+				tint: bar_color(sample.frame_time),
+				..Sprite::new(white, [x, graph_bottom - bar_height / 2.0], [BAR_WIDTH, bar_height])
+			});
+		}
+	}
+}
+
+fn bar_color(frame_time: Duration) -> [f32; 4] {
+	if frame_time <= TARGET_FRAME_TIME {
+		FAST_COLOR
+	} else if frame_time <= GRAPH_MAX {
+		SLOW_COLOR
+	} else {
+		VERY_SLOW_COLOR
+	}
+}
@@ -0,0 +1,335 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use crate::tilemap::{Tile, Tilemap};
+
+pub use self::hierarchy::{CLUSTER_SIZE, HierarchicalPathfinder};
+pub use self::regions::Regions;
+
+use self::cache::PathCache;
+use self::search::Search;
+
+mod cache;
+mod hierarchy;
+mod regions;
+mod search;
+
+/// Cost of moving one tile straight across a tile of cost 1.
+pub const ORTHOGONAL_COST: u32 = 100;
+/// Cost of moving one tile diagonally across a tile of cost 1, roughly `100 * sqrt(2)`.
+pub const DIAGONAL_COST: u32 = 141;
+
+/// Tiles the pathfinding system expands per tick, shared by every waiting request.
+pub const DEFAULT_NODE_BUDGET: u32 = 4096;
+
+/// Finished searches kept for reuse.
+pub const DEFAULT_CACHE_CAPACITY: usize = 1024;
+
+/// How expensive each tile is to walk through, from 1 upwards, or impassable.
+#[derive(Clone, Debug)]
+pub struct NavGrid {
+	width: u32,
+	height: u32,
+	/// Zero marks impassable tiles.
+	costs: Vec<u8>,
+}
+
+impl NavGrid {
+	/// A grid where every tile costs 1.
+	pub fn new(width: u32, height: u32) -> Self {
+		Self {
+			width,
+			height,
+			costs: vec![1; (width * height) as usize],
+		}
+	}
+
+	/// A grid with the cost `cost` gives for each tile of `tilemap`, `None` being impassable.
+	pub fn from_tilemap(tilemap: &Tilemap, cost: impl Fn(Tile) -> Option<u8>) -> Self {
+		let mut grid = Self::new(tilemap.width(), tilemap.height());
+		for y in 0..tilemap.height() as i32 {
+			for x in 0..tilemap.width() as i32 {
+				let tile = tilemap.get(x, y).unwrap_or_default();
+				grid.set_cost([x, y], cost(tile));
+			}
+		}
+		grid
+	}
+
+	pub fn width(&self) -> u32 {
+		self.width
+	}
+
+	pub fn height(&self) -> u32 {
+		self.height
+	}
+
+	pub fn contains(&self, position: [i32; 2]) -> bool {
+		let [x, y] = position;
+		x >= 0 && y >= 0 && (x as u32) < self.width && (y as u32) < self.height
+	}
+
+	/// Cost of walking through the tile, `None` if it is impassable or outside the grid.
+	pub fn cost(&self, position: [i32; 2]) -> Option<u8> {
+		if !self.contains(position) {
+			return None;
+		}
+		let cost = self.costs[(position[1] as u32 * self.width + position[0] as u32) as usize];
+		(cost > 0).then_some(cost)
+	}
+
+	pub fn is_passable(&self, position: [i32; 2]) -> bool {
+		self.cost(position).is_some()
+	}
+
+	/// Changes the cost of a tile, returning the previous one. Does nothing outside the grid.
+	///
+	/// A cost of zero is treated as 1, use `None` for impassable tiles.
+	pub fn set_cost(&mut self, position: [i32; 2], cost: Option<u8>) -> Option<u8> {
+		if !self.contains(position) {
+			return None;
+		}
+		let previous = self.cost(position);
+		let index = (position[1] as u32 * self.width + position[0] as u32) as usize;
+		self.costs[index] = cost.map_or(0, |cost| cost.max(1));
+		previous
+	}
+}
+
+/// Tiles to walk through from start to goal, both included.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Path {
+	pub tiles: Vec<[i32; 2]>,
+	/// Sum of the step costs, in units of [`ORTHOGONAL_COST`].
+	pub cost: u32,
+}
+
+/// Handle to a path request made with [`Pathfinder::request`].
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct PathRequest(u64);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PathResult {
+	Found(Arc<Path>),
+	/// The goal is impassable or cut off from the start.
+	Unreachable,
+}
+
+impl From<Option<Arc<Path>>> for PathResult {
+	fn from(path: Option<Arc<Path>>) -> Self {
+		path.map_or(PathResult::Unreachable, PathResult::Found)
+	}
+}
+
+/// The search in progress, if any.
+struct Active {
+	request: PathRequest,
+	start: [i32; 2],
+	goal: [i32; 2],
+}
+
+/// Finds paths over a [`NavGrid`], either straight away or spread over ticks.
+///
+/// Requests are answered first come first served within a budget of expanded tiles per call
+/// to [`run`](Self::run), so a burst of requests delays answers rather than stalling a frame.
+/// Results are cached until a tile change could make them wrong, and requests between tiles
+/// that are not connected at all are answered without searching.
+pub struct Pathfinder {
+	grid: NavGrid,
+	regions: Regions,
+	/// Set when passability has changed since `regions` was labelled.
+	regions_stale: bool,
+	search: Search,
+	cache: PathCache,
+	queue: VecDeque<(PathRequest, [i32; 2], [i32; 2])>,
+	active: Option<Active>,
+	results: HashMap<PathRequest, PathResult>,
+	next_request: u64,
+}
+
+impl Pathfinder {
+	pub fn new(grid: NavGrid) -> Self {
+		Self::with_cache_capacity(grid, DEFAULT_CACHE_CAPACITY)
+	}
+
+	pub fn with_cache_capacity(grid: NavGrid, capacity: usize) -> Self {
+		Self {
+			regions: Regions::new(&grid),
+			regions_stale: false,
+			grid,
+			search: Search::default(),
+			cache: PathCache::new(capacity),
+			queue: VecDeque::new(),
+			active: None,
+			results: HashMap::new(),
+			next_request: 0,
+		}
+	}
+
+	pub fn grid(&self) -> &NavGrid {
+		&self.grid
+	}
+
+	/// Connected regions of the grid, relabelled first if passability has changed.
+	pub fn regions(&mut self) -> &Regions {
+		if self.regions_stale {
+			self.regions = Regions::new(&self.grid);
+			self.regions_stale = false;
+		}
+		&self.regions
+	}
+
+	/// Whether a search from `start` could reach `goal`. Searches may start on impassable tiles,
+	/// such as a colonist standing in a doorway that was just built over, so those are only
+	/// ruled out by the search itself.
+	fn may_reach(&mut self, start: [i32; 2], goal: [i32; 2]) -> bool {
+		!self.grid.is_passable(start) || self.regions().connected(start, goal)
+	}
+
+	/// Number of finished searches currently cached.
+	pub fn cached(&self) -> usize {
+		self.cache.len()
+	}
+
+	/// Requests waiting for or in the middle of a search.
+	pub fn pending(&self) -> usize {
+		self.queue.len() + self.active.is_some() as usize
+	}
+
+	/// Changes the cost of a tile, dropping cached paths it could affect and restarting the
+	/// search in progress if it already looked at the tile.
+	pub fn set_cost(&mut self, position: [i32; 2], cost: Option<u8>) {
+		if !self.grid.contains(position) {
+			return;
+		}
+		let previous = self.grid.set_cost(position, cost);
+		let cost = self.grid.cost(position);
+		if previous == cost {
+			return;
+		}
+		if previous.is_some() != cost.is_some() {
+			self.regions_stale = true;
+		}
+
+		// `None` is impassable, so it is the most expensive cost rather than the cheapest.
+		let cheaper = match (previous, cost) {
+			(_, None) => false,
+			(None, Some(_)) => true,
+			(Some(previous), Some(cost)) => cost < previous,
+		};
+		self.cache.invalidate(position, cheaper);
+
+		if let Some(active) = &self.active {
+			if self.search.bounds().contains(position) {
+				self.search.begin(&self.grid, active.start, active.goal);
+			}
+		}
+	}
+
+	/// Queues a search from `start` to `goal`. Answered by [`result`](Self::result) once
+	/// [`run`](Self::run) has found it, or straight away if it is cached.
+	pub fn request(&mut self, start: [i32; 2], goal: [i32; 2]) -> PathRequest {
+		let request = PathRequest(self.next_request);
+		self.next_request += 1;
+		if !self.may_reach(start, goal) {
+			self.results.insert(request, PathResult::Unreachable);
+			return request;
+		}
+		match self.cache.get(start, goal) {
+			Some(path) => {
+				self.results.insert(request, path.into());
+			}
+			None => self.queue.push_back((request, start, goal)),
+		}
+		request
+	}
+
+	/// Takes the answer to a request, or `None` while it is still waiting.
+	pub fn result(&mut self, request: PathRequest) -> Option<PathResult> {
+		self.results.remove(&request)
+	}
+
+	/// Forgets a request, whether or not it has been answered.
+	pub fn cancel(&mut self, request: PathRequest) {
+		self.results.remove(&request);
+		self.queue.retain(|&(queued, _, _)| queued != request);
+		if self.active.as_ref().is_some_and(|active| active.request == request) {
+			self.active = None;
+		}
+	}
+
+	/// Works through waiting requests, expanding at most `budget` tiles. Returns how many were
+	/// expanded.
+	pub fn run(&mut self, budget: u32) -> u32 {
+		let mut remaining = budget;
+		while remaining > 0 {
+			let active = match self.active.take() {
+				Some(active) => active,
+				None => {
+					let Some((request, start, goal)) = self.queue.pop_front() else {
+						break;
+					};
+					// The map may have changed, or an earlier request for the same path been
+					// answered, since this one was queued.
+					if !self.may_reach(start, goal) {
+						self.results.insert(request, PathResult::Unreachable);
+						continue;
+					}
+					if let Some(path) = self.cache.get(start, goal) {
+						self.results.insert(request, path.into());
+						continue;
+					}
+					self.search.begin(&self.grid, start, goal);
+					Active { request, start, goal }
+				}
+			};
+
+			match self.search.step(&self.grid, &mut remaining) {
+				Some(outcome) => {
+					let path = outcome.path.map(Arc::new);
+					self.cache.insert(active.start, active.goal, path.clone(), outcome.bounds);
+					self.results.insert(active.request, path.into());
+				}
+				None => self.active = Some(active),
+			}
+		}
+		budget - remaining
+	}
+
+	/// Finds a path straight away, however long it takes.
+	pub fn find_path(&mut self, start: [i32; 2], goal: [i32; 2]) -> PathResult {
+		if !self.may_reach(start, goal) {
+			return PathResult::Unreachable;
+		}
+		if let Some(path) = self.cache.get(start, goal) {
+			return path.into();
+		}
+
+		// The shared search state is borrowed, so a search in progress starts over next run.
+		if let Some(active) = self.active.take() {
+			self.queue.push_front((active.request, active.start, active.goal));
+		}
+		self.search.begin(&self.grid, start, goal);
+		let outcome = loop {
+			let mut budget = u32::MAX;
+			if let Some(outcome) = self.search.step(&self.grid, &mut budget) {
+				break outcome;
+			}
+		};
+		let path = outcome.path.map(Arc::new);
+		self.cache.insert(start, goal, path.clone(), outcome.bounds);
+		path.into()
+	}
+
+	/// Drops every cached path.
+	pub fn clear_cache(&mut self) {
+		self.cache.clear();
+	}
+}
+
+/// Octile distance at the lowest tile cost, which never overestimates.
+fn heuristic(from: [i32; 2], to: [i32; 2]) -> u32 {
+	let dx = from[0].abs_diff(to[0]);
+	let dy = from[1].abs_diff(to[1]);
+	ORTHOGONAL_COST * dx.max(dy) + (DIAGONAL_COST - ORTHOGONAL_COST) * dx.min(dy)
+}
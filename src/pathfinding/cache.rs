@@ -0,0 +1,71 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use super::Path;
+use super::search::Bounds;
+
+struct Entry {
+	/// `None` when the goal could not be reached.
+	path: Option<Arc<Path>>,
+	bounds: Bounds,
+}
+
+/// Finished searches by start and goal, dropped as soon as a tile change could alter them.
+pub(super) struct PathCache {
+	capacity: usize,
+	entries: HashMap<([i32; 2], [i32; 2]), Entry>,
+	/// Keys oldest first, for evicting when full.
+	order: VecDeque<([i32; 2], [i32; 2])>,
+}
+
+impl PathCache {
+	pub fn new(capacity: usize) -> Self {
+		Self {
+			capacity,
+			entries: HashMap::new(),
+			order: VecDeque::new(),
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	/// The cached result, `Some(None)` meaning the goal is known to be unreachable.
+	pub fn get(&self, start: [i32; 2], goal: [i32; 2]) -> Option<Option<Arc<Path>>> {
+		self.entries.get(&(start, goal)).map(|entry| entry.path.clone())
+	}
+
+	pub fn insert(&mut self, start: [i32; 2], goal: [i32; 2], path: Option<Arc<Path>>, bounds: Bounds) {
+		if self.capacity == 0 {
+			return;
+		}
+		if self.entries.insert((start, goal), Entry { path, bounds }).is_none() {
+			self.order.push_back((start, goal));
+		}
+		if self.entries.len() > self.capacity {
+			let oldest = self.order.pop_front().expect("every entry is in the order");
+			self.entries.remove(&oldest);
+		}
+	}
+
+	/// Drops every entry a change to the cost of `position` could make wrong.
+	///
+	/// Any change matters to paths through the tile. A cheaper tile can also open up a better
+	/// route, but only for searches that looked at it: anything further away was already known
+	/// to cost more than the path that was found.
+	pub fn invalidate(&mut self, position: [i32; 2], cheaper: bool) {
+		self.entries.retain(|_, entry| {
+			let passes = entry.path.as_ref().is_some_and(|path| path.tiles.contains(&position));
+			let searched = entry.bounds.contains(position);
+			!(searched && (cheaper || passes))
+		});
+		let entries = &self.entries;
+		self.order.retain(|key| entries.contains_key(key));
+	}
+
+	pub fn clear(&mut self) {
+		self.entries.clear();
+		self.order.clear();
+	}
+}
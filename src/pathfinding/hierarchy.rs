@@ -0,0 +1,473 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::Arc;
+
+use super::regions::Regions;
+use super::{DIAGONAL_COST, NavGrid, ORTHOGONAL_COST, Path, PathResult, heuristic};
+
+/// Width and height of a cluster in tiles.
+pub const CLUSTER_SIZE: i32 = 16;
+
+/// Most tiles between transitions along an open stretch of border. Narrow openings get one
+/// transition in the middle, wider ones get one at each end and more in between, so paths do
+/// not have to bend towards a few crossing points.
+const TRANSITION_SPACING: usize = 5;
+
+/// Tiles of path straightened at a time after walking the abstract steps, and how far around
+/// them the straighter route may stray.
+const SMOOTHING_WINDOW: usize = 2 * CLUSTER_SIZE as usize;
+const SMOOTHING_MARGIN: i32 = 3;
+
+/// Moves to the eight neighbours, orthogonal ones first.
+const DIRECTIONS: [[i32; 2]; 8] = [[1, 0], [-1, 0], [0, 1], [0, -1], [1, 1], [1, -1], [-1, 1], [-1, -1]];
+
+/// Inclusive tile bounds `[min_x, min_y, max_x, max_y]`.
+type Area = [i32; 4];
+
+#[derive(Default)]
+struct Cluster {
+	/// Transition tiles on the border, the nodes of the abstract graph.
+	nodes: Vec<[i32; 2]>,
+	/// Costs from each node to the other nodes of the cluster and across the border.
+	edges: HashMap<[i32; 2], Vec<([i32; 2], u32)>>,
+}
+
+/// A state of the abstract search. The start and goal are kept apart from transition nodes on
+/// the same tile, whose edges they do not share.
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+enum Key {
+	Start,
+	Node([i32; 2]),
+	Goal,
+}
+
+/// Pathfinding over a coarse graph of clusters, for maps too large for plain A* to answer many
+/// requests.
+///
+/// The map is cut into square clusters. Wherever two clusters share an open stretch of border
+/// there are transition tiles on both sides, and the cost of walking between every two
+/// transitions of a cluster is worked out ahead of time. A query only searches that small
+/// graph, then walks each step of the result inside its cluster and straightens the result a
+/// window at a time. Paths found this way are close to the shortest, not always exactly the
+/// shortest.
+///
+/// Changing a tile only rebuilds the clusters next to it, the next time a path is asked for.
+pub struct HierarchicalPathfinder {
+	grid: NavGrid,
+	regions: Regions,
+	regions_stale: bool,
+	clusters_x: i32,
+	clusters_y: i32,
+	clusters: Vec<Cluster>,
+	dirty: Vec<bool>,
+	local: LocalSearch,
+}
+
+impl HierarchicalPathfinder {
+	pub fn new(grid: NavGrid) -> Self {
+		let clusters_x = grid.width().div_ceil(CLUSTER_SIZE as u32) as i32;
+		let clusters_y = grid.height().div_ceil(CLUSTER_SIZE as u32) as i32;
+		let count = (clusters_x * clusters_y) as usize;
+
+		let mut pathfinder = Self {
+			regions: Regions::new(&grid),
+			grid,
+			regions_stale: false,
+			clusters_x,
+			clusters_y,
+			clusters: (0..count).map(|_| Cluster::default()).collect(),
+			dirty: vec![true; count],
+			local: LocalSearch::default(),
+		};
+		pathfinder.refresh();
+		pathfinder
+	}
+
+	pub fn grid(&self) -> &NavGrid {
+		&self.grid
+	}
+
+	pub fn regions(&mut self) -> &Regions {
+		if self.regions_stale {
+			self.regions = Regions::new(&self.grid);
+			self.regions_stale = false;
+		}
+		&self.regions
+	}
+
+	/// Number of transition nodes in the abstract graph.
+	pub fn node_count(&mut self) -> usize {
+		self.refresh();
+		self.clusters.iter().map(|cluster| cluster.nodes.len()).sum()
+	}
+
+	/// Changes the cost of a tile. The clusters it borders are rebuilt before the next query.
+	pub fn set_cost(&mut self, position: [i32; 2], cost: Option<u8>) {
+		if !self.grid.contains(position) {
+			return;
+		}
+		let previous = self.grid.set_cost(position, cost);
+		if previous.is_some() != cost.is_some() {
+			self.regions_stale = true;
+		}
+
+		let [x, y] = position;
+		let [cluster_x, cluster_y] = [x / CLUSTER_SIZE, y / CLUSTER_SIZE];
+		self.mark_dirty(cluster_x, cluster_y);
+		// Tiles on a cluster's edge decide the transitions of the cluster next to it too.
+		match x % CLUSTER_SIZE {
+			0 => self.mark_dirty(cluster_x - 1, cluster_y),
+			edge if edge == CLUSTER_SIZE - 1 => self.mark_dirty(cluster_x + 1, cluster_y),
+			_ => {}
+		}
+		match y % CLUSTER_SIZE {
+			0 => self.mark_dirty(cluster_x, cluster_y - 1),
+			edge if edge == CLUSTER_SIZE - 1 => self.mark_dirty(cluster_x, cluster_y + 1),
+			_ => {}
+		}
+	}
+
+	fn mark_dirty(&mut self, cluster_x: i32, cluster_y: i32) {
+		if (0..self.clusters_x).contains(&cluster_x) && (0..self.clusters_y).contains(&cluster_y) {
+			self.dirty[(cluster_y * self.clusters_x + cluster_x) as usize] = true;
+		}
+	}
+
+	fn cluster_of(&self, position: [i32; 2]) -> usize {
+		(position[1] / CLUSTER_SIZE * self.clusters_x + position[0] / CLUSTER_SIZE) as usize
+	}
+
+	fn area(&self, cluster: usize) -> Area {
+		let x = cluster as i32 % self.clusters_x * CLUSTER_SIZE;
+		let y = cluster as i32 / self.clusters_x * CLUSTER_SIZE;
+		[
+			x,
+			y,
+			(x + CLUSTER_SIZE).min(self.grid.width() as i32) - 1,
+			(y + CLUSTER_SIZE).min(self.grid.height() as i32) - 1,
+		]
+	}
+
+	/// Rebuilds every cluster whose tiles have changed.
+	fn refresh(&mut self) {
+		for cluster in 0..self.clusters.len() {
+			if self.dirty[cluster] {
+				self.rebuild(cluster);
+				self.dirty[cluster] = false;
+			}
+		}
+	}
+
+	fn rebuild(&mut self, cluster: usize) {
+		let [min_x, min_y, max_x, max_y] = self.area(cluster);
+		let mut crossings = Vec::new();
+
+		// Each side as the tiles along it inside the cluster and the step out of it.
+		let sides = [
+			((min_y..=max_y).map(|y| [min_x, y]).collect::<Vec<_>>(), [-1, 0]),
+			((min_y..=max_y).map(|y| [max_x, y]).collect(), [1, 0]),
+			((min_x..=max_x).map(|x| [x, min_y]).collect(), [0, -1]),
+			((min_x..=max_x).map(|x| [x, max_y]).collect(), [0, 1]),
+		];
+		for (tiles, [dx, dy]) in sides {
+			let open = |&[x, y]: &[i32; 2]| self.grid.is_passable([x, y]) && self.grid.is_passable([x + dx, y + dy]);
+			let mut run_start = None;
+			for index in 0..=tiles.len() {
+				match (tiles.get(index).is_some_and(open), run_start) {
+					(true, None) => run_start = Some(index),
+					(false, Some(first)) => {
+						let last = index - 1;
+						// Both clusters pick the same tiles, as they see the same stretch of border.
+						let gaps = (last - first).div_ceil(TRANSITION_SPACING);
+						let picks = match gaps {
+							0 | 1 if last - first < TRANSITION_SPACING / 2 => vec![(first + last) / 2],
+							_ => (0..=gaps).map(|gap| first + (last - first) * gap / gaps).collect(),
+						};
+						for pick in picks {
+							let [x, y] = tiles[pick];
+							crossings.push(([x, y], [x + dx, y + dy]));
+						}
+						run_start = None;
+					}
+					_ => {}
+				}
+			}
+		}
+
+		let mut nodes = crossings.iter().map(|&(inside, _)| inside).collect::<Vec<_>>();
+		nodes.sort_unstable();
+		nodes.dedup();
+
+		let area = self.area(cluster);
+		let mut edges: HashMap<[i32; 2], Vec<([i32; 2], u32)>> = HashMap::new();
+		for &node in &nodes {
+			self.local.run(&self.grid, area, node, false);
+			let list = edges.entry(node).or_default();
+			for &other in &nodes {
+				if other != node {
+					if let Some(cost) = self.local.cost(other) {
+						list.push((other, cost));
+					}
+				}
+			}
+		}
+		for (inside, outside) in crossings {
+			let cost = ORTHOGONAL_COST * self.grid.cost(outside).expect("crossings lead to open tiles") as u32;
+			edges.entry(inside).or_default().push((outside, cost));
+		}
+
+		self.clusters[cluster] = Cluster { nodes, edges };
+	}
+
+	/// Finds a path from `start` to `goal`, which are both unreachable if either is impassable.
+	pub fn find_path(&mut self, start: [i32; 2], goal: [i32; 2]) -> PathResult {
+		if !self.regions().connected(start, goal) {
+			return PathResult::Unreachable;
+		}
+		if start == goal {
+			return PathResult::Found(Arc::new(Path {
+				tiles: vec![start],
+				cost: 0,
+			}));
+		}
+		self.refresh();
+
+		let start_cluster = self.cluster_of(start);
+		let goal_cluster = self.cluster_of(goal);
+
+		// Ways out of the start's cluster, and into the goal from its cluster's transitions.
+		self.local.run(&self.grid, self.area(start_cluster), start, false);
+		let mut from_start = self.clusters[start_cluster].nodes.iter()
+			.filter_map(|&node| Some((Key::Node(node), self.local.cost(node)?)))
+			.collect::<Vec<_>>();
+		if start_cluster == goal_cluster {
+			if let Some(cost) = self.local.cost(goal) {
+				from_start.push((Key::Goal, cost));
+			}
+		}
+		self.local.run(&self.grid, self.area(goal_cluster), goal, true);
+		let to_goal = self.clusters[goal_cluster].nodes.iter()
+			.filter_map(|&node| Some((node, self.local.cost(node)?)))
+			.collect::<HashMap<_, _>>();
+
+		let Some(keys) = self.search(start, goal, &from_start, &to_goal) else {
+			return PathResult::Unreachable;
+		};
+
+		// Walk each abstract step on the grid. Steps inside one cluster are searched again,
+		// steps across a border are a single move.
+		let positions = keys.iter().map(|&key| match key {
+			Key::Start => start,
+			Key::Node(position) => position,
+			Key::Goal => goal,
+		}).collect::<Vec<_>>();
+		let mut tiles = vec![start];
+		for step in positions.windows(2) {
+			let [from, to] = [step[0], step[1]];
+			let cluster = self.cluster_of(from);
+			if cluster == self.cluster_of(to) {
+				self.local.run(&self.grid, self.area(cluster), from, false);
+				tiles.extend(self.local.path_to(to).into_iter().skip(1));
+			} else {
+				tiles.push(to);
+			}
+		}
+
+		self.smooth(&mut tiles);
+
+		let cost = tiles.windows(2).map(|step| step_cost(&self.grid, step[0], step[1])).sum();
+		PathResult::Found(Arc::new(Path { tiles, cost }))
+	}
+
+	/// Replaces overlapping windows of the path with the cheapest route between their ends
+	/// within a little of the window, taking out the detours through transition tiles.
+	fn smooth(&mut self, tiles: &mut Vec<[i32; 2]>) {
+		let mut first = 0;
+		loop {
+			let last = (first + SMOOTHING_WINDOW).min(tiles.len() - 1);
+			let mut area = [i32::MAX, i32::MAX, i32::MIN, i32::MIN];
+			for &[x, y] in &tiles[first..=last] {
+				area = [area[0].min(x), area[1].min(y), area[2].max(x), area[3].max(y)];
+			}
+			let area = [
+				(area[0] - SMOOTHING_MARGIN).max(0),
+				(area[1] - SMOOTHING_MARGIN).max(0),
+				(area[2] + SMOOTHING_MARGIN).min(self.grid.width() as i32 - 1),
+				(area[3] + SMOOTHING_MARGIN).min(self.grid.height() as i32 - 1),
+			];
+
+			// The window itself lies in the area, so the replacement is never worse.
+			self.local.run(&self.grid, area, tiles[first], false);
+			let straighter = self.local.path_to(tiles[last]);
+			let end = first + straighter.len() - 1;
+			let finished = last == tiles.len() - 1;
+			tiles.splice(first..=last, straighter);
+			if finished {
+				break;
+			}
+			first = (first + SMOOTHING_WINDOW / 2).min(end);
+		}
+	}
+
+	/// A* over the transition nodes, returning the abstract steps from start to goal.
+	fn search(
+		&self,
+		start: [i32; 2],
+		goal: [i32; 2],
+		from_start: &[(Key, u32)],
+		to_goal: &HashMap<[i32; 2], u32>,
+	) -> Option<Vec<Key>> {
+		let goal_cluster = self.cluster_of(goal);
+		let estimate = |key: Key| match key {
+			Key::Start => heuristic(start, goal),
+			Key::Node(position) => heuristic(position, goal),
+			Key::Goal => 0,
+		};
+
+		let mut best: HashMap<Key, (u32, Key)> = HashMap::from([(Key::Start, (0, Key::Start))]);
+		let mut open = BinaryHeap::from([Reverse((estimate(Key::Start), Key::Start))]);
+		let mut edges = Vec::new();
+
+		while let Some(Reverse((estimated, key))) = open.pop() {
+			let cost = best[&key].0;
+			// Left behind when a cheaper way to the same node was found.
+			if estimated > cost + estimate(key) {
+				continue;
+			}
+			if key == Key::Goal {
+				let mut keys = vec![Key::Goal];
+				let mut current = Key::Goal;
+				while current != Key::Start {
+					current = best[&current].1;
+					keys.push(current);
+				}
+				keys.reverse();
+				return Some(keys);
+			}
+
+			edges.clear();
+			match key {
+				Key::Start => edges.extend_from_slice(from_start),
+				Key::Node(position) => {
+					let cluster = &self.clusters[self.cluster_of(position)];
+					let neighbours = cluster.edges.get(&position).into_iter().flatten();
+					edges.extend(neighbours.map(|&(next, cost)| (Key::Node(next), cost)));
+					if self.cluster_of(position) == goal_cluster {
+						edges.extend(to_goal.get(&position).map(|&cost| (Key::Goal, cost)));
+					}
+				}
+				Key::Goal => unreachable!("the search stops at the goal"),
+			}
+
+			for &(next, step) in &edges {
+				let next_cost = cost + step;
+				if best.get(&next).is_some_and(|&(known, _)| known <= next_cost) {
+					continue;
+				}
+				best.insert(next, (next_cost, key));
+				open.push(Reverse((next_cost + estimate(next), next)));
+			}
+		}
+		None
+	}
+}
+
+/// Cost of one move between neighbouring tiles.
+fn step_cost(grid: &NavGrid, from: [i32; 2], to: [i32; 2]) -> u32 {
+	let diagonal = from[0] != to[0] && from[1] != to[1];
+	let step = if diagonal { DIAGONAL_COST } else { ORTHOGONAL_COST };
+	step * grid.cost(to).expect("paths only enter open tiles") as u32
+}
+
+/// Dijkstra from one tile to every tile of a small area, reused for every cluster.
+#[derive(Default)]
+struct LocalSearch {
+	area: Area,
+	/// `u32::MAX` for tiles not reached.
+	cost: Vec<u32>,
+	/// The tile each tile was reached from, or the next tile towards the source when searching
+	/// in reverse.
+	parent: Vec<usize>,
+	open: BinaryHeap<Reverse<(u32, usize)>>,
+}
+
+impl LocalSearch {
+	fn index(&self, position: [i32; 2]) -> Option<usize> {
+		let [min_x, min_y, max_x, max_y] = self.area;
+		let [x, y] = position;
+		if x < min_x || y < min_y || x > max_x || y > max_y {
+			return None;
+		}
+		Some(((y - min_y) * (max_x - min_x + 1) + (x - min_x)) as usize)
+	}
+
+	fn position(&self, index: usize) -> [i32; 2] {
+		let [min_x, min_y, max_x, _] = self.area;
+		let width = (max_x - min_x + 1) as usize;
+		[min_x + (index % width) as i32, min_y + (index / width) as i32]
+	}
+
+	/// Costs from `source` to every tile of `area` without leaving it, or from every tile to
+	/// `source` when `reverse` is set.
+	fn run(&mut self, grid: &NavGrid, area: Area, source: [i32; 2], reverse: bool) {
+		self.area = area;
+		let tiles = ((area[2] - area[0] + 1) * (area[3] - area[1] + 1)) as usize;
+		self.cost.clear();
+		self.cost.resize(tiles, u32::MAX);
+		self.parent.clear();
+		self.parent.resize(tiles, 0);
+		self.open.clear();
+
+		let source = self.index(source).expect("source is inside the area");
+		self.cost[source] = 0;
+		self.parent[source] = source;
+		self.open.push(Reverse((0, source)));
+
+		while let Some(Reverse((cost, index))) = self.open.pop() {
+			if cost > self.cost[index] {
+				continue;
+			}
+			let [x, y] = self.position(index);
+			for [dx, dy] in DIRECTIONS {
+				let next = [x + dx, y + dy];
+				let Some(next_index) = self.index(next) else {
+					continue;
+				};
+				if !grid.is_passable(next) {
+					continue;
+				}
+				if dx != 0 && dy != 0 && (!grid.is_passable([x + dx, y]) || !grid.is_passable([x, y + dy])) {
+					continue;
+				}
+				// Searching backwards, the move is from `next` onto this tile.
+				let step = match reverse {
+					true => step_cost(grid, next, [x, y]),
+					false => step_cost(grid, [x, y], next),
+				};
+				let next_cost = cost + step;
+				if next_cost < self.cost[next_index] {
+					self.cost[next_index] = next_cost;
+					self.parent[next_index] = index;
+					self.open.push(Reverse((next_cost, next_index)));
+				}
+			}
+		}
+	}
+
+	fn cost(&self, position: [i32; 2]) -> Option<u32> {
+		let cost = self.cost[self.index(position)?];
+		(cost != u32::MAX).then_some(cost)
+	}
+
+	/// Tiles from the source to `target`, after a forward search that reached it.
+	fn path_to(&self, target: [i32; 2]) -> Vec<[i32; 2]> {
+		let mut index = self.index(target).expect("target is inside the area");
+		let mut tiles = vec![target];
+		while self.parent[index] != index {
+			index = self.parent[index];
+			tiles.push(self.position(index));
+		}
+		tiles.reverse();
+		tiles
+	}
+}
@@ -0,0 +1,73 @@
+use std::collections::VecDeque;
+
+use super::NavGrid;
+
+/// Labels every passable tile with the connected region it belongs to, so two tiles can be
+/// checked for a path between them in constant time.
+///
+/// Diagonal moves are only allowed when both orthogonal neighbours are open, so tiles that are
+/// connected at all are connected orthogonally and a 4-way flood fill finds the regions.
+#[derive(Clone, Debug, Default)]
+pub struct Regions {
+	width: u32,
+	/// Zero for impassable tiles, otherwise the region counting from 1.
+	labels: Vec<u32>,
+	count: u32,
+}
+
+impl Regions {
+	pub fn new(grid: &NavGrid) -> Self {
+		let mut regions = Self {
+			width: grid.width(),
+			labels: vec![0; (grid.width() * grid.height()) as usize],
+			count: 0,
+		};
+
+		let mut queue = VecDeque::new();
+		for y in 0..grid.height() as i32 {
+			for x in 0..grid.width() as i32 {
+				if !grid.is_passable([x, y]) || regions.label([x, y]).is_some() {
+					continue;
+				}
+				regions.count += 1;
+				regions.set([x, y]);
+				queue.push_back([x, y]);
+
+				while let Some([x, y]) = queue.pop_front() {
+					for next in [[x + 1, y], [x - 1, y], [x, y + 1], [x, y - 1]] {
+						if grid.is_passable(next) && regions.label(next).is_none() {
+							regions.set(next);
+							queue.push_back(next);
+						}
+					}
+				}
+			}
+		}
+		regions
+	}
+
+	fn set(&mut self, position: [i32; 2]) {
+		let index = (position[1] as u32 * self.width + position[0] as u32) as usize;
+		self.labels[index] = self.count;
+	}
+
+	/// Number of separate regions.
+	pub fn count(&self) -> u32 {
+		self.count
+	}
+
+	/// Region of a passable tile, `None` for impassable tiles and those outside the grid.
+	pub fn label(&self, position: [i32; 2]) -> Option<u32> {
+		let [x, y] = position;
+		if x < 0 || y < 0 || x as u32 >= self.width {
+			return None;
+		}
+		let label = *self.labels.get((y as u32 * self.width + x as u32) as usize)?;
+		(label > 0).then_some(label)
+	}
+
+	/// Whether a path from `from` to `to` exists.
+	pub fn connected(&self, from: [i32; 2], to: [i32; 2]) -> bool {
+		self.label(from).is_some_and(|label| self.label(to) == Some(label))
+	}
+}
@@ -0,0 +1,188 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use super::{DIAGONAL_COST, NavGrid, ORTHOGONAL_COST, Path, heuristic};
+
+/// Moves to the eight neighbours, orthogonal ones first.
+const DIRECTIONS: [[i32; 2]; 8] = [[1, 0], [-1, 0], [0, 1], [0, -1], [1, 1], [1, -1], [-1, 1], [-1, -1]];
+
+/// Area of the grid a search looked at, as inclusive tile bounds `[min_x, min_y, max_x, max_y]`.
+///
+/// A change to a tile outside this area cannot change the result of the search.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(super) struct Bounds([i32; 4]);
+
+impl Bounds {
+	fn around(position: [i32; 2]) -> Self {
+		let [x, y] = position;
+		Self([x - 1, y - 1, x + 1, y + 1])
+	}
+
+	fn include(&mut self, position: [i32; 2]) {
+		let [x, y] = position;
+		let [min_x, min_y, max_x, max_y] = &mut self.0;
+		*min_x = (*min_x).min(x - 1);
+		*min_y = (*min_y).min(y - 1);
+		*max_x = (*max_x).max(x + 1);
+		*max_y = (*max_y).max(y + 1);
+	}
+
+	pub(super) fn contains(&self, position: [i32; 2]) -> bool {
+		let [min_x, min_y, max_x, max_y] = self.0;
+		(min_x..=max_x).contains(&position[0]) && (min_y..=max_y).contains(&position[1])
+	}
+}
+
+/// How a finished search ended, along with the area it looked at.
+#[derive(Debug)]
+pub(super) struct Outcome {
+	pub path: Option<Path>,
+	pub bounds: Bounds,
+}
+
+/// A* over a [`NavGrid`] that can be paused after any number of expanded tiles and resumed later.
+///
+/// Per tile bookkeeping is kept between searches and marked with the search it belongs to, so
+/// starting a search does not clear the whole grid.
+#[derive(Default)]
+pub(super) struct Search {
+	width: i32,
+	/// Search that last touched each tile, to tell stale `cost` and `parent` entries apart.
+	visited: Vec<u32>,
+	closed: Vec<bool>,
+	cost: Vec<u32>,
+	parent: Vec<u32>,
+	/// Ordered by estimated total cost, then by estimated remaining cost, then by tile index, so
+	/// ties are always broken the same way.
+	open: BinaryHeap<Reverse<(u32, u32, u32)>>,
+	current: u32,
+	start: [i32; 2],
+	goal: [i32; 2],
+	bounds: Option<Bounds>,
+}
+
+impl Search {
+	pub fn begin(&mut self, grid: &NavGrid, start: [i32; 2], goal: [i32; 2]) {
+		let tiles = (grid.width() * grid.height()) as usize;
+		if self.visited.len() != tiles || self.current == u32::MAX {
+			*self = Self {
+				visited: vec![0; tiles],
+				closed: vec![false; tiles],
+				cost: vec![0; tiles],
+				parent: vec![0; tiles],
+				..Self::default()
+			};
+		}
+		self.width = grid.width() as i32;
+		self.current += 1;
+		self.open.clear();
+		self.start = start;
+		self.goal = goal;
+		self.bounds = Some(Bounds::around(start));
+
+		if grid.contains(start) {
+			let index = self.index(start);
+			self.visit(index, 0, index as u32);
+			self.open.push(Reverse((heuristic(start, goal), heuristic(start, goal), index as u32)));
+		}
+	}
+
+	fn index(&self, position: [i32; 2]) -> usize {
+		(position[1] * self.width + position[0]) as usize
+	}
+
+	fn position(&self, index: u32) -> [i32; 2] {
+		[index as i32 % self.width, index as i32 / self.width]
+	}
+
+	fn visit(&mut self, index: usize, cost: u32, parent: u32) {
+		self.visited[index] = self.current;
+		self.closed[index] = false;
+		self.cost[index] = cost;
+		self.parent[index] = parent;
+	}
+
+	fn seen(&self, index: usize) -> bool {
+		self.visited[index] == self.current
+	}
+
+	/// Area looked at so far.
+	pub fn bounds(&self) -> Bounds {
+		self.bounds.expect("search has begun")
+	}
+
+	/// Expands at most `budget` tiles, taking what it used from the budget. Returns the outcome
+	/// once the search has finished.
+	pub fn step(&mut self, grid: &NavGrid, budget: &mut u32) -> Option<Outcome> {
+		if !grid.is_passable(self.goal) {
+			return Some(self.finish(None));
+		}
+
+		while *budget > 0 {
+			let Some(Reverse((_, _, index))) = self.open.pop() else {
+				return Some(self.finish(None));
+			};
+			let index = index as usize;
+			if self.closed[index] {
+				continue;
+			}
+			self.closed[index] = true;
+			*budget -= 1;
+
+			let position = self.position(index as u32);
+			self.bounds.as_mut().expect("search has begun").include(position);
+			if position == self.goal {
+				let path = self.path(index);
+				return Some(self.finish(Some(path)));
+			}
+
+			for [dx, dy] in DIRECTIONS {
+				let next = [position[0] + dx, position[1] + dy];
+				let Some(tile_cost) = grid.cost(next) else {
+					continue;
+				};
+				let step = if dx != 0 && dy != 0 {
+					// Cutting a corner would clip whatever blocks either side.
+					if !grid.is_passable([position[0] + dx, position[1]]) || !grid.is_passable([position[0], position[1] + dy]) {
+						continue;
+					}
+					DIAGONAL_COST
+				} else {
+					ORTHOGONAL_COST
+				};
+
+				let cost = self.cost[index] + step * tile_cost as u32;
+				let next_index = self.index(next);
+				if self.seen(next_index) && (self.closed[next_index] || self.cost[next_index] <= cost) {
+					continue;
+				}
+				self.visit(next_index, cost, index as u32);
+				let remaining = heuristic(next, self.goal);
+				self.open.push(Reverse((cost + remaining, remaining, next_index as u32)));
+			}
+		}
+		None
+	}
+
+	fn path(&self, goal: usize) -> Path {
+		let mut tiles = vec![self.position(goal as u32)];
+		let mut index = goal;
+		while self.parent[index] as usize != index {
+			index = self.parent[index] as usize;
+			tiles.push(self.position(index as u32));
+		}
+		tiles.reverse();
+		Path {
+			tiles,
+			cost: self.cost[goal],
+		}
+	}
+
+	fn finish(&mut self, path: Option<Path>) -> Outcome {
+		self.open.clear();
+		Outcome {
+			path,
+			bounds: self.bounds(),
+		}
+	}
+}
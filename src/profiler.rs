@@ -0,0 +1,147 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::renderer::PassTiming;
+
+/// File frame samples are exported to, relative to the working directory.
+pub const DEFAULT_CSV_PATH: &str = "frame-samples.csv";
+
+/// Number of frames kept for statistics and export, a minute at 60 frames per second.
+pub const DEFAULT_CAPACITY: usize = 3600;
+
+/// Measurements of one frame.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct FrameSample {
+	/// Time since the previous frame started.
+	pub frame_time: Duration,
+	/// Time spent updating and encoding the frame on the CPU.
+	pub cpu_time: Duration,
+	/// Time the GPU spent on the passes of a recent frame, when the adapter can measure it. GPU
+	/// results arrive a few frames late, so this belongs to an earlier frame than the other fields.
+	pub gpu_time: Option<Duration>,
+	pub draw_calls: u32,
+	pub vertices: u32,
+}
+
+/// Frame time statistics over a number of frames.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct FrameSummary {
+	pub frames: usize,
+	pub fps: f32,
+	pub min: Duration,
+	pub avg: Duration,
+	pub max: Duration,
+	/// 99th percentile, the frame time only one frame in a hundred exceeds.
+	pub p99: Duration,
+}
+
+/// Rolling record of the most recent frames.
+pub struct Profiler {
+	samples: VecDeque<FrameSample>,
+	capacity: usize,
+	gpu_passes: Vec<PassTiming>,
+}
+
+impl Profiler {
+	pub fn new(capacity: usize) -> Self {
+		Self {
+			samples: VecDeque::with_capacity(capacity),
+			capacity: capacity.max(1),
+			gpu_passes: Vec::new(),
+		}
+	}
+
+	/// Adds a frame, forgetting the oldest one once full.
+	pub fn record(&mut self, sample: FrameSample) {
+		if self.samples.len() == self.capacity {
+			self.samples.pop_front();
+		}
+		self.samples.push_back(sample);
+	}
+
+	/// Records a frame along with the GPU time of each pass, as reported by
+	/// [`Renderer::gpu_timings`](crate::renderer::Renderer::gpu_timings). The frame's GPU time is
+	/// their total.
+	pub fn record_with_passes(&mut self, sample: FrameSample, passes: &[PassTiming]) {
+		let gpu_time = (!passes.is_empty()).then(|| passes.iter().map(|pass| pass.duration).sum());
+		self.record(FrameSample { gpu_time, ..sample });
+		self.gpu_passes.clear();
+		self.gpu_passes.extend_from_slice(passes);
+	}
+
+	/// GPU time of each pass in the most recently measured frame.
+	pub fn gpu_passes(&self) -> &[PassTiming] {
+		&self.gpu_passes
+	}
+
+	/// Recorded frames, oldest first.
+	pub fn samples(&self) -> impl ExactSizeIterator<Item = &FrameSample> + DoubleEndedIterator {
+		self.samples.iter()
+	}
+
+	pub fn latest(&self) -> Option<&FrameSample> {
+		self.samples.back()
+	}
+
+	/// Statistics over the last `frames` frames, or all of them when fewer were recorded.
+	pub fn summary(&self, frames: usize) -> FrameSummary {
+		let mut times = self.samples
+			.iter()
+			.rev()
+			.take(frames)
+			.map(|sample| sample.frame_time)
+			.collect::<Vec<_>>();
+		if times.is_empty() {
+			return FrameSummary::default();
+		}
+		times.sort();
+
+		let total = times.iter().sum::<Duration>();
+		let p99_index = (times.len() * 99).div_ceil(100) - 1;
+		FrameSummary {
+			frames: times.len(),
+			fps: if total.is_zero() { 0.0 } else { times.len() as f32 / total.as_secs_f32() },
+			min: times[0],
+			avg: total / times.len() as u32,
+			max: times[times.len() - 1],
+			p99: times[p99_index],
+		}
+	}
+
+	/// Writes every recorded frame as CSV, times in milliseconds. The GPU time column is empty for
+	/// frames it was not measured for.
+	pub fn write_csv(&self, mut writer: impl Write) -> io::Result<()> {
+		writeln!(writer, "frame,frame_time_ms,cpu_time_ms,gpu_time_ms,draw_calls,vertices")?;
+		for (index, sample) in self.samples.iter().enumerate() {
+			let gpu_time = sample.gpu_time.map(|time| format!("{:.3}", milliseconds(time))).unwrap_or_default();
+			writeln!(
+				writer,
+				"{index},{:.3},{:.3},{gpu_time},{},{}",
+				milliseconds(sample.frame_time),
+				milliseconds(sample.cpu_time),
+				sample.draw_calls,
+				sample.vertices,
+			)?;
+		}
+		Ok(())
+	}
+
+	pub fn export_csv(&self, path: &Path) -> io::Result<()> {
+		let mut writer = BufWriter::new(File::create(path)?);
+		self.write_csv(&mut writer)?;
+		writer.flush()
+	}
+}
+
+impl Default for Profiler {
+	fn default() -> Self {
+		Self::new(DEFAULT_CAPACITY)
+	}
+}
+
+pub fn milliseconds(duration: Duration) -> f64 {
+	duration.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,643 @@
+use std::iter;
+use std::num::NonZeroU32;
+use std::path::Path;
+use std::sync::mpsc::channel;
+
+use wgpu::{
+	Adapter, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BlendState, Buffer,
+	BufferAddress, BufferDescriptor, BufferUsages, Color, ColorTargetState, ColorWrites,
+	COPY_BYTES_PER_ROW_ALIGNMENT, CommandEncoder, CommandEncoderDescriptor, CompositeAlphaMode, Device,
+	DeviceDescriptor, Extent3d, Face, Features, FragmentState, FrontFace, ImageCopyBuffer,
+	ImageCopyTexture, ImageDataLayout, include_wgsl, Instance, Limits, LoadOp, Maintain, MapMode,
+	MultisampleState, Operations, Origin3d, PipelineLayoutDescriptor, PolygonMode, PresentMode,
+	PrimitiveState, PrimitiveTopology, Queue, RenderPass, RenderPassColorAttachment, RenderPassDescriptor,
+	RenderPipeline, RenderPipelineDescriptor, RequestAdapterOptions, Surface, SurfaceConfiguration,
+	SurfaceTexture, TextureAspect, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+	TextureView, TextureViewDescriptor, VertexState,
+};
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use winit::dpi::PhysicalSize;
+use winit::window::Window;
+
+use crate::config::GraphicsConfig;
+use crate::tilemap::Tilemap;
+
+pub use self::atlas::{Atlas, AtlasBuilder, AtlasRegion};
+pub use self::camera::Camera2D;
+pub use self::error::{FontError, FrameError, InitError, TextureError};
+pub use self::image::Image;
+pub use self::sprite::{Sprite, UvRect};
+pub use self::text::{Align, FontId, TextLayout, TextSpan, TextStyle};
+pub use self::texture::TextureId;
+pub use self::tilemap::TileSet;
+pub use self::timestamps::PassTiming;
+
+use self::sprite::{SpriteBatch, SpriteVertex};
+use self::text::{GLYPH_CACHE_SIZE, TextRenderer};
+use self::texture::Texture;
+use self::tilemap::TilemapRenderer;
+use self::timestamps::GpuTimer;
+
+pub mod atlas;
+mod buffer;
+pub mod camera;
+mod error;
+mod image;
+mod sprite;
+mod text;
+mod texture;
+mod tilemap;
+mod timestamps;
+
+/// Format used for offscreen render targets, chosen so read back pixels are plain RGBA bytes.
+pub const OFFSCREEN_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+/// What a [`Renderer`] draws into.
+pub enum RenderTarget {
+	Surface {
+		surface: Surface,
+		config: SurfaceConfiguration,
+	},
+	Texture {
+		texture: wgpu::Texture,
+		size: Extent3d,
+	},
+}
+
+impl RenderTarget {
+	pub fn size(&self) -> PhysicalSize<u32> {
+		match self {
+			RenderTarget::Surface { config, .. } => PhysicalSize::new(config.width, config.height),
+			RenderTarget::Texture { size, .. } => PhysicalSize::new(size.width, size.height),
+		}
+	}
+
+	fn format(&self) -> TextureFormat {
+		match self {
+			RenderTarget::Surface { config, .. } => config.format,
+			RenderTarget::Texture { .. } => OFFSCREEN_FORMAT,
+		}
+	}
+}
+
+/// Work done to draw a frame.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct RenderStats {
+	pub draw_calls: u32,
+	pub vertices: u32,
+}
+
+impl RenderStats {
+	fn add(self, other: RenderStats) -> RenderStats {
+		RenderStats {
+			draw_calls: self.draw_calls + other.draw_calls,
+			vertices: self.vertices + other.vertices,
+		}
+	}
+}
+
+pub struct Renderer {
+	device: Device,
+	queue: Queue,
+	pipeline: RenderPipeline,
+	camera: Camera2D,
+	camera_buffer: Buffer,
+	camera_bind_group: BindGroup,
+	screen_buffer: Buffer,
+	screen_bind_group: BindGroup,
+	texture_layout: BindGroupLayout,
+	textures: Vec<Texture>,
+	white_texture: TextureId,
+	sprites: SpriteBatch,
+	ui_sprites: SpriteBatch,
+	tilemap: TilemapRenderer,
+	text: TextRenderer,
+	stats: RenderStats,
+	gpu_timer: Option<GpuTimer>,
+	target: RenderTarget,
+}
+
+impl Renderer {
+	/// Creates a renderer that presents to the given window.
+	pub async fn new_windowed(window: &Window, config: &GraphicsConfig) -> Result<Self, InitError> {
+		let (adapter, surface) = select_adapter(config, Some(window)).await?;
+		let surface = surface.expect("surface is created for every windowed adapter");
+		let format = *surface.get_supported_formats(&adapter).first().ok_or(InitError::UnsupportedSurface)?;
+
+		let size = window.inner_size();
+		let config = SurfaceConfiguration {
+			usage: TextureUsages::RENDER_ATTACHMENT,
+			format,
+			width: size.width,
+			height: size.height,
+			present_mode: PresentMode::AutoVsync,
+			alpha_mode: CompositeAlphaMode::Auto,
+		};
+
+		let (device, queue) = request_device(&adapter).await?;
+		if size.width > 0 && size.height > 0 {
+			surface.configure(&device, &config);
+		}
+
+		Ok(Self::with_target(device, queue, RenderTarget::Surface { surface, config }))
+	}
+
+	/// Creates a renderer that draws into an offscreen texture of the given size, using the
+	/// software fallback adapter so it works on machines without a GPU.
+	pub async fn new_headless(width: u32, height: u32) -> Result<Self, InitError> {
+		let (adapter, _) = select_adapter(&GraphicsConfig::headless(), None).await?;
+		let (device, queue) = request_device(&adapter).await?;
+
+		let size = Extent3d {
+			width,
+			height,
+			depth_or_array_layers: 1,
+		};
+
+		let texture = device.create_texture(&TextureDescriptor {
+			label: Some("offscreen target"),
+			size,
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: TextureDimension::D2,
+			format: OFFSCREEN_FORMAT,
+			usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+		});
+
+		Ok(Self::with_target(device, queue, RenderTarget::Texture { texture, size }))
+	}
+
+	fn with_target(device: Device, queue: Queue, target: RenderTarget) -> Self {
+		let camera = Camera2D::new(target.size());
+		let camera_layout = Camera2D::create_bind_group_layout(&device);
+		let camera_buffer = device.create_buffer_init(&BufferInitDescriptor {
+			label: Some("camera"),
+			contents: bytemuck::cast_slice(&camera.view_projection()),
+			usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+		});
+		let camera_bind_group = device.create_bind_group(&BindGroupDescriptor {
+			label: Some("camera"),
+			layout: &camera_layout,
+			entries: &[
+				BindGroupEntry {
+					binding: 0,
+					resource: camera_buffer.as_entire_binding(),
+				},
+			],
+		});
+		// Screen space drawing reuses the camera layout with a fixed pixel projection.
+		let screen_buffer = device.create_buffer_init(&BufferInitDescriptor {
+			label: Some("screen"),
+			contents: bytemuck::cast_slice(&screen_projection(target.size())),
+			usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+		});
+		let screen_bind_group = device.create_bind_group(&BindGroupDescriptor {
+			label: Some("screen"),
+			layout: &camera_layout,
+			entries: &[
+				BindGroupEntry {
+					binding: 0,
+					resource: screen_buffer.as_entire_binding(),
+				},
+			],
+		});
+
+		let texture_layout = Texture::create_bind_group_layout(&device);
+		let bind_group_layouts = [&camera_layout, &texture_layout];
+		// Blended in the world pass as well, for translucent sprites such as building previews.
+		let pipeline = create_render_pipeline(&device, &bind_group_layouts, target.format(), BlendState::ALPHA_BLENDING);
+		let sprites = SpriteBatch::new(&device);
+		let ui_sprites = SpriteBatch::new(&device);
+
+		let mut renderer = Self {
+			device,
+			queue,
+			pipeline,
+			camera,
+			camera_buffer,
+			camera_bind_group,
+			screen_buffer,
+			screen_bind_group,
+			texture_layout,
+			textures: Vec::new(),
+			white_texture: TextureId(0),
+			sprites,
+			ui_sprites,
+			tilemap: TilemapRenderer::default(),
+			text: TextRenderer::new(TextureId(0)),
+			stats: RenderStats::default(),
+			gpu_timer: None,
+			target,
+		};
+		renderer.white_texture = renderer.add_texture("white", 1, 1, &[255; 4]);
+		let glyph_cache = renderer.add_texture(
+			"glyph cache",
+			GLYPH_CACHE_SIZE,
+			GLYPH_CACHE_SIZE,
+			&vec![0; (GLYPH_CACHE_SIZE * GLYPH_CACHE_SIZE * 4) as usize],
+		);
+		renderer.text = TextRenderer::new(glyph_cache);
+		renderer.gpu_timer = GpuTimer::new(&renderer.device, &renderer.queue);
+		renderer
+	}
+
+	pub fn device(&self) -> &Device {
+		&self.device
+	}
+
+	pub fn queue(&self) -> &Queue {
+		&self.queue
+	}
+
+	pub fn target(&self) -> &RenderTarget {
+		&self.target
+	}
+
+	pub fn camera(&self) -> &Camera2D {
+		&self.camera
+	}
+
+	pub fn camera_mut(&mut self) -> &mut Camera2D {
+		&mut self.camera
+	}
+
+	/// Uploads tightly packed RGBA pixels as a texture sprites can be drawn with.
+	pub fn add_texture(&mut self, label: &str, width: u32, height: u32, pixels: &[u8]) -> TextureId {
+		let texture = Texture::from_rgba(&self.device, &self.queue, &self.texture_layout, label, width, height, pixels);
+		self.textures.push(texture);
+		TextureId(self.textures.len() - 1)
+	}
+
+	pub fn load_texture(&mut self, path: &Path) -> Result<TextureId, TextureError> {
+		let image = Image::load_png(path)?;
+		Ok(self.add_texture(&path.display().to_string(), image.width, image.height, &image.pixels))
+	}
+
+	pub fn texture_size(&self, texture: TextureId) -> (u32, u32) {
+		let texture = &self.textures[texture.0];
+		(texture.width, texture.height)
+	}
+
+	/// A single white pixel, for drawing untextured sprites with a tint.
+	pub fn white_texture(&self) -> TextureId {
+		self.white_texture
+	}
+
+	/// Sets the atlas regions tiles are drawn with.
+	pub fn set_tileset(&mut self, tileset: TileSet) {
+		self.tilemap.set_tileset(tileset);
+	}
+
+	/// Draws `tilemap` beneath the sprites in the next [`render`](Self::render), updating the
+	/// meshes of any visible chunks that changed.
+	pub fn draw_tilemap(&mut self, tilemap: &Tilemap) {
+		self.tilemap.prepare(&self.device, tilemap, &self.camera);
+	}
+
+	/// Draw calls and vertices of the last frame drawn by [`render`](Self::render).
+	pub fn stats(&self) -> RenderStats {
+		self.stats
+	}
+
+	/// Queues a sprite to be drawn by the next [`render`](Self::render).
+	pub fn draw_sprite(&mut self, sprite: Sprite) {
+		self.sprites.push(sprite);
+	}
+
+	/// Queues a sprite to be drawn over the world, alpha blended, with its position and size in
+	/// screen pixels from the top left of the target.
+	pub fn draw_ui_sprite(&mut self, sprite: Sprite) {
+		// The screen projection keeps y pointing up so sprite corners stay counter clockwise.
+		self.ui_sprites.push(Sprite {
+			position: [sprite.position[0], -sprite.position[1]],
+			..sprite
+		});
+	}
+
+	pub fn load_font(&mut self, path: &Path) -> Result<FontId, FontError> {
+		self.text.load_font(path)
+	}
+
+	/// Breaks `spans` into lines and positions their glyphs. Layouts can be kept and drawn again
+	/// while the text is unchanged.
+	pub fn layout_text(&self, spans: &[TextSpan], style: &TextStyle) -> TextLayout {
+		self.text.layout(spans, style)
+	}
+
+	/// Queues text to be drawn over the world with its top left corner at `position` in screen
+	/// pixels.
+	pub fn draw_text(&mut self, layout: &TextLayout, position: [f32; 2]) {
+		let texture = &self.textures[self.text.cache_texture().0];
+		let ui_sprites = &mut self.ui_sprites;
+		self.text.queue(layout, position, &self.queue, texture, |sprite| {
+			ui_sprites.push(Sprite {
+				position: [sprite.position[0], -sprite.position[1]],
+				..sprite
+			});
+		});
+	}
+
+	/// Reconfigures the window surface and camera for a new size. Offscreen targets keep their
+	/// size.
+	pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
+		if let RenderTarget::Surface { surface, config } = &mut self.target {
+			self.camera.resize(new_size);
+			config.width = new_size.width;
+			config.height = new_size.height;
+			// A minimised window has no area to draw to, the surface is configured again once it
+			// is restored.
+			if new_size.width > 0 && new_size.height > 0 {
+				surface.configure(&self.device, config);
+			}
+		}
+	}
+
+	pub fn render(&mut self) -> Result<(), FrameError> {
+		let (output, view) = match self.acquire_frame() {
+			Ok(Some(frame)) => frame,
+			skipped => {
+				self.skip_frame();
+				return skipped.map(|_| ());
+			}
+		};
+
+		self.queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&self.camera.view_projection()));
+		self.queue.write_buffer(&self.screen_buffer, 0, bytemuck::cast_slice(&screen_projection(self.target.size())));
+		self.sprites.prepare(&self.device, &self.queue);
+		self.ui_sprites.prepare(&self.device, &self.queue);
+		self.stats = self.tilemap.stats().add(self.sprites.stats()).add(self.ui_sprites.stats());
+		self.draw(&view);
+		self.tilemap.clear_visible();
+		self.text.end_frame();
+
+		if let Some(output) = output {
+			output.present();
+		}
+
+		Ok(())
+	}
+
+	/// Drops everything queued for the current frame without drawing it. [`render`](Self::render)
+	/// does so for frames it cannot draw, so nothing piles up while the window is minimised or
+	/// gets drawn twice after a frame is lost.
+	pub fn skip_frame(&mut self) {
+		self.sprites.clear();
+		self.ui_sprites.clear();
+		self.tilemap.clear_visible();
+		self.text.end_frame();
+	}
+
+	/// The texture to draw the next frame to, presented afterwards for a window surface. `None`
+	/// while the window is minimised.
+	fn acquire_frame(&self) -> Result<Option<(Option<SurfaceTexture>, TextureView)>, FrameError> {
+		match &self.target {
+			RenderTarget::Surface { config, .. } if config.width == 0 || config.height == 0 => Ok(None),
+			RenderTarget::Surface { surface, config } => {
+				let output = match surface.get_current_texture() {
+					Ok(output) => output,
+					Err(error) => {
+						let error = FrameError::from(error);
+						if let FrameError::Reconfigured = error {
+							surface.configure(&self.device, config);
+						}
+						return Err(error);
+					}
+				};
+				let view = output.texture.create_view(&TextureViewDescriptor::default());
+				Ok(Some((Some(output), view)))
+			}
+			RenderTarget::Texture { texture, .. } => {
+				Ok(Some((None, texture.create_view(&TextureViewDescriptor::default()))))
+			}
+		}
+	}
+
+	fn draw(&mut self, view: &TextureView) {
+		let mut encoder = self.device.create_command_encoder(&CommandEncoderDescriptor::default());
+		if let Some(timer) = &mut self.gpu_timer {
+			timer.begin_frame();
+			timer.begin_pass(&mut encoder, "world");
+		}
+
+		{
+			let mut render_pass = begin_render_pass(&mut encoder, "world", view, LoadOp::Clear(Color::BLACK));
+			render_pass.set_pipeline(&self.pipeline);
+			render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+			self.tilemap.draw(&mut render_pass, &self.textures);
+			self.sprites.draw(&mut render_pass, &self.textures);
+		}
+
+		if let Some(timer) = &mut self.gpu_timer {
+			timer.end_pass(&mut encoder);
+			timer.begin_pass(&mut encoder, "ui");
+		}
+
+		{
+			let mut render_pass = begin_render_pass(&mut encoder, "ui", view, LoadOp::Load);
+			render_pass.set_pipeline(&self.pipeline);
+			render_pass.set_bind_group(0, &self.screen_bind_group, &[]);
+			self.ui_sprites.draw(&mut render_pass, &self.textures);
+		}
+
+		if let Some(timer) = &mut self.gpu_timer {
+			timer.end_pass(&mut encoder);
+			timer.resolve(&mut encoder);
+		}
+
+		self.queue.submit(iter::once(encoder.finish()));
+
+		if let Some(timer) = &mut self.gpu_timer {
+			timer.end_frame();
+			timer.collect(&self.device);
+		}
+	}
+
+	/// GPU time spent on each render pass of a recent frame, or `None` when the adapter cannot
+	/// measure it. Results arrive a few frames after the frame they measure, until then the
+	/// list is empty.
+	pub fn gpu_timings(&self) -> Option<&[PassTiming]> {
+		self.gpu_timer.as_ref().map(GpuTimer::latest)
+	}
+
+	/// Copies the contents of an offscreen target into tightly packed RGBA rows.
+	/// Returns `None` when rendering to a window surface.
+	pub fn read_pixels(&self) -> Option<Vec<u8>> {
+		let RenderTarget::Texture { texture, size } = &self.target else {
+			return None;
+		};
+
+		let unpadded_bytes_per_row = size.width * 4;
+		let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT)
+			* COPY_BYTES_PER_ROW_ALIGNMENT;
+
+		let buffer = self.device.create_buffer(&BufferDescriptor {
+			label: Some("read back buffer"),
+			size: (padded_bytes_per_row * size.height) as BufferAddress,
+			usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+			mapped_at_creation: false,
+		});
+
+		let mut encoder = self.device.create_command_encoder(&CommandEncoderDescriptor::default());
+		encoder.copy_texture_to_buffer(
+			ImageCopyTexture {
+				texture,
+				mip_level: 0,
+				origin: Origin3d::ZERO,
+				aspect: TextureAspect::All,
+			},
+			ImageCopyBuffer {
+				buffer: &buffer,
+				layout: ImageDataLayout {
+					offset: 0,
+					bytes_per_row: NonZeroU32::new(padded_bytes_per_row),
+					rows_per_image: NonZeroU32::new(size.height),
+				},
+			},
+			*size,
+		);
+		self.queue.submit(iter::once(encoder.finish()));
+
+		let slice = buffer.slice(..);
+		let (sender, receiver) = channel();
+		slice.map_async(MapMode::Read, move |result| {
+			let _ = sender.send(result);
+		});
+		self.device.poll(Maintain::Wait);
+		receiver.recv().ok()?.ok()?;
+
+		let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * size.height) as usize);
+		{
+			let data = slice.get_mapped_range();
+			for row in data.chunks(padded_bytes_per_row as usize) {
+				pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+			}
+		}
+		buffer.unmap();
+
+		Some(pixels)
+	}
+}
+
+/// Tries each allowed backend in turn, then the same backends with the fallback adapter, until
+/// one of them yields an adapter.
+async fn select_adapter(
+	config: &GraphicsConfig,
+	window: Option<&Window>,
+) -> Result<(Adapter, Option<Surface>), InitError> {
+	for (backend, force_fallback_adapter) in config.adapter_attempts() {
+		let instance = Instance::new(backend);
+		let surface = window.map(|window| unsafe { instance.create_surface(window) });
+
+		let adapter = instance.request_adapter(&RequestAdapterOptions {
+			power_preference: config.power_preference,
+			compatible_surface: surface.as_ref(),
+			force_fallback_adapter,
+		}).await;
+
+		match adapter {
+			Some(adapter) => {
+				let info = adapter.get_info();
+				log::info!("using {:?} adapter {} ({:?})", info.backend, info.name, info.device_type);
+				return Ok((adapter, surface));
+			}
+			None => log::warn!("no {backend:?} adapter found (fallback adapter: {force_fallback_adapter})"),
+		}
+	}
+
+	Err(InitError::NoAdapter)
+}
+
+fn begin_render_pass<'a>(
+	encoder: &'a mut CommandEncoder,
+	label: &str,
+	view: &'a TextureView,
+	load: LoadOp<Color>,
+) -> RenderPass<'a> {
+	encoder.begin_render_pass(&RenderPassDescriptor {
+		label: Some(label),
+		color_attachments: &[Some(RenderPassColorAttachment {
+			view,
+			resolve_target: None,
+			ops: Operations { load, store: true },
+		})],
+		depth_stencil_attachment: None,
+	})
+}
+
+async fn request_device(adapter: &Adapter) -> Result<(Device, Queue), InitError> {
+	// Pass timing is only available where the adapter supports timestamp queries.
+	let features = adapter.features() & Features::TIMESTAMP_QUERY;
+	if !features.contains(Features::TIMESTAMP_QUERY) {
+		log::info!("adapter has no timestamp queries, GPU pass timing is disabled");
+	}
+
+	Ok(adapter.request_device(&DeviceDescriptor {
+		features,
+		limits: Limits::downlevel_defaults().using_resolution(adapter.limits()),
+		label: None,
+	}, None).await?)
+}
+
+/// Column major matrix taking pixel positions, measured from the top left of the target with y
+/// negated, to clip space.
+fn screen_projection(size: PhysicalSize<u32>) -> [[f32; 4]; 4] {
+	let scale_x = 2.0 / size.width.max(1) as f32;
+	let scale_y = 2.0 / size.height.max(1) as f32;
+
+	[
+		[scale_x, 0.0, 0.0, 0.0],
+		[0.0, scale_y, 0.0, 0.0],
+		[0.0, 0.0, 1.0, 0.0],
+		[-1.0, 1.0, 0.0, 1.0],
+	]
+}
+
+fn create_render_pipeline(
+	device: &Device,
+	bind_group_layouts: &[&BindGroupLayout],
+	format: TextureFormat,
+	blend: BlendState,
+) -> RenderPipeline {
+	let shader = device.create_shader_module(include_wgsl!("renderer/shader.wgsl"));
+
+	let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+		label: None,
+		bind_group_layouts,
+		push_constant_ranges: &[],
+	});
+
+	device.create_render_pipeline(&RenderPipelineDescriptor {
+		label: None,
+		layout: Some(&render_pipeline_layout),
+		vertex: VertexState {
+			module: &shader,
+			entry_point: "vertex_main",
+			buffers: &[SpriteVertex::descriptor()],
+		},
+		fragment: Some(FragmentState {
+			module: &shader,
+			entry_point: "fragment_main",
+			targets: &[Some(ColorTargetState {
+				format,
+				blend: Some(blend),
+				write_mask: ColorWrites::ALL,
+			})],
+		}),
+		primitive: PrimitiveState {
+			topology: PrimitiveTopology::TriangleList,
+			strip_index_format: None,
+			front_face: FrontFace::Ccw,
+			cull_mode: Some(Face::Back),
+			polygon_mode: PolygonMode::Fill,
+			unclipped_depth: false,
+			conservative: false,
+		},
+		depth_stencil: None,
+		multisample: MultisampleState {
+			count: 1,
+			mask: !0,
+			alpha_to_coverage_enabled: false,
+		},
+		multiview: None,
+	})
+}
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use super::error::TextureError;
+use super::image::Image;
+use super::sprite::{Sprite, UvRect};
+use super::texture::TextureId;
+use super::Renderer;
+
+pub const DEFAULT_PAGE_SIZE: u32 = 1024;
+
+/// Transparent gap left around every sprite so neighbours never bleed into each other.
+const PADDING: u32 = 1;
+
+/// Where a named sprite ended up in an [`Atlas`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AtlasRegion {
+	pub texture: TextureId,
+	pub uv_rect: UvRect,
+	pub width: u32,
+	pub height: u32,
+}
+
+/// Sprites packed into a few large textures, looked up by name.
+pub struct Atlas {
+	pages: Vec<TextureId>,
+	regions: HashMap<String, AtlasRegion>,
+}
+
+impl Atlas {
+	pub fn get(&self, name: &str) -> Option<AtlasRegion> {
+		self.regions.get(name).copied()
+	}
+
+	/// A sprite showing the named region, or `None` if no sprite has that name.
+	pub fn sprite(&self, name: &str, position: [f32; 2], size: [f32; 2]) -> Option<Sprite> {
+		let region = self.get(name)?;
+		Some(Sprite {
+			uv_rect: region.uv_rect,
+			..Sprite::new(region.texture, position, size)
+		})
+	}
+
+	pub fn pages(&self) -> &[TextureId] {
+		&self.pages
+	}
+}
+
+/// Collects sprite images and packs them into atlas pages.
+pub struct AtlasBuilder {
+	page_size: u32,
+	images: Vec<(String, Image)>,
+}
+
+impl AtlasBuilder {
+	pub fn new(page_size: u32) -> Self {
+		Self {
+			page_size,
+			images: Vec::new(),
+		}
+	}
+
+	pub fn add(&mut self, name: impl Into<String>, image: Image) -> Result<(), TextureError> {
+		let name = name.into();
+		if image.width + PADDING * 2 > self.page_size || image.height + PADDING * 2 > self.page_size {
+			return Err(TextureError::TooLarge {
+				name,
+				width: image.width,
+				height: image.height,
+				page_size: self.page_size,
+			});
+		}
+		if self.images.iter().any(|(existing, _)| *existing == name) {
+			return Err(TextureError::DuplicateName(name));
+		}
+
+		self.images.push((name, image));
+		Ok(())
+	}
+
+	pub fn add_png(&mut self, name: impl Into<String>, path: &Path) -> Result<(), TextureError> {
+		self.add(name, Image::load_png(path)?)
+	}
+
+	/// Adds every PNG below `directory`, named by its path relative to `directory` without the
+	/// extension, using `/` as separator, such as `buildings/drill`.
+	pub fn add_directory(&mut self, directory: &Path) -> Result<(), TextureError> {
+		self.add_directory_with_prefix(directory, "")
+	}
+
+	fn add_directory_with_prefix(&mut self, directory: &Path, prefix: &str) -> Result<(), TextureError> {
+		let read_error = |error| TextureError::Io(directory.to_owned(), error);
+
+		let mut entries = fs::read_dir(directory)
+			.map_err(read_error)?
+			.map(|entry| entry.map(|entry| entry.path()))
+			.collect::<Result<Vec<_>, _>>()
+			.map_err(read_error)?;
+		// Directory order differs between platforms, sorting keeps the packed atlas identical.
+		entries.sort();
+
+		for path in entries {
+			let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) else {
+				continue;
+			};
+			let name = format!("{prefix}{stem}");
+
+			if path.is_dir() {
+				self.add_directory_with_prefix(&path, &format!("{name}/"))?;
+			} else if path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("png")) {
+				self.add_png(name, &path)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Packs the collected images and uploads one texture per page.
+	pub fn build(self, renderer: &mut Renderer) -> Atlas {
+		let placements = pack(
+			self.images.iter().map(|(_, image)| (image.width, image.height)),
+			self.page_size,
+		);
+
+		let page_count = placements.iter().map(|placement| placement.page + 1).max().unwrap_or(0);
+		let mut pages = vec![Image::new(self.page_size, self.page_size); page_count];
+		for ((_, image), placement) in self.images.iter().zip(&placements) {
+			pages[placement.page].blit(image, placement.x, placement.y);
+		}
+
+		let pages = pages
+			.iter()
+			.enumerate()
+			.map(|(index, page)| {
+				renderer.add_texture(&format!("atlas page {index}"), page.width, page.height, &page.pixels)
+			})
+			.collect::<Vec<_>>();
+
+		let size = self.page_size as f32;
+		let regions = self.images
+			.into_iter()
+			.zip(placements)
+			.map(|((name, image), placement)| {
+				let region = AtlasRegion {
+					texture: pages[placement.page],
+					uv_rect: UvRect {
+						min: [placement.x as f32 / size, placement.y as f32 / size],
+						max: [
+							(placement.x + image.width) as f32 / size,
+							(placement.y + image.height) as f32 / size,
+						],
+					},
+					width: image.width,
+					height: image.height,
+				};
+				(name, region)
+			})
+			.collect();
+
+		Atlas { pages, regions }
+	}
+}
+
+impl Default for AtlasBuilder {
+	fn default() -> Self {
+		Self::new(DEFAULT_PAGE_SIZE)
+	}
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Placement {
+	page: usize,
+	x: u32,
+	y: u32,
+}
+
+/// Shelf packing: images are placed tallest first in rows across the page, starting a new row
+/// when one is full and a new page when a row no longer fits.
+fn pack(sizes: impl Iterator<Item = (u32, u32)>, page_size: u32) -> Vec<Placement> {
+	let sizes = sizes.collect::<Vec<_>>();
+	let mut order = (0..sizes.len()).collect::<Vec<_>>();
+	order.sort_by_key(|&index| (std::cmp::Reverse(sizes[index].1), index));
+
+	let mut placements = vec![Placement { page: 0, x: 0, y: 0 }; sizes.len()];
+	let mut page = 0;
+	let mut x = 0;
+	let mut y = 0;
+	let mut row_height = 0;
+
+	for index in order {
+		let (width, height) = (sizes[index].0 + PADDING * 2, sizes[index].1 + PADDING * 2);
+
+		if x + width > page_size {
+			x = 0;
+			y += row_height;
+			row_height = 0;
+		}
+		if y + height > page_size {
+			page += 1;
+			x = 0;
+			y = 0;
+			row_height = 0;
+		}
+
+		placements[index] = Placement {
+			page,
+			x: x + PADDING,
+			y: y + PADDING,
+		};
+		x += width;
+		row_height = row_height.max(height);
+	}
+
+	placements
+}
@@ -0,0 +1,47 @@
+use wgpu::{Buffer, BufferAddress, BufferDescriptor, BufferUsages, Device, Queue};
+
+/// A GPU buffer that is rewritten every frame and reallocated when its contents outgrow it.
+pub struct DynamicBuffer {
+	label: &'static str,
+	usage: BufferUsages,
+	buffer: Buffer,
+	capacity: BufferAddress,
+}
+
+impl DynamicBuffer {
+	pub fn new(device: &Device, label: &'static str, usage: BufferUsages) -> Self {
+		let usage = usage | BufferUsages::COPY_DST;
+		let capacity = 1024;
+
+		Self {
+			label,
+			usage,
+			buffer: create_buffer(device, label, usage, capacity),
+			capacity,
+		}
+	}
+
+	pub fn buffer(&self) -> &Buffer {
+		&self.buffer
+	}
+
+	/// Replaces the buffer contents, growing it to the next power of two when `data` does not fit.
+	pub fn write(&mut self, device: &Device, queue: &Queue, data: &[u8]) {
+		let size = data.len() as BufferAddress;
+		if size > self.capacity {
+			self.capacity = size.next_power_of_two();
+			self.buffer = create_buffer(device, self.label, self.usage, self.capacity);
+		}
+
+		queue.write_buffer(&self.buffer, 0, data);
+	}
+}
+
+fn create_buffer(device: &Device, label: &str, usage: BufferUsages, size: BufferAddress) -> Buffer {
+	device.create_buffer(&BufferDescriptor {
+		label: Some(label),
+		size,
+		usage,
+		mapped_at_creation: false,
+	})
+}
@@ -0,0 +1,131 @@
+use std::mem::size_of;
+
+use wgpu::{
+	BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingType, BufferAddress,
+	BufferBindingType, BufferSize, Device, ShaderStages,
+};
+use winit::dpi::PhysicalSize;
+
+/// Screen pixels per world unit at zoom level 1. A tile is one world unit across.
+pub const PIXELS_PER_UNIT: f32 = 32.0;
+
+pub const MIN_ZOOM: f32 = 0.125;
+pub const MAX_ZOOM: f32 = 8.0;
+
+/// Orthographic camera looking at the world plane, with y pointing up.
+///
+/// Screen coordinates are in physical pixels with the origin at the top left and y pointing down,
+/// matching winit cursor positions.
+#[derive(Clone, Debug)]
+pub struct Camera2D {
+	/// World position shown at the centre of the screen.
+	pub position: [f32; 2],
+	zoom: f32,
+	viewport: [f32; 2],
+}
+
+impl Camera2D {
+	pub fn new(viewport: PhysicalSize<u32>) -> Self {
+		Self {
+			position: [0.0, 0.0],
+			zoom: 1.0,
+			viewport: [viewport.width as f32, viewport.height as f32],
+		}
+	}
+
+	pub fn zoom(&self) -> f32 {
+		self.zoom
+	}
+
+	pub fn set_zoom(&mut self, zoom: f32) {
+		self.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+	}
+
+	pub fn viewport(&self) -> [f32; 2] {
+		self.viewport
+	}
+
+	pub fn resize(&mut self, viewport: PhysicalSize<u32>) {
+		self.viewport = [viewport.width as f32, viewport.height as f32];
+	}
+
+	fn pixels_per_unit(&self) -> f32 {
+		PIXELS_PER_UNIT * self.zoom
+	}
+
+	/// Column major matrix taking world positions to clip space, keeping world units square
+	/// whatever the window's aspect ratio.
+	pub fn view_projection(&self) -> [[f32; 4]; 4] {
+		let scale_x = 2.0 * self.pixels_per_unit() / self.viewport[0].max(1.0);
+		let scale_y = 2.0 * self.pixels_per_unit() / self.viewport[1].max(1.0);
+
+		[
+			[scale_x, 0.0, 0.0, 0.0],
+			[0.0, scale_y, 0.0, 0.0],
+			[0.0, 0.0, 1.0, 0.0],
+			[-self.position[0] * scale_x, -self.position[1] * scale_y, 0.0, 1.0],
+		]
+	}
+
+	pub fn screen_to_world(&self, screen: [f32; 2]) -> [f32; 2] {
+		[
+			self.position[0] + (screen[0] - self.viewport[0] / 2.0) / self.pixels_per_unit(),
+			self.position[1] - (screen[1] - self.viewport[1] / 2.0) / self.pixels_per_unit(),
+		]
+	}
+
+	pub fn world_to_screen(&self, world: [f32; 2]) -> [f32; 2] {
+		[
+			(world[0] - self.position[0]) * self.pixels_per_unit() + self.viewport[0] / 2.0,
+			(self.position[1] - world[1]) * self.pixels_per_unit() + self.viewport[1] / 2.0,
+		]
+	}
+
+	/// Smallest and largest world positions on screen.
+	pub fn visible_bounds(&self) -> ([f32; 2], [f32; 2]) {
+		let bottom_left = self.screen_to_world([0.0, self.viewport[1]]);
+		let top_right = self.screen_to_world([self.viewport[0], 0.0]);
+		(bottom_left, top_right)
+	}
+
+	/// Multiplies the zoom by `factor`, keeping the world position under `screen` in place.
+	pub fn zoom_at(&mut self, factor: f32, screen: [f32; 2]) {
+		let before = self.screen_to_world(screen);
+		self.set_zoom(self.zoom * factor);
+		let after = self.screen_to_world(screen);
+
+		self.position[0] += before[0] - after[0];
+		self.position[1] += before[1] - after[1];
+	}
+
+	/// Moves the view so the world follows a cursor dragged by `delta` screen pixels.
+	pub fn drag(&mut self, delta: [f32; 2]) {
+		self.position[0] -= delta[0] / self.pixels_per_unit();
+		self.position[1] += delta[1] / self.pixels_per_unit();
+	}
+
+	/// Moves the view by `delta` pixels of screen distance with y pointing up, so keyboard panning
+	/// feels the same at every zoom level.
+	pub fn pan(&mut self, delta: [f32; 2]) {
+		self.position[0] += delta[0] / self.pixels_per_unit();
+		self.position[1] += delta[1] / self.pixels_per_unit();
+	}
+
+	pub fn create_bind_group_layout(device: &Device) -> BindGroupLayout {
+		device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+			label: Some("camera"),
+			entries: &[
+				BindGroupLayoutEntry {
+					binding: 0,
+					visibility: ShaderStages::VERTEX,
+					ty: BindingType::Buffer {
+						ty: BufferBindingType::Uniform,
+						has_dynamic_offset: false,
+						min_binding_size: BufferSize::new(size_of::<[[f32; 4]; 4]>() as BufferAddress),
+					},
+					count: None,
+				},
+			],
+		})
+	}
+}
@@ -0,0 +1,153 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::path::PathBuf;
+
+use wgpu::{RequestDeviceError, SurfaceError};
+
+/// Reasons a [`Renderer`](super::Renderer) could not be created.
+#[derive(Debug)]
+pub enum InitError {
+	/// None of the allowed backends offered an adapter.
+	NoAdapter,
+	/// The adapter cannot present to the window surface.
+	UnsupportedSurface,
+	RequestDevice(RequestDeviceError),
+}
+
+impl Display for InitError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			InitError::NoAdapter => write!(
+				f,
+				"no suitable graphics adapter found, try another backend with --backend or WGPU_BACKEND",
+			),
+			InitError::UnsupportedSurface => write!(f, "the graphics adapter cannot present to the window"),
+			InitError::RequestDevice(error) => write!(f, "failed to open graphics device: {error}"),
+		}
+	}
+}
+
+impl Error for InitError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			InitError::RequestDevice(error) => Some(error),
+			_ => None,
+		}
+	}
+}
+
+impl From<RequestDeviceError> for InitError {
+	fn from(error: RequestDeviceError) -> Self {
+		InitError::RequestDevice(error)
+	}
+}
+
+/// Reasons a frame was not drawn.
+#[derive(Debug)]
+pub enum FrameError {
+	/// The surface took too long to hand out a texture, the frame was skipped.
+	Timeout,
+	/// The surface was lost or outdated and has been reconfigured, the frame was skipped.
+	Reconfigured,
+	OutOfMemory,
+}
+
+impl FrameError {
+	/// Whether rendering can carry on with the next frame.
+	pub fn is_recoverable(&self) -> bool {
+		!matches!(self, FrameError::OutOfMemory)
+	}
+}
+
+impl Display for FrameError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			FrameError::Timeout => write!(f, "timed out acquiring the next frame"),
+			FrameError::Reconfigured => write!(f, "surface was lost or outdated and has been reconfigured"),
+			FrameError::OutOfMemory => write!(f, "out of graphics memory"),
+		}
+	}
+}
+
+impl Error for FrameError {}
+
+impl From<SurfaceError> for FrameError {
+	/// Lost and outdated surfaces count as reconfigured, the caller has to configure them again.
+	fn from(error: SurfaceError) -> Self {
+		match error {
+			SurfaceError::Timeout => FrameError::Timeout,
+			SurfaceError::Lost | SurfaceError::Outdated => FrameError::Reconfigured,
+			SurfaceError::OutOfMemory => FrameError::OutOfMemory,
+		}
+	}
+}
+
+/// Reasons a texture or atlas could not be loaded.
+#[derive(Debug)]
+pub enum TextureError {
+	Io(PathBuf, io::Error),
+	Decode(PathBuf, png::DecodingError),
+	/// Two atlas sprites were given the same name.
+	DuplicateName(String),
+	/// No atlas sprite has this name.
+	MissingSprite(String),
+	/// A sprite does not fit on an atlas page.
+	TooLarge {
+		name: String,
+		width: u32,
+		height: u32,
+		page_size: u32,
+	},
+}
+
+impl Display for TextureError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			TextureError::Io(path, error) => write!(f, "failed to read {}: {error}", path.display()),
+			TextureError::Decode(path, error) => write!(f, "failed to decode {}: {error}", path.display()),
+			TextureError::DuplicateName(name) => write!(f, "more than one sprite is named {name}"),
+			TextureError::MissingSprite(name) => write!(f, "no sprite is named {name}"),
+			TextureError::TooLarge { name, width, height, page_size } => write!(
+				f,
+				"sprite {name} is {width}x{height} which does not fit on a {page_size}x{page_size} atlas page",
+			),
+		}
+	}
+}
+
+impl Error for TextureError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			TextureError::Io(_, error) => Some(error),
+			TextureError::Decode(_, error) => Some(error),
+			_ => None,
+		}
+	}
+}
+
+/// Reasons a font could not be loaded.
+#[derive(Debug)]
+pub enum FontError {
+	Io(PathBuf, io::Error),
+	/// The file is not a TrueType or OpenType font.
+	Invalid(PathBuf),
+}
+
+impl Display for FontError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			FontError::Io(path, error) => write!(f, "failed to read {}: {error}", path.display()),
+			FontError::Invalid(path) => write!(f, "{} is not a valid TrueType or OpenType font", path.display()),
+		}
+	}
+}
+
+impl Error for FontError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			FontError::Io(_, error) => Some(error),
+			FontError::Invalid(_) => None,
+		}
+	}
+}
@@ -0,0 +1,69 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use png::{ColorType, Decoder, Transformations};
+
+use super::error::TextureError;
+
+/// Tightly packed 8 bit RGBA pixels, top row first.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+	pub width: u32,
+	pub height: u32,
+	pub pixels: Vec<u8>,
+}
+
+impl Image {
+	pub fn new(width: u32, height: u32) -> Self {
+		Self {
+			width,
+			height,
+			pixels: vec![0; (width * height * 4) as usize],
+		}
+	}
+
+	pub fn load_png(path: &Path) -> Result<Self, TextureError> {
+		let file = File::open(path).map_err(|error| TextureError::Io(path.to_owned(), error))?;
+		Self::decode_png(BufReader::new(file))
+			.map_err(|error| TextureError::Decode(path.to_owned(), error))
+	}
+
+	/// Decodes a PNG of any colour type and bit depth into RGBA.
+	pub fn decode_png(reader: impl Read) -> Result<Self, png::DecodingError> {
+		let mut decoder = Decoder::new(reader);
+		// Expands palettes, low bit depths and missing alpha channels, leaving only grey with
+		// alpha to convert by hand.
+		decoder.set_transformations(Transformations::normalize_to_color8() | Transformations::ALPHA);
+
+		let mut reader = decoder.read_info()?;
+		let mut buffer = vec![0; reader.output_buffer_size()];
+		let info = reader.next_frame(&mut buffer)?;
+		buffer.truncate(info.buffer_size());
+
+		let pixels = match info.color_type {
+			ColorType::GrayscaleAlpha => buffer
+				.chunks_exact(2)
+				.flat_map(|pixel| [pixel[0], pixel[0], pixel[0], pixel[1]])
+				.collect(),
+			_ => buffer,
+		};
+
+		Ok(Self {
+			width: info.width,
+			height: info.height,
+			pixels,
+		})
+	}
+
+	/// Copies `source` into this image with its top left corner at `x`, `y`.
+	pub fn blit(&mut self, source: &Image, x: u32, y: u32) {
+		assert!(x + source.width <= self.width && y + source.height <= self.height, "blit out of bounds");
+
+		let row_length = (source.width * 4) as usize;
+		for (row, source_row) in source.pixels.chunks_exact(row_length).enumerate() {
+			let start = (((y + row as u32) * self.width + x) * 4) as usize;
+			self.pixels[start..start + row_length].copy_from_slice(source_row);
+		}
+	}
+}
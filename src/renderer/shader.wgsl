@@ -0,0 +1,26 @@
+struct VertexOutput {
+	@builtin(position) position: vec4<f32>,
+	@location(0) uv: vec2<f32>,
+	@location(1) color: vec4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> view_projection: mat4x4<f32>;
+
+@group(1) @binding(0) var sprite_texture: texture_2d<f32>;
+@group(1) @binding(1) var sprite_sampler: sampler;
+
+@vertex fn vertex_main(
+	@location(0) position: vec2<f32>,
+	@location(1) uv: vec2<f32>,
+	@location(2) color: vec4<f32>,
+) -> VertexOutput {
+	var output: VertexOutput;
+	output.position = view_projection * vec4<f32>(position, 0.0, 1.0);
+	output.uv = uv;
+	output.color = color;
+	return output;
+}
+
+@fragment fn fragment_main(input: VertexOutput) -> @location(0) vec4<f32> {
+	return textureSample(sprite_texture, sprite_sampler, input.uv) * input.color;
+}
@@ -0,0 +1,202 @@
+use std::mem::size_of;
+use std::ops::Range;
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::{
+	BufferAddress, BufferUsages, Device, IndexFormat, Queue, RenderPass, VertexAttribute,
+	VertexBufferLayout, VertexFormat, VertexStepMode,
+};
+
+use super::buffer::DynamicBuffer;
+use super::texture::{Texture, TextureId};
+use super::RenderStats;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct SpriteVertex {
+	pub position: [f32; 2],
+	pub uv: [f32; 2],
+	pub color: [f32; 4],
+}
+
+impl SpriteVertex {
+	pub fn descriptor<'a>() -> VertexBufferLayout<'a> {
+		VertexBufferLayout {
+			array_stride: size_of::<SpriteVertex>() as BufferAddress,
+			step_mode: VertexStepMode::Vertex,
+			attributes: &[
+				VertexAttribute {
+					offset: 0,
+					shader_location: 0,
+					format: VertexFormat::Float32x2,
+				},
+				VertexAttribute {
+					offset: size_of::<[f32; 2]>() as BufferAddress,
+					shader_location: 1,
+					format: VertexFormat::Float32x2,
+				},
+				VertexAttribute {
+					offset: (size_of::<[f32; 2]>() * 2) as BufferAddress,
+					shader_location: 2,
+					format: VertexFormat::Float32x4,
+				},
+			],
+		}
+	}
+}
+
+/// Region of a texture in normalized coordinates, with `min` at the top left.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct UvRect {
+	pub min: [f32; 2],
+	pub max: [f32; 2],
+}
+
+impl UvRect {
+	pub const FULL: UvRect = UvRect {
+		min: [0.0, 0.0],
+		max: [1.0, 1.0],
+	};
+}
+
+/// One textured quad, centred on `position` and rotated counter clockwise by `rotation` radians.
+///
+/// Sprites on lower layers are drawn first. Within a layer, sprites are grouped by texture so the
+/// order between sprites with different textures is not preserved.
+#[derive(Copy, Clone, Debug)]
+pub struct Sprite {
+	pub texture: TextureId,
+	pub position: [f32; 2],
+	pub size: [f32; 2],
+	pub rotation: f32,
+	pub tint: [f32; 4],
+	pub uv_rect: UvRect,
+	pub layer: i32,
+}
+
+impl Sprite {
+	pub fn new(texture: TextureId, position: [f32; 2], size: [f32; 2]) -> Self {
+		Self {
+			texture,
+			position,
+			size,
+			rotation: 0.0,
+			tint: [1.0; 4],
+			uv_rect: UvRect::FULL,
+			layer: 0,
+		}
+	}
+
+	/// Corners in counter clockwise order starting at the bottom left.
+	fn vertices(&self) -> [SpriteVertex; 4] {
+		let (sin, cos) = self.rotation.sin_cos();
+		let [half_width, half_height] = [self.size[0] / 2.0, self.size[1] / 2.0];
+		let UvRect { min, max } = self.uv_rect;
+
+		[
+			([-half_width, -half_height], [min[0], max[1]]),
+			([half_width, -half_height], [max[0], max[1]]),
+			([half_width, half_height], [max[0], min[1]]),
+			([-half_width, half_height], [min[0], min[1]]),
+		].map(|([x, y], uv)| SpriteVertex {
+			position: [
+				self.position[0] + x * cos - y * sin,
+				self.position[1] + x * sin + y * cos,
+			],
+			uv,
+			color: self.tint,
+		})
+	}
+}
+
+struct SpriteDraw {
+	texture: TextureId,
+	indices: Range<u32>,
+}
+
+/// Collects the sprites drawn during a frame and turns them into as few draw calls as possible.
+pub struct SpriteBatch {
+	sprites: Vec<Sprite>,
+	vertices: Vec<SpriteVertex>,
+	indices: Vec<u32>,
+	draws: Vec<SpriteDraw>,
+	vertex_buffer: DynamicBuffer,
+	index_buffer: DynamicBuffer,
+}
+
+impl SpriteBatch {
+	pub fn new(device: &Device) -> Self {
+		Self {
+			sprites: Vec::new(),
+			vertices: Vec::new(),
+			indices: Vec::new(),
+			draws: Vec::new(),
+			vertex_buffer: DynamicBuffer::new(device, "sprite vertices", BufferUsages::VERTEX),
+			index_buffer: DynamicBuffer::new(device, "sprite indices", BufferUsages::INDEX),
+		}
+	}
+
+	pub fn push(&mut self, sprite: Sprite) {
+		self.sprites.push(sprite);
+	}
+
+	/// Drops the queued sprites without drawing them, for frames that are skipped.
+	pub fn clear(&mut self) {
+		self.sprites.clear();
+	}
+
+	/// Sorts the queued sprites, builds their geometry and uploads it. The queue is emptied, ready
+	/// for the next frame.
+	pub fn prepare(&mut self, device: &Device, queue: &Queue) {
+		self.sprites.sort_by_key(|sprite| (sprite.layer, sprite.texture));
+
+		self.vertices.clear();
+		self.indices.clear();
+		self.draws.clear();
+
+		for sprite in self.sprites.drain(..) {
+			let base = self.vertices.len() as u32;
+			self.vertices.extend_from_slice(&sprite.vertices());
+
+			let start = self.indices.len() as u32;
+			self.indices.extend([0, 1, 2, 0, 2, 3].map(|index| base + index));
+			let end = self.indices.len() as u32;
+
+			match self.draws.last_mut() {
+				Some(draw) if draw.texture == sprite.texture => draw.indices.end = end,
+				_ => self.draws.push(SpriteDraw {
+					texture: sprite.texture,
+					indices: start..end,
+				}),
+			}
+		}
+
+		if !self.indices.is_empty() {
+			self.vertex_buffer.write(device, queue, bytemuck::cast_slice(&self.vertices));
+			self.index_buffer.write(device, queue, bytemuck::cast_slice(&self.indices));
+		}
+	}
+
+	/// Work the geometry uploaded by the last [`prepare`](Self::prepare) takes to draw.
+	pub fn stats(&self) -> RenderStats {
+		RenderStats {
+			draw_calls: self.draws.len() as u32,
+			vertices: self.vertices.len() as u32,
+		}
+	}
+
+	/// Records the draw calls for the geometry uploaded by the last [`prepare`](Self::prepare).
+	pub fn draw<'a>(&'a self, render_pass: &mut RenderPass<'a>, textures: &'a [Texture]) {
+		if self.draws.is_empty() {
+			return;
+		}
+
+		render_pass.set_vertex_buffer(0, self.vertex_buffer.buffer().slice(..));
+		render_pass.set_index_buffer(self.index_buffer.buffer().slice(..), IndexFormat::Uint32);
+
+		for draw in &self.draws {
+			render_pass.set_bind_group(1, &textures[draw.texture.0].bind_group, &[]);
+			render_pass.draw_indexed(draw.indices.clone(), 0, 0..1);
+		}
+	}
+}
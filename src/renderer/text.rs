@@ -0,0 +1,395 @@
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+use ab_glyph::{Font, FontArc, GlyphId, PxScale, PxScaleFont, ScaleFont};
+use wgpu::Queue;
+
+use super::error::FontError;
+use super::sprite::{Sprite, UvRect};
+use super::texture::{Texture, TextureId};
+
+/// Width and height of the glyph cache texture.
+pub const GLYPH_CACHE_SIZE: u32 = 1024;
+
+/// Transparent gap left around every cached glyph so neighbours never bleed into each other.
+const PADDING: u32 = 1;
+
+/// Handle to a font loaded into a [`Renderer`](super::Renderer).
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct FontId(usize);
+
+/// How the lines of a text are placed within its width.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum Align {
+	#[default]
+	Left,
+	Center,
+	Right,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct TextStyle {
+	pub font: FontId,
+	/// Height of the font in pixels.
+	pub size: f32,
+	pub align: Align,
+	/// Lines longer than this many pixels are broken at the last space, or mid word when a word
+	/// is longer than a whole line. `None` only breaks lines at `\n`.
+	pub wrap_width: Option<f32>,
+	/// Multiplier for the distance between baselines recommended by the font.
+	pub line_spacing: f32,
+}
+
+impl TextStyle {
+	pub fn new(font: FontId, size: f32) -> Self {
+		Self {
+			font,
+			size,
+			align: Align::Left,
+			wrap_width: None,
+			line_spacing: 1.0,
+		}
+	}
+}
+
+/// A run of text drawn in one colour.
+#[derive(Copy, Clone, Debug)]
+pub struct TextSpan<'a> {
+	pub text: &'a str,
+	pub color: [f32; 4],
+}
+
+impl<'a> TextSpan<'a> {
+	pub fn new(text: &'a str, color: [f32; 4]) -> Self {
+		Self { text, color }
+	}
+}
+
+#[derive(Copy, Clone, Debug)]
+struct PositionedGlyph {
+	id: GlyphId,
+	/// Pen position on the baseline, in whole pixels from the top left of the text.
+	position: [f32; 2],
+	color: [f32; 4],
+}
+
+/// Text broken into lines and positioned glyph by glyph, ready to be drawn any number of times.
+#[derive(Clone, Debug)]
+pub struct TextLayout {
+	font: FontId,
+	size: f32,
+	glyphs: Vec<PositionedGlyph>,
+	width: f32,
+	height: f32,
+	lines: usize,
+}
+
+impl TextLayout {
+	/// Width of the widest line, or the wrap width when one was given.
+	pub fn width(&self) -> f32 {
+		self.width
+	}
+
+	pub fn height(&self) -> f32 {
+		self.height
+	}
+
+	pub fn line_count(&self) -> usize {
+		self.lines
+	}
+}
+
+#[derive(Copy, Clone, Debug)]
+struct CachedGlyph {
+	uv_rect: UvRect,
+	/// From the pen position to the top left of the glyph image, in pixels.
+	offset: [f32; 2],
+	size: [f32; 2],
+}
+
+/// Rasterized glyphs packed into one texture in rows as they are first drawn.
+struct GlyphCache {
+	texture: TextureId,
+	/// Glyphs with no outline, such as spaces, are cached as `None`.
+	glyphs: HashMap<(FontId, GlyphId, u32), Option<CachedGlyph>>,
+	x: u32,
+	y: u32,
+	row_height: u32,
+	full: bool,
+}
+
+impl GlyphCache {
+	fn clear(&mut self) {
+		self.glyphs.clear();
+		self.x = 0;
+		self.y = 0;
+		self.row_height = 0;
+		self.full = false;
+	}
+
+	/// Finds room for a `width` by `height` image, or `None` when the texture is full.
+	fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+		let (width, height) = (width + PADDING * 2, height + PADDING * 2);
+		if self.x + width > GLYPH_CACHE_SIZE {
+			self.x = 0;
+			self.y += self.row_height;
+			self.row_height = 0;
+		}
+		if self.x + width > GLYPH_CACHE_SIZE || self.y + height > GLYPH_CACHE_SIZE {
+			return None;
+		}
+
+		let position = (self.x + PADDING, self.y + PADDING);
+		self.x += width;
+		self.row_height = self.row_height.max(height);
+		Some(position)
+	}
+}
+
+/// Loaded fonts and the glyph cache shared by all text.
+pub struct TextRenderer {
+	fonts: Vec<FontArc>,
+	cache: GlyphCache,
+}
+
+impl TextRenderer {
+	/// Uses `texture`, which must be [`GLYPH_CACHE_SIZE`] pixels square, as the glyph cache.
+	pub fn new(texture: TextureId) -> Self {
+		Self {
+			fonts: Vec::new(),
+			cache: GlyphCache {
+				texture,
+				glyphs: HashMap::new(),
+				x: 0,
+				y: 0,
+				row_height: 0,
+				full: false,
+			},
+		}
+	}
+
+	pub fn cache_texture(&self) -> TextureId {
+		self.cache.texture
+	}
+
+	/// Loads a TrueType or OpenType font.
+	pub fn load_font(&mut self, path: &Path) -> Result<FontId, FontError> {
+		let data = fs::read(path).map_err(|error| FontError::Io(path.to_owned(), error))?;
+		let font = FontArc::try_from_vec(data).map_err(|_| FontError::Invalid(path.to_owned()))?;
+		self.fonts.push(font);
+		Ok(FontId(self.fonts.len() - 1))
+	}
+
+	/// Breaks `spans` into lines and positions every glyph.
+	pub fn layout(&self, spans: &[TextSpan], style: &TextStyle) -> TextLayout {
+		let font = self.fonts[style.font.0].as_scaled(PxScale::from(style.size));
+		let characters = spans
+			.iter()
+			.flat_map(|span| span.text.chars().map(move |character| (character, span.color)))
+			.collect::<Vec<_>>();
+
+		let lines = break_lines(&font, &characters, style.wrap_width);
+		let widths = lines
+			.iter()
+			.map(|line| measure(&font, &characters[trim_end(&characters, line.clone())]))
+			.collect::<Vec<_>>();
+		let width = style.wrap_width.unwrap_or_else(|| widths.iter().copied().fold(0.0, f32::max));
+		let line_height = (font.height() + font.line_gap()) * style.line_spacing;
+
+		let mut glyphs = Vec::new();
+		for (index, (line, line_width)) in lines.iter().zip(&widths).enumerate() {
+			let mut x = match style.align {
+				Align::Left => 0.0,
+				Align::Center => (width - line_width) / 2.0,
+				Align::Right => width - line_width,
+			};
+			let baseline = (font.ascent() + index as f32 * line_height).round();
+			let mut previous = None;
+
+			for &(character, color) in &characters[line.clone()] {
+				let id = font.glyph_id(character);
+				if let Some(previous) = previous {
+					x += font.kern(previous, id);
+				}
+				if !character.is_whitespace() {
+					glyphs.push(PositionedGlyph {
+						id,
+						position: [x.round(), baseline],
+						color,
+					});
+				}
+				x += font.h_advance(id);
+				previous = Some(id);
+			}
+		}
+
+		TextLayout {
+			font: style.font,
+			size: style.size,
+			glyphs,
+			width,
+			height: lines.len() as f32 * line_height,
+			lines: lines.len(),
+		}
+	}
+
+	/// Turns `layout` into one sprite per glyph with its top left corner at `position`, in screen
+	/// pixels, rasterizing glyphs that are not cached yet into `texture`.
+	pub fn queue(
+		&mut self,
+		layout: &TextLayout,
+		position: [f32; 2],
+		queue: &Queue,
+		texture: &Texture,
+		mut push: impl FnMut(Sprite),
+	) {
+		let origin = [position[0].round(), position[1].round()];
+		for glyph in &layout.glyphs {
+			let Some(cached) = self.cached(layout.font, layout.size, glyph.id, queue, texture) else {
+				continue;
+			};
+
+			let top_left = [
+				origin[0] + glyph.position[0] + cached.offset[0],
+				origin[1] + glyph.position[1] + cached.offset[1],
+			];
+			push(Sprite {
+				tint: glyph.color,
+				uv_rect: cached.uv_rect,
+				..Sprite::new(
+					self.cache.texture,
+					[top_left[0] + cached.size[0] / 2.0, top_left[1] + cached.size[1] / 2.0],
+					cached.size,
+				)
+			});
+		}
+	}
+
+	fn cached(
+		&mut self,
+		font: FontId,
+		size: f32,
+		id: GlyphId,
+		queue: &Queue,
+		texture: &Texture,
+	) -> Option<CachedGlyph> {
+		let key = (font, id, size.to_bits());
+		if let Some(cached) = self.cache.glyphs.get(&key) {
+			return *cached;
+		}
+
+		let outlined = self.fonts[font.0].outline_glyph(id.with_scale(size));
+		let Some(outlined) = outlined else {
+			self.cache.glyphs.insert(key, None);
+			return None;
+		};
+
+		let bounds = outlined.px_bounds();
+		let (width, height) = (bounds.width() as u32, bounds.height() as u32);
+		let Some((x, y)) = self.cache.allocate(width, height) else {
+			if !self.cache.full {
+				log::warn!("glyph cache is full, some text is missing until the next frame");
+				self.cache.full = true;
+			}
+			return None;
+		};
+
+		let mut pixels = vec![255; (width * height * 4) as usize];
+		outlined.draw(|glyph_x, glyph_y, coverage| {
+			let index = ((glyph_y * width + glyph_x) * 4 + 3) as usize;
+			pixels[index] = (coverage.clamp(0.0, 1.0) * 255.0).round() as u8;
+		});
+		for pixel in pixels.chunks_mut(4) {
+			// Fully transparent texels stay black, matching what an untouched texture holds.
+			if pixel[3] == 0 {
+				pixel[..3].fill(0);
+			}
+		}
+		texture.write(queue, x, y, width, height, &pixels);
+
+		let scale = GLYPH_CACHE_SIZE as f32;
+		let cached = CachedGlyph {
+			uv_rect: UvRect {
+				min: [x as f32 / scale, y as f32 / scale],
+				max: [(x + width) as f32 / scale, (y + height) as f32 / scale],
+			},
+			offset: [bounds.min.x, bounds.min.y],
+			size: [width as f32, height as f32],
+		};
+		self.cache.glyphs.insert(key, Some(cached));
+		Some(cached)
+	}
+
+	/// Starts over with an empty cache if it filled up during the frame, so text that went
+	/// missing can be drawn again.
+	pub fn end_frame(&mut self) {
+		if self.cache.full {
+			self.cache.clear();
+		}
+	}
+}
+
+/// Splits text into lines at `\n` and wherever it would grow wider than `wrap_width`. The ranges
+/// leave out the `\n` and the spaces lines were broken at.
+fn break_lines(font: &PxScaleFont<&FontArc>, characters: &[(char, [f32; 4])], wrap_width: Option<f32>) -> Vec<Range<usize>> {
+	let mut lines = Vec::new();
+	let mut start = 0;
+	let mut last_space = None;
+
+	for (index, &(character, _)) in characters.iter().enumerate() {
+		if character == '\n' {
+			lines.push(start..index);
+			start = index + 1;
+			last_space = None;
+			continue;
+		}
+		if character.is_whitespace() {
+			last_space = Some(index);
+			continue;
+		}
+
+		let Some(wrap_width) = wrap_width else {
+			continue;
+		};
+		if index > start && measure(font, &characters[start..=index]) > wrap_width {
+			match last_space {
+				Some(space) => {
+					lines.push(start..space);
+					start = space + 1;
+				}
+				None => {
+					lines.push(start..index);
+					start = index;
+				}
+			}
+			last_space = None;
+		}
+	}
+
+	lines.push(start..characters.len());
+	lines
+}
+
+/// Range without its trailing whitespace, which takes up no visible room at the end of a line.
+fn trim_end(characters: &[(char, [f32; 4])], mut line: Range<usize>) -> Range<usize> {
+	while line.end > line.start && characters[line.end - 1].0.is_whitespace() {
+		line.end -= 1;
+	}
+	line
+}
+
+fn measure(font: &PxScaleFont<&FontArc>, characters: &[(char, [f32; 4])]) -> f32 {
+	let mut width = 0.0;
+	let mut previous = None;
+	for &(character, _) in characters {
+		let id = font.glyph_id(character);
+		if let Some(previous) = previous {
+			width += font.kern(previous, id);
+		}
+		width += font.h_advance(id);
+		previous = Some(id);
+	}
+	width
+}
@@ -0,0 +1,139 @@
+use std::num::NonZeroU32;
+
+use wgpu::{
+	AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+	BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, Device, Extent3d,
+	FilterMode, ImageCopyTexture, ImageDataLayout, Origin3d, Queue, SamplerBindingType,
+	SamplerDescriptor, ShaderStages, TextureAspect, TextureDescriptor, TextureDimension,
+	TextureFormat, TextureSampleType, TextureUsages, TextureViewDescriptor, TextureViewDimension,
+};
+
+/// Handle to a texture owned by a [`Renderer`](super::Renderer).
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct TextureId(pub(crate) usize);
+
+/// A sampled texture, kept alive by the bind group that exposes it to the sprite shader.
+pub struct Texture {
+	pub texture: wgpu::Texture,
+	pub bind_group: BindGroup,
+	pub width: u32,
+	pub height: u32,
+}
+
+impl Texture {
+	/// Uploads tightly packed RGBA pixels into a new texture.
+	pub fn from_rgba(
+		device: &Device,
+		queue: &Queue,
+		layout: &BindGroupLayout,
+		label: &str,
+		width: u32,
+		height: u32,
+		pixels: &[u8],
+	) -> Self {
+		let texture = Self::new(device, layout, label, width, height);
+		texture.write(queue, 0, 0, width, height, pixels);
+		texture
+	}
+
+	/// Replaces the pixels of the `width` by `height` region whose top left corner is `(x, y)`.
+	pub fn write(&self, queue: &Queue, x: u32, y: u32, width: u32, height: u32, pixels: &[u8]) {
+		assert_eq!(pixels.len(), (width * height * 4) as usize, "pixel data does not match the region size");
+		assert!(x + width <= self.width && y + height <= self.height, "region lies outside the texture");
+
+		queue.write_texture(
+			ImageCopyTexture {
+				texture: &self.texture,
+				mip_level: 0,
+				origin: Origin3d { x, y, z: 0 },
+				aspect: TextureAspect::All,
+			},
+			pixels,
+			ImageDataLayout {
+				offset: 0,
+				bytes_per_row: NonZeroU32::new(width * 4),
+				rows_per_image: NonZeroU32::new(height),
+			},
+			Extent3d {
+				width,
+				height,
+				depth_or_array_layers: 1,
+			},
+		);
+	}
+
+	/// Creates a texture with undefined contents, to be filled in with [`write`](Self::write).
+	pub fn new(device: &Device, layout: &BindGroupLayout, label: &str, width: u32, height: u32) -> Self {
+		let texture = device.create_texture(&TextureDescriptor {
+			label: Some(label),
+			size: Extent3d {
+				width,
+				height,
+				depth_or_array_layers: 1,
+			},
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: TextureDimension::D2,
+			format: TextureFormat::Rgba8UnormSrgb,
+			usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+		});
+
+		let view = texture.create_view(&TextureViewDescriptor::default());
+		let sampler = device.create_sampler(&SamplerDescriptor {
+			label: Some(label),
+			address_mode_u: AddressMode::ClampToEdge,
+			address_mode_v: AddressMode::ClampToEdge,
+			address_mode_w: AddressMode::ClampToEdge,
+			mag_filter: FilterMode::Nearest,
+			min_filter: FilterMode::Nearest,
+			mipmap_filter: FilterMode::Nearest,
+			..Default::default()
+		});
+
+		let bind_group = device.create_bind_group(&BindGroupDescriptor {
+			label: Some(label),
+			layout,
+			entries: &[
+				BindGroupEntry {
+					binding: 0,
+					resource: BindingResource::TextureView(&view),
+				},
+				BindGroupEntry {
+					binding: 1,
+					resource: BindingResource::Sampler(&sampler),
+				},
+			],
+		});
+
+		Self {
+			texture,
+			bind_group,
+			width,
+			height,
+		}
+	}
+
+	pub fn create_bind_group_layout(device: &Device) -> BindGroupLayout {
+		device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+			label: Some("texture"),
+			entries: &[
+				BindGroupLayoutEntry {
+					binding: 0,
+					visibility: ShaderStages::FRAGMENT,
+					ty: BindingType::Texture {
+						sample_type: TextureSampleType::Float { filterable: true },
+						view_dimension: TextureViewDimension::D2,
+						multisampled: false,
+					},
+					count: None,
+				},
+				BindGroupLayoutEntry {
+					binding: 1,
+					visibility: ShaderStages::FRAGMENT,
+					ty: BindingType::Sampler(SamplerBindingType::Filtering),
+					count: None,
+				},
+			],
+		})
+	}
+}
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use wgpu::{Buffer, BufferUsages, Device, IndexFormat, RenderPass};
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+
+use crate::tilemap::{CHUNK_SIZE, Tile, Tilemap};
+
+use super::atlas::{Atlas, AtlasRegion};
+use super::camera::Camera2D;
+use super::error::TextureError;
+use super::sprite::SpriteVertex;
+use super::texture::{Texture, TextureId};
+use super::RenderStats;
+
+/// Maps tile indices to the atlas regions they are drawn with.
+#[derive(Clone, Debug, Default)]
+pub struct TileSet {
+	regions: Vec<Option<AtlasRegion>>,
+}
+
+impl TileSet {
+	/// Uses the atlas sprite `names[i]` for `Tile(i + 1)`, leaving [`Tile::EMPTY`] undrawn.
+	pub fn from_atlas<'a>(atlas: &Atlas, names: impl IntoIterator<Item = &'a str>) -> Result<Self, TextureError> {
+		let mut regions = vec![None];
+		for name in names {
+			let region = atlas.get(name).ok_or_else(|| TextureError::MissingSprite(name.to_owned()))?;
+			regions.push(Some(region));
+		}
+		Ok(Self { regions })
+	}
+
+	fn region(&self, tile: Tile) -> Option<AtlasRegion> {
+		self.regions.get(tile.0 as usize).copied().flatten()
+	}
+}
+
+struct ChunkMesh {
+	revision: u64,
+	vertex_buffer: Buffer,
+	index_buffer: Buffer,
+	vertex_count: u32,
+	draws: Vec<(TextureId, Range<u32>)>,
+}
+
+/// Draws a [`Tilemap`] with one mesh per chunk. Meshes are only rebuilt when their chunk has
+/// changed, and only chunks inside the camera view are rebuilt or drawn.
+#[derive(Default)]
+pub struct TilemapRenderer {
+	tileset: TileSet,
+	meshes: HashMap<(u32, u32), ChunkMesh>,
+	visible: Vec<(u32, u32)>,
+}
+
+impl TilemapRenderer {
+	pub fn set_tileset(&mut self, tileset: TileSet) {
+		self.tileset = tileset;
+		self.meshes.clear();
+	}
+
+	/// Brings the meshes of all visible chunks up to date with `tilemap`.
+	pub fn prepare(&mut self, device: &Device, tilemap: &Tilemap, camera: &Camera2D) {
+		self.visible.clear();
+
+		let (chunks_x, chunks_y) = tilemap.chunk_counts();
+		let (min, max) = camera.visible_bounds();
+		// Views entirely off the map give empty ranges.
+		let chunk_range = |min: f32, max: f32, count: u32| {
+			let first = (min / CHUNK_SIZE as f32).floor().clamp(0.0, count as f32) as u32;
+			let end = ((max / CHUNK_SIZE as f32).floor() + 1.0).clamp(0.0, count as f32) as u32;
+			first..end
+		};
+		let columns = chunk_range(min[0], max[0], chunks_x);
+		let rows = chunk_range(min[1], max[1], chunks_y);
+		for chunk_y in rows {
+			for chunk_x in columns.clone() {
+				let revision = tilemap.chunk_revision(chunk_x, chunk_y);
+				let stale = self.meshes.get(&(chunk_x, chunk_y)).is_none_or(|mesh| mesh.revision != revision);
+				if stale {
+					match self.build_mesh(device, tilemap, chunk_x, chunk_y) {
+						Some(mesh) => self.meshes.insert((chunk_x, chunk_y), mesh),
+						None => self.meshes.remove(&(chunk_x, chunk_y)),
+					};
+				}
+
+				if self.meshes.contains_key(&(chunk_x, chunk_y)) {
+					self.visible.push((chunk_x, chunk_y));
+				}
+			}
+		}
+	}
+
+	/// Builds the mesh for a chunk, or `None` when it has nothing to draw.
+	fn build_mesh(&self, device: &Device, tilemap: &Tilemap, chunk_x: u32, chunk_y: u32) -> Option<ChunkMesh> {
+		let mut quads = Vec::new();
+		tilemap.for_each_in_chunk(chunk_x, chunk_y, |x, y, tile| {
+			if let Some(region) = self.tileset.region(tile) {
+				quads.push((region, x as f32, y as f32));
+			}
+		});
+		if quads.is_empty() {
+			return None;
+		}
+		quads.sort_by_key(|(region, ..)| region.texture);
+
+		let mut vertices = Vec::with_capacity(quads.len() * 4);
+		let mut indices = Vec::with_capacity(quads.len() * 6);
+		let mut draws: Vec<(TextureId, Range<u32>)> = Vec::new();
+
+		for (region, x, y) in quads {
+			let base = vertices.len() as u32;
+			let (min, max) = (region.uv_rect.min, region.uv_rect.max);
+			vertices.extend([
+				([x, y], [min[0], max[1]]),
+				([x + 1.0, y], [max[0], max[1]]),
+				([x + 1.0, y + 1.0], [max[0], min[1]]),
+				([x, y + 1.0], [min[0], min[1]]),
+			].map(|(position, uv)| SpriteVertex {
+				position,
+				uv,
+				color: [1.0; 4],
+			}));
+
+			let start = indices.len() as u32;
+			indices.extend([0, 1, 2, 0, 2, 3].map(|index| base + index));
+			let end = indices.len() as u32;
+
+			match draws.last_mut() {
+				Some((texture, range)) if *texture == region.texture => range.end = end,
+				_ => draws.push((region.texture, start..end)),
+			}
+		}
+
+		let label = format!("tilemap chunk {chunk_x},{chunk_y}");
+		Some(ChunkMesh {
+			revision: tilemap.chunk_revision(chunk_x, chunk_y),
+			vertex_buffer: device.create_buffer_init(&BufferInitDescriptor {
+				label: Some(&label),
+				contents: bytemuck::cast_slice(&vertices),
+				usage: BufferUsages::VERTEX,
+			}),
+			index_buffer: device.create_buffer_init(&BufferInitDescriptor {
+				label: Some(&label),
+				contents: bytemuck::cast_slice(&indices),
+				usage: BufferUsages::INDEX,
+			}),
+			vertex_count: vertices.len() as u32,
+			draws,
+		})
+	}
+
+	/// Forgets the visible chunks, so nothing is drawn until the next [`prepare`](Self::prepare).
+	pub fn clear_visible(&mut self) {
+		self.visible.clear();
+	}
+
+	/// Work the chunks found visible by the last [`prepare`](Self::prepare) take to draw.
+	pub fn stats(&self) -> RenderStats {
+		let mut stats = RenderStats::default();
+		for chunk in &self.visible {
+			let mesh = &self.meshes[chunk];
+			stats.draw_calls += mesh.draws.len() as u32;
+			stats.vertices += mesh.vertex_count;
+		}
+		stats
+	}
+
+	/// Records draw calls for the chunks found visible by the last [`prepare`](Self::prepare).
+	pub fn draw<'a>(&'a self, render_pass: &mut RenderPass<'a>, textures: &'a [Texture]) {
+		for chunk in &self.visible {
+			let mesh = &self.meshes[chunk];
+			render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+			render_pass.set_index_buffer(mesh.index_buffer.slice(..), IndexFormat::Uint32);
+
+			for (texture, indices) in &mesh.draws {
+				render_pass.set_bind_group(1, &textures[texture.0].bind_group, &[]);
+				render_pass.draw_indexed(indices.clone(), 0, 0..1);
+			}
+		}
+	}
+}
@@ -0,0 +1,187 @@
+use std::mem::size_of;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use wgpu::{
+	Buffer, BufferAddress, BufferAsyncError, BufferDescriptor, BufferUsages, CommandEncoder, Device,
+	Features, Maintain, MapMode, QuerySet, QuerySetDescriptor, QueryType, Queue,
+};
+
+/// Most render passes that can be timed in one frame.
+const MAX_PASSES: u32 = 8;
+
+/// Frames whose timestamps can be waiting to be read back at once. Frames beyond that go
+/// untimed rather than stalling on the GPU.
+const FRAMES_IN_FLIGHT: usize = 3;
+
+const QUERY_COUNT: u32 = MAX_PASSES * 2;
+const BUFFER_SIZE: BufferAddress = (QUERY_COUNT as usize * size_of::<u64>()) as BufferAddress;
+
+/// How long the GPU spent on one render pass.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PassTiming {
+	pub name: &'static str,
+	pub duration: Duration,
+}
+
+struct Readback {
+	buffer: Buffer,
+	passes: Vec<&'static str>,
+	frame: u64,
+	in_use: bool,
+	/// Set by the map callback once the timestamps can be read.
+	mapped: Arc<Mutex<Option<Result<(), BufferAsyncError>>>>,
+}
+
+/// Measures render passes with timestamp queries, reading the results back a few frames later
+/// without waiting for the GPU.
+pub struct GpuTimer {
+	query_set: QuerySet,
+	readbacks: Vec<Readback>,
+	/// Readback used by the frame being encoded, if one was free.
+	current: Option<usize>,
+	frame: u64,
+	latest_frame: u64,
+	latest: Vec<PassTiming>,
+	/// Nanoseconds per timestamp tick.
+	period: f64,
+}
+
+impl GpuTimer {
+	/// Creates a timer, or returns `None` when the device was opened without
+	/// [`Features::TIMESTAMP_QUERY`].
+	pub fn new(device: &Device, queue: &Queue) -> Option<Self> {
+		if !device.features().contains(Features::TIMESTAMP_QUERY) {
+			return None;
+		}
+
+		let readbacks = (0..FRAMES_IN_FLIGHT)
+			.map(|_| Readback {
+				buffer: device.create_buffer(&BufferDescriptor {
+					label: Some("timestamp read back"),
+					size: BUFFER_SIZE,
+					usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+					mapped_at_creation: false,
+				}),
+				passes: Vec::new(),
+				frame: 0,
+				in_use: false,
+				mapped: Arc::new(Mutex::new(None)),
+			})
+			.collect();
+
+		Some(Self {
+			query_set: device.create_query_set(&QuerySetDescriptor {
+				label: Some("pass timestamps"),
+				ty: QueryType::Timestamp,
+				count: QUERY_COUNT,
+			}),
+			readbacks,
+			current: None,
+			frame: 0,
+			latest_frame: 0,
+			latest: Vec::new(),
+			period: queue.get_timestamp_period() as f64,
+		})
+	}
+
+	/// Starts timing a new frame, if a readback buffer is free for it.
+	pub fn begin_frame(&mut self) {
+		self.frame += 1;
+		self.current = self.readbacks.iter().position(|readback| !readback.in_use);
+		if let Some(index) = self.current {
+			let readback = &mut self.readbacks[index];
+			readback.passes.clear();
+			readback.frame = self.frame;
+		}
+	}
+
+	/// Writes the timestamp before a pass named `name`.
+	pub fn begin_pass(&mut self, encoder: &mut CommandEncoder, name: &'static str) {
+		let Some(index) = self.current else {
+			return;
+		};
+		let passes = &mut self.readbacks[index].passes;
+		assert!(passes.len() < MAX_PASSES as usize, "more than {MAX_PASSES} timed passes in a frame");
+		encoder.write_timestamp(&self.query_set, passes.len() as u32 * 2);
+		passes.push(name);
+	}
+
+	/// Writes the timestamp after the pass started by the last [`begin_pass`](Self::begin_pass).
+	pub fn end_pass(&mut self, encoder: &mut CommandEncoder) {
+		let Some(index) = self.current else {
+			return;
+		};
+		let passes = self.readbacks[index].passes.len() as u32;
+		encoder.write_timestamp(&self.query_set, passes * 2 - 1);
+	}
+
+	/// Copies the frame's timestamps into its readback buffer, before the encoder is finished.
+	pub fn resolve(&mut self, encoder: &mut CommandEncoder) {
+		let Some(index) = self.current else {
+			return;
+		};
+		let readback = &self.readbacks[index];
+		let queries = readback.passes.len() as u32 * 2;
+		if queries == 0 {
+			return;
+		}
+		encoder.resolve_query_set(&self.query_set, 0..queries, &readback.buffer, 0);
+	}
+
+	/// Starts reading back the frame's timestamps. Call once its commands have been submitted.
+	pub fn end_frame(&mut self) {
+		let Some(index) = self.current.take() else {
+			return;
+		};
+		let readback = &mut self.readbacks[index];
+		if readback.passes.is_empty() {
+			return;
+		}
+
+		readback.in_use = true;
+		let mapped = readback.mapped.clone();
+		readback.buffer.slice(..).map_async(MapMode::Read, move |result| {
+			*mapped.lock().unwrap() = Some(result);
+		});
+	}
+
+	/// Reads the timestamps of any frames the GPU has finished, without waiting for the rest.
+	pub fn collect(&mut self, device: &Device) {
+		device.poll(Maintain::Poll);
+
+		for readback in &mut self.readbacks {
+			if !readback.in_use {
+				continue;
+			}
+			let Some(result) = readback.mapped.lock().unwrap().take() else {
+				continue;
+			};
+			readback.in_use = false;
+			if let Err(error) = result {
+				log::warn!("failed to read back GPU timestamps: {error}");
+				continue;
+			}
+
+			if readback.frame > self.latest_frame {
+				let data = readback.buffer.slice(..).get_mapped_range();
+				let timestamps: &[u64] = bytemuck::cast_slice(&data);
+				self.latest = readback.passes
+					.iter()
+					.zip(timestamps.chunks(2))
+					.map(|(&name, pair)| PassTiming {
+						name,
+						duration: Duration::from_nanos((pair[1].saturating_sub(pair[0]) as f64 * self.period) as u64),
+					})
+					.collect();
+				self.latest_frame = readback.frame;
+			}
+			readback.buffer.unmap();
+		}
+	}
+
+	/// Pass timings of the most recent frame read back so far, usually a few frames old.
+	pub fn latest(&self) -> &[PassTiming] {
+		&self.latest
+	}
+}
@@ -0,0 +1,79 @@
+/// Small, fast random number generator (xoshiro256**) with a fixed algorithm, so a seed gives the
+/// same sequence on every platform and in every version of the game.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Rng {
+	state: [u64; 4],
+}
+
+impl Rng {
+	pub fn new(seed: u64) -> Self {
+		let mut seed = seed;
+		Self {
+			state: [(); 4].map(|_| split_mix(&mut seed)),
+		}
+	}
+
+	/// Restores a generator saved with [`state`](Self::state).
+	pub fn from_state(state: [u64; 4]) -> Self {
+		// An all zero state would only ever produce zeros.
+		if state == [0; 4] {
+			return Self::new(0);
+		}
+		Self { state }
+	}
+
+	pub fn state(&self) -> [u64; 4] {
+		self.state
+	}
+
+	pub fn next_u64(&mut self) -> u64 {
+		let [s0, s1, s2, s3] = &mut self.state;
+		let result = s1.wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+		let t = *s1 << 17;
+
+		*s2 ^= *s0;
+		*s3 ^= *s1;
+		*s1 ^= *s2;
+		*s0 ^= *s3;
+		*s2 ^= t;
+		*s3 = s3.rotate_left(45);
+
+		result
+	}
+
+	pub fn next_u32(&mut self) -> u32 {
+		(self.next_u64() >> 32) as u32
+	}
+
+	/// Uniformly distributed in `[0, 1)`.
+	pub fn next_f32(&mut self) -> f32 {
+		(self.next_u64() >> 40) as f32 / (1u32 << 24) as f32
+	}
+
+	/// Uniformly distributed in `[0, bound)`. `bound` must not be zero.
+	pub fn below(&mut self, bound: u32) -> u32 {
+		assert!(bound > 0, "bound must not be zero");
+		// Multiply and shift keeps the result unbiased enough for game purposes without looping.
+		((self.next_u32() as u64 * bound as u64) >> 32) as u32
+	}
+
+	/// `true` with the given probability.
+	pub fn chance(&mut self, probability: f32) -> bool {
+		self.next_f32() < probability
+	}
+}
+
+/// Step of the SplitMix64 generator, used to spread seeds over the full state.
+pub fn split_mix(state: &mut u64) -> u64 {
+	*state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+	let mut z = *state;
+	z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+	z ^ (z >> 31)
+}
+
+/// Hashes a seed and grid position into 64 well mixed bits.
+pub fn hash2(seed: u64, x: i32, y: i32) -> u64 {
+	let mut state = seed ^ (x as u32 as u64) ^ ((y as u32 as u64) << 32);
+	split_mix(&mut state)
+}
@@ -0,0 +1,440 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::building::{self, Occupancy};
+use crate::colonist;
+use crate::conveyor;
+use crate::ecs::EntitySlots;
+use crate::game::Game;
+use crate::renderer::Image;
+use crate::rng::Rng;
+use crate::tilemap::{CHUNK_SIZE, Tile, Tilemap};
+
+pub use self::codec::{Reader, Writer};
+pub use self::registry::{Persist, Registry};
+
+mod codec;
+mod registry;
+
+/// Quick save written and read with the save and load keys.
+pub const DEFAULT_SAVE_PATH: &str = "quicksave.sav";
+
+/// Marks a file as a save, ahead of everything else.
+const MAGIC: [u8; 4] = *b"FOSV";
+
+/// Most tiles a saved map may have, counting the whole chunks its sides are rounded up to.
+/// Larger sizes are taken as corruption rather than allocated.
+const MAX_TILES: u64 = 1 << 28;
+
+/// Largest width or height of a save's thumbnail in pixels.
+pub const THUMBNAIL_SIZE: u32 = 64;
+
+/// Upgrades the sections of a save by one version.
+pub type Migration = fn(&mut Sections) -> Result<(), SaveError>;
+
+/// `MIGRATIONS[n]` upgrades a version `n + 1` save to version `n + 2`. Adding a migration is what
+/// bumps [`VERSION`], so every change to the layout of a section needs one.
+const MIGRATIONS: &[Migration] = &[
+	colonist::migrate_hauls_to_pickup_lists,
+];
+
+/// Version of saves written by this build. Saves from any earlier version can be loaded.
+pub const VERSION: u32 = MIGRATIONS.len() as u32 + 1;
+
+/// Reasons a save could not be written or read.
+#[derive(Debug)]
+pub enum SaveError {
+	Io(PathBuf, io::Error),
+	/// The data does not start like a save.
+	NotASave,
+	/// The save was written by a newer build, or its version number is corrupt.
+	UnsupportedVersion(u32),
+	/// The data ends part way through.
+	Truncated,
+	/// A section every save has is missing.
+	MissingSection(String),
+	Corrupt(String),
+}
+
+impl Display for SaveError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			SaveError::Io(path, error) => write!(f, "failed to access {}: {error}", path.display()),
+			SaveError::NotASave => write!(f, "not a Frontier Outpost save"),
+			SaveError::UnsupportedVersion(version) => {
+				write!(f, "save version {version} is not supported, this build reads up to version {VERSION}")
+			}
+			SaveError::Truncated => write!(f, "save is truncated"),
+			SaveError::MissingSection(name) => write!(f, "save has no {name} section"),
+			SaveError::Corrupt(reason) => write!(f, "save is corrupt: {reason}"),
+		}
+	}
+}
+
+impl Error for SaveError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			SaveError::Io(_, error) => Some(error),
+			_ => None,
+		}
+	}
+}
+
+/// What a save is, readable without loading the rest of it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Header {
+	pub version: u32,
+	pub seed: u64,
+	pub thumbnail: Image,
+}
+
+/// The body of a save: named blocks of bytes, each holding one part of the game state.
+///
+/// Every section is length prefixed, so sections this build does not know, such as those of
+/// components that no longer exist, are skipped rather than breaking the load.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Sections {
+	sections: BTreeMap<String, Vec<u8>>,
+}
+
+impl Sections {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn get(&self, name: &str) -> Option<&[u8]> {
+		self.sections.get(name).map(Vec::as_slice)
+	}
+
+	/// Adds or replaces a section, returning the data it replaced.
+	pub fn insert(&mut self, name: impl Into<String>, data: Vec<u8>) -> Option<Vec<u8>> {
+		self.sections.insert(name.into(), data)
+	}
+
+	pub fn remove(&mut self, name: &str) -> Option<Vec<u8>> {
+		self.sections.remove(name)
+	}
+
+	pub fn names(&self) -> impl Iterator<Item = &str> {
+		self.sections.keys().map(String::as_str)
+	}
+}
+
+/// Runs the migrations that upgrade a `version` save to the newest version `migrations` knows.
+pub fn migrate(sections: &mut Sections, version: u32, migrations: &[Migration]) -> Result<(), SaveError> {
+	if version == 0 || version as usize > migrations.len() + 1 {
+		return Err(SaveError::UnsupportedVersion(version));
+	}
+	for migration in &migrations[version as usize - 1..] {
+		migration(sections)?;
+	}
+	Ok(())
+}
+
+/// The game in the current save format:
+///
+/// - the magic bytes `FOSV` and the version as a `u32`
+/// - the length prefixed header, whose layout never changes so any build can list saves
+/// - the number of sections, followed by each section's name and length prefixed data
+pub fn encode(game: &Game, thumbnail: &Image) -> Vec<u8> {
+	let mut sections = Sections::new();
+	sections.insert("tiles", write_section(|writer| save_tiles(&game.tilemap, writer)));
+	sections.insert("entities", write_section(|writer| save_slots(&game.world.slots(), writer)));
+	sections.insert("time", write_section(|writer| writer.u64(game.time.as_nanos() as u64)));
+	sections.insert("rng", write_section(|writer| {
+		for word in game.rng.state() {
+			writer.u64(word);
+		}
+	}));
+	game.persistence.save(&game.world, &mut sections);
+
+	let mut writer = Writer::new();
+	writer.raw(&MAGIC);
+	writer.u32(VERSION);
+	writer.bytes(&write_section(|writer| {
+		writer.u64(game.seed);
+		writer.varint(thumbnail.width as u64);
+		writer.varint(thumbnail.height as u64);
+		// Runs of the same colour, like the tiles they show.
+		let mut pixels = thumbnail.pixels.chunks_exact(4).peekable();
+		while let Some(pixel) = pixels.next() {
+			let mut run = 1;
+			while pixels.next_if_eq(&pixel).is_some() {
+				run += 1;
+			}
+			writer.varint(run);
+			writer.raw(pixel);
+		}
+	}));
+	writer.varint(sections.sections.len() as u64);
+	for (name, data) in &sections.sections {
+		writer.str(name);
+		writer.bytes(data);
+	}
+	writer.into_bytes()
+}
+
+/// Reads the header of a save without looking at the rest.
+pub fn decode_header(bytes: &[u8]) -> Result<Header, SaveError> {
+	read_header(&mut Reader::new(bytes))
+}
+
+fn read_header(reader: &mut Reader) -> Result<Header, SaveError> {
+	if reader.raw(MAGIC.len()).ok() != Some(&MAGIC[..]) {
+		return Err(SaveError::NotASave);
+	}
+	let version = reader.u32()?;
+	if version == 0 || version > VERSION {
+		return Err(SaveError::UnsupportedVersion(version));
+	}
+
+	read_section("header", reader.bytes()?, |reader| {
+		let seed = reader.u64()?;
+		let width = reader.varint_u32()?;
+		let height = reader.varint_u32()?;
+		if width > THUMBNAIL_SIZE || height > THUMBNAIL_SIZE {
+			return Err(SaveError::Corrupt(format!("{width}x{height} thumbnail is too large")));
+		}
+		let count = width as u64 * height as u64;
+		let mut pixels = Vec::new();
+		while (pixels.len() as u64) < count * 4 {
+			let run = reader.varint()?;
+			let pixel = reader.raw(4)?;
+			if run == 0 || run > count - pixels.len() as u64 / 4 {
+				return Err(SaveError::Corrupt(format!("run of {run} pixels does not fit the thumbnail")));
+			}
+			for _ in 0..run {
+				pixels.extend_from_slice(pixel);
+			}
+		}
+		Ok(Header {
+			version,
+			seed,
+			thumbnail: Image { width, height, pixels },
+		})
+	})
+}
+
+/// Loads a save of this or any earlier version.
+pub fn decode(bytes: &[u8]) -> Result<Game, SaveError> {
+	let mut reader = Reader::new(bytes);
+	let header = read_header(&mut reader)?;
+
+	let mut sections = Sections::new();
+	for _ in 0..reader.varint()? {
+		let name = reader.str()?.to_owned();
+		let data = reader.bytes()?.to_vec();
+		if sections.insert(name.clone(), data).is_some() {
+			return Err(SaveError::Corrupt(format!("section {name} appears twice")));
+		}
+	}
+	if !reader.is_empty() {
+		return Err(SaveError::Corrupt("data after the last section".into()));
+	}
+	migrate(&mut sections, header.version, MIGRATIONS)?;
+
+	let required = |name: &str| sections.get(name).ok_or_else(|| SaveError::MissingSection(name.into()));
+	let tilemap = read_section("tiles", required("tiles")?, load_tiles)?;
+	let slots = read_section("entities", required("entities")?, load_slots)?;
+	let time = read_section("time", required("time")?, |reader| Ok(Duration::from_nanos(reader.u64()?)))?;
+	let rng = read_section("rng", required("rng")?, |reader| {
+		Ok(Rng::from_state([reader.u64()?, reader.u64()?, reader.u64()?, reader.u64()?]))
+	})?;
+
+	let mut game = Game::with_tilemap(header.seed, tilemap);
+	game.time = time;
+	game.rng = rng;
+	if !game.world.restore_slots(slots) {
+		return Err(SaveError::Corrupt("entities section is inconsistent".into()));
+	}
+	game.persistence.load(&mut game.world, &sections)?;
+	let mut occupancy = Occupancy::from_world(&game.world, game.tilemap.width(), game.tilemap.height());
+	conveyor::occupy(&game.world, &mut occupancy);
+	game.world.insert_resource(occupancy);
+	building::block_paths(&mut game.world);
+
+	for name in sections.names() {
+		if !matches!(name, "tiles" | "entities" | "time" | "rng") && !game.persistence.contains(name) {
+			log::warn!("ignoring unknown save section {name}");
+		}
+	}
+	Ok(game)
+}
+
+/// Writes a save to `path`. The previous save there is only replaced once the new one is
+/// complete, so a crash part way through never leaves a broken save behind.
+pub fn save(path: &Path, game: &Game, thumbnail: &Image) -> Result<(), SaveError> {
+	let mut partial = path.as_os_str().to_owned();
+	partial.push(".partial");
+	let partial = PathBuf::from(partial);
+
+	fs::write(&partial, encode(game, thumbnail)).map_err(|error| SaveError::Io(partial.clone(), error))?;
+	fs::rename(&partial, path).map_err(|error| SaveError::Io(path.to_owned(), error))
+}
+
+pub fn load(path: &Path) -> Result<Game, SaveError> {
+	decode(&read_file(path)?)
+}
+
+pub fn load_header(path: &Path) -> Result<Header, SaveError> {
+	decode_header(&read_file(path)?)
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, SaveError> {
+	fs::read(path).map_err(|error| SaveError::Io(path.to_owned(), error))
+}
+
+fn write_section(f: impl FnOnce(&mut Writer)) -> Vec<u8> {
+	let mut writer = Writer::new();
+	f(&mut writer);
+	writer.into_bytes()
+}
+
+/// Reads a whole section with `f`, naming the section in any error.
+fn read_section<T>(
+	name: &str,
+	data: &[u8],
+	f: impl FnOnce(&mut Reader) -> Result<T, SaveError>,
+) -> Result<T, SaveError> {
+	let mut reader = Reader::new(data);
+	let value = f(&mut reader).and_then(|value| match reader.is_empty() {
+		true => Ok(value),
+		false => Err(SaveError::Corrupt("unread data at the end".into())),
+	});
+	value.map_err(|error| match error {
+		SaveError::Truncated => SaveError::Corrupt(format!("{name} section is truncated")),
+		SaveError::Corrupt(reason) => SaveError::Corrupt(format!("{name} section: {reason}")),
+		error => error,
+	})
+}
+
+/// Tiles row by row as runs of the same tile, which most of a map is.
+fn save_tiles(tilemap: &Tilemap, writer: &mut Writer) {
+	writer.varint(tilemap.width() as u64);
+	writer.varint(tilemap.height() as u64);
+
+	let mut tiles = (0..tilemap.height() as i32)
+		.flat_map(|y| (0..tilemap.width() as i32).map(move |x| (x, y)))
+		.map(|(x, y)| tilemap.get(x, y).unwrap_or_default())
+		.peekable();
+	while let Some(tile) = tiles.next() {
+		let mut run = 1;
+		while tiles.next_if_eq(&tile).is_some() {
+			run += 1;
+		}
+		writer.varint(run);
+		writer.varint(tile.0 as u64);
+	}
+}
+
+fn load_tiles(reader: &mut Reader) -> Result<Tilemap, SaveError> {
+	let width = reader.varint_u32()?;
+	let height = reader.varint_u32()?;
+	let count = width as u64 * height as u64;
+	let chunks = width.div_ceil(CHUNK_SIZE) as u64 * height.div_ceil(CHUNK_SIZE) as u64;
+	if chunks * (CHUNK_SIZE * CHUNK_SIZE) as u64 > MAX_TILES {
+		return Err(SaveError::Corrupt(format!("{width}x{height} map is too large")));
+	}
+
+	let mut tilemap = Tilemap::new(width, height);
+	let mut index = 0;
+	while index < count {
+		let run = reader.varint()?;
+		let tile = u16::try_from(reader.varint()?)
+			.map_err(|_| SaveError::Corrupt("tile index is out of range".into()))?;
+		if run == 0 || run > count - index {
+			return Err(SaveError::Corrupt(format!("run of {run} tiles does not fit the map")));
+		}
+		for index in index..index + run {
+			let (x, y) = (index % width as u64, index / width as u64);
+			tilemap.set(x as i32, y as i32, Tile(tile));
+		}
+		index += run;
+	}
+	Ok(tilemap)
+}
+
+fn save_slots(slots: &EntitySlots, writer: &mut Writer) {
+	writer.varint(slots.generations.len() as u64);
+	for (&generation, &alive) in slots.generations.iter().zip(&slots.alive) {
+		writer.varint(generation as u64);
+		writer.bool(alive);
+	}
+	writer.varint(slots.free.len() as u64);
+	for &index in &slots.free {
+		writer.varint(index as u64);
+	}
+}
+
+fn load_slots(reader: &mut Reader) -> Result<EntitySlots, SaveError> {
+	let mut slots = EntitySlots::default();
+	for _ in 0..reader.varint()? {
+		slots.generations.push(reader.varint_u32()?);
+		slots.alive.push(reader.bool()?);
+	}
+	for _ in 0..reader.varint()? {
+		slots.free.push(reader.varint_u32()?);
+	}
+	Ok(slots)
+}
+
+/// A picture of the whole map at most [`THUMBNAIL_SIZE`] pixels across, each pixel coloured by
+/// `color` of the tile under its centre.
+pub fn thumbnail(tilemap: &Tilemap, color: impl Fn(Tile) -> [u8; 4]) -> Image {
+	let (map_width, map_height) = (tilemap.width().max(1), tilemap.height().max(1));
+	let longest = map_width.max(map_height);
+	let scale = |size: u32| match longest > THUMBNAIL_SIZE {
+		true => (size * THUMBNAIL_SIZE / longest).max(1),
+		false => size,
+	};
+	let (width, height) = (scale(map_width), scale(map_height));
+
+	let mut image = Image::new(width, height);
+	for row in 0..height {
+		// Image rows go down from the top, map rows go up.
+		let y = (2 * (height - 1 - row) + 1) * map_height / (2 * height);
+		for column in 0..width {
+			let x = (2 * column + 1) * map_width / (2 * width);
+			let pixel = tilemap.get(x as i32, y as i32).map_or([0; 4], &color);
+			let offset = ((row * width + column) * 4) as usize;
+			image.pixels[offset..offset + 4].copy_from_slice(&pixel);
+		}
+	}
+	image
+}
+
+/// Decides when to save automatically, measured in simulated time so a paused or stalled game is
+/// not saved over and over.
+#[derive(Clone, Debug)]
+pub struct Autosave {
+	interval: Duration,
+	next: Duration,
+}
+
+impl Autosave {
+	/// Saves every `interval` starting from simulated time `now`.
+	pub fn new(interval: Duration, now: Duration) -> Self {
+		Self {
+			interval,
+			next: now + interval,
+		}
+	}
+
+	pub fn interval(&self) -> Duration {
+		self.interval
+	}
+
+	/// Whether a save is due at simulated time `now`. The next one is then due an interval later.
+	pub fn due(&mut self, now: Duration) -> bool {
+		if now < self.next {
+			return false;
+		}
+		self.next = now + self.interval;
+		true
+	}
+}
@@ -0,0 +1,168 @@
+use crate::ecs::Entity;
+
+use super::SaveError;
+
+/// Appends values to a save in little endian byte order.
+///
+/// Counts and indices are written as variable length integers, so small values, which are most
+/// of them, take a single byte.
+#[derive(Debug, Default)]
+pub struct Writer {
+	bytes: Vec<u8>,
+}
+
+impl Writer {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn into_bytes(self) -> Vec<u8> {
+		self.bytes
+	}
+
+	pub fn u8(&mut self, value: u8) {
+		self.bytes.push(value);
+	}
+
+	pub fn bool(&mut self, value: bool) {
+		self.u8(value as u8);
+	}
+
+	pub fn u32(&mut self, value: u32) {
+		self.bytes.extend_from_slice(&value.to_le_bytes());
+	}
+
+	pub fn u64(&mut self, value: u64) {
+		self.bytes.extend_from_slice(&value.to_le_bytes());
+	}
+
+	pub fn i32(&mut self, value: i32) {
+		self.bytes.extend_from_slice(&value.to_le_bytes());
+	}
+
+	/// Stores the exact bits, so a loaded value is identical to the saved one.
+	pub fn f32(&mut self, value: f32) {
+		self.u32(value.to_bits());
+	}
+
+	/// Unsigned LEB128: seven bits per byte, the high bit set on every byte but the last.
+	pub fn varint(&mut self, mut value: u64) {
+		while value >= 0x80 {
+			self.u8(value as u8 | 0x80);
+			value >>= 7;
+		}
+		self.u8(value as u8);
+	}
+
+	/// Bytes without a length, for data whose size the reader already knows.
+	pub fn raw(&mut self, bytes: &[u8]) {
+		self.bytes.extend_from_slice(bytes);
+	}
+
+	/// Length prefixed bytes.
+	pub fn bytes(&mut self, bytes: &[u8]) {
+		self.varint(bytes.len() as u64);
+		self.raw(bytes);
+	}
+
+	pub fn str(&mut self, value: &str) {
+		self.bytes(value.as_bytes());
+	}
+
+	/// A reference to an entity, valid in the loaded world since entity slots are saved too.
+	pub fn entity(&mut self, entity: Entity) {
+		self.varint(entity.index() as u64);
+		self.varint(entity.generation() as u64);
+	}
+}
+
+/// Reads values written by a [`Writer`], failing instead of panicking on truncated or corrupt
+/// data.
+#[derive(Debug)]
+pub struct Reader<'a> {
+	bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+	pub fn new(bytes: &'a [u8]) -> Self {
+		Self { bytes }
+	}
+
+	/// Whether every byte has been read.
+	pub fn is_empty(&self) -> bool {
+		self.bytes.is_empty()
+	}
+
+	/// The next `count` bytes.
+	pub fn raw(&mut self, count: usize) -> Result<&'a [u8], SaveError> {
+		if count > self.bytes.len() {
+			return Err(SaveError::Truncated);
+		}
+		let (taken, rest) = self.bytes.split_at(count);
+		self.bytes = rest;
+		Ok(taken)
+	}
+
+	fn array<const N: usize>(&mut self) -> Result<[u8; N], SaveError> {
+		Ok(self.raw(N)?.try_into().expect("took exactly N bytes"))
+	}
+
+	pub fn u8(&mut self) -> Result<u8, SaveError> {
+		Ok(self.raw(1)?[0])
+	}
+
+	pub fn bool(&mut self) -> Result<bool, SaveError> {
+		match self.u8()? {
+			0 => Ok(false),
+			1 => Ok(true),
+			value => Err(SaveError::Corrupt(format!("{value} is not a boolean"))),
+		}
+	}
+
+	pub fn u32(&mut self) -> Result<u32, SaveError> {
+		Ok(u32::from_le_bytes(self.array()?))
+	}
+
+	pub fn u64(&mut self) -> Result<u64, SaveError> {
+		Ok(u64::from_le_bytes(self.array()?))
+	}
+
+	pub fn i32(&mut self) -> Result<i32, SaveError> {
+		Ok(i32::from_le_bytes(self.array()?))
+	}
+
+	pub fn f32(&mut self) -> Result<f32, SaveError> {
+		Ok(f32::from_bits(self.u32()?))
+	}
+
+	pub fn varint(&mut self) -> Result<u64, SaveError> {
+		let mut value = 0;
+		for shift in (0..64).step_by(7) {
+			let byte = self.u8()?;
+			value |= ((byte & 0x7f) as u64) << shift;
+			if byte & 0x80 == 0 {
+				return Ok(value);
+			}
+		}
+		Err(SaveError::Corrupt("variable length integer is too long".into()))
+	}
+
+	/// A varint that has to fit in a `u32`, such as a count or an index.
+	pub fn varint_u32(&mut self) -> Result<u32, SaveError> {
+		let value = self.varint()?;
+		value.try_into().map_err(|_| SaveError::Corrupt(format!("{value} is out of range")))
+	}
+
+	pub fn bytes(&mut self) -> Result<&'a [u8], SaveError> {
+		let length = usize::try_from(self.varint()?).map_err(|_| SaveError::Truncated)?;
+		self.raw(length)
+	}
+
+	pub fn str(&mut self) -> Result<&'a str, SaveError> {
+		std::str::from_utf8(self.bytes()?).map_err(|_| SaveError::Corrupt("string is not UTF-8".into()))
+	}
+
+	pub fn entity(&mut self) -> Result<Entity, SaveError> {
+		Ok(Entity::from_parts(self.varint_u32()?, self.varint_u32()?))
+	}
+}
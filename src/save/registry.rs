@@ -0,0 +1,112 @@
+use crate::ecs::World;
+
+use super::{Reader, SaveError, Sections, Writer, read_section};
+
+/// A component or resource that is kept in saves.
+pub trait Persist: Sized + 'static {
+	fn save(&self, writer: &mut Writer);
+	fn load(reader: &mut Reader) -> Result<Self, SaveError>;
+}
+
+struct Entry {
+	section: String,
+	/// `None` when the world has nothing of this type.
+	save: fn(&World) -> Option<Vec<u8>>,
+	load: fn(&mut World, &mut Reader) -> Result<(), SaveError>,
+}
+
+/// The component and resource types written to saves, each in its own section.
+///
+/// Types that are not registered, such as ones referring to GPU resources, are left out of saves
+/// and have to be rebuilt after loading.
+#[derive(Default)]
+pub struct Registry {
+	entries: Vec<Entry>,
+}
+
+impl Registry {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Saves every `T` component in the section `component.<name>`.
+	///
+	/// Panics if the name is already taken.
+	pub fn register_component<T: Persist>(&mut self, name: &str) {
+		self.add(format!("component.{name}"), save_component::<T>, load_component::<T>);
+	}
+
+	/// Saves the `T` resource in the section `resource.<name>`.
+	///
+	/// Panics if the name is already taken.
+	pub fn register_resource<T: Persist>(&mut self, name: &str) {
+		self.add(format!("resource.{name}"), save_resource::<T>, load_resource::<T>);
+	}
+
+	fn add(
+		&mut self,
+		section: String,
+		save: fn(&World) -> Option<Vec<u8>>,
+		load: fn(&mut World, &mut Reader) -> Result<(), SaveError>,
+	) {
+		assert!(!self.contains(&section), "{section} is registered twice");
+		self.entries.push(Entry { section, save, load });
+	}
+
+	/// Whether a section is written by one of the registered types.
+	pub fn contains(&self, section: &str) -> bool {
+		self.entries.iter().any(|entry| entry.section == section)
+	}
+
+	pub(super) fn save(&self, world: &World, sections: &mut Sections) {
+		for entry in &self.entries {
+			if let Some(data) = (entry.save)(world) {
+				sections.insert(entry.section.clone(), data);
+			}
+		}
+	}
+
+	/// Loads every registered section present in `sections` into `world`, whose entities have to
+	/// be restored already.
+	pub(super) fn load(&self, world: &mut World, sections: &Sections) -> Result<(), SaveError> {
+		for entry in &self.entries {
+			if let Some(data) = sections.get(&entry.section) {
+				read_section(&entry.section, data, |reader| (entry.load)(world, reader))?;
+			}
+		}
+		Ok(())
+	}
+}
+
+fn save_component<T: Persist>(world: &World) -> Option<Vec<u8>> {
+	let storage = world.storage::<T>()?;
+	let mut writer = Writer::new();
+	writer.varint(storage.len() as u64);
+	// Storage order is kept, so systems iterate loaded components in the same order as before.
+	for (entity, component) in storage.iter() {
+		writer.varint(entity.index() as u64);
+		component.save(&mut writer);
+	}
+	Some(writer.into_bytes())
+}
+
+fn load_component<T: Persist>(world: &mut World, reader: &mut Reader) -> Result<(), SaveError> {
+	for _ in 0..reader.varint()? {
+		let index = reader.varint_u32()?;
+		let entity = world.entity(index)
+			.ok_or_else(|| SaveError::Corrupt(format!("entity {index} is not alive")))?;
+		world.insert(entity, T::load(reader)?);
+	}
+	Ok(())
+}
+
+fn save_resource<T: Persist>(world: &World) -> Option<Vec<u8>> {
+	let mut writer = Writer::new();
+	world.resource::<T>()?.save(&mut writer);
+	Some(writer.into_bytes())
+}
+
+fn load_resource<T: Persist>(world: &mut World, reader: &mut Reader) -> Result<(), SaveError> {
+	world.insert_resource(T::load(reader)?);
+	Ok(())
+}
@@ -1,7 +0,0 @@
-@vertex fn vertex_main(@location(0) position: vec2<f32>) -> @builtin(position) vec4<f32> {
-	return vec4<f32>(position, 0.0, 1.0);
-}
-
-@fragment fn fragment_main() -> @location(0) vec4<f32> {
-	return vec4<f32>(1.0, 1.0, 1.0, 1.0);
-}
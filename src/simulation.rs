@@ -0,0 +1,96 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::SimulationConfig;
+
+/// Game state advanced in fixed steps, independently of how often it is drawn.
+pub trait Simulation {
+	/// Advances the state by one tick of length `dt`.
+	fn tick(&mut self, dt: Duration);
+}
+
+/// Converts variable frame times into a whole number of fixed length ticks.
+pub struct FixedTimestep {
+	tick_length: Duration,
+	max_ticks_per_update: u32,
+	accumulator: Duration,
+	ticks: u64,
+}
+
+impl FixedTimestep {
+	pub fn new(config: &SimulationConfig) -> Self {
+		Self {
+			tick_length: Duration::from_secs(1) / config.ticks_per_second,
+			max_ticks_per_update: config.max_ticks_per_frame.max(1),
+			accumulator: Duration::ZERO,
+			ticks: 0,
+		}
+	}
+
+	pub fn tick_length(&self) -> Duration {
+		self.tick_length
+	}
+
+	/// Total number of ticks run so far.
+	pub fn ticks(&self) -> u64 {
+		self.ticks
+	}
+
+	/// Adds `elapsed` real time and runs every tick that is now due, returning how far between
+	/// the last tick and the next one the current time is, for interpolating drawn state.
+	///
+	/// When more than the configured maximum of ticks is due the excess time is dropped, so a
+	/// slow simulation runs behind real time instead of spending ever longer catching up.
+	pub fn update(&mut self, elapsed: Duration, simulation: &mut impl Simulation) -> f32 {
+		self.accumulator += elapsed;
+
+		let mut ticks_run = 0;
+		while self.accumulator >= self.tick_length {
+			if ticks_run == self.max_ticks_per_update {
+				log::warn!("simulation is running behind, skipping {:?}", self.accumulator);
+				self.accumulator = Duration::ZERO;
+				break;
+			}
+
+			simulation.tick(self.tick_length);
+			self.accumulator -= self.tick_length;
+			self.ticks += 1;
+			ticks_run += 1;
+		}
+
+		self.alpha()
+	}
+
+	/// Fraction of a tick accumulated but not yet simulated, in `[0, 1)`.
+	pub fn alpha(&self) -> f32 {
+		self.accumulator.as_secs_f32() / self.tick_length.as_secs_f32()
+	}
+}
+
+/// Runs `ticks` ticks back to back, as fast as possible.
+pub fn run_ticks(simulation: &mut impl Simulation, config: &SimulationConfig, ticks: u64) {
+	let tick_length = Duration::from_secs(1) / config.ticks_per_second;
+	for _ in 0..ticks {
+		simulation.tick(tick_length);
+	}
+}
+
+/// Runs the simulation in real time without a window, until `keep_running` returns false.
+/// `keep_running` is called between updates and may inspect or change the simulation.
+pub fn run_headless<S: Simulation>(
+	simulation: &mut S,
+	config: &SimulationConfig,
+	mut keep_running: impl FnMut(&mut S, &FixedTimestep) -> bool,
+) {
+	let mut timestep = FixedTimestep::new(config);
+	let mut last_time = Instant::now();
+
+	while keep_running(simulation, &timestep) {
+		let now = Instant::now();
+		timestep.update(now - last_time, simulation);
+		last_time = now;
+
+		let until_next_tick = timestep.tick_length().mul_f32(1.0 - timestep.alpha());
+		thread::sleep(until_next_tick);
+	}
+}
@@ -0,0 +1,167 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::building::{Building, BuildingCatalog, ConstructionSite};
+use crate::colonist;
+use crate::components::{Position, Renderable, Velocity};
+use crate::content::Content;
+use crate::conveyor::{self, Belt, BeltNetwork, Inserter};
+use crate::crafting::{self, CraftingStatus, Crafter};
+use crate::economy::{Ledger, Producer, Stockpile};
+use crate::ecs::World;
+use crate::logistics;
+use crate::pathfinding::{DEFAULT_NODE_BUDGET, Pathfinder};
+use crate::renderer::{Renderer, Sprite};
+
+/// Remembers where every entity was before this tick's systems move it. Runs first.
+pub fn store_previous_positions(world: &mut World, _dt: Duration) {
+	for (_, position) in world.query_mut::<Position>() {
+		position.previous = position.current;
+	}
+}
+
+/// Moves entities by their velocity.
+pub fn movement(world: &mut World, dt: Duration) {
+	let seconds = dt.as_secs_f32();
+	world.query2_mut::<Position, Velocity>(|_, position, velocity| {
+		position.current[0] += velocity.0[0] * seconds;
+		position.current[1] += velocity.0[1] * seconds;
+	});
+}
+
+/// Layer buildings are drawn on, below entities on the default layer.
+pub const BUILDING_LAYER: i32 = -1;
+
+/// Works through waiting path requests, within the per tick budget.
+pub fn pathfinding(world: &mut World, _dt: Duration) {
+	if let Some(pathfinder) = world.resource_mut::<Pathfinder>() {
+		pathfinder.run(DEFAULT_NODE_BUDGET);
+	}
+}
+
+/// Runs every [`Producer`] against the [`Stockpile`] on its entity, in entity order so the
+/// outcome only depends on the world, and updates the [`Ledger`].
+pub fn economy(world: &mut World, dt: Duration) {
+	let Some(mut ledger) = world.remove_resource::<Ledger>() else {
+		return;
+	};
+	world.query2_mut::<Producer, Stockpile>(|_, producer, stockpile| producer.tick(stockpile, &mut ledger, dt));
+	ledger.end_tick(dt, world.query::<Stockpile>().map(|(_, stockpile)| stockpile));
+	world.insert_resource(ledger);
+}
+
+/// Runs every [`Crafter`] on the recipes of the loaded [`Content`], powered by the
+/// [`Stockpile`] on its entity if it has one, then moves outputs along production chains.
+pub fn crafting(world: &mut World, dt: Duration) {
+	let Some(content) = world.resource::<Arc<Content>>().cloned() else {
+		return;
+	};
+	let Some(mut ledger) = world.remove_resource::<Ledger>() else {
+		return;
+	};
+	let mut tick = |crafter: &mut Crafter, power: Option<&mut Stockpile>| match content.recipe(crafter.recipe) {
+		Some(recipe) => crafter.tick(recipe, power, &mut ledger, dt),
+		None => crafter.status = CraftingStatus::Idle,
+	};
+	world.query2_mut::<Crafter, Stockpile>(|_, crafter, stockpile| tick(crafter, Some(stockpile)));
+	let unpowered = world.query::<Crafter>()
+		.map(|(entity, _)| entity)
+		.filter(|&entity| !world.has::<Stockpile>(entity))
+		.collect::<Vec<_>>();
+	for entity in unpowered {
+		tick(world.get_mut::<Crafter>(entity).expect("queried above"), None);
+	}
+	world.insert_resource(ledger);
+	crafting::deliver_outputs(world, &content);
+}
+
+/// Moves stacks along conveyor belts and swings inserters between belts and buildings.
+pub fn conveyors(world: &mut World, dt: Duration) {
+	conveyor::tick(world, dt);
+}
+
+/// Posts haul jobs bringing crafters and other demands what they need.
+pub fn logistics(world: &mut World, _dt: Duration) {
+	logistics::plan(world);
+}
+
+/// Updates colonists' needs, hands waiting jobs to idle colonists and moves every colonist on
+/// through its job.
+pub fn colonists(world: &mut World, dt: Duration) {
+	colonist::update_needs(world, dt);
+	colonist::assign_jobs(world);
+	colonist::work(world, dt);
+}
+
+/// Colours of straight belts, splitters and mergers until there is belt art.
+const BELT_COLORS: [[f32; 4]; 3] = [[0.35, 0.35, 0.38, 1.0], [0.45, 0.35, 0.25, 1.0], [0.25, 0.35, 0.45, 1.0]];
+const INSERTER_COLOR: [f32; 4] = [0.85, 0.7, 0.2, 1.0];
+const STACK_COLOR: [f32; 4] = [0.9, 0.9, 0.85, 1.0];
+
+/// Queues a block of colour for every belt and inserter, and a dot for every stack on a belt.
+pub fn extract_conveyors(world: &World, renderer: &mut Renderer) {
+	let center = |[x, y]: [i32; 2]| [x as f32 + 0.5, y as f32 + 0.5];
+	for (_, belt) in world.query::<Belt>() {
+		renderer.draw_sprite(Sprite {
+			rotation: belt.direction.radians(),
+			tint: BELT_COLORS[belt.kind as usize],
+			layer: BUILDING_LAYER,
+			..Sprite::new(renderer.white_texture(), center(belt.tile), [0.8, 1.0])
+		});
+	}
+	for (_, inserter) in world.query::<Inserter>() {
+		renderer.draw_sprite(Sprite {
+			rotation: inserter.direction.radians(),
+			tint: INSERTER_COLOR,
+			layer: BUILDING_LAYER,
+			..Sprite::new(renderer.white_texture(), center(inserter.tile), [0.3, 0.8])
+		});
+	}
+	let Some(network) = world.resource::<BeltNetwork>() else {
+		return;
+	};
+	for (position, _) in network.stack_positions(world) {
+		renderer.draw_sprite(Sprite {
+			tint: STACK_COLOR,
+			..Sprite::new(renderer.white_texture(), position, [0.2, 0.2])
+		});
+	}
+}
+
+/// Queues a sprite for every entity with a [`Position`] and a [`Renderable`], `alpha` of the way
+/// from the last tick towards the next.
+pub fn extract_sprites(world: &World, renderer: &mut Renderer, alpha: f32) {
+	for (_, position, renderable) in world.query2::<Position, Renderable>() {
+		renderer.draw_sprite(Sprite {
+			rotation: renderable.rotation,
+			tint: renderable.tint,
+			uv_rect: renderable.uv_rect,
+			layer: renderable.layer,
+			..Sprite::new(renderable.texture, position.interpolated(alpha), renderable.size)
+		});
+	}
+}
+
+/// Queues a block of colour over every tile of every building, faded out while it is still a
+/// construction site.
+pub fn extract_buildings(world: &World, renderer: &mut Renderer) {
+	let Some(catalog) = world.resource::<BuildingCatalog>() else {
+		return;
+	};
+	for (entity, building) in world.query::<Building>() {
+		let Some(def) = catalog.get(building.kind) else {
+			continue;
+		};
+		let mut tint = def.color.map(|channel| channel as f32 / 255.0);
+		if world.has::<ConstructionSite>(entity) {
+			tint[3] *= 0.5;
+		}
+		for &[x, y] in &building.tiles {
+			renderer.draw_sprite(Sprite {
+				tint,
+				layer: BUILDING_LAYER,
+				..Sprite::new(renderer.white_texture(), [x as f32 + 0.5, y as f32 + 0.5], [1.0, 1.0])
+			});
+		}
+	}
+}
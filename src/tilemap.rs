@@ -0,0 +1,113 @@
+/// Width and height of a chunk in tiles.
+pub const CHUNK_SIZE: u32 = 32;
+
+/// Index of a tile type. What each index means is up to the content definitions, only
+/// [`Tile::EMPTY`] is special.
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Tile(pub u16);
+
+impl Tile {
+	pub const EMPTY: Tile = Tile(0);
+}
+
+struct Chunk {
+	tiles: Box<[Tile]>,
+	/// Incremented on every change, so each consumer can tell whether its derived data is stale.
+	revision: u64,
+}
+
+/// Grid of tiles covering the world, stored in square chunks of [`CHUNK_SIZE`] tiles.
+///
+/// Tile `(x, y)` covers the world rectangle from `(x, y)` to `(x + 1, y + 1)`.
+pub struct Tilemap {
+	width: u32,
+	height: u32,
+	chunks_x: u32,
+	chunks_y: u32,
+	chunks: Vec<Chunk>,
+}
+
+impl Tilemap {
+	pub fn new(width: u32, height: u32) -> Self {
+		let chunks_x = width.div_ceil(CHUNK_SIZE);
+		let chunks_y = height.div_ceil(CHUNK_SIZE);
+		let chunks = (0..chunks_x * chunks_y)
+			.map(|_| Chunk {
+				tiles: vec![Tile::EMPTY; (CHUNK_SIZE * CHUNK_SIZE) as usize].into_boxed_slice(),
+				revision: 0,
+			})
+			.collect();
+
+		Self {
+			width,
+			height,
+			chunks_x,
+			chunks_y,
+			chunks,
+		}
+	}
+
+	pub fn width(&self) -> u32 {
+		self.width
+	}
+
+	pub fn height(&self) -> u32 {
+		self.height
+	}
+
+	/// Number of chunks across and down.
+	pub fn chunk_counts(&self) -> (u32, u32) {
+		(self.chunks_x, self.chunks_y)
+	}
+
+	pub fn contains(&self, x: i32, y: i32) -> bool {
+		x >= 0 && y >= 0 && (x as u32) < self.width && (y as u32) < self.height
+	}
+
+	fn locate(&self, x: u32, y: u32) -> (usize, usize) {
+		let chunk = (y / CHUNK_SIZE * self.chunks_x + x / CHUNK_SIZE) as usize;
+		let tile = (y % CHUNK_SIZE * CHUNK_SIZE + x % CHUNK_SIZE) as usize;
+		(chunk, tile)
+	}
+
+	/// The tile at `(x, y)`, or `None` outside the map.
+	pub fn get(&self, x: i32, y: i32) -> Option<Tile> {
+		if !self.contains(x, y) {
+			return None;
+		}
+		let (chunk, tile) = self.locate(x as u32, y as u32);
+		Some(self.chunks[chunk].tiles[tile])
+	}
+
+	/// Changes the tile at `(x, y)`, returning the previous one. Does nothing outside the map.
+	pub fn set(&mut self, x: i32, y: i32, tile: Tile) -> Option<Tile> {
+		if !self.contains(x, y) {
+			return None;
+		}
+		let (chunk, index) = self.locate(x as u32, y as u32);
+		let chunk = &mut self.chunks[chunk];
+		let previous = std::mem::replace(&mut chunk.tiles[index], tile);
+		if previous != tile {
+			chunk.revision += 1;
+		}
+		Some(previous)
+	}
+
+	/// How many times the chunk at chunk coordinates `(chunk_x, chunk_y)` has changed.
+	pub fn chunk_revision(&self, chunk_x: u32, chunk_y: u32) -> u64 {
+		self.chunks[(chunk_y * self.chunks_x + chunk_x) as usize].revision
+	}
+
+	/// Calls `f` with the map position and tile of every tile in a chunk that lies inside the map.
+	pub fn for_each_in_chunk(&self, chunk_x: u32, chunk_y: u32, mut f: impl FnMut(u32, u32, Tile)) {
+		let chunk = &self.chunks[(chunk_y * self.chunks_x + chunk_x) as usize];
+		let (origin_x, origin_y) = (chunk_x * CHUNK_SIZE, chunk_y * CHUNK_SIZE);
+
+		for local_y in 0..CHUNK_SIZE.min(self.height - origin_y) {
+			for local_x in 0..CHUNK_SIZE.min(self.width - origin_x) {
+				let tile = chunk.tiles[(local_y * CHUNK_SIZE + local_x) as usize];
+				f(origin_x + local_x, origin_y + local_y, tile);
+			}
+		}
+	}
+}
@@ -0,0 +1,311 @@
+use crate::rng::{hash2, split_mix};
+use crate::tilemap::{Tile, Tilemap};
+
+/// Tiles placed by the generator.
+pub mod tiles {
+	use crate::tilemap::Tile;
+
+	pub const WATER: Tile = Tile(1);
+	pub const SAND: Tile = Tile(2);
+	pub const GRASS: Tile = Tile(3);
+	pub const FOREST_FLOOR: Tile = Tile(4);
+	pub const DESERT: Tile = Tile(5);
+	pub const ROCK: Tile = Tile(6);
+	pub const IRON_ORE: Tile = Tile(7);
+	pub const COPPER_ORE: Tile = Tile(8);
+	pub const COAL: Tile = Tile(9);
+	pub const TREE: Tile = Tile(10);
+	pub const BUSH: Tile = Tile(11);
+
+	/// Names of the tiles above, in index order starting at [`WATER`].
+	pub const NAMES: [&str; 11] = [
+		"water", "sand", "grass", "forest_floor", "desert", "rock",
+		"iron_ore", "copper_ore", "coal", "tree", "bush",
+	];
+
+	/// Colours the tiles above stand for, in the same order as [`NAMES`], for placeholder art and
+	/// map thumbnails.
+	pub const COLORS: [[u8; 4]; NAMES.len()] = [
+		[40, 90, 170, 255],
+		[220, 200, 140, 255],
+		[90, 160, 70, 255],
+		[50, 110, 50, 255],
+		[200, 170, 100, 255],
+		[120, 115, 110, 255],
+		[150, 95, 80, 255],
+		[190, 110, 50, 255],
+		[40, 40, 40, 255],
+		[20, 70, 30, 255],
+		[70, 130, 60, 255],
+	];
+
+	/// How slow `tile` is to walk through, `None` for tiles that cannot be walked on. Empty and
+	/// unknown tiles are open ground.
+	pub fn movement_cost(tile: Tile) -> Option<u8> {
+		match tile {
+			WATER => None,
+			TREE => Some(4),
+			ROCK => Some(3),
+			SAND | DESERT | BUSH | IRON_ORE | COPPER_ORE | COAL => Some(2),
+			_ => Some(1),
+		}
+	}
+
+	/// Colour of `tile`, transparent for empty and unknown tiles.
+	pub fn color(tile: Tile) -> [u8; 4] {
+		match tile.0 {
+			0 => [0; 4],
+			index => COLORS.get(index as usize - 1).copied().unwrap_or([0; 4]),
+		}
+	}
+}
+
+/// Height below which tiles are under water.
+const SEA_LEVEL: u8 = 96;
+/// Height below which dry land is beach.
+const BEACH_LEVEL: u8 = 106;
+/// Height above which land is bare mountain rock.
+const MOUNTAIN_LEVEL: u8 = 190;
+
+/// Noise values above this become ore deposits.
+const ORE_THRESHOLD: f32 = 0.77;
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+#[repr(u8)]
+pub enum Biome {
+	Water,
+	Beach,
+	Desert,
+	Grassland,
+	Forest,
+	Mountain,
+}
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+#[repr(u8)]
+pub enum Ore {
+	Iron = 1,
+	Copper,
+	Coal,
+}
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+#[repr(u8)]
+pub enum Vegetation {
+	None,
+	Bush,
+	Tree,
+}
+
+/// Every layer of a generated map, one value per tile in rows from `y = 0`.
+pub struct WorldMap {
+	pub seed: u64,
+	pub width: u32,
+	pub height: u32,
+	/// Terrain height from 0 to 255.
+	pub heights: Vec<u8>,
+	pub biomes: Vec<Biome>,
+	pub ores: Vec<Option<Ore>>,
+	pub vegetation: Vec<Vegetation>,
+}
+
+impl WorldMap {
+	fn index(&self, x: u32, y: u32) -> usize {
+		(y * self.width + x) as usize
+	}
+
+	pub fn is_water(&self, x: u32, y: u32) -> bool {
+		self.biomes[self.index(x, y)] == Biome::Water
+	}
+
+	/// The tile shown at `(x, y)`: vegetation over ore over the biome's ground.
+	pub fn tile(&self, x: u32, y: u32) -> Tile {
+		let index = self.index(x, y);
+		match (self.vegetation[index], self.ores[index]) {
+			(Vegetation::Tree, _) => tiles::TREE,
+			(Vegetation::Bush, _) => tiles::BUSH,
+			(Vegetation::None, Some(Ore::Iron)) => tiles::IRON_ORE,
+			(Vegetation::None, Some(Ore::Copper)) => tiles::COPPER_ORE,
+			(Vegetation::None, Some(Ore::Coal)) => tiles::COAL,
+			(Vegetation::None, None) => match self.biomes[index] {
+				Biome::Water => tiles::WATER,
+				Biome::Beach => tiles::SAND,
+				Biome::Desert => tiles::DESERT,
+				Biome::Grassland => tiles::GRASS,
+				Biome::Forest => tiles::FOREST_FLOOR,
+				Biome::Mountain => tiles::ROCK,
+			},
+		}
+	}
+
+	pub fn to_tilemap(&self) -> Tilemap {
+		let mut tilemap = Tilemap::new(self.width, self.height);
+		for y in 0..self.height {
+			for x in 0..self.width {
+				tilemap.set(x as i32, y as i32, self.tile(x, y));
+			}
+		}
+		tilemap
+	}
+
+	/// All layers packed into bytes, for comparing maps exactly.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut bytes = Vec::with_capacity(self.heights.len() * 4 + 16);
+		bytes.extend_from_slice(&self.seed.to_le_bytes());
+		bytes.extend_from_slice(&self.width.to_le_bytes());
+		bytes.extend_from_slice(&self.height.to_le_bytes());
+		bytes.extend_from_slice(&self.heights);
+		bytes.extend(self.biomes.iter().map(|&biome| biome as u8));
+		bytes.extend(self.ores.iter().map(|ore| ore.map_or(0, |ore| ore as u8)));
+		bytes.extend(self.vegetation.iter().map(|&vegetation| vegetation as u8));
+		bytes
+	}
+}
+
+/// Noise settings for one layer.
+#[derive(Copy, Clone, Debug)]
+struct Layer {
+	/// Width of the largest features in tiles.
+	scale: f32,
+	octaves: u32,
+	/// How much each octave contributes compared to the one before.
+	persistence: f32,
+}
+
+const HEIGHT: Layer = Layer { scale: 64.0, octaves: 5, persistence: 0.5 };
+const MOISTURE: Layer = Layer { scale: 96.0, octaves: 4, persistence: 0.5 };
+const ORE: Layer = Layer { scale: 12.0, octaves: 3, persistence: 0.45 };
+
+/// Builds terrain from a seed.
+///
+/// Only addition, subtraction, multiplication and division are used on floats, which IEEE 754
+/// defines exactly, so a seed gives the same map on every platform.
+pub struct WorldGenerator {
+	seed: u64,
+}
+
+impl WorldGenerator {
+	pub fn new(seed: u64) -> Self {
+		Self { seed }
+	}
+
+	/// Seed for one noise layer, so layers are unrelated to each other.
+	fn layer_seed(&self, layer: u64) -> u64 {
+		let mut state = self.seed ^ layer.wrapping_mul(0xa076_1d64_78bd_642f);
+		split_mix(&mut state)
+	}
+
+	pub fn generate(&self, width: u32, height: u32) -> WorldMap {
+		let height_seed = self.layer_seed(1);
+		let moisture_seed = self.layer_seed(2);
+		let ore_seeds = [self.layer_seed(3), self.layer_seed(4), self.layer_seed(5)];
+		let vegetation_seed = self.layer_seed(6);
+
+		let count = (width * height) as usize;
+		let mut map = WorldMap {
+			seed: self.seed,
+			width,
+			height,
+			heights: Vec::with_capacity(count),
+			biomes: Vec::with_capacity(count),
+			ores: Vec::with_capacity(count),
+			vegetation: Vec::with_capacity(count),
+		};
+
+		for y in 0..height as i32 {
+			for x in 0..width as i32 {
+				let elevation = (fractal_noise(height_seed, HEIGHT, x, y) * 255.0) as u8;
+				let moisture = fractal_noise(moisture_seed, MOISTURE, x, y);
+				let biome = biome(elevation, moisture);
+
+				let ore = match biome {
+					Biome::Water | Biome::Beach => None,
+					_ => [Ore::Iron, Ore::Copper, Ore::Coal]
+						.into_iter()
+						.zip(ore_seeds)
+						.map(|(ore, seed)| (ore, fractal_noise(seed, ORE, x, y)))
+						.filter(|&(_, value)| value > ORE_THRESHOLD)
+						.max_by(|a, b| a.1.total_cmp(&b.1))
+						.map(|(ore, _)| ore),
+				};
+
+				let roll = unit(hash2(vegetation_seed, x, y));
+				let vegetation = match (biome, ore) {
+					(_, Some(_)) => Vegetation::None,
+					(Biome::Forest, _) if roll < 0.45 => Vegetation::Tree,
+					(Biome::Forest, _) if roll < 0.55 => Vegetation::Bush,
+					(Biome::Grassland, _) if roll < 0.04 => Vegetation::Tree,
+					(Biome::Grassland, _) if roll < 0.12 => Vegetation::Bush,
+					(Biome::Desert, _) if roll < 0.02 => Vegetation::Bush,
+					_ => Vegetation::None,
+				};
+
+				map.heights.push(elevation);
+				map.biomes.push(biome);
+				map.ores.push(ore);
+				map.vegetation.push(vegetation);
+			}
+		}
+
+		map
+	}
+}
+
+fn biome(elevation: u8, moisture: f32) -> Biome {
+	if elevation < SEA_LEVEL {
+		Biome::Water
+	} else if elevation < BEACH_LEVEL {
+		Biome::Beach
+	} else if elevation > MOUNTAIN_LEVEL {
+		Biome::Mountain
+	} else if moisture < 0.35 {
+		Biome::Desert
+	} else if moisture < 0.58 {
+		Biome::Grassland
+	} else {
+		Biome::Forest
+	}
+}
+
+/// Maps hash bits to `[0, 1)`.
+fn unit(hash: u64) -> f32 {
+	(hash >> 40) as f32 / (1u32 << 24) as f32
+}
+
+/// Octaves of value noise summed and normalized to `[0, 1]`.
+fn fractal_noise(seed: u64, layer: Layer, x: i32, y: i32) -> f32 {
+	let mut total = 0.0;
+	let mut amplitude = 1.0;
+	let mut amplitude_sum = 0.0;
+	let mut frequency = 1.0 / layer.scale;
+
+	for octave in 0..layer.octaves {
+		let octave_seed = seed.wrapping_add(octave as u64);
+		total += value_noise(octave_seed, x as f32 * frequency, y as f32 * frequency) * amplitude;
+		amplitude_sum += amplitude;
+		amplitude *= layer.persistence;
+		frequency *= 2.0;
+	}
+
+	total / amplitude_sum
+}
+
+/// Random values on the integer lattice, smoothly interpolated in between.
+fn value_noise(seed: u64, x: f32, y: f32) -> f32 {
+	// Coordinates are never negative, so truncating is flooring.
+	let (cell_x, cell_y) = (x as i32, y as i32);
+	let (fraction_x, fraction_y) = (smooth(x - cell_x as f32), smooth(y - cell_y as f32));
+
+	let corner = |dx, dy| unit(hash2(seed, cell_x + dx, cell_y + dy));
+	let top = lerp(corner(0, 0), corner(1, 0), fraction_x);
+	let bottom = lerp(corner(0, 1), corner(1, 1), fraction_x);
+	lerp(top, bottom, fraction_y)
+}
+
+fn smooth(t: f32) -> f32 {
+	t * t * (3.0 - 2.0 * t)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+	a + (b - a) * t
+}
@@ -0,0 +1,168 @@
+use frontier_outpost::building::{
+	AdjacencyRule, Building, BuildingCatalog, BuildingDef, BuildingId, ConstructionSite, Occupancy, PlacementError,
+	Rotation,
+};
+use frontier_outpost::game::Game;
+use frontier_outpost::pathfinding::{PathResult, Pathfinder};
+use frontier_outpost::save;
+use frontier_outpost::worldgen::tiles;
+
+/// A game with grass in the bottom left 16 by 16 tiles, crossed by a water column at `x = 10`,
+/// and the catalog used below.
+fn sample_game() -> (Game, [BuildingId; 3]) {
+	let mut game = Game::new();
+	for y in 0..16 {
+		for x in 0..16 {
+			game.set_tile(x, y, if x == 10 { tiles::WATER } else { tiles::GRASS });
+		}
+	}
+
+	let mut catalog = BuildingCatalog::new();
+	let house = catalog.add(BuildingDef {
+		terrain: vec![tiles::GRASS],
+		..BuildingDef::new("house", [3, 2])
+	});
+	let pump = catalog.add(BuildingDef {
+		adjacency: vec![AdjacencyRule::NextToTerrain(vec![tiles::WATER])],
+		..BuildingDef::new("pump", [1, 1])
+	});
+	let shed = catalog.add(BuildingDef {
+		adjacency: vec![AdjacencyRule::NextToBuilding(house), AdjacencyRule::AwayFromBuilding(pump)],
+		..BuildingDef::new("shed", [1, 1])
+	});
+	game.world.insert_resource(catalog);
+	(game, [house, pump, shed])
+}
+
+#[test]
+fn footprints_turn_with_the_building() {
+	let house = BuildingDef::new("house", [3, 2]);
+	assert_eq!(house.size(), [3, 2]);
+	let mut north = house.tiles([5, 5], Rotation::North);
+	north.sort();
+	assert_eq!(north, [[5, 5], [5, 6], [6, 5], [6, 6], [7, 5], [7, 6]]);
+	let mut east = house.tiles([5, 5], Rotation::East);
+	east.sort();
+	assert_eq!(east, [[5, 5], [5, 6], [5, 7], [6, 5], [6, 6], [6, 7]]);
+
+	// The long arm of an L points up, then right, then down, then left.
+	let corner = BuildingDef {
+		footprint: vec![[0, 0], [1, 0], [0, 1], [0, 2]],
+		..BuildingDef::new("corner", [1, 1])
+	};
+	let arm_tip = |rotation| corner.tiles([0, 0], rotation)[3];
+	assert_eq!(arm_tip(Rotation::North), [0, 2]);
+	assert_eq!(arm_tip(Rotation::East), [2, 1]);
+	assert_eq!(arm_tip(Rotation::South), [1, 0]);
+	assert_eq!(arm_tip(Rotation::West), [0, 0]);
+	for rotation in Rotation::ALL {
+		assert!(corner.tiles([0, 0], rotation).iter().all(|&[x, y]| (0..3).contains(&x) && (0..3).contains(&y)));
+	}
+	assert_eq!(Rotation::West.clockwise(), Rotation::North);
+}
+
+#[test]
+fn placement_checks_every_tile() {
+	let (game, [house, ..]) = sample_game();
+	assert!(game.check_placement(house, [2, 2], Rotation::North).is_valid());
+
+	// Hanging over the water and off the edge of the map.
+	let placement = game.check_placement(house, [9, -1], Rotation::East);
+	let invalid = placement.tiles.iter().filter(|(_, error)| error.is_some()).count();
+	assert_eq!(invalid, 4);
+	assert!(placement.tiles.contains(&([9, 1], None)));
+	assert!(placement.tiles.contains(&([10, 1], Some(PlacementError::WrongTerrain([10, 1], tiles::WATER)))));
+	assert!(placement.tiles.contains(&([9, -1], Some(PlacementError::OutsideMap([9, -1])))));
+	assert_eq!(placement.error(), Some(&PlacementError::OutsideMap([9, -1])));
+
+	let unknown = game.check_placement(BuildingId(99), [2, 2], Rotation::North);
+	assert_eq!(unknown.error(), Some(&PlacementError::UnknownBuilding(BuildingId(99))));
+}
+
+#[test]
+fn adjacency_rules_look_at_neighbouring_tiles() {
+	let (mut game, [house, pump, shed]) = sample_game();
+	assert!(game.check_placement(pump, [9, 3], Rotation::North).is_valid());
+	// Diagonal neighbours do not count.
+	assert_eq!(
+		game.check_placement(pump, [8, 3], Rotation::North).errors,
+		[PlacementError::Adjacency(AdjacencyRule::NextToTerrain(vec![tiles::WATER]))],
+	);
+
+	assert!(!game.check_placement(shed, [8, 4], Rotation::North).is_valid());
+	game.place_building(house, [7, 2], Rotation::North).unwrap();
+	assert!(game.check_placement(shed, [8, 4], Rotation::North).is_valid());
+	assert!(!game.check_placement(shed, [6, 4], Rotation::North).is_valid());
+
+	game.place_building(pump, [9, 4], Rotation::North).unwrap();
+	assert_eq!(
+		game.place_building(shed, [8, 4], Rotation::North),
+		Err(PlacementError::Adjacency(AdjacencyRule::AwayFromBuilding(pump))),
+	);
+}
+
+#[test]
+fn placing_reserves_tiles_for_a_construction_site() {
+	let (mut game, [house, pump, _]) = sample_game();
+	let site = game.place_building(house, [2, 2], Rotation::East).unwrap();
+	assert_eq!(game.world.get::<ConstructionSite>(site), Some(&ConstructionSite { progress: 0.0 }));
+	let building = game.world.get::<Building>(site).unwrap();
+	assert_eq!((building.kind, building.origin, building.rotation), (house, [2, 2], Rotation::East));
+	assert_eq!(building.tiles.len(), 6);
+
+	let occupancy = game.world.resource::<Occupancy>().unwrap();
+	assert_eq!(occupancy.get([3, 4]), Some(site));
+	assert_eq!(occupancy.get([4, 2]), None);
+
+	assert_eq!(
+		game.place_building(pump, [3, 4], Rotation::North),
+		Err(PlacementError::Occupied([3, 4], site)),
+	);
+	assert!(game.demolish(site));
+	assert!(!game.world.is_alive(site));
+	assert_eq!(game.world.resource::<Occupancy>().unwrap().get([3, 4]), None);
+	assert!(game.place_building(house, [2, 2], Rotation::North).is_ok());
+}
+
+#[test]
+fn reservations_survive_saving() {
+	let (mut game, [house, pump, _]) = sample_game();
+	let site = game.place_building(house, [2, 2], Rotation::South).unwrap();
+	let thumbnail = save::thumbnail(&game.tilemap, tiles::color);
+
+	let mut loaded = save::decode(&save::encode(&game, &thumbnail)).unwrap();
+	assert_eq!(loaded.world.get::<Building>(site), game.world.get::<Building>(site));
+	assert!(loaded.world.has::<ConstructionSite>(site));
+	assert_eq!(loaded.world.resource::<Occupancy>().unwrap().get([4, 3]), Some(site));
+
+	loaded.world.insert_resource(game.world.remove_resource::<BuildingCatalog>().unwrap());
+	assert_eq!(
+		loaded.place_building(pump, [4, 3], Rotation::North),
+		Err(PlacementError::Occupied([4, 3], site)),
+	);
+}
+
+#[test]
+fn paths_go_around_buildings() {
+	let (mut game, [house, ..]) = sample_game();
+	let site = game.place_building(house, [4, 4], Rotation::North).unwrap();
+	let footprint = game.world.get::<Building>(site).unwrap().tiles.clone();
+	let path = |game: &mut Game| match game.world.resource_mut::<Pathfinder>().unwrap().find_path([2, 5], [8, 5]) {
+		PathResult::Found(path) => (path.tiles.clone(), path.cost),
+		PathResult::Unreachable => panic!("no way past the house"),
+	};
+
+	let (around, detour) = path(&mut game);
+	assert!(around.iter().all(|tile| !footprint.contains(tile)), "walked through the house: {around:?}");
+	let mut loaded = save::decode(&save::encode(&game, &save::thumbnail(&game.tilemap, tiles::color))).unwrap();
+	assert!(!loaded.world.resource::<Pathfinder>().unwrap().grid().is_passable([5, 5]));
+	assert!(path(&mut loaded).0.iter().all(|tile| !footprint.contains(tile)));
+
+	// Demolishing gives the ground back.
+	let grass = game.world.resource::<Pathfinder>().unwrap().grid().cost([3, 5]);
+	assert!(game.demolish(site));
+	assert!(footprint.iter().all(|&tile| game.world.resource::<Pathfinder>().unwrap().grid().cost(tile) == grass));
+	let (straight, cost) = path(&mut game);
+	assert!(straight.iter().any(|tile| footprint.contains(tile)));
+	assert!(cost < detour);
+}
@@ -0,0 +1,349 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use frontier_outpost::building::{BuildingCatalog, BuildingDef, ConstructionSite, Rotation};
+use frontier_outpost::colonist::{self, Activity, Colonist, Job, JobBoard, JobId, JobKind, Skill, TIRED};
+use frontier_outpost::components::Position;
+use frontier_outpost::config::SimulationConfig;
+use frontier_outpost::content::{Content, DEFAULT_CONTENT_DIR};
+use frontier_outpost::economy::{Resource, Stockpile};
+use frontier_outpost::ecs::Entity;
+use frontier_outpost::game::Game;
+use frontier_outpost::logistics::Pickup;
+use frontier_outpost::pathfinding::Pathfinder;
+use frontier_outpost::save::{self, Reader, Writer};
+use frontier_outpost::simulation;
+use frontier_outpost::worldgen::tiles;
+
+fn content() -> Arc<Content> {
+	Arc::new(Content::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join(DEFAULT_CONTENT_DIR)).unwrap())
+}
+
+fn spawn_colonist(game: &mut Game, name: &str, [x, y]: [i32; 2], skill: Skill, level: u8) -> Entity {
+	let entity = game.world.spawn();
+	game.world.insert(entity, Position::new([x as f32 + 0.5, y as f32 + 0.5]));
+	game.world.insert(entity, Colonist::new(name).with_skill(skill, level));
+	entity
+}
+
+fn spawn_stockpile(game: &mut Game, [x, y]: [i32; 2], contents: &[(Resource, u64)]) -> Entity {
+	let entity = game.world.spawn();
+	game.world.insert(entity, Position::new([x as f32 + 0.5, y as f32 + 0.5]));
+	let mut stockpile = Stockpile::new(100);
+	for &(resource, amount) in contents {
+		stockpile.add(resource, amount);
+	}
+	game.world.insert(entity, stockpile);
+	entity
+}
+
+fn post(game: &mut Game, kind: JobKind, priority: u8, work: u32) -> JobId {
+	game.world.resource_mut::<JobBoard>().unwrap().post(Job::new(kind, priority, work))
+}
+
+fn job(game: &Game, id: JobId) -> Option<&Job> {
+	game.world.resource::<JobBoard>().unwrap().get(id)
+}
+
+fn colonist(game: &Game, entity: Entity) -> &Colonist {
+	game.world.get::<Colonist>(entity).unwrap()
+}
+
+fn run(game: &mut Game, ticks: u64) {
+	simulation::run_ticks(game, &SimulationConfig::default(), ticks);
+}
+
+/// Runs ticks until `done`, failing after `limit`. Returns the ticks taken.
+fn run_until(game: &mut Game, limit: u64, done: impl Fn(&Game) -> bool) -> u64 {
+	for tick in 1..=limit {
+		run(game, 1);
+		if done(game) {
+			return tick;
+		}
+	}
+	panic!("still waiting after {limit} ticks");
+}
+
+#[test]
+fn jobs_go_to_the_best_suited_colonist_by_priority() {
+	let mut game = Game::new();
+	let near = spawn_colonist(&mut game, "Near", [0, 0], Skill::Mining, 0);
+	let skilled = spawn_colonist(&mut game, "Skilled", [8, 0], Skill::Mining, 2);
+	// Five tiles of walking are outweighed by two skill levels.
+	let dig = post(&mut game, JobKind::Mine([3, 0], tiles::SAND), 1, 100);
+	run(&mut game, 1);
+	assert_eq!(job(&game, dig).unwrap().claimed_by(), Some(skilled));
+	assert_eq!(colonist(&game, near).activity(), &Activity::Idle);
+
+	// The most pressing job is handed out first, however far away.
+	let close = post(&mut game, JobKind::Mine([0, 1], tiles::SAND), 1, 100);
+	let urgent = post(&mut game, JobKind::Mine([40, 40], tiles::SAND), 5, 100);
+	run(&mut game, 1);
+	assert_eq!(job(&game, urgent).unwrap().claimed_by(), Some(near));
+	assert_eq!(job(&game, close).unwrap().claimed_by(), None);
+}
+
+#[test]
+fn colonists_only_take_jobs_they_can_reach() {
+	let mut game = Game::new();
+	for x in 0..6 {
+		game.set_tile(x, 5, tiles::WATER);
+	}
+	for y in 0..5 {
+		game.set_tile(5, y, tiles::WATER);
+	}
+	let stranded = spawn_colonist(&mut game, "Stranded", [1, 1], Skill::Mining, 10);
+	let outside = spawn_colonist(&mut game, "Outside", [30, 30], Skill::Mining, 0);
+	let dig = post(&mut game, JobKind::Mine([8, 8], tiles::SAND), 1, 100);
+	run(&mut game, 1);
+	assert_eq!(job(&game, dig).unwrap().claimed_by(), Some(outside));
+	assert_eq!(colonist(&game, stranded).activity(), &Activity::Idle);
+}
+
+#[test]
+fn colonists_walk_to_sites_and_build_over_ticks() {
+	let mut game = Game::new();
+	let mut catalog = BuildingCatalog::new();
+	let hut = catalog.add(BuildingDef::new("hut", [1, 1]));
+	game.world.insert_resource(catalog);
+	let site = game.place_building(hut, [5, 0], Rotation::North).unwrap();
+	let builder = spawn_colonist(&mut game, "Builder", [0, 0], Skill::Construction, 5);
+	let build = post(&mut game, JobKind::Build(site), 1, 300);
+
+	// Up to the hut, four tiles at two tiles a second.
+	let walked = run_until(&mut game, 400, |game| matches!(colonist(game, builder).activity(), Activity::Working(_)));
+	assert!((120..130).contains(&walked), "walked for {walked} ticks");
+	assert_eq!(game.world.get::<Position>(builder).unwrap().current, [4.5, 0.5]);
+
+	// Fifteen units of work a tick.
+	run(&mut game, 10);
+	assert_eq!(job(&game, build).unwrap().done, 150);
+	assert_eq!(game.world.get::<ConstructionSite>(site), Some(&ConstructionSite { progress: 0.5 }));
+	run(&mut game, 10);
+	assert!(job(&game, build).is_none());
+	assert!(!game.world.has::<ConstructionSite>(site));
+	assert_eq!(colonist(&game, builder).activity(), &Activity::Idle);
+}
+
+#[test]
+fn interrupted_colonists_hand_back_jobs_and_what_they_carry() {
+	let content = content();
+	let wood = Resource::Item(content.item_id("wood").unwrap());
+	let mut game = Game::new();
+	game.set_content(content);
+	let from = spawn_stockpile(&mut game, [4, 0], &[(wood, 5)]);
+	let to = spawn_stockpile(&mut game, [12, 0], &[]);
+	let hauler = spawn_colonist(&mut game, "Hauler", [0, 0], Skill::Hauling, 0);
+	let pickups = vec![Pickup { from, resource: wood, amount: 5 }];
+	let haul = post(&mut game, JobKind::Haul { pickups, to }, 1, 1);
+
+	run_until(&mut game, 400, |game| !colonist(game, hauler).carrying.is_empty());
+	assert_eq!(game.world.get::<Stockpile>(from).unwrap().get(wood), 0);
+	assert!(colonist::interrupt(&mut game.world, hauler));
+	assert_eq!(game.world.get::<Stockpile>(from).unwrap().get(wood), 5);
+	assert_eq!(job(&game, haul).unwrap().claimed_by(), None);
+	assert!(colonist(&game, hauler).carrying.is_empty());
+
+	// A tired colonist sleeps and someone else picks the job up.
+	let helper = spawn_colonist(&mut game, "Helper", [20, 0], Skill::Hauling, 0);
+	run(&mut game, 1);
+	assert_eq!(job(&game, haul).unwrap().claimed_by(), Some(hauler));
+	game.world.get_mut::<Colonist>(hauler).unwrap().needs.rest = TIRED - 0.01;
+	run(&mut game, 2);
+	assert_eq!(colonist(&game, hauler).activity(), &Activity::Sleeping);
+	assert_eq!(job(&game, haul).unwrap().claimed_by(), Some(helper));
+
+	run_until(&mut game, 1000, |game| job(game, haul).is_none());
+	assert_eq!(game.world.get::<Stockpile>(to).unwrap().get(wood), 5);
+	assert_eq!(game.world.get::<Stockpile>(from).unwrap().get(wood), 0);
+}
+
+/// Rewrites a save as version 1, with a job board in the version 1 layout holding `hauls`, each
+/// with a single pickup.
+fn version_1_save(bytes: &[u8], next_id: u64, hauls: &[(JobId, &Job)]) -> Vec<u8> {
+	let mut reader = Reader::new(bytes);
+	let mut writer = Writer::new();
+	writer.raw(reader.raw(4).unwrap());
+	reader.u32().unwrap();
+	writer.u32(1);
+	writer.bytes(reader.bytes().unwrap());
+	let count = reader.varint().unwrap();
+	writer.varint(count);
+	for _ in 0..count {
+		let name = reader.str().unwrap();
+		let data = reader.bytes().unwrap();
+		writer.str(name);
+		if name != "resource.job_board" {
+			writer.bytes(data);
+			continue;
+		}
+		let mut section = Writer::new();
+		section.varint(next_id);
+		section.varint(hauls.len() as u64);
+		for &(id, job) in hauls {
+			let JobKind::Haul { ref pickups, to } = job.kind else { unreachable!() };
+			let [Pickup { from, resource: Resource::Item(item), amount }] = pickups[..] else { unreachable!() };
+			section.varint(id.0);
+			section.u8(3);
+			section.entity(from);
+			section.entity(to);
+			section.u8(0);
+			section.varint(item.0 as u64);
+			section.varint(amount);
+			section.u8(job.priority);
+			section.varint(1);
+			section.varint(0);
+			section.bool(job.claimed_by().is_some());
+			if let Some(colonist) = job.claimed_by() {
+				section.entity(colonist);
+			}
+		}
+		writer.bytes(&section.into_bytes());
+	}
+	writer.into_bytes()
+}
+
+#[test]
+fn hauls_from_version_1_saves_start_over() {
+	let content = content();
+	let wood = Resource::Item(content.item_id("wood").unwrap());
+	let mut game = Game::new();
+	game.set_content(content.clone());
+	let from = spawn_stockpile(&mut game, [4, 0], &[(wood, 5)]);
+	let to = spawn_stockpile(&mut game, [12, 0], &[]);
+	let hauler = spawn_colonist(&mut game, "Hauler", [0, 0], Skill::Hauling, 0);
+	let pickups = vec![Pickup { from, resource: wood, amount: 5 }];
+	let haul = post(&mut game, JobKind::Haul { pickups: pickups.clone(), to }, 2, 1);
+	run_until(&mut game, 400, |game| !colonist(game, hauler).carrying.is_empty());
+
+	// Saved by the old build half way, with the wood in hand.
+	let thumbnail = save::thumbnail(&game.tilemap, tiles::color);
+	let old = version_1_save(&save::encode(&game, &thumbnail), haul.0 + 1, &[(haul, job(&game, haul).unwrap())]);
+	assert_eq!(save::decode_header(&old).unwrap().version, 1);
+	let mut loaded = save::decode(&old).unwrap();
+	loaded.set_content(content);
+	let job = job(&loaded, haul).unwrap();
+	assert_eq!(job.kind, JobKind::Haul { pickups, to });
+	assert_eq!((job.priority, job.work, job.done, job.claimed_by()), (2, 1, 0, None));
+
+	// The hauler puts the wood back and the haul is done from the start.
+	run(&mut loaded, 1);
+	assert_eq!(loaded.world.get::<Stockpile>(from).unwrap().get(wood), 5);
+	run_until(&mut loaded, 1000, |game| game.world.resource::<JobBoard>().unwrap().get(haul).is_none());
+	assert_eq!(loaded.world.get::<Stockpile>(to).unwrap().get(wood), 5);
+	assert_eq!(loaded.world.get::<Stockpile>(from).unwrap().get(wood), 0);
+}
+
+#[test]
+fn colonists_let_go_only_of_jobs_they_still_hold() {
+	let mut game = Game::new();
+	let mut catalog = BuildingCatalog::new();
+	let hut = catalog.add(BuildingDef::new("hut", [1, 1]));
+	game.world.insert_resource(catalog);
+	let site = game.place_building(hut, [3, 0], Rotation::North).unwrap();
+	let first = spawn_colonist(&mut game, "First", [0, 0], Skill::Construction, 0);
+	let second = spawn_colonist(&mut game, "Second", [0, 2], Skill::Construction, 0);
+	let build = post(&mut game, JobKind::Build(site), 1, 3000);
+	run(&mut game, 1);
+	assert_eq!(job(&game, build).unwrap().claimed_by(), Some(first));
+
+	// The first colonist is called away and the second takes the job over, while a stale copy
+	// of the first still thinks the job is its own.
+	let stale = colonist(&game, first).clone();
+	colonist::interrupt(&mut game.world, first);
+	game.world.remove::<Colonist>(first);
+	run_until(&mut game, 400, |game| matches!(colonist(game, second).activity(), Activity::Working(_)));
+	run(&mut game, 10);
+	let done = job(&game, build).unwrap().done;
+	assert!(done > 0);
+	game.world.insert(first, stale);
+
+	run(&mut game, 1);
+	assert_eq!(colonist(&game, first).activity(), &Activity::Idle);
+	let job = job(&game, build).unwrap();
+	assert_eq!(job.claimed_by(), Some(second));
+	assert!(job.done > done, "lost the work done: {} of {done}", job.done);
+}
+
+#[test]
+fn hand_crafting_takes_inputs_from_the_stockpile() {
+	let content = content();
+	let (plate, gear) = (Resource::Item(content.item_id("iron_plate").unwrap()), Resource::Item(content.item_id("gear").unwrap()));
+	let hand_gear = content.recipe_id("hand_gear").unwrap();
+	let mut game = Game::new();
+	game.set_content(content);
+	let bench = spawn_stockpile(&mut game, [0, 0], &[(plate, 4)]);
+	let crafter = spawn_colonist(&mut game, "Crafter", [0, 0], Skill::Crafting, 5);
+	let jobs = [0; 3].map(|_| post(&mut game, JobKind::Craft(hand_gear, bench), 1, 150));
+
+	run(&mut game, 30);
+	let stockpile = game.world.get::<Stockpile>(bench).unwrap();
+	assert_eq!((stockpile.get(plate), stockpile.get(gear)), (0, 2));
+	assert!(job(&game, jobs[0]).is_none() && job(&game, jobs[1]).is_none());
+	// Nothing left to craft from.
+	assert_eq!(job(&game, jobs[2]).unwrap().claimed_by(), None);
+	assert_eq!(colonist(&game, crafter).activity(), &Activity::Idle);
+}
+
+#[test]
+fn mined_tiles_change_the_map() {
+	let mut game = Game::new();
+	game.set_tile(3, 0, tiles::ROCK);
+	spawn_colonist(&mut game, "Miner", [0, 0], Skill::Mining, 0);
+	let dig = post(&mut game, JobKind::Mine([3, 0], tiles::SAND), 1, 200);
+
+	run_until(&mut game, 400, |game| job(game, dig).is_none());
+	assert_eq!(game.tilemap.get(3, 0), Some(tiles::SAND));
+	assert_eq!(game.world.resource::<Pathfinder>().unwrap().grid().cost([3, 0]), tiles::movement_cost(tiles::SAND));
+}
+
+#[test]
+fn needs_fall_and_hungry_colonists_eat() {
+	let content = content();
+	let food = Resource::Item(content.item_id("food").unwrap());
+	let mut game = Game::new();
+	game.set_content(content);
+	let pantry = spawn_stockpile(&mut game, [9, 9], &[(food, 1)]);
+	let eater = spawn_colonist(&mut game, "Eater", [0, 0], Skill::Hauling, 0);
+
+	// Two minutes awake.
+	run(&mut game, 7200);
+	let needs = colonist(&game, eater).needs;
+	assert!((needs.hunger - 0.75).abs() < 1e-3, "{needs:?}");
+	assert!((needs.rest - 5.0 / 6.0).abs() < 1e-3, "{needs:?}");
+	assert!(needs.morale < 1.0 && needs.morale > 0.79, "{needs:?}");
+
+	game.world.get_mut::<Colonist>(eater).unwrap().needs.hunger = 0.2;
+	run(&mut game, 1);
+	assert!(colonist(&game, eater).needs.hunger > 0.79);
+	assert_eq!(game.world.get::<Stockpile>(pantry).unwrap().get(food), 0);
+
+	// Nothing left to eat.
+	game.world.get_mut::<Colonist>(eater).unwrap().needs.hunger = 0.2;
+	run(&mut game, 1);
+	assert!(colonist(&game, eater).needs.hunger < 0.2);
+}
+
+#[test]
+fn colonists_carry_on_identically_after_loading() {
+	let mut game = Game::new();
+	let miner = spawn_colonist(&mut game, "Miner", [0, 0], Skill::Mining, 3);
+	let other = spawn_colonist(&mut game, "Other", [20, 20], Skill::Mining, 0);
+	post(&mut game, JobKind::Mine([10, 4], tiles::SAND), 1, 500);
+	post(&mut game, JobKind::Mine([25, 20], tiles::SAND), 1, 800);
+	run(&mut game, 200);
+	assert!(matches!(colonist(&game, miner).activity(), Activity::Walking { .. }));
+
+	let thumbnail = save::thumbnail(&game.tilemap, tiles::color);
+	let mut loaded = save::decode(&save::encode(&game, &thumbnail)).unwrap();
+	assert_eq!(loaded.world.resource::<JobBoard>(), game.world.resource::<JobBoard>());
+
+	run(&mut game, 600);
+	run(&mut loaded, 600);
+	for entity in [miner, other] {
+		assert_eq!(loaded.world.get::<Colonist>(entity), game.world.get::<Colonist>(entity));
+		assert_eq!(loaded.world.get::<Position>(entity), game.world.get::<Position>(entity));
+	}
+	assert!(game.world.resource::<JobBoard>().unwrap().is_empty());
+	assert_eq!(loaded.tilemap.get(10, 4), Some(tiles::SAND));
+}
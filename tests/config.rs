@@ -0,0 +1,166 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use frontier_outpost::config::{Config, GraphicsConfig};
+use wgpu::{Backends, PowerPreference};
+
+/// Held by every test that loads a config, as the environment is shared by all of them.
+static ENV: Mutex<()> = Mutex::new(());
+
+/// Writes `contents` to a config file of its own and returns its path.
+fn config_file(name: &str, contents: &str) -> PathBuf {
+	let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("config");
+	fs::create_dir_all(&dir).unwrap();
+	let path = dir.join(format!("{name}.toml"));
+	fs::write(&path, contents).unwrap();
+	path
+}
+
+/// Loads a config from `args`, with the graphics environment variables set to `backend` and
+/// `power` or unset.
+fn load(args: &[&str], backend: Option<&str>, power: Option<&str>) -> Result<Config, String> {
+	let _guard = ENV.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+	for (name, value) in [("WGPU_BACKEND", backend), ("WGPU_POWER_PREF", power)] {
+		match value {
+			Some(value) => env::set_var(name, value),
+			None => env::remove_var(name),
+		}
+	}
+	let result = Config::load(args.iter().map(|arg| arg.to_string())).map_err(|error| error.to_string());
+	env::remove_var("WGPU_BACKEND");
+	env::remove_var("WGPU_POWER_PREF");
+	result
+}
+
+#[test]
+fn later_sources_override_earlier_ones() {
+	let file = config_file("precedence", concat!(
+		"[graphics]\n",
+		"backends = \"gl\"\n",
+		"power_preference = \"low\"\n",
+		"[simulation]\n",
+		"ticks_per_second = 30\n",
+		"[world]\n",
+		"seed = 7\n",
+		"[save]\n",
+		"autosave_interval = 0\n",
+	));
+	let file = file.to_str().unwrap();
+
+	let defaults = load(&["--config", config_file("empty", "").to_str().unwrap()], None, None).unwrap();
+	assert_eq!(defaults.graphics.backends, Backends::all());
+	assert_eq!(defaults.graphics.power_preference, PowerPreference::HighPerformance);
+	assert!(!defaults.graphics.force_fallback_adapter);
+	assert_eq!(defaults.simulation.ticks_per_second, 60);
+	assert_eq!(defaults.world.seed, None);
+	assert_eq!(defaults.save.autosave_interval, Some(Duration::from_secs(300)));
+	assert!(!defaults.headless);
+
+	let from_file = load(&["--config", file], None, None).unwrap();
+	assert_eq!(from_file.graphics.backends, Backends::GL);
+	assert_eq!(from_file.graphics.power_preference, PowerPreference::LowPower);
+	assert_eq!(from_file.simulation.ticks_per_second, 30);
+	assert_eq!(from_file.world.seed, Some(7));
+	assert_eq!(from_file.save.autosave_interval, None);
+
+	let from_env = load(&["--config", file], Some("vulkan"), Some("high")).unwrap();
+	assert_eq!(from_env.graphics.backends, Backends::VULKAN);
+	assert_eq!(from_env.graphics.power_preference, PowerPreference::HighPerformance);
+	assert_eq!(from_env.simulation.ticks_per_second, 30);
+
+	let args = [
+		"--config", file,
+		"--backend", "dx12",
+		"--power-preference", "low",
+		"--ticks-per-second", "120",
+		"--seed", "99",
+		"--load", "quicksave.sav",
+		"--fallback-adapter",
+		"--headless",
+	];
+	let from_flags = load(&args, Some("vulkan"), Some("high")).unwrap();
+	assert_eq!(from_flags.graphics.backends, Backends::DX12);
+	assert_eq!(from_flags.graphics.power_preference, PowerPreference::LowPower);
+	assert!(from_flags.graphics.force_fallback_adapter);
+	assert_eq!(from_flags.simulation.ticks_per_second, 120);
+	assert_eq!(from_flags.world.seed, Some(99));
+	assert_eq!(from_flags.save.load, Some(PathBuf::from("quicksave.sav")));
+	assert!(from_flags.headless);
+}
+
+#[test]
+fn graphics_settings_are_parsed_leniently() {
+	let config = load(&["--backend", "Vulkan,gl", "--power-preference", "HIGH"], None, Some("Low")).unwrap();
+	assert_eq!(config.graphics.backends, Backends::VULKAN | Backends::GL);
+	assert_eq!(config.graphics.power_preference, PowerPreference::HighPerformance);
+	let config = load(&[], Some("metal"), Some("Low")).unwrap();
+	assert_eq!(config.graphics.backends, Backends::METAL);
+	assert_eq!(config.graphics.power_preference, PowerPreference::LowPower);
+
+	assert_eq!(load(&["--backend", "glide"], None, None).unwrap_err(), "no known graphics backend in \"glide\"");
+	assert_eq!(
+		load(&["--power-preference", "medium"], None, None).unwrap_err(),
+		"unknown power preference \"medium\", expected low or high",
+	);
+	assert_eq!(load(&[], None, Some("max")).unwrap_err(), "unknown power preference \"max\", expected low or high");
+}
+
+#[test]
+fn backends_are_tried_in_order_of_preference() {
+	let graphics = GraphicsConfig {
+		backends: Backends::GL | Backends::DX12 | Backends::VULKAN,
+		..GraphicsConfig::default()
+	};
+	assert_eq!(graphics.backend_attempts().collect::<Vec<_>>(), [Backends::VULKAN, Backends::DX12, Backends::GL]);
+
+	let only_gl = GraphicsConfig {
+		backends: Backends::GL,
+		..GraphicsConfig::default()
+	};
+	assert_eq!(only_gl.backend_attempts().collect::<Vec<_>>(), [Backends::GL]);
+
+	let all = GraphicsConfig::default().backend_attempts().collect::<Vec<_>>();
+	assert_eq!(all.first(), Some(&Backends::VULKAN));
+	assert!(all.iter().all(|backend| backend.bits().count_ones() == 1));
+}
+
+#[test]
+fn the_fallback_adapter_is_tried_after_every_backend() {
+	let graphics = GraphicsConfig {
+		backends: Backends::GL | Backends::VULKAN,
+		..GraphicsConfig::default()
+	};
+	assert_eq!(
+		graphics.adapter_attempts().collect::<Vec<_>>(),
+		[(Backends::VULKAN, false), (Backends::GL, false), (Backends::VULKAN, true), (Backends::GL, true)],
+	);
+
+	let forced = GraphicsConfig {
+		force_fallback_adapter: true,
+		..graphics
+	};
+	assert_eq!(forced.adapter_attempts().collect::<Vec<_>>(), [(Backends::VULKAN, true), (Backends::GL, true)]);
+	assert!(GraphicsConfig::headless().adapter_attempts().all(|(_, fallback)| fallback));
+}
+
+#[test]
+fn bad_arguments_are_errors() {
+	assert_eq!(load(&["--turbo"], None, None).unwrap_err(), "unknown argument --turbo");
+	assert_eq!(load(&["--seed"], None, None).unwrap_err(), "--seed requires a value");
+	assert_eq!(
+		load(&["--ticks-per-second", "0"], None, None).unwrap_err(),
+		"invalid tick rate \"0\", expected a positive whole number",
+	);
+
+	let missing = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("config").join("missing.toml");
+	let _ = fs::remove_file(&missing);
+	let error = load(&["--config", missing.to_str().unwrap()], None, None).unwrap_err();
+	assert!(error.starts_with(&missing.display().to_string()), "{error}");
+
+	let typo = config_file("typo", "[graphics]\nbackend = \"gl\"\n");
+	let error = load(&["--config", typo.to_str().unwrap()], None, None).unwrap_err();
+	assert!(error.starts_with(&typo.display().to_string()) && error.contains("backend"), "{error}");
+}
@@ -0,0 +1,164 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use frontier_outpost::building::Rotation;
+use frontier_outpost::content::{
+	BUILDINGS_FILE, Content, ContentError, ITEMS_FILE, ItemCount, RECIPES_FILE, RESEARCH_FILE, TERRAIN_FILE,
+};
+use frontier_outpost::game::Game;
+use frontier_outpost::pathfinding::{PathResult, Pathfinder};
+use frontier_outpost::tilemap::Tile;
+use frontier_outpost::worldgen::tiles;
+
+const FILES: [&str; 5] = [TERRAIN_FILE, ITEMS_FILE, BUILDINGS_FILE, RECIPES_FILE, RESEARCH_FILE];
+
+fn shipped_dir() -> PathBuf {
+	Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/content")
+}
+
+/// Loads a copy of the shipped content with `file` changed by `edit`.
+fn load_edited(name: &str, file: &str, edit: impl FnOnce(String) -> String) -> Result<Content, ContentError> {
+	let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("content").join(name);
+	fs::create_dir_all(&dir).unwrap();
+	for shipped in FILES {
+		fs::copy(shipped_dir().join(shipped), dir.join(shipped)).unwrap();
+	}
+	let edited = edit(fs::read_to_string(dir.join(file)).unwrap());
+	fs::write(dir.join(file), edited).unwrap();
+	Content::load(&dir)
+}
+
+/// Message of the error a load failed with, naming the file without its directory.
+fn error(result: Result<Content, ContentError>) -> String {
+	let error = result.expect_err("content should be rejected");
+	let (ContentError::Io(path, _) | ContentError::Parse(path, _) | ContentError::Invalid { file: path, .. }) = &error;
+	let message = error.to_string();
+	let full_path = path.display().to_string();
+	assert!(message.contains(&full_path), "{message} does not name {full_path}");
+	message.replace(&full_path, path.file_name().unwrap().to_str().unwrap())
+}
+
+#[test]
+fn shipped_content_loads_with_resolved_references() {
+	let content = Content::load(&shipped_dir()).unwrap();
+
+	// Terrain agrees with the tiles the map generator places.
+	for (index, id) in tiles::NAMES.into_iter().enumerate() {
+		let tile = Tile(index as u16 + 1);
+		assert_eq!(content.terrain_id(id), Some(tile));
+		assert_eq!(content.movement_cost(tile), tiles::movement_cost(tile), "{id}");
+		assert_eq!(content.color(tile), tiles::color(tile), "{id}");
+	}
+
+	let circuit = content.recipe(content.recipe_id("circuit").unwrap()).unwrap();
+	assert_eq!(circuit.inputs, [
+		ItemCount { item: content.item_id("iron_plate").unwrap(), count: 1 },
+		ItemCount { item: content.item_id("copper_wire").unwrap(), count: 3 },
+	]);
+	assert_eq!(circuit.building, content.building_id("workshop"));
+	assert_eq!(content.recipe(content.recipe_id("hand_gear").unwrap()).unwrap().building, None);
+
+	let electronics = content.research(content.research_id("electronics").unwrap()).unwrap();
+	assert_eq!(electronics.prerequisites, [content.research_id("machining").unwrap()]);
+
+	let pump = content.buildings().get(content.building_id("water_pump").unwrap()).unwrap();
+	assert_eq!(pump.name, "Water pump");
+	assert_eq!(pump.size(), [1, 2]);
+	assert_eq!(content.item_id("unobtainium"), None);
+}
+
+#[test]
+fn errors_name_the_file_and_field() {
+	let unknown_terrain = load_edited("unknown_terrain", BUILDINGS_FILE, |file| {
+		file.replacen("terrain = [\"sand\",", "terrain = [\"lava\",", 1)
+	});
+	assert_eq!(error(unknown_terrain), "buildings.toml: building[0].terrain[0]: unknown terrain \"lava\"");
+
+	let unknown_item = load_edited("unknown_item", RECIPES_FILE, |file| {
+		file.replace("copper_wire\", count = 3", "copper_cable\", count = 3")
+	});
+	assert_eq!(error(unknown_item), "recipes.toml: recipe[4].inputs[1].item: unknown item \"copper_cable\"");
+
+	let adjacency = load_edited("adjacency", BUILDINGS_FILE, |file| {
+		file.replace("away_from_building = \"stockpile\"", "away_from_building = \"stockpil\"")
+	});
+	assert_eq!(
+		error(adjacency),
+		"buildings.toml: building[2].adjacency[0].away_from_building: unknown building \"stockpil\"",
+	);
+
+	let duplicate = load_edited("duplicate", ITEMS_FILE, |file| file.replace("id = \"stone\"", "id = \"wood\""));
+	assert_eq!(error(duplicate), "items.toml: item[1].id: \"wood\" is defined more than once");
+
+	let empty_stack = load_edited("empty_stack", ITEMS_FILE, |file| file.replacen("stack_size = 50", "stack_size = 0", 1));
+	assert_eq!(error(empty_stack), "items.toml: item[0].stack_size: has to be at least 1");
+
+	let cycle = load_edited("cycle", RESEARCH_FILE, |file| {
+		file.replacen("cost = 50\n", "cost = 50\nprerequisites = [\"electronics\"]\n", 1)
+	});
+	assert_eq!(error(cycle), "research.toml: research[0].prerequisites: \"smelting\" ends up depending on itself");
+
+	let reordered = load_edited("reordered", TERRAIN_FILE, |file| file.replacen("id = \"water\"", "id = \"lake\"", 1));
+	assert_eq!(
+		error(reordered),
+		"terrain.toml: terrain[0]: has to be \"water\", the map generator places it as tile 1",
+	);
+
+	let both_shapes = load_edited("both_shapes", BUILDINGS_FILE, |file| {
+		file.replace("footprint = [[0, 0]", "size = [1, 1]\nfootprint = [[0, 0]")
+	});
+	assert_eq!(error(both_shapes), "buildings.toml: building[6].size: cannot be given along with a footprint");
+
+	// Recipe times and power draws have to be real amounts, named for what is wrong with them.
+	for (name, from, to, message) in [
+		("instant", "duration = 3.2", "duration = 0.0", "duration: has to be a positive number of seconds"),
+		("undefined_time", "duration = 3.2", "duration = nan", "duration: has to be a positive number of seconds"),
+		("endless", "duration = 3.2", "duration = 1e30", "duration: is too long to count in seconds"),
+		("forever", "duration = 3.2", "duration = inf", "duration: is too long to count in seconds"),
+		("generator", "power = 90000.0", "power = -1.0", "power: cannot be negative"),
+		("undefined_power", "power = 90000.0", "power = nan", "power: has to be a finite number of watts"),
+		("limitless", "power = 90000.0", "power = inf", "power: has to be a finite number of watts"),
+	] {
+		let edited = load_edited(name, RECIPES_FILE, |file| file.replacen(from, to, 1));
+		assert_eq!(error(edited), format!("recipes.toml: recipe[0].{message}"), "{to}");
+	}
+}
+
+#[test]
+fn unreadable_files_are_reported_with_their_path() {
+	let typo = load_edited("typo", RECIPES_FILE, |file| file.replacen("duration = 3.2", "durration = 3.2", 1));
+	let message = error(typo);
+	assert!(message.starts_with("recipes.toml: "), "{message}");
+	assert!(message.contains("durration"), "{message}");
+
+	let wrong_type = load_edited("wrong_type", ITEMS_FILE, |file| file.replacen("stack_size = 50", "stack_size = \"lots\"", 1));
+	assert!(error(wrong_type).starts_with("items.toml: "));
+
+	let missing = Content::load(Path::new("no/such/directory"));
+	assert!(matches!(missing, Err(ContentError::Io(path, _)) if path.ends_with(TERRAIN_FILE)));
+}
+
+#[test]
+fn games_play_with_loaded_content() {
+	let content = Arc::new(load_edited("slow_grass", TERRAIN_FILE, |file| {
+		let forest = "\n\n[[terrain]]\nid = \"forest_floor\"";
+		file.replacen(&format!("movement_cost = 1{forest}"), &format!("movement_cost = 5{forest}"), 1)
+	}).unwrap());
+	let mut game = Game::new();
+	for x in 0..4 {
+		game.set_tile(x, 0, tiles::GRASS);
+	}
+	game.set_content(content.clone());
+	game.set_tile(4, 0, tiles::GRASS);
+
+	let pathfinder = game.world.resource_mut::<Pathfinder>().unwrap();
+	assert_eq!(pathfinder.grid().cost([2, 0]), Some(5));
+	assert_eq!(pathfinder.grid().cost([4, 0]), Some(5));
+	assert!(matches!(pathfinder.find_path([0, 0], [4, 0]), PathResult::Found(_)));
+
+	let stockpile = content.building_id("stockpile").unwrap();
+	let placement = game.check_placement(stockpile, [0, 0], Rotation::North);
+	assert_eq!(placement.tiles.iter().filter(|(_, error)| error.is_none()).count(), 3);
+	game.place_building(content.building_id("wall_corner").unwrap(), [0, 0], Rotation::North).unwrap();
+}
@@ -0,0 +1,273 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use frontier_outpost::building::{PlacementError, Rotation};
+use frontier_outpost::config::SimulationConfig;
+use frontier_outpost::content::{Content, DEFAULT_CONTENT_DIR, ItemId};
+use frontier_outpost::conveyor::{Belt, BeltKind, BeltNetwork, Inserter, Stack};
+use frontier_outpost::crafting::{Crafter, CraftingStatus};
+use frontier_outpost::economy::{Resource, Stockpile};
+use frontier_outpost::ecs::Entity;
+use frontier_outpost::game::Game;
+use frontier_outpost::pathfinding::{PathResult, Pathfinder};
+use frontier_outpost::save;
+use frontier_outpost::simulation;
+use frontier_outpost::worldgen::tiles;
+
+fn game() -> (Game, Arc<Content>) {
+	let content = Arc::new(Content::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join(DEFAULT_CONTENT_DIR)).unwrap());
+	let mut game = Game::new();
+	for y in 0..20 {
+		for x in 0..20 {
+			game.set_tile(x, y, tiles::GRASS);
+		}
+	}
+	game.set_content(content.clone());
+	(game, content)
+}
+
+fn run(game: &mut Game, ticks: u64) {
+	simulation::run_ticks(game, &SimulationConfig::default(), ticks);
+}
+
+fn belt(game: &mut Game, tile: [i32; 2], direction: Rotation, kind: BeltKind) -> Entity {
+	game.place_belt(Belt::new(tile, direction, kind)).unwrap()
+}
+
+/// Lays straight belts from `from` on, `length` tiles long.
+fn line(game: &mut Game, from: [i32; 2], direction: Rotation, length: i32) -> Vec<Entity> {
+	let [dx, dy] = direction.offset();
+	(0..length).map(|step| belt(game, [from[0] + dx * step, from[1] + dy * step], direction, BeltKind::Straight)).collect()
+}
+
+fn network(game: &Game) -> &BeltNetwork {
+	game.world.resource::<BeltNetwork>().unwrap()
+}
+
+fn network_mut(game: &mut Game) -> &mut BeltNetwork {
+	game.world.resource_mut::<BeltNetwork>().unwrap()
+}
+
+/// Amounts of the stacks on `tiles`, front first within each tile.
+fn amounts(game: &Game, tiles: &[[i32; 2]]) -> Vec<u64> {
+	tiles.iter().flat_map(|&tile| network(game).stacks_at(tile)).map(|stack| stack.amount).collect()
+}
+
+/// Puts `stack` on the belt at `tile`, running the game until there is room.
+fn feed(game: &mut Game, tile: [i32; 2], stack: Stack) {
+	while !network_mut(game).insert(tile, stack) {
+		run(game, 1);
+	}
+}
+
+#[test]
+fn straight_runs_are_grouped_into_segments() {
+	let (mut game, _) = game();
+	let run_in = line(&mut game, [0, 5], Rotation::East, 5);
+	let splitter = belt(&mut game, [5, 5], Rotation::East, BeltKind::Splitter);
+	let north = belt(&mut game, [5, 6], Rotation::North, BeltKind::Straight);
+	let south = belt(&mut game, [5, 4], Rotation::South, BeltKind::Straight);
+	let belts = network(&game);
+	assert!(run_in.iter().all(|&belt| belts.segment_of(belt) == belts.segment_of(run_in[0])));
+	assert_ne!(belts.segment_of(splitter), belts.segment_of(run_in[0]));
+	assert_ne!(belts.segment_of(north), belts.segment_of(south));
+	assert_eq!(belts.segment_count(), 4);
+
+	// Longer branches and loops turning corners are still one segment each.
+	let further = belt(&mut game, [5, 7], Rotation::North, BeltKind::Straight);
+	let ring = [
+		belt(&mut game, [10, 10], Rotation::East, BeltKind::Straight),
+		belt(&mut game, [11, 10], Rotation::North, BeltKind::Straight),
+		belt(&mut game, [11, 11], Rotation::West, BeltKind::Straight),
+		belt(&mut game, [10, 11], Rotation::South, BeltKind::Straight),
+	];
+	let belts = network(&game);
+	assert_eq!(belts.segment_of(further), belts.segment_of(north));
+	assert!(ring.iter().all(|&belt| belts.segment_of(belt) == belts.segment_of(ring[0])));
+	assert_eq!(belts.segment_count(), 5);
+}
+
+#[test]
+fn stacks_move_at_belt_speed_and_queue_at_the_end() {
+	let (mut game, content) = game();
+	let ore = content.item_id("iron_ore").unwrap();
+	line(&mut game, [0, 5], Rotation::East, 5);
+	let tiles = (0..5).map(|x| [x, 5]).collect::<Vec<_>>();
+	assert!(network_mut(&mut game).insert([0, 5], Stack { item: ore, amount: 1 }));
+
+	// Two tiles a second, from the middle of the first tile.
+	run(&mut game, 60);
+	assert_eq!(amounts(&game, &[[2, 5]]), [1]);
+	run(&mut game, 75);
+	assert_eq!(amounts(&game, &[[4, 5]]), [1]);
+	run(&mut game, 60);
+	assert_eq!(amounts(&game, &[[4, 5]]), [1]);
+
+	// Four stacks to a tile, and the first tile is fed in its middle.
+	for _ in 0..30 {
+		network_mut(&mut game).insert([0, 5], Stack { item: ore, amount: 2 });
+		run(&mut game, 10);
+	}
+	assert_eq!(amounts(&game, &tiles).len(), 19);
+	assert_eq!(amounts(&game, &[[4, 5]]), [1, 2, 2, 2]);
+	assert!(!network_mut(&mut game).insert([0, 5], Stack { item: ore, amount: 2 }));
+}
+
+#[test]
+fn splitters_alternate_and_mergers_take_turns() {
+	let (mut game, content) = game();
+	let ore = content.item_id("iron_ore").unwrap();
+	line(&mut game, [0, 5], Rotation::East, 3);
+	belt(&mut game, [3, 5], Rotation::East, BeltKind::Splitter);
+	line(&mut game, [3, 6], Rotation::North, 2);
+	line(&mut game, [3, 4], Rotation::South, 2);
+	for amount in 1..=6 {
+		feed(&mut game, [0, 5], Stack { item: ore, amount });
+	}
+	run(&mut game, 600);
+	// Left of east is north.
+	assert_eq!(amounts(&game, &[[3, 7], [3, 6]]), [1, 3, 5]);
+	assert_eq!(amounts(&game, &[[3, 3], [3, 4]]), [2, 4, 6]);
+
+	line(&mut game, [8, 5], Rotation::East, 2);
+	line(&mut game, [10, 7], Rotation::South, 2);
+	belt(&mut game, [10, 5], Rotation::East, BeltKind::Merger);
+	line(&mut game, [11, 5], Rotation::East, 2);
+	let belts = network_mut(&mut game);
+	for (tile, amount) in [([9, 5], 10), ([8, 5], 11), ([10, 6], 20), ([10, 7], 21)] {
+		assert!(belts.insert(tile, Stack { item: ore, amount }));
+	}
+	run(&mut game, 600);
+	assert_eq!(amounts(&game, &[[12, 5], [11, 5]]), [10, 20, 11, 21]);
+}
+
+#[test]
+fn splitters_hand_on_to_the_side_with_room() {
+	let (mut game, content) = game();
+	let ore = content.item_id("iron_ore").unwrap();
+	line(&mut game, [0, 5], Rotation::East, 3);
+	belt(&mut game, [3, 5], Rotation::East, BeltKind::Splitter);
+	line(&mut game, [3, 6], Rotation::North, 1);
+	line(&mut game, [3, 4], Rotation::South, 3);
+	for amount in 1..=12 {
+		feed(&mut game, [0, 5], Stack { item: ore, amount });
+	}
+	run(&mut game, 600);
+	// A single tile holds five stacks when the last has only just come on.
+	assert_eq!(amounts(&game, &[[3, 6]]), [1, 3, 5, 7, 9]);
+	assert_eq!(amounts(&game, &[[3, 2], [3, 3], [3, 4]]), [2, 4, 6, 8, 10, 11, 12]);
+}
+
+#[test]
+fn inserters_feed_buildings_from_belts_and_back() {
+	let (mut game, content) = game();
+	let ore = Resource::Item(content.item_id("iron_ore").unwrap());
+	let plate = content.item_id("iron_plate").unwrap();
+	let store = game.place_building(content.building_id("stockpile").unwrap(), [0, 0], Rotation::North).unwrap();
+	let mut stockpile = Stockpile::new(100);
+	stockpile.add(ore, 10);
+	game.world.insert(store, stockpile);
+	let smelter = game.place_building(content.building_id("smelter").unwrap(), [9, 0], Rotation::North).unwrap();
+	game.world.insert(smelter, Crafter::new(&content, content.recipe_id("iron_plate").unwrap()).unwrap());
+	let mut power = Stockpile::new(10_000);
+	power.add(Resource::Power, 10_000);
+	game.world.insert(smelter, power);
+
+	game.place_inserter(Inserter::new([3, 1], Rotation::East)).unwrap();
+	line(&mut game, [4, 1], Rotation::East, 4);
+	game.place_inserter(Inserter::new([8, 1], Rotation::East)).unwrap();
+	game.place_inserter(Inserter::new([11, 1], Rotation::East).with_stack(5)).unwrap();
+	let out = line(&mut game, [12, 1], Rotation::East, 4);
+
+	// Ten plates of 192 ticks each, fed an ore every 45 ticks.
+	run(&mut game, 2400);
+	assert_eq!(game.world.get::<Stockpile>(store).unwrap().get(ore), 0);
+	let crafter = game.world.get::<Crafter>(smelter).unwrap();
+	assert_eq!(crafter.status, CraftingStatus::Starved(ore));
+	assert_eq!(crafter.output.get(Resource::Item(plate)), 0);
+	let stacks = out.iter()
+		.flat_map(|&belt| network(&game).stacks_at(game.world.get::<Belt>(belt).unwrap().tile))
+		.collect::<Vec<_>>();
+	assert!(stacks.iter().all(|stack| stack.item == plate));
+	assert_eq!(stacks.iter().map(|stack| stack.amount).sum::<u64>(), 10);
+}
+
+#[test]
+fn belts_are_laid_on_walkable_ground_and_walked_over() {
+	let (mut game, _) = game();
+	game.set_tile(3, 5, tiles::WATER);
+	assert_eq!(
+		game.place_belt(Belt::new([3, 5], Rotation::East, BeltKind::Straight)),
+		Err(PlacementError::WrongTerrain([3, 5], tiles::WATER)),
+	);
+	assert_eq!(
+		game.place_inserter(Inserter::new([3, 5], Rotation::East)),
+		Err(PlacementError::WrongTerrain([3, 5], tiles::WATER)),
+	);
+
+	let cost = |game: &Game| game.world.resource::<Pathfinder>().unwrap().grid().cost([1, 5]);
+	let grass = cost(&game);
+	let belts = line(&mut game, [0, 5], Rotation::East, 3);
+	assert_eq!(cost(&game), grass);
+	let crossing = game.world.resource_mut::<Pathfinder>().unwrap().find_path([1, 4], [1, 6]);
+	assert!(matches!(crossing, PathResult::Found(path) if path.tiles.contains(&[1, 5])));
+	game.remove_conveyor(belts[1]);
+	assert_eq!(cost(&game), grass);
+}
+
+#[test]
+fn removing_a_belt_returns_its_stacks() {
+	let (mut game, _) = game();
+	let belts = line(&mut game, [0, 5], Rotation::East, 3);
+	let stack = Stack { item: ItemId(0), amount: 4 };
+	assert!(network_mut(&mut game).insert([1, 5], stack));
+	assert!(network_mut(&mut game).insert([2, 5], stack));
+	assert_eq!(game.place_belt(Belt::new([1, 5], Rotation::North, BeltKind::Straight)), Err(PlacementError::Occupied([1, 5], belts[1])));
+
+	assert_eq!(game.remove_conveyor(belts[1]), Some(vec![stack]));
+	assert_eq!(game.remove_conveyor(belts[1]), None);
+	assert_ne!(network(&game).segment_of(belts[0]), network(&game).segment_of(belts[2]));
+	assert_eq!(amounts(&game, &[[2, 5]]), [4]);
+
+	// Nothing is after the last belt, so its stack waits at the end.
+	run(&mut game, 120);
+	assert_eq!(amounts(&game, &[[2, 5]]), [4]);
+	belt(&mut game, [1, 5], Rotation::East, BeltKind::Straight);
+	assert_eq!(network(&game).segment_count(), 1);
+	assert!(network_mut(&mut game).insert([0, 5], stack));
+	run(&mut game, 120);
+	assert_eq!(amounts(&game, &[[2, 5]]), [4, 4]);
+}
+
+#[test]
+fn belts_carry_on_identically_after_loading() {
+	let (mut game, content) = game();
+	let ore = content.item_id("iron_ore").unwrap();
+	line(&mut game, [0, 5], Rotation::East, 3);
+	belt(&mut game, [3, 5], Rotation::East, BeltKind::Splitter);
+	line(&mut game, [3, 6], Rotation::North, 3);
+	line(&mut game, [3, 4], Rotation::South, 3);
+	let store = game.place_building(content.building_id("stockpile").unwrap(), [0, 1], Rotation::North).unwrap();
+	let mut stockpile = Stockpile::new(100);
+	stockpile.add(Resource::Item(ore), 20);
+	game.world.insert(store, stockpile);
+	let inserter = game.place_inserter(Inserter::new([0, 4], Rotation::North)).unwrap();
+	run(&mut game, 400);
+
+	let thumbnail = save::thumbnail(&game.tilemap, tiles::color);
+	let mut loaded = save::decode(&save::encode(&game, &thumbnail)).unwrap();
+	loaded.set_content(content);
+	assert_eq!(loaded.world.get::<Inserter>(inserter), game.world.get::<Inserter>(inserter));
+	let tiles = [[0, 5], [1, 5], [2, 5], [3, 5], [3, 6], [3, 7], [3, 8], [3, 4], [3, 3], [3, 2]];
+	run(&mut game, 1);
+	run(&mut loaded, 1);
+	assert_eq!(amounts(&loaded, &tiles), amounts(&game, &tiles));
+
+	run(&mut game, 1000);
+	run(&mut loaded, 1000);
+	for tile in tiles {
+		assert_eq!(network(&loaded).stacks_at(tile), network(&game).stacks_at(tile));
+	}
+	assert_eq!(loaded.world.get::<Stockpile>(store), game.world.get::<Stockpile>(store));
+	assert_eq!(amounts(&game, &tiles).len(), 20);
+}
@@ -0,0 +1,179 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use frontier_outpost::config::SimulationConfig;
+use frontier_outpost::content::{Content, DEFAULT_CONTENT_DIR};
+use frontier_outpost::crafting::{self, Crafter, CraftingStatus};
+use frontier_outpost::economy::{Ledger, Resource, Stockpile};
+use frontier_outpost::ecs::Entity;
+use frontier_outpost::game::Game;
+use frontier_outpost::save;
+use frontier_outpost::simulation;
+use frontier_outpost::worldgen::tiles;
+
+fn game() -> (Game, Arc<Content>) {
+	let content = Arc::new(Content::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join(DEFAULT_CONTENT_DIR)).unwrap());
+	let mut game = Game::new();
+	game.set_content(content.clone());
+	(game, content)
+}
+
+fn item(content: &Content, id: &str) -> Resource {
+	Resource::Item(content.item_id(id).unwrap())
+}
+
+/// Spawns a crafter for `recipe`, with `power` kilojoules stored on its entity if given.
+fn spawn(game: &mut Game, content: &Content, recipe: &str, power: Option<u64>) -> Entity {
+	let entity = game.world.spawn();
+	let crafter = Crafter::new(content, content.recipe_id(recipe).unwrap()).unwrap();
+	game.world.insert(entity, crafter);
+	if let Some(power) = power {
+		let mut stockpile = Stockpile::new(power);
+		stockpile.add(Resource::Power, power);
+		game.world.insert(entity, stockpile);
+	}
+	entity
+}
+
+fn link(game: &mut Game, from: Entity, to: Entity) {
+	game.world.get_mut::<Crafter>(from).unwrap().output_to = Some(to);
+}
+
+fn crafter(game: &Game, entity: Entity) -> &Crafter {
+	game.world.get::<Crafter>(entity).unwrap()
+}
+
+/// Runs `ticks` ticks, keeping the input buffer of `mine` full of `ore` as if a drill fed it.
+fn run_fed(game: &mut Game, mine: Entity, ore: Resource, ticks: u64) {
+	for _ in 0..ticks {
+		let crafter = game.world.get_mut::<Crafter>(mine).unwrap();
+		let room = crafter.input.free();
+		crafter.input.add(ore, room);
+		simulation::run_ticks(game, &SimulationConfig::default(), 1);
+	}
+}
+
+#[test]
+fn energy_is_rounded_up_to_whole_kilojoules() {
+	let (_, content) = game();
+	let recipe = |id| content.recipe(content.recipe_id(id).unwrap()).unwrap();
+	// 90 kW for 3.2 s, which is not exact as a float.
+	assert_eq!(crafting::energy(recipe("iron_plate")), 288);
+	// 75 kW for half a second.
+	assert_eq!(crafting::energy(recipe("gear")), 38);
+	assert_eq!(crafting::energy(recipe("hand_gear")), 0);
+}
+
+#[test]
+fn chains_turn_ore_into_plates_into_gears() {
+	let (mut game, content) = game();
+	let (ore, plate, gear) = (item(&content, "iron_ore"), item(&content, "iron_plate"), item(&content, "gear"));
+	let smelter = spawn(&mut game, &content, "iron_plate", Some(10_000));
+	let workshop = spawn(&mut game, &content, "gear", Some(1_000));
+	let store = game.world.spawn();
+	game.world.insert(store, Stockpile::new(100));
+	link(&mut game, smelter, workshop);
+	link(&mut game, workshop, store);
+
+	// Plates take 192 ticks and are finished on ticks 193, 385, 577, 769 and 961. Gears take
+	// two plates and 30 ticks, so are finished on ticks 416 and 800.
+	run_fed(&mut game, smelter, ore, 1000);
+	assert_eq!(game.world.get::<Stockpile>(store).unwrap().get(gear), 2);
+	assert_eq!(crafter(&game, workshop).input.get(plate), 1);
+	assert_eq!(crafter(&game, workshop).status, CraftingStatus::Starved(plate));
+	assert_eq!(crafter(&game, smelter).status, CraftingStatus::Crafting);
+	assert_eq!(crafter(&game, smelter).progress(), Some(39));
+
+	// Six plates started, two gears.
+	assert_eq!(game.world.get::<Stockpile>(smelter).unwrap().get(Resource::Power), 10_000 - 6 * 288);
+	assert_eq!(game.world.get::<Stockpile>(workshop).unwrap().get(Resource::Power), 1_000 - 2 * 38);
+	let ledger = game.world.resource::<Ledger>().unwrap();
+	assert_eq!((ledger.current().expense(ore), ledger.current().income(plate)), (6, 5));
+	assert_eq!((ledger.current().expense(plate), ledger.current().income(gear)), (4, 2));
+}
+
+#[test]
+fn crafters_starve_without_inputs_or_power() {
+	let (mut game, content) = game();
+	let (ore, plate) = (item(&content, "iron_ore"), item(&content, "iron_plate"));
+	let unpowered = spawn(&mut game, &content, "iron_plate", None);
+	let low = spawn(&mut game, &content, "iron_plate", Some(300));
+	for smelter in [unpowered, low] {
+		game.world.get_mut::<Crafter>(smelter).unwrap().input.add(ore, 2);
+	}
+	let by_hand = spawn(&mut game, &content, "hand_gear", None);
+	game.world.get_mut::<Crafter>(by_hand).unwrap().input.add(plate, 2);
+
+	simulation::run_ticks(&mut game, &SimulationConfig::default(), 400);
+	assert_eq!(crafter(&game, unpowered).status, CraftingStatus::Starved(Resource::Power));
+	assert_eq!(crafter(&game, unpowered).input.get(ore), 2);
+	// Enough power for one plate, the second waits for more.
+	assert_eq!(crafter(&game, low).status, CraftingStatus::Starved(Resource::Power));
+	assert_eq!((crafter(&game, low).input.get(ore), crafter(&game, low).output.get(plate)), (1, 1));
+	// Hand recipes need no power, only their inputs.
+	assert_eq!(crafter(&game, by_hand).output.get(item(&content, "gear")), 1);
+	assert_eq!(crafter(&game, by_hand).status, CraftingStatus::Starved(plate));
+}
+
+#[test]
+fn full_output_buffers_block_crafting() {
+	let (mut game, content) = game();
+	let (ore, plate) = (item(&content, "iron_ore"), item(&content, "iron_plate"));
+	let smelter = spawn(&mut game, &content, "iron_plate", Some(10_000));
+
+	// Two plates fill the buffer, the third is finished on tick 577 but cannot be put out.
+	run_fed(&mut game, smelter, ore, 600);
+	assert_eq!(crafter(&game, smelter).output.get(plate), 2);
+	assert_eq!(crafter(&game, smelter).status, CraftingStatus::Blocked(plate));
+	assert_eq!(crafter(&game, smelter).progress(), Some(192));
+	assert_eq!(game.world.resource::<Ledger>().unwrap().current().income(plate), 2);
+
+	game.world.get_mut::<Crafter>(smelter).unwrap().output.remove(plate, 1);
+	run_fed(&mut game, smelter, ore, 1);
+	assert_eq!(crafter(&game, smelter).output.get(plate), 2);
+	assert_eq!(crafter(&game, smelter).status, CraftingStatus::Crafting);
+	assert_eq!(crafter(&game, smelter).progress(), Some(0));
+}
+
+#[test]
+fn inputs_are_shared_fairly_between_ingredients() {
+	let (mut game, content) = game();
+	let (plate, wire) = (item(&content, "iron_plate"), item(&content, "copper_wire"));
+	let workshop = spawn(&mut game, &content, "circuit", Some(1_000));
+	let recipe = content.recipe(content.recipe_id("circuit").unwrap()).unwrap();
+	let crafter = game.world.get_mut::<Crafter>(workshop).unwrap();
+	assert_eq!(crafter.input.capacity(), 8);
+	assert_eq!(crafter.room_for(recipe, content.item_id("iron_plate").unwrap()), 2);
+	crafter.input.add(plate, 2);
+	assert_eq!(crafter.room_for(recipe, content.item_id("iron_plate").unwrap()), 0);
+	assert_eq!(crafter.room_for(recipe, content.item_id("copper_wire").unwrap()), 6);
+	crafter.input.add(wire, 6);
+	assert_eq!(crafter.room_for(recipe, content.item_id("gear").unwrap()), 0);
+}
+
+#[test]
+fn chains_carry_on_identically_after_loading() {
+	let (mut game, content) = game();
+	let (ore, gear) = (item(&content, "iron_ore"), item(&content, "gear"));
+	let smelter = spawn(&mut game, &content, "iron_plate", Some(10_000));
+	let workshop = spawn(&mut game, &content, "gear", Some(1_000));
+	let store = game.world.spawn();
+	game.world.insert(store, Stockpile::new(100));
+	link(&mut game, smelter, workshop);
+	link(&mut game, workshop, store);
+	run_fed(&mut game, smelter, ore, 500);
+
+	let thumbnail = save::thumbnail(&game.tilemap, tiles::color);
+	let mut loaded = save::decode(&save::encode(&game, &thumbnail)).unwrap();
+	loaded.set_content(content.clone());
+	assert_eq!(crafter(&loaded, workshop).output_to, Some(store));
+	assert_eq!(crafter(&loaded, smelter).progress(), crafter(&game, smelter).progress());
+
+	run_fed(&mut game, smelter, ore, 1000);
+	run_fed(&mut loaded, smelter, ore, 1000);
+	for entity in [smelter, workshop] {
+		assert_eq!(crafter(&loaded, entity), crafter(&game, entity));
+	}
+	assert_eq!(loaded.world.get::<Stockpile>(store), game.world.get::<Stockpile>(store));
+	assert_eq!(game.world.get::<Stockpile>(store).unwrap().get(gear), 3);
+}
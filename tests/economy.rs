@@ -0,0 +1,172 @@
+use std::path::Path;
+
+use frontier_outpost::config::SimulationConfig;
+use frontier_outpost::content::{Content, DEFAULT_CONTENT_DIR};
+use frontier_outpost::economy::{HISTORY_MINUTES, Ledger, Producer, ProducerStatus, Rate, Resource, Stockpile};
+use frontier_outpost::ecs::Entity;
+use frontier_outpost::game::Game;
+use frontier_outpost::save;
+use frontier_outpost::simulation;
+use frontier_outpost::worldgen::tiles;
+
+const MINUTE: u64 = 60 * 60;
+
+fn item(id: &str) -> Resource {
+	let content = Content::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join(DEFAULT_CONTENT_DIR)).unwrap();
+	Resource::Item(content.item_id(id).unwrap())
+}
+
+fn spawn(game: &mut Game, stockpile: Stockpile, producer: Producer) -> Entity {
+	let entity = game.world.spawn();
+	game.world.insert(entity, stockpile);
+	game.world.insert(entity, producer);
+	entity
+}
+
+fn run(game: &mut Game, ticks: u64) {
+	simulation::run_ticks(game, &SimulationConfig::default(), ticks);
+}
+
+#[test]
+fn stockpiles_share_their_capacity() {
+	let (wood, stone) = (item("wood"), item("stone"));
+	let mut stockpile = Stockpile::new(10);
+	assert_eq!(stockpile.add(wood, 7), 7);
+	assert_eq!(stockpile.add(stone, 5), 3);
+	assert_eq!((stockpile.total(), stockpile.free()), (10, 0));
+	assert_eq!(stockpile.add(Resource::Power, 1), 0);
+
+	assert_eq!(stockpile.remove(wood, 10), 7);
+	assert_eq!(stockpile.remove(Resource::Power, 1), 0);
+	assert_eq!(stockpile.iter().collect::<Vec<_>>(), [(stone, 3)]);
+
+	stockpile.set_capacity(2);
+	assert_eq!((stockpile.get(stone), stockpile.free()), (3, 0));
+}
+
+#[test]
+fn producers_make_exact_amounts() {
+	let food = item("food");
+	let mut game = Game::new();
+	let farm = spawn(&mut game, Stockpile::new(100), Producer::new().producing(food, Rate::per_minute(30)));
+	let generator = spawn(&mut game, Stockpile::new(100), Producer::new().producing(Resource::Power, Rate::per_second(3)));
+
+	run(&mut game, 119);
+	assert_eq!(game.world.get::<Stockpile>(farm).unwrap().get(food), 0);
+	run(&mut game, 1);
+	assert_eq!(game.world.get::<Stockpile>(farm).unwrap().get(food), 1);
+	run(&mut game, 480);
+	assert_eq!(game.world.get::<Stockpile>(farm).unwrap().get(food), 5);
+	assert_eq!(game.world.get::<Stockpile>(generator).unwrap().get(Resource::Power), 30);
+	assert_eq!(game.world.get::<Producer>(farm).unwrap().status, ProducerStatus::Running);
+}
+
+#[test]
+fn starved_producers_pause() {
+	let (ore, plate) = (item("iron_ore"), item("iron_plate"));
+	let mut game = Game::new();
+	let mut stockpile = Stockpile::new(100);
+	stockpile.add(ore, 3);
+	let smelter = spawn(
+		&mut game,
+		stockpile,
+		Producer::new().consuming(ore, Rate::per_second(1)).producing(plate, Rate::per_second(1)),
+	);
+
+	run(&mut game, 300);
+	let stockpile = game.world.get::<Stockpile>(smelter).unwrap();
+	assert_eq!((stockpile.get(ore), stockpile.get(plate)), (0, 3));
+	assert_eq!(game.world.get::<Producer>(smelter).unwrap().status, ProducerStatus::Starved(ore));
+
+	// Delivering more ore starts it up again.
+	game.world.get_mut::<Stockpile>(smelter).unwrap().add(ore, 1);
+	run(&mut game, 1);
+	let stockpile = game.world.get::<Stockpile>(smelter).unwrap();
+	assert_eq!((stockpile.get(ore), stockpile.get(plate)), (0, 4));
+	assert_eq!(game.world.get::<Producer>(smelter).unwrap().status, ProducerStatus::Running);
+}
+
+#[test]
+fn full_stockpiles_block_producers() {
+	let food = item("food");
+	let mut game = Game::new();
+	let farm = spawn(&mut game, Stockpile::new(4), Producer::new().producing(food, Rate::per_second(1)));
+
+	run(&mut game, 600);
+	assert_eq!(game.world.get::<Stockpile>(farm).unwrap().get(food), 4);
+	assert_eq!(game.world.get::<Producer>(farm).unwrap().status, ProducerStatus::Blocked(food));
+	assert_eq!(game.world.resource::<Ledger>().unwrap().current().income(food), 4);
+}
+
+#[test]
+fn ledger_keeps_minute_by_minute_history() {
+	let food = item("food");
+	let mut game = Game::new();
+	spawn(&mut game, Stockpile::new(1000), Producer::new().producing(food, Rate::per_minute(30)));
+	let mut pantry = Stockpile::new(1000);
+	pantry.add(food, 100);
+	spawn(&mut game, pantry, Producer::new().consuming(food, Rate::per_minute(10)));
+
+	run(&mut game, 3 * MINUTE);
+	let ledger = game.world.resource::<Ledger>().unwrap();
+	let history = ledger.history().collect::<Vec<_>>();
+	assert_eq!(history.len(), 3);
+	for record in &history {
+		assert_eq!((record.income(food), record.expense(food)), (30, 10));
+	}
+	assert_eq!(history.iter().map(|record| record.stock(food)).collect::<Vec<_>>(), [120, 140, 160]);
+	assert_eq!(ledger.net_history(food).collect::<Vec<_>>(), [20, 20, 20]);
+	assert_eq!(ledger.net_history(item("wood")).collect::<Vec<_>>(), [0, 0, 0]);
+	assert_eq!(ledger.current().income(food), 0);
+}
+
+#[test]
+fn history_is_limited_and_independent_of_tick_length() {
+	let food = item("food");
+	let mut game = Game::new();
+	spawn(&mut game, Stockpile::new(u64::MAX), Producer::new().producing(food, Rate::per_minute(30)));
+
+	let config = SimulationConfig { ticks_per_second: 1, ..SimulationConfig::default() };
+	simulation::run_ticks(&mut game, &config, 60 * (HISTORY_MINUTES as u64 + 5));
+	let ledger = game.world.resource::<Ledger>().unwrap();
+	assert_eq!(ledger.history().len(), HISTORY_MINUTES);
+	assert!(ledger.history().all(|record| record.income(food) == 30));
+	assert_eq!(ledger.history().last().unwrap().stock(food), 30 * (HISTORY_MINUTES as u64 + 5));
+}
+
+#[test]
+fn economies_are_deterministic_and_saved() {
+	let (ore, plate) = (item("iron_ore"), item("iron_plate"));
+	let mut game = Game::new();
+	let mut stockpile = Stockpile::new(200);
+	stockpile.add(ore, 20);
+	stockpile.add(Resource::Power, 100);
+	let smelter = spawn(
+		&mut game,
+		stockpile,
+		Producer::new()
+			.consuming(ore, Rate::per_minute(40))
+			.consuming(Resource::Power, Rate::per_second(2))
+			.producing(plate, Rate::per_minute(40)),
+	);
+	spawn(&mut game, Stockpile::new(500), Producer::new().producing(Resource::Power, Rate::per_second(1)));
+	run(&mut game, MINUTE + 1234);
+
+	let thumbnail = save::thumbnail(&game.tilemap, tiles::color);
+	let mut loaded = save::decode(&save::encode(&game, &thumbnail)).unwrap();
+	assert_eq!(loaded.world.get::<Stockpile>(smelter), game.world.get::<Stockpile>(smelter));
+	assert_eq!(loaded.world.resource::<Ledger>(), game.world.resource::<Ledger>());
+
+	run(&mut game, 2 * MINUTE);
+	run(&mut loaded, 2 * MINUTE);
+	for (entity, stockpile) in game.world.query::<Stockpile>() {
+		assert_eq!(loaded.world.get::<Stockpile>(entity), Some(stockpile));
+		assert_eq!(loaded.world.get::<Producer>(entity), game.world.get::<Producer>(entity));
+	}
+	let ledger = game.world.resource::<Ledger>().unwrap();
+	assert_eq!(loaded.world.resource::<Ledger>(), Some(ledger));
+	assert_eq!(ledger.history().map(|record| record.income(plate)).sum::<u64>(), 20);
+	// Power is drawn every half second, twice more before the 21st ore would have been due.
+	assert_eq!(ledger.history().map(|record| record.expense(Resource::Power)).sum::<u64>(), 62);
+	assert_eq!(game.world.get::<Producer>(smelter).unwrap().status, ProducerStatus::Starved(ore));
+}
@@ -0,0 +1,109 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use frontier_outpost::components::{Position, Velocity};
+use frontier_outpost::config::SimulationConfig;
+use frontier_outpost::ecs::{Schedule, World};
+use frontier_outpost::game::Game;
+use frontier_outpost::simulation;
+
+#[derive(Debug, PartialEq)]
+struct Health(u32);
+
+#[derive(Debug, PartialEq)]
+struct Name(&'static str);
+
+#[test]
+fn despawned_handles_go_stale() {
+	let mut world = World::new();
+	let first = world.spawn();
+	world.insert(first, Health(10));
+	assert!(world.despawn(first));
+	assert!(!world.despawn(first));
+
+	// The index is reused, the old handle does not see the new entity's components.
+	let second = world.spawn();
+	assert_eq!(second.index(), first.index());
+	assert!(world.is_alive(second) && !world.is_alive(first));
+	world.insert(second, Health(20));
+	assert_eq!(world.get::<Health>(first), None);
+	assert_eq!(world.get::<Health>(second), Some(&Health(20)));
+	assert_eq!(world.len(), 1);
+}
+
+#[test]
+fn queries_match_entities_with_every_component() {
+	let mut world = World::new();
+	let entities = (0..4).map(|_| world.spawn()).collect::<Vec<_>>();
+	for (index, &entity) in entities.iter().enumerate() {
+		world.insert(entity, Health(index as u32));
+	}
+	world.insert(entities[1], Name("drill"));
+	world.insert(entities[3], Name("hauler"));
+	world.despawn(entities[0]);
+
+	let healths = world.query::<Health>().map(|(_, health)| health.0).collect::<Vec<_>>();
+	assert_eq!(healths, [3, 1, 2]);
+
+	let named = world.query2::<Name, Health>().map(|(_, name, health)| (name.0, health.0)).collect::<Vec<_>>();
+	assert_eq!(named, [("drill", 1), ("hauler", 3)]);
+
+	world.query2_mut::<Health, Name>(|_, health, name| {
+		health.0 += 100;
+		name.0 = "renamed";
+	});
+	assert_eq!(world.get::<Health>(entities[2]), Some(&Health(2)));
+	assert_eq!(world.get::<Health>(entities[3]), Some(&Health(103)));
+	assert_eq!(world.get::<Name>(entities[1]), Some(&Name("renamed")));
+
+	assert_eq!(world.remove::<Name>(entities[1]), Some(Name("renamed")));
+	assert!(!world.has::<Name>(entities[1]));
+}
+
+#[test]
+fn resources_are_looked_up_by_type() {
+	let mut world = World::new();
+	assert_eq!(world.resource::<Health>(), None);
+	world.insert_resource(Health(5));
+	world.resource_mut::<Health>().unwrap().0 += 1;
+	assert_eq!(world.insert_resource(Health(0)), Some(Health(6)));
+	assert_eq!(world.remove_resource::<Health>(), Some(Health(0)));
+}
+
+#[test]
+fn systems_run_in_schedule_order() {
+	let order = Rc::new(RefCell::new(Vec::new()));
+	let system = |name: &'static str| {
+		let order = order.clone();
+		move |_: &mut World, _: Duration| order.borrow_mut().push(name)
+	};
+
+	let mut schedule = Schedule::new();
+	schedule.add("b", system("b"));
+	schedule.add("d", system("d"));
+	schedule.add_before("a", "b", system("a"));
+	schedule.add_after("c", "b", system("c"));
+	assert_eq!(schedule.names().collect::<Vec<_>>(), ["a", "b", "c", "d"]);
+
+	schedule.run(&mut World::new(), Duration::ZERO);
+	assert_eq!(*order.borrow(), ["a", "b", "c", "d"]);
+}
+
+#[test]
+fn game_tick_moves_entities() {
+	let mut game = Game::new();
+	let entity = game.world.spawn();
+	game.world.insert(entity, Position::new([1.0, 2.0]));
+	game.world.insert(entity, Velocity([3.0, -1.0]));
+
+	let config = SimulationConfig::default();
+	simulation::run_ticks(&mut game, &config, config.ticks_per_second as u64);
+
+	let position = game.world.get::<Position>(entity).unwrap();
+	assert!((position.current[0] - 4.0).abs() < 1e-3 && (position.current[1] - 1.0).abs() < 1e-3);
+	// Half way through the next tick.
+	let step = 1.0 / config.ticks_per_second as f32;
+	let interpolated = position.interpolated(0.5);
+	assert!((interpolated[0] - (4.0 - 1.5 * step)).abs() < 1e-3);
+}
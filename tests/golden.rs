@@ -0,0 +1,309 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use frontier_outpost::renderer::{
+	Align, AtlasBuilder, Image, Renderer, Sprite, TextSpan, TextStyle, TileSet, UvRect,
+};
+use frontier_outpost::building::{BuildingCatalog, BuildingDef, ConstructionSite, Rotation};
+use frontier_outpost::components::{Position, Renderable};
+use frontier_outpost::game::Game;
+use frontier_outpost::tilemap::{Tile, Tilemap};
+
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 64;
+
+/// Maximum allowed difference per colour channel, to absorb rasterizer differences between
+/// software adapters.
+const TOLERANCE: u8 = 2;
+
+fn golden_path(name: &str) -> PathBuf {
+	Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{name}.png"))
+}
+
+fn output_path(name: &str) -> PathBuf {
+	Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden").join(format!("{name}.png"))
+}
+
+fn save_png(path: &Path, image: &Image) {
+	fs::create_dir_all(path.parent().unwrap()).unwrap();
+	let mut encoder = png::Encoder::new(BufWriter::new(File::create(path).unwrap()), image.width, image.height);
+	encoder.set_color(png::ColorType::Rgba);
+	encoder.set_depth(png::BitDepth::Eight);
+	encoder.write_header().unwrap().write_image_data(&image.pixels).unwrap();
+}
+
+/// Compares a rendered frame against `tests/golden/<name>.png`.
+///
+/// Set `UPDATE_GOLDEN=1` to (re)write the reference image instead. On mismatch the actual frame
+/// and a diff image, with differing pixels in red, are written next to the test binaries.
+fn assert_golden(name: &str, actual: Image) {
+	let golden = golden_path(name);
+	if env::var_os("UPDATE_GOLDEN").is_some() {
+		save_png(&golden, &actual);
+		return;
+	}
+	assert!(golden.exists(), "missing {}, run with UPDATE_GOLDEN=1 to create it", golden.display());
+
+	let expected = Image::load_png(&golden).unwrap();
+	assert_eq!(
+		(actual.width, actual.height),
+		(expected.width, expected.height),
+		"{name} size differs from reference",
+	);
+
+	let mut diff = Vec::with_capacity(actual.pixels.len());
+	let mut mismatched = 0;
+	for (a, e) in actual.pixels.chunks(4).zip(expected.pixels.chunks(4)) {
+		if a.iter().zip(e).any(|(a, e)| a.abs_diff(*e) > TOLERANCE) {
+			mismatched += 1;
+			diff.extend_from_slice(&[255, 0, 0, 255]);
+		} else {
+			diff.extend_from_slice(&[e[0] / 4, e[1] / 4, e[2] / 4, 255]);
+		}
+	}
+
+	if mismatched > 0 {
+		let actual_path = output_path(&format!("{name}.actual"));
+		let diff_path = output_path(&format!("{name}.diff"));
+		save_png(&actual_path, &actual);
+		save_png(&diff_path, &Image { width: actual.width, height: actual.height, pixels: diff });
+		panic!(
+			"{name}: {mismatched} pixels differ from reference by more than {TOLERANCE}, see {} and {}",
+			actual_path.display(),
+			diff_path.display(),
+		);
+	}
+}
+
+fn render(renderer: &mut Renderer) -> Image {
+	renderer.render().unwrap();
+
+	let size = renderer.target().size();
+	Image {
+		width: size.width,
+		height: size.height,
+		pixels: renderer.read_pixels().unwrap(),
+	}
+}
+
+#[async_std::test]
+async fn white_quad() {
+	let mut renderer = Renderer::new_headless(WIDTH, HEIGHT).await.unwrap();
+	renderer.draw_sprite(Sprite::new(renderer.white_texture(), [0.0, 0.0], [1.0, 1.0]));
+	assert_golden("white_quad", render(&mut renderer));
+}
+
+#[async_std::test]
+async fn sprite_batch() {
+	let mut renderer = Renderer::new_headless(WIDTH, HEIGHT).await.unwrap();
+	let checker = renderer.add_texture("checker", 2, 2, &[
+		255, 0, 0, 255, 0, 0, 255, 255,
+		0, 0, 255, 255, 255, 0, 0, 255,
+	]);
+	let white = renderer.white_texture();
+
+	renderer.draw_sprite(Sprite {
+		layer: 1,
+		tint: [0.0, 1.0, 0.0, 1.0],
+		..Sprite::new(white, [0.25, 0.25], [0.5, 0.5])
+	});
+	renderer.draw_sprite(Sprite::new(checker, [-0.5, 0.5], [0.75, 0.75]));
+	renderer.draw_sprite(Sprite {
+		rotation: std::f32::consts::FRAC_PI_4,
+		..Sprite::new(white, [0.0, 0.0], [0.75, 0.75])
+	});
+	renderer.draw_sprite(Sprite {
+		uv_rect: UvRect { min: [0.5, 0.0], max: [1.0, 0.5] },
+		..Sprite::new(checker, [0.5, -0.5], [0.5, 0.5])
+	});
+
+	assert_golden("sprite_batch", render(&mut renderer));
+}
+
+#[async_std::test]
+async fn skipped_frames_are_not_drawn_later() {
+	let mut renderer = Renderer::new_headless(WIDTH, HEIGHT).await.unwrap();
+	renderer.draw_sprite(Sprite {
+		rotation: std::f32::consts::FRAC_PI_4,
+		tint: [1.0, 0.0, 0.0, 1.0],
+		..Sprite::new(renderer.white_texture(), [0.5, 0.5], [1.5, 1.5])
+	});
+	renderer.skip_frame();
+	renderer.draw_sprite(Sprite::new(renderer.white_texture(), [0.0, 0.0], [1.0, 1.0]));
+	assert_golden("white_quad", render(&mut renderer));
+}
+
+fn solid(width: u32, height: u32, color: [u8; 4]) -> Image {
+	Image {
+		width,
+		height,
+		pixels: color.repeat((width * height) as usize),
+	}
+}
+
+#[async_std::test]
+async fn atlas() {
+	let mut renderer = Renderer::new_headless(WIDTH, HEIGHT).await.unwrap();
+
+	// Small pages so the sprites spill over onto a second page.
+	let mut builder = AtlasBuilder::new(16);
+	builder.add("red", solid(8, 8, [255, 0, 0, 255])).unwrap();
+	builder.add("green", solid(4, 12, [0, 255, 0, 255])).unwrap();
+	builder.add("blue", solid(12, 4, [0, 0, 255, 255])).unwrap();
+	builder.add("yellow", solid(6, 6, [255, 255, 0, 255])).unwrap();
+	let atlas = builder.build(&mut renderer);
+	assert_eq!(atlas.pages().len(), 2);
+
+	renderer.draw_sprite(atlas.sprite("red", [-0.5, 0.5], [0.5, 0.5]).unwrap());
+	renderer.draw_sprite(atlas.sprite("green", [0.5, 0.5], [0.25, 0.75]).unwrap());
+	renderer.draw_sprite(atlas.sprite("blue", [-0.5, -0.5], [0.75, 0.25]).unwrap());
+	renderer.draw_sprite(atlas.sprite("yellow", [0.5, -0.5], [0.375, 0.375]).unwrap());
+
+	assert_golden("atlas", render(&mut renderer));
+}
+
+#[async_std::test]
+async fn camera() {
+	// A wide target, so a stretched projection would turn the squares into rectangles.
+	let mut renderer = Renderer::new_headless(WIDTH * 2, HEIGHT).await.unwrap();
+	let white = renderer.white_texture();
+
+	let camera = renderer.camera_mut();
+	camera.position = [1.0, 0.5];
+	camera.zoom_at(0.5, [0.0, 0.0]);
+
+	renderer.draw_sprite(Sprite::new(white, [0.0, 0.0], [1.0, 1.0]));
+	renderer.draw_sprite(Sprite {
+		tint: [1.0, 0.0, 0.0, 1.0],
+		..Sprite::new(white, [3.0, 1.0], [2.0, 2.0])
+	});
+
+	assert_golden("camera", render(&mut renderer));
+}
+
+#[async_std::test]
+async fn tilemap() {
+	let mut renderer = Renderer::new_headless(WIDTH, HEIGHT).await.unwrap();
+
+	let mut builder = AtlasBuilder::new(16);
+	builder.add("grass", solid(2, 2, [0, 255, 0, 255])).unwrap();
+	builder.add("water", solid(2, 2, [0, 0, 255, 255])).unwrap();
+	builder.add("road", solid(2, 2, [255, 255, 255, 255])).unwrap();
+	let atlas = builder.build(&mut renderer);
+	renderer.set_tileset(TileSet::from_atlas(&atlas, ["grass", "water", "road"]).unwrap());
+
+	// Two by two chunks, the outer ones only partly inside the map.
+	let mut tilemap = Tilemap::new(40, 40);
+	for y in 0..40 {
+		for x in 0..40 {
+			let tile = if (x / 4 + y / 4) % 2 == 0 { Tile(1) } else { Tile(2) };
+			tilemap.set(x, y, tile);
+		}
+	}
+
+	// Centred on the corner shared by all four chunks, with the map edge in view.
+	let camera = renderer.camera_mut();
+	camera.position = [34.0, 34.0];
+	camera.set_zoom(0.125);
+
+	renderer.draw_tilemap(&tilemap);
+	assert_golden("tilemap", render(&mut renderer));
+
+	// Only the two chunks the road crosses are rebuilt for the change to show.
+	for x in 28..36 {
+		tilemap.set(x, 30, Tile(3));
+	}
+	renderer.draw_tilemap(&tilemap);
+	assert_golden("tilemap_changed", render(&mut renderer));
+}
+
+#[async_std::test]
+async fn text() {
+	let mut renderer = Renderer::new_headless(WIDTH, HEIGHT).await.unwrap();
+	let font_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/fonts/DejaVuSansMono.ttf");
+	let font = renderer.load_font(&font_path).unwrap();
+	let white = renderer.white_texture();
+
+	// World sprite showing through a translucent panel.
+	renderer.draw_sprite(Sprite {
+		tint: [0.0, 0.0, 1.0, 1.0],
+		..Sprite::new(white, [0.0, 0.0], [1.0, 1.0])
+	});
+	renderer.draw_ui_sprite(Sprite {
+		tint: [1.0, 1.0, 1.0, 0.5],
+		layer: -1,
+		..Sprite::new(white, [32.0, 32.0], [48.0, 16.0])
+	});
+
+	let mut style = TextStyle::new(font, 12.0);
+	let heading = renderer.layout_text(&[TextSpan::new("Hi\nok", [1.0; 4])], &style);
+	assert_eq!(heading.line_count(), 2);
+	renderer.draw_text(&heading, [2.0, 2.0]);
+
+	style.align = Align::Right;
+	style.wrap_width = Some(60.0);
+	let spans = [
+		TextSpan::new("red ", [1.0, 0.0, 0.0, 1.0]),
+		TextSpan::new("green wrap", [0.0, 1.0, 0.0, 1.0]),
+	];
+	let wrapped = renderer.layout_text(&spans, &style);
+	assert_eq!(wrapped.line_count(), 2);
+	renderer.draw_text(&wrapped, [2.0, 34.0]);
+
+	assert_golden("text", render(&mut renderer));
+}
+
+#[async_std::test]
+async fn entities() {
+	let mut renderer = Renderer::new_headless(WIDTH, HEIGHT).await.unwrap();
+	let white = renderer.white_texture();
+
+	let mut game = Game::new();
+	let still = game.world.spawn();
+	game.world.insert(still, Position::new([-0.5, 0.5]));
+	game.world.insert(still, Renderable::new(white, [0.5, 0.5]));
+
+	// Drawn half way between its previous and current positions, above the other sprite.
+	let moving = game.world.spawn();
+	game.world.insert(moving, Position { current: [1.0, -0.5], previous: [0.0, -0.5] });
+	game.world.insert(moving, Renderable {
+		tint: [1.0, 0.0, 0.0, 1.0],
+		layer: 1,
+		..Renderable::new(white, [0.75, 0.5])
+	});
+
+	game.draw(&mut renderer, 0.5);
+	assert_golden("entities", render(&mut renderer));
+}
+
+#[async_std::test]
+async fn buildings() {
+	let mut renderer = Renderer::new_headless(WIDTH, HEIGHT).await.unwrap();
+	let white = renderer.white_texture();
+
+	let mut game = Game::new();
+	let mut catalog = BuildingCatalog::new();
+	let hut = catalog.add(BuildingDef {
+		color: [255, 0, 0, 255],
+		..BuildingDef::new("hut", [2, 1])
+	});
+	game.world.insert_resource(catalog);
+
+	// The finished hut hides the blue ground, the construction site lets it show through.
+	let built = game.place_building(hut, [0, 0], Rotation::North).unwrap();
+	game.world.remove::<ConstructionSite>(built);
+	game.place_building(hut, [0, 1], Rotation::West).unwrap();
+	renderer.draw_sprite(Sprite {
+		tint: [0.0, 0.0, 1.0, 1.0],
+		layer: -2,
+		..Sprite::new(white, [1.0, 1.0], [2.0, 2.0])
+	});
+
+	let camera = renderer.camera_mut();
+	camera.position = [1.0, 1.0];
+	camera.set_zoom(0.5);
+	game.draw(&mut renderer, 0.0);
+	assert_golden("buildings", render(&mut renderer));
+}
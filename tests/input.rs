@@ -0,0 +1,143 @@
+use std::fs;
+use std::path::PathBuf;
+
+use frontier_outpost::input::{Action, ActionBindings, Binding, Bindings, Input};
+use winit::dpi::PhysicalPosition;
+use winit::event::{DeviceId, ElementState, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent};
+
+fn device() -> DeviceId {
+	// SAFETY: the id is only compared, never handed to the platform.
+	unsafe { DeviceId::dummy() }
+}
+
+#[allow(deprecated)]
+fn key(key: VirtualKeyCode, state: ElementState) -> WindowEvent<'static> {
+	WindowEvent::KeyboardInput {
+		device_id: device(),
+		input: KeyboardInput {
+			scancode: 0,
+			state,
+			virtual_keycode: Some(key),
+			modifiers: Default::default(),
+		},
+		is_synthetic: false,
+	}
+}
+
+#[allow(deprecated)]
+fn mouse(button: MouseButton, state: ElementState) -> WindowEvent<'static> {
+	WindowEvent::MouseInput {
+		device_id: device(),
+		state,
+		button,
+		modifiers: Default::default(),
+	}
+}
+
+#[allow(deprecated)]
+fn cursor_moved(x: f64, y: f64) -> WindowEvent<'static> {
+	WindowEvent::CursorMoved {
+		device_id: device(),
+		position: PhysicalPosition::new(x, y),
+		modifiers: Default::default(),
+	}
+}
+
+fn bindings_file(name: &str, contents: &str) -> PathBuf {
+	let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("bindings");
+	fs::create_dir_all(&dir).unwrap();
+	let path = dir.join(format!("{name}.toml"));
+	fs::write(&path, contents).unwrap();
+	path
+}
+
+#[test]
+fn presses_last_a_frame_and_holds_until_released() {
+	use ElementState::{Pressed, Released};
+
+	let mut input = Input::new(Bindings::default());
+	input.handle_event(&key(VirtualKeyCode::R, Pressed));
+	assert!(input.pressed(Action::Rotate) && input.held(Action::Rotate));
+	assert!(!input.released(Action::Rotate));
+
+	input.end_frame();
+	assert!(!input.pressed(Action::Rotate) && input.held(Action::Rotate));
+
+	// Key repeat while held is not a new press.
+	input.handle_event(&key(VirtualKeyCode::R, Pressed));
+	assert!(!input.pressed(Action::Rotate));
+
+	input.handle_event(&key(VirtualKeyCode::R, Released));
+	assert!(input.released(Action::Rotate) && !input.held(Action::Rotate));
+	input.end_frame();
+	assert!(!input.released(Action::Rotate));
+
+	// Letting go of one of two held bindings does not release the action.
+	input.handle_event(&key(VirtualKeyCode::A, Pressed));
+	input.handle_event(&key(VirtualKeyCode::Left, Pressed));
+	input.end_frame();
+	input.handle_event(&key(VirtualKeyCode::A, Released));
+	assert!(input.held(Action::PanLeft) && !input.released(Action::PanLeft));
+	input.handle_event(&key(VirtualKeyCode::Left, Released));
+	assert!(input.released(Action::PanLeft));
+}
+
+#[test]
+fn losing_focus_releases_everything() {
+	let mut input = Input::new(Bindings::default());
+	input.handle_event(&key(VirtualKeyCode::W, ElementState::Pressed));
+	input.handle_event(&mouse(MouseButton::Right, ElementState::Pressed));
+	input.end_frame();
+
+	input.handle_event(&WindowEvent::Focused(false));
+	assert!(!input.held(Action::PanUp) && !input.held(Action::DragCamera));
+	assert!(input.released(Action::PanUp) && input.released(Action::DragCamera));
+
+	// Pressing again afterwards is a fresh press.
+	input.end_frame();
+	input.handle_event(&key(VirtualKeyCode::W, ElementState::Pressed));
+	assert!(input.pressed(Action::PanUp));
+}
+
+#[test]
+fn the_cursor_moves_from_where_it_first_appears() {
+	let mut input = Input::new(Bindings::default());
+	input.handle_event(&cursor_moved(400.0, 300.0));
+	assert_eq!(input.cursor(), [400.0, 300.0]);
+	assert_eq!(input.cursor_delta(), [0.0, 0.0]);
+
+	input.handle_event(&cursor_moved(410.0, 295.0));
+	input.handle_event(&cursor_moved(415.0, 290.0));
+	assert_eq!(input.cursor_delta(), [15.0, -10.0]);
+	input.end_frame();
+	assert_eq!(input.cursor_delta(), [0.0, 0.0]);
+	assert_eq!(input.cursor(), [415.0, 290.0]);
+}
+
+#[test]
+fn bindings_files_override_some_actions() {
+	let path = bindings_file("partial", "[Rotate]\nkeys = [\"Q\"]\n\n[Build]\nmouse = [\"Right\"]\n");
+	let bindings = Bindings::load(&path).unwrap();
+	let defaults = Bindings::default();
+	assert_eq!(bindings.get(Action::Rotate), Some(&ActionBindings { keys: vec![VirtualKeyCode::Q], mouse: vec![] }));
+	assert_eq!(bindings.get(Action::Build).unwrap().mouse, [MouseButton::Right]);
+	assert_eq!(bindings.get(Action::PanLeft), defaults.get(Action::PanLeft));
+	assert_eq!(bindings.get(Action::Cancel), defaults.get(Action::Cancel));
+
+	let input = Input::new(bindings.clone());
+	assert!(input.is_bound(Action::Rotate, Binding::Key(VirtualKeyCode::Q)));
+	assert!(!input.is_bound(Action::Rotate, Binding::Key(VirtualKeyCode::R)));
+
+	// Saved bindings load back unchanged.
+	let saved = bindings_file("saved", "");
+	bindings.save(&saved).unwrap();
+	assert_eq!(Bindings::load(&saved).unwrap(), bindings);
+
+	let missing = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("bindings").join("missing.toml");
+	let _ = fs::remove_file(&missing);
+	assert_eq!(Bindings::load(&missing).unwrap(), defaults);
+
+	let unknown = bindings_file("unknown", "[Jump]\nkeys = [\"Space\"]\n");
+	let error = Bindings::load(&unknown).unwrap_err().to_string();
+	assert!(error.starts_with(&unknown.display().to_string()) && error.contains("Jump"), "{error}");
+}
@@ -0,0 +1,216 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use frontier_outpost::colonist::{Colonist, JobBoard, JobKind, Skill};
+use frontier_outpost::components::Position;
+use frontier_outpost::config::SimulationConfig;
+use frontier_outpost::content::{Content, DEFAULT_CONTENT_DIR};
+use frontier_outpost::crafting::{Crafter, CraftingStatus};
+use frontier_outpost::economy::{Ledger, Resource, Stockpile};
+use frontier_outpost::ecs::Entity;
+use frontier_outpost::game::Game;
+use frontier_outpost::logistics::{Demand, LogisticsReport, Pickup, Shortage};
+use frontier_outpost::save;
+use frontier_outpost::simulation;
+use frontier_outpost::worldgen::tiles;
+
+struct Items {
+	wood: Resource,
+	stone: Resource,
+	ore: Resource,
+	plate: Resource,
+}
+
+fn game() -> (Game, Arc<Content>, Items) {
+	let content = Arc::new(Content::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join(DEFAULT_CONTENT_DIR)).unwrap());
+	let item = |id| Resource::Item(content.item_id(id).unwrap());
+	let items = Items {
+		wood: item("wood"),
+		stone: item("stone"),
+		ore: item("iron_ore"),
+		plate: item("iron_plate"),
+	};
+	let mut game = Game::new();
+	game.set_content(content.clone());
+	(game, content, items)
+}
+
+fn at(game: &mut Game, [x, y]: [i32; 2]) -> Entity {
+	let entity = game.world.spawn();
+	game.world.insert(entity, Position::new([x as f32 + 0.5, y as f32 + 0.5]));
+	entity
+}
+
+fn spawn_stockpile(game: &mut Game, tile: [i32; 2], contents: &[(Resource, u64)]) -> Entity {
+	let entity = at(game, tile);
+	let mut stockpile = Stockpile::new(1000);
+	for &(resource, amount) in contents {
+		stockpile.add(resource, amount);
+	}
+	game.world.insert(entity, stockpile);
+	entity
+}
+
+fn spawn_demand(game: &mut Game, tile: [i32; 2], demand: Demand) -> Entity {
+	let entity = spawn_stockpile(game, tile, &[]);
+	game.world.insert(entity, demand);
+	entity
+}
+
+fn run(game: &mut Game, ticks: u64) {
+	simulation::run_ticks(game, &SimulationConfig::default(), ticks);
+}
+
+/// Pickups and destination of every haul job on the board, in the order they were posted.
+fn hauls(game: &Game) -> Vec<(Vec<Pickup>, Entity)> {
+	game.world.resource::<JobBoard>().unwrap().iter()
+		.filter_map(|(_, job)| match &job.kind {
+			JobKind::Haul { pickups, to } => Some((pickups.clone(), *to)),
+			_ => None,
+		})
+		.collect()
+}
+
+fn shortages(game: &Game) -> &[Shortage] {
+	&game.world.resource::<LogisticsReport>().unwrap().shortages
+}
+
+#[test]
+fn demands_gather_from_the_nearest_supplies_in_one_trip() {
+	let (mut game, _, Items { wood, .. }) = game();
+	let far = spawn_stockpile(&mut game, [2, 0], &[(wood, 40)]);
+	let near = spawn_stockpile(&mut game, [18, 0], &[(wood, 10)]);
+	let site = spawn_demand(&mut game, [20, 0], Demand::new(1).want(wood, 30));
+
+	run(&mut game, 1);
+	assert_eq!(hauls(&game), [(
+		vec![Pickup { from: near, resource: wood, amount: 10 }, Pickup { from: far, resource: wood, amount: 20 }],
+		site,
+	)]);
+	assert!(shortages(&game).is_empty());
+
+	// Covered by the job already posted.
+	run(&mut game, 5);
+	assert_eq!(hauls(&game).len(), 1);
+}
+
+#[test]
+fn trips_carry_a_limited_number_of_stacks() {
+	let (mut game, _, Items { wood, stone, .. }) = game();
+	let yard = spawn_stockpile(&mut game, [0, 0], &[(wood, 200), (stone, 200)]);
+	let small = spawn_demand(&mut game, [5, 0], Demand::new(1).want(wood, 30).want(stone, 30));
+	let large = spawn_demand(&mut game, [9, 0], Demand::new(1).want(wood, 60).want(stone, 120));
+
+	run(&mut game, 1);
+	let trip = |pickups: &[(Resource, u64)], to| {
+		(pickups.iter().map(|&(resource, amount)| Pickup { from: yard, resource, amount }).collect::<Vec<_>>(), to)
+	};
+	// Wood and stones are fifty to a stack, and a hauler carries two stacks.
+	assert_eq!(hauls(&game), [
+		trip(&[(wood, 30), (stone, 30)], small),
+		trip(&[(wood, 60)], large),
+		trip(&[(stone, 100)], large),
+		trip(&[(stone, 20)], large),
+	]);
+}
+
+#[test]
+fn reservations_share_out_scarce_supplies() {
+	let (mut game, _, Items { wood, .. }) = game();
+	let yard = spawn_stockpile(&mut game, [0, 0], &[(wood, 40)]);
+	let urgent = spawn_demand(&mut game, [30, 0], Demand::new(5).want(wood, 30));
+	let later = spawn_demand(&mut game, [3, 0], Demand::new(1).want(wood, 30));
+
+	run(&mut game, 1);
+	assert_eq!(hauls(&game), [
+		(vec![Pickup { from: yard, resource: wood, amount: 30 }], urgent),
+		(vec![Pickup { from: yard, resource: wood, amount: 10 }], later),
+	]);
+	assert_eq!(shortages(&game), [Shortage { entity: later, resource: wood, missing: 20 }]);
+
+	// New stock goes to what is still missing.
+	game.world.get_mut::<Stockpile>(yard).unwrap().add(wood, 25);
+	run(&mut game, 1);
+	assert_eq!(hauls(&game)[2], (vec![Pickup { from: yard, resource: wood, amount: 20 }], later));
+	assert!(shortages(&game).is_empty());
+}
+
+#[test]
+fn unreachable_and_missing_supplies_are_reported() {
+	let (mut game, _, Items { wood, stone, .. }) = game();
+	for y in 0..10 {
+		game.set_tile(10, y, tiles::WATER);
+	}
+	for x in 0..10 {
+		game.set_tile(x, 10, tiles::WATER);
+	}
+	game.set_tile(10, 10, tiles::WATER);
+	spawn_stockpile(&mut game, [2, 2], &[(wood, 50)]);
+	let site = spawn_demand(&mut game, [20, 20], Demand::new(1).want(wood, 10).want(stone, 5));
+
+	run(&mut game, 1);
+	assert!(hauls(&game).is_empty());
+	assert_eq!(shortages(&game), [
+		Shortage { entity: site, resource: wood, missing: 10 },
+		Shortage { entity: site, resource: stone, missing: 5 },
+	]);
+}
+
+#[test]
+fn haulers_deliver_and_demands_are_met() {
+	let (mut game, _, Items { wood, .. }) = game();
+	let far = spawn_stockpile(&mut game, [2, 0], &[(wood, 40)]);
+	let near = spawn_stockpile(&mut game, [18, 0], &[(wood, 10)]);
+	let site = spawn_demand(&mut game, [20, 0], Demand::new(1).want(wood, 30));
+	let hauler = at(&mut game, [10, 0]);
+	game.world.insert(hauler, Colonist::new("Hauler").with_skill(Skill::Hauling, 3));
+
+	// Nearest to the site first: 8 tiles, then 16 back and 18 to the site, at two a second.
+	run(&mut game, 1300);
+	assert!(game.world.get::<Demand>(site).unwrap().is_met());
+	assert_eq!(game.world.get::<Stockpile>(site).unwrap().get(wood), 30);
+	assert_eq!(game.world.get::<Stockpile>(near).unwrap().get(wood), 0);
+	assert_eq!(game.world.get::<Stockpile>(far).unwrap().get(wood), 20);
+	assert!(game.world.resource::<JobBoard>().unwrap().is_empty());
+}
+
+#[test]
+fn crafters_are_fed_by_haulers() {
+	let (mut game, content, Items { ore, plate, .. }) = game();
+	let mine = spawn_stockpile(&mut game, [0, 0], &[(ore, 10)]);
+	let smelter = spawn_stockpile(&mut game, [6, 0], &[(Resource::Power, 1000)]);
+	game.world.insert(smelter, Crafter::new(&content, content.recipe_id("iron_plate").unwrap()).unwrap());
+	let hauler = at(&mut game, [0, 0]);
+	game.world.insert(hauler, Colonist::new("Hauler"));
+
+	run(&mut game, 1500);
+	let crafter = game.world.get::<Crafter>(smelter).unwrap();
+	assert_eq!(crafter.status, CraftingStatus::Blocked(plate));
+	assert_eq!((crafter.output.get(plate), crafter.input.get(ore)), (2, 2));
+	assert_eq!(game.world.get::<Stockpile>(mine).unwrap().get(ore), 5);
+	assert_eq!(game.world.resource::<Ledger>().unwrap().current().expense(ore), 3);
+}
+
+#[test]
+fn hauls_in_progress_survive_saving() {
+	let (mut game, content, Items { wood, .. }) = game();
+	spawn_stockpile(&mut game, [2, 0], &[(wood, 40)]);
+	spawn_stockpile(&mut game, [18, 0], &[(wood, 10)]);
+	let site = spawn_demand(&mut game, [20, 0], Demand::new(1).want(wood, 30));
+	let hauler = at(&mut game, [10, 0]);
+	game.world.insert(hauler, Colonist::new("Hauler"));
+	run(&mut game, 300);
+	assert!(!game.world.get::<Colonist>(hauler).unwrap().carrying.is_empty());
+
+	let thumbnail = save::thumbnail(&game.tilemap, tiles::color);
+	let mut loaded = save::decode(&save::encode(&game, &thumbnail)).unwrap();
+	loaded.set_content(content);
+	assert_eq!(hauls(&loaded), hauls(&game));
+	assert_eq!(loaded.world.get::<Demand>(site), game.world.get::<Demand>(site));
+
+	run(&mut game, 1000);
+	run(&mut loaded, 1000);
+	assert!(loaded.world.get::<Demand>(site).unwrap().is_met());
+	assert_eq!(loaded.world.get::<Stockpile>(site), game.world.get::<Stockpile>(site));
+	assert_eq!(loaded.world.get::<Colonist>(hauler), game.world.get::<Colonist>(hauler));
+}
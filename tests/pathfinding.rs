@@ -0,0 +1,275 @@
+use frontier_outpost::game::Game;
+use frontier_outpost::pathfinding::{
+	DIAGONAL_COST, HierarchicalPathfinder, NavGrid, ORTHOGONAL_COST, Path, PathResult, Pathfinder, Regions,
+};
+use frontier_outpost::rng::Rng;
+use frontier_outpost::worldgen::{WorldGenerator, tiles};
+
+/// Grid from rows of `.` for open ground, `#` for walls and digits for costlier tiles. The first
+/// row is `y = 0`.
+fn grid(rows: &[&str]) -> NavGrid {
+	let mut grid = NavGrid::new(rows[0].len() as u32, rows.len() as u32);
+	for (y, row) in rows.iter().enumerate() {
+		for (x, cell) in row.chars().enumerate() {
+			let cost = match cell {
+				'#' => None,
+				'.' => Some(1),
+				digit => Some(digit.to_digit(10).unwrap() as u8),
+			};
+			grid.set_cost([x as i32, y as i32], cost);
+		}
+	}
+	grid
+}
+
+fn found(result: PathResult) -> Vec<[i32; 2]> {
+	match result {
+		PathResult::Found(path) => path.tiles.clone(),
+		PathResult::Unreachable => panic!("no path found"),
+	}
+}
+
+/// Checks that every step of `path` is a legal move and that its cost adds up.
+fn assert_walkable(grid: &NavGrid, path: &Path) {
+	let mut cost = 0;
+	for step in path.tiles.windows(2) {
+		let [[x0, y0], [x1, y1]] = [step[0], step[1]];
+		assert!((x0 - x1).abs() <= 1 && (y0 - y1).abs() <= 1 && step[0] != step[1], "{step:?} is not a move");
+		let diagonal = x0 != x1 && y0 != y1;
+		if diagonal {
+			assert!(grid.is_passable([x1, y0]) && grid.is_passable([x0, y1]), "{step:?} cuts a corner");
+		}
+		let tile_cost = grid.cost([x1, y1]).expect("paths only enter open tiles") as u32;
+		cost += tile_cost * if diagonal { DIAGONAL_COST } else { ORTHOGONAL_COST };
+	}
+	assert_eq!(path.cost, cost);
+}
+
+#[test]
+fn paths_take_the_cheapest_route() {
+	let mut pathfinder = Pathfinder::new(grid(&[
+		"......",
+		".####.",
+		".9....",
+		"......",
+	]));
+
+	let PathResult::Found(path) = pathfinder.find_path([0, 0], [5, 0]) else {
+		panic!("no path found");
+	};
+	assert_eq!(path.tiles.len(), 6);
+	assert_eq!(path.cost, 5 * ORTHOGONAL_COST);
+
+	// Straight through the expensive tile costs more than stepping around it.
+	let tiles = found(pathfinder.find_path([0, 2], [3, 2]));
+	assert!(!tiles.contains(&[1, 2]));
+	assert_eq!(tiles.first(), Some(&[0, 2]));
+	assert_eq!(tiles.last(), Some(&[3, 2]));
+}
+
+#[test]
+fn diagonals_never_cut_corners() {
+	let mut open = Pathfinder::new(grid(&[
+		"...",
+		"...",
+	]));
+	let PathResult::Found(path) = open.find_path([0, 0], [1, 1]) else {
+		panic!("no path found");
+	};
+	assert_eq!(path.cost, DIAGONAL_COST);
+
+	// The wall's corners have to be walked around, not clipped.
+	let mut pathfinder = Pathfinder::new(grid(&[
+		"...",
+		".#.",
+		"...",
+	]));
+	let PathResult::Found(path) = pathfinder.find_path([0, 1], [1, 2]) else {
+		panic!("no path found");
+	};
+	assert_eq!(path.cost, 2 * ORTHOGONAL_COST);
+	let tiles = found(pathfinder.find_path([0, 0], [2, 2]));
+	for step in tiles.windows(2) {
+		let [[x0, y0], [x1, y1]] = [step[0], step[1]];
+		if x0 != x1 && y0 != y1 {
+			assert!(pathfinder.grid().is_passable([x1, y0]) && pathfinder.grid().is_passable([x0, y1]));
+		}
+	}
+
+	let mut squeezed = Pathfinder::new(grid(&[
+		".#",
+		"#.",
+	]));
+	assert_eq!(squeezed.find_path([0, 0], [1, 1]), PathResult::Unreachable);
+}
+
+#[test]
+fn blocked_goals_are_unreachable() {
+	let mut pathfinder = Pathfinder::new(grid(&[
+		"..#..",
+		"..#..",
+		"..#..",
+	]));
+	assert_eq!(pathfinder.find_path([0, 0], [4, 0]), PathResult::Unreachable);
+	assert_eq!(pathfinder.find_path([0, 0], [2, 0]), PathResult::Unreachable);
+	assert_eq!(pathfinder.find_path([0, 0], [9, 9]), PathResult::Unreachable);
+	assert_eq!(found(pathfinder.find_path([1, 1], [1, 1])), [[1, 1]]);
+}
+
+#[test]
+fn requests_are_answered_within_the_budget() {
+	let mut pathfinder = Pathfinder::new(NavGrid::new(64, 64));
+	let requests = (0..10).map(|row| pathfinder.request([0, row], [63, 63 - row])).collect::<Vec<_>>();
+	assert_eq!(pathfinder.pending(), 10);
+
+	let mut expanded = 0;
+	let mut runs = 0;
+	while pathfinder.pending() > 0 {
+		let used = pathfinder.run(100);
+		assert!(used <= 100);
+		expanded += used;
+		runs += 1;
+	}
+	assert_eq!(runs, expanded.div_ceil(100));
+	assert!(runs > 1);
+
+	for (row, request) in requests.into_iter().enumerate() {
+		let tiles = found(pathfinder.result(request).unwrap());
+		assert_eq!(tiles.last(), Some(&[63, 63 - row as i32]));
+		assert_eq!(pathfinder.result(request), None);
+	}
+}
+
+#[test]
+fn tile_changes_invalidate_affected_paths() {
+	let mut pathfinder = Pathfinder::new(grid(&[
+		".....",
+		".....",
+		".....",
+	]));
+	let PathResult::Found(straight) = pathfinder.find_path([0, 1], [4, 1]) else {
+		panic!("no path found");
+	};
+	pathfinder.find_path([0, 0], [1, 0]);
+	assert_eq!(pathfinder.cached(), 2);
+
+	// Blocking a tile off the short path keeps it, blocking one on the long path drops it.
+	pathfinder.set_cost([4, 2], None);
+	assert_eq!(pathfinder.cached(), 2);
+	let wall = straight.tiles[2];
+	pathfinder.set_cost(wall, None);
+	assert_eq!(pathfinder.cached(), 1);
+	let PathResult::Found(detour) = pathfinder.find_path([0, 1], [4, 1]) else {
+		panic!("no path found");
+	};
+	assert!(!detour.tiles.contains(&wall) && detour.cost > straight.cost);
+
+	// Reopening it might give a better path, so searches that looked at it are dropped too.
+	let request = pathfinder.request([0, 1], [4, 1]);
+	pathfinder.run(u32::MAX);
+	assert_eq!(pathfinder.result(request), Some(PathResult::Found(detour)));
+	pathfinder.set_cost(wall, Some(1));
+	assert_eq!(pathfinder.find_path([0, 1], [4, 1]), PathResult::Found(straight));
+}
+
+#[test]
+fn searches_restart_when_the_grid_changes_under_them() {
+	let mut pathfinder = Pathfinder::new(NavGrid::new(32, 1));
+	let request = pathfinder.request([0, 0], [31, 0]);
+	pathfinder.run(10);
+	pathfinder.set_cost([5, 0], None);
+	pathfinder.run(u32::MAX);
+	assert_eq!(pathfinder.result(request), Some(PathResult::Unreachable));
+}
+
+#[test]
+fn game_tiles_drive_the_pathfinder() {
+	let mut game = Game::new();
+	for y in 0..10 {
+		game.set_tile(3, y, tiles::WATER);
+	}
+
+	let pathfinder = game.world.resource_mut::<Pathfinder>().unwrap();
+	let tiles = found(pathfinder.find_path([0, 0], [6, 0]));
+	assert!(tiles.iter().all(|&[x, y]| x != 3 || y >= 10));
+	assert!(tiles.contains(&[3, 10]));
+}
+
+#[test]
+fn regions_follow_walls() {
+	let mut pathfinder = Pathfinder::new(grid(&[
+		"..#..",
+		"..#..",
+		"....#",
+	]));
+	assert_eq!(pathfinder.regions().count(), 1);
+	assert!(pathfinder.regions().connected([0, 0], [3, 0]));
+	assert!(!pathfinder.regions().connected([0, 0], [2, 0]));
+
+	pathfinder.set_cost([2, 2], None);
+	assert_eq!(pathfinder.regions().count(), 2);
+	assert!(!pathfinder.regions().connected([0, 0], [3, 0]));
+	assert_eq!(pathfinder.find_path([0, 0], [3, 0]), PathResult::Unreachable);
+
+	// Diagonal gaps between walls are not a way through.
+	let diagonal = Regions::new(&grid(&[
+		".#",
+		"#.",
+	]));
+	assert_eq!(diagonal.count(), 2);
+}
+
+#[test]
+fn hierarchical_paths_are_close_to_the_shortest() {
+	let map = WorldGenerator::new(21).generate(96, 96).to_tilemap();
+	let grid = NavGrid::from_tilemap(&map, tiles::movement_cost);
+	let mut flat = Pathfinder::with_cache_capacity(grid.clone(), 0);
+	let mut hierarchical = HierarchicalPathfinder::new(grid.clone());
+	assert!(hierarchical.node_count() > 0);
+
+	let mut rng = Rng::new(5);
+	let mut random_tile = || [rng.below(96) as i32, rng.below(96) as i32];
+	let mut found = 0;
+	for _ in 0..200 {
+		let (start, goal) = (random_tile(), random_tile());
+		if !grid.is_passable(start) {
+			continue;
+		}
+		match (flat.find_path(start, goal), hierarchical.find_path(start, goal)) {
+			(PathResult::Found(shortest), PathResult::Found(path)) => {
+				assert_walkable(&grid, &path);
+				assert_eq!((path.tiles[0], *path.tiles.last().unwrap()), (start, goal));
+				assert!(path.cost >= shortest.cost);
+				assert!(path.cost as f32 <= shortest.cost as f32 * 1.1, "{} against {}", path.cost, shortest.cost);
+				found += 1;
+			}
+			(PathResult::Unreachable, PathResult::Unreachable) => {}
+			(flat, hierarchical) => panic!("{start:?} to {goal:?}: {flat:?} but {hierarchical:?}"),
+		}
+	}
+	assert!(found > 100);
+}
+
+#[test]
+fn hierarchy_rebuilds_changed_clusters() {
+	let mut hierarchical = HierarchicalPathfinder::new(NavGrid::new(64, 40));
+	let PathResult::Found(open) = hierarchical.find_path([2, 20], [60, 20]) else {
+		panic!("no path found");
+	};
+	assert_eq!(open.cost, 58 * ORTHOGONAL_COST);
+
+	// A wall across the map on a cluster border, with one gap in it.
+	for y in 0..40 {
+		hierarchical.set_cost([31, y], (y == 5).then_some(1));
+	}
+	let PathResult::Found(around) = hierarchical.find_path([2, 20], [60, 20]) else {
+		panic!("no path found");
+	};
+	assert_walkable(hierarchical.grid(), &around);
+	assert!(around.tiles.contains(&[31, 5]));
+
+	hierarchical.set_cost([31, 5], None);
+	assert_eq!(hierarchical.find_path([2, 20], [60, 20]), PathResult::Unreachable);
+	hierarchical.set_cost([31, 5], Some(1));
+	assert!(matches!(hierarchical.find_path([2, 20], [60, 20]), PathResult::Found(_)));
+}
@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+use frontier_outpost::profiler::{FrameSample, Profiler};
+use frontier_outpost::renderer::PassTiming;
+
+fn frame(milliseconds: u64) -> FrameSample {
+	FrameSample {
+		frame_time: Duration::from_millis(milliseconds),
+		..FrameSample::default()
+	}
+}
+
+#[test]
+fn summary_covers_the_latest_frames() {
+	let mut profiler = Profiler::new(200);
+	profiler.record(frame(1000));
+	for index in 0..100 {
+		profiler.record(frame(if index == 50 { 50 } else { 10 }));
+	}
+
+	let summary = profiler.summary(100);
+	assert_eq!(summary.frames, 100);
+	assert_eq!(summary.min, Duration::from_millis(10));
+	assert_eq!(summary.max, Duration::from_millis(50));
+	assert_eq!(summary.avg, Duration::from_micros(10_400));
+	// One slow frame in a hundred is exactly the 99th percentile's allowance.
+	assert_eq!(summary.p99, Duration::from_millis(10));
+	assert!((summary.fps - 100.0 / 1.04).abs() < 0.01);
+
+	assert_eq!(profiler.summary(1000).max, Duration::from_millis(1000));
+}
+
+#[test]
+fn oldest_frames_are_forgotten() {
+	let mut profiler = Profiler::new(3);
+	for milliseconds in 1..=5 {
+		profiler.record(frame(milliseconds));
+	}
+
+	let times = profiler.samples().map(|sample| sample.frame_time.as_millis()).collect::<Vec<_>>();
+	assert_eq!(times, [3, 4, 5]);
+	assert_eq!(Profiler::new(3).summary(10).frames, 0);
+}
+
+#[test]
+fn csv_export() {
+	let mut profiler = Profiler::new(10);
+	profiler.record(FrameSample {
+		frame_time: Duration::from_micros(16_667),
+		cpu_time: Duration::from_micros(2_500),
+		gpu_time: None,
+		draw_calls: 3,
+		vertices: 120,
+	});
+	profiler.record(FrameSample {
+		gpu_time: Some(Duration::from_micros(1_250)),
+		..frame(17)
+	});
+
+	let mut csv = Vec::new();
+	profiler.write_csv(&mut csv).unwrap();
+	assert_eq!(
+		String::from_utf8(csv).unwrap(),
+		"frame,frame_time_ms,cpu_time_ms,gpu_time_ms,draw_calls,vertices\n\
+		0,16.667,2.500,,3,120\n\
+		1,17.000,0.000,1.250,0,0\n",
+	);
+}
+
+#[test]
+fn gpu_time_is_the_total_of_its_passes() {
+	let mut profiler = Profiler::new(10);
+	let passes = [
+		PassTiming { name: "world", duration: Duration::from_micros(700) },
+		PassTiming { name: "ui", duration: Duration::from_micros(50) },
+	];
+	profiler.record_with_passes(frame(16), &passes);
+	assert_eq!(profiler.latest().unwrap().gpu_time, Some(Duration::from_micros(750)));
+	assert_eq!(profiler.gpu_passes(), passes);
+
+	// Adapters without timestamp queries report no passes.
+	profiler.record_with_passes(frame(16), &[]);
+	assert_eq!(profiler.latest().unwrap().gpu_time, None);
+}
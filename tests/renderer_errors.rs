@@ -0,0 +1,30 @@
+use std::error::Error;
+
+use frontier_outpost::renderer::{FrameError, InitError};
+use wgpu::{RequestDeviceError, SurfaceError};
+
+#[test]
+fn surface_errors_become_frame_errors() {
+	assert!(matches!(FrameError::from(SurfaceError::Timeout), FrameError::Timeout));
+	assert!(matches!(FrameError::from(SurfaceError::Lost), FrameError::Reconfigured));
+	assert!(matches!(FrameError::from(SurfaceError::Outdated), FrameError::Reconfigured));
+	assert!(matches!(FrameError::from(SurfaceError::OutOfMemory), FrameError::OutOfMemory));
+}
+
+#[test]
+fn only_running_out_of_memory_stops_rendering() {
+	assert!(FrameError::Timeout.is_recoverable());
+	assert!(FrameError::Reconfigured.is_recoverable());
+	assert!(!FrameError::OutOfMemory.is_recoverable());
+	assert_eq!(FrameError::OutOfMemory.to_string(), "out of graphics memory");
+}
+
+#[test]
+fn init_errors_explain_what_to_try() {
+	assert!(InitError::NoAdapter.to_string().contains("--backend or WGPU_BACKEND"));
+	assert!(InitError::NoAdapter.source().is_none());
+
+	let device = InitError::from(RequestDeviceError);
+	assert_eq!(device.to_string(), "failed to open graphics device: Requesting a device failed");
+	assert!(device.source().is_some_and(|source| source.is::<RequestDeviceError>()));
+}
@@ -0,0 +1,216 @@
+use std::time::Duration;
+
+use frontier_outpost::components::{Position, Velocity};
+use frontier_outpost::config::SimulationConfig;
+use frontier_outpost::game::Game;
+use frontier_outpost::save::{
+	self, Autosave, Migration, Persist, Reader, SaveError, Sections, VERSION, Writer,
+};
+use frontier_outpost::simulation;
+use frontier_outpost::tilemap::Tile;
+use frontier_outpost::worldgen::tiles;
+
+fn sample_game() -> Game {
+	let mut game = Game::generate(3, 40);
+	game.tilemap.set(5, 6, Tile(300));
+	for index in 0..5 {
+		let entity = game.world.spawn();
+		game.world.insert(entity, Position::new([index as f32, 2.5]));
+		if index % 2 == 0 {
+			game.world.insert(entity, Velocity([0.25, -1.0 / 3.0]));
+		}
+	}
+	let despawned = game.world.entities().nth(1).unwrap();
+	game.world.despawn(despawned);
+
+	simulation::run_ticks(&mut game, &SimulationConfig::default(), 90);
+	game.rng.next_u64();
+	game
+}
+
+fn encode(game: &Game) -> Vec<u8> {
+	save::encode(game, &save::thumbnail(&game.tilemap, tiles::color))
+}
+
+#[test]
+fn loaded_games_match_and_carry_on_identically() {
+	let mut game = sample_game();
+	let mut loaded = save::decode(&encode(&game)).unwrap();
+
+	assert_eq!(loaded.seed, game.seed);
+	assert_eq!(loaded.time, game.time);
+	assert_eq!(loaded.rng, game.rng);
+	assert_eq!(loaded.world.slots(), game.world.slots());
+	for y in 0..40 {
+		for x in 0..40 {
+			assert_eq!(loaded.tilemap.get(x, y), game.tilemap.get(x, y));
+		}
+	}
+
+	let config = SimulationConfig::default();
+	simulation::run_ticks(&mut game, &config, 30);
+	simulation::run_ticks(&mut loaded, &config, 30);
+	let positions = |game: &Game| {
+		game.world.query::<Position>().map(|(entity, position)| (entity, *position)).collect::<Vec<_>>()
+	};
+	assert_eq!(positions(&loaded), positions(&game));
+	assert_eq!(loaded.world.query::<Velocity>().count(), 3);
+	// Reused indices come out in the same order.
+	assert_eq!(loaded.world.spawn(), game.world.spawn());
+	assert_eq!(loaded.time_of_day(), game.time_of_day());
+}
+
+#[test]
+fn header_holds_version_seed_and_thumbnail() {
+	let game = Game::generate(11, 128);
+	let bytes = encode(&game);
+	// Thumbnail included, smaller than the tiles alone stored as plain 16 bit indices.
+	assert!(bytes.len() < 128 * 128 * 2, "{} bytes is not compact", bytes.len());
+
+	let header = save::decode_header(&bytes).unwrap();
+	assert_eq!((header.version, header.seed), (VERSION, 11));
+	assert_eq!((header.thumbnail.width, header.thumbnail.height), (64, 64));
+
+	// The top left pixel shows the top left of the map, which is the highest row.
+	let corner = tiles::color(game.tilemap.get(1, 126).unwrap());
+	assert_eq!(header.thumbnail.pixels[..4], corner);
+}
+
+#[test]
+fn damaged_saves_are_rejected() {
+	let bytes = encode(&sample_game());
+	assert!(matches!(save::decode(b"PNG image"), Err(SaveError::NotASave)));
+
+	let mut newer = bytes.clone();
+	newer[4..8].copy_from_slice(&(VERSION + 1).to_le_bytes());
+	assert!(matches!(save::decode(&newer), Err(SaveError::UnsupportedVersion(version)) if version == VERSION + 1));
+
+	// Every truncation fails cleanly rather than panicking or loading part of a game.
+	for length in 0..bytes.len() {
+		assert!(save::decode(&bytes[..length]).is_err(), "loaded a save cut at {length} bytes");
+	}
+
+	// Sizes that would take far more memory than the file backs are refused before allocating.
+	let huge_thumbnail = forged_save([u32::MAX, u32::MAX], None);
+	let error = save::decode_header(&huge_thumbnail).unwrap_err();
+	assert_eq!(error.to_string(), "save is corrupt: header section: 4294967295x4294967295 thumbnail is too large");
+	assert!(matches!(save::decode(&huge_thumbnail), Err(SaveError::Corrupt(_))));
+
+	// A single row is only a few tiles, but a whole row of chunks.
+	let long_map = forged_save([1, 1], Some([1 << 28, 1]));
+	let error = save::decode(&long_map).err().unwrap();
+	assert_eq!(error.to_string(), "save is corrupt: tiles section: 268435456x1 map is too large");
+}
+
+/// A save with a `thumbnail` sized header of one black run and, given a map size, a tiles
+/// section of one run of grass.
+fn forged_save(thumbnail: [u32; 2], map: Option<[u32; 2]>) -> Vec<u8> {
+	let mut header = Writer::new();
+	header.u64(1);
+	header.varint(thumbnail[0] as u64);
+	header.varint(thumbnail[1] as u64);
+	header.varint(thumbnail[0] as u64 * thumbnail[1] as u64);
+	header.raw(&[0, 0, 0, 255]);
+
+	let mut writer = Writer::new();
+	writer.raw(b"FOSV");
+	writer.u32(VERSION);
+	writer.bytes(&header.into_bytes());
+	match map {
+		Some([width, height]) => {
+			let mut tiles = Writer::new();
+			tiles.varint(width as u64);
+			tiles.varint(height as u64);
+			tiles.varint(width as u64 * height as u64);
+			tiles.varint(tiles::GRASS.0 as u64);
+			writer.varint(1);
+			writer.str("tiles");
+			writer.bytes(&tiles.into_bytes());
+		}
+		None => writer.varint(0),
+	}
+	writer.into_bytes()
+}
+
+struct Research(u32);
+
+impl Persist for Research {
+	fn save(&self, writer: &mut Writer) {
+		writer.varint(self.0 as u64);
+	}
+
+	fn load(reader: &mut Reader) -> Result<Self, SaveError> {
+		Ok(Self(reader.varint_u32()?))
+	}
+}
+
+#[test]
+fn unknown_sections_are_skipped() {
+	let mut game = sample_game();
+	game.persistence.register_resource::<Research>("research");
+	game.world.insert_resource(Research(7));
+
+	// A build without the resource still loads everything else.
+	let loaded = save::decode(&encode(&game)).unwrap();
+	assert!(loaded.world.resource::<Research>().is_none());
+	assert_eq!(loaded.world.len(), game.world.len());
+}
+
+#[test]
+fn migrations_upgrade_old_sections() {
+	fn rename_clock(sections: &mut Sections) -> Result<(), SaveError> {
+		let clock = sections.remove("clock").ok_or_else(|| SaveError::MissingSection("clock".into()))?;
+		sections.insert("time", clock);
+		Ok(())
+	}
+	fn double_time(sections: &mut Sections) -> Result<(), SaveError> {
+		let mut reader = Reader::new(sections.get("time").unwrap());
+		let nanos = reader.u64()?;
+		let mut writer = Writer::new();
+		writer.u64(nanos * 2);
+		sections.insert("time", writer.into_bytes());
+		Ok(())
+	}
+	let migrations: [Migration; 2] = [rename_clock, double_time];
+
+	let mut writer = Writer::new();
+	writer.u64(5);
+	let mut sections = Sections::new();
+	sections.insert("clock", writer.into_bytes());
+
+	// A version 1 save goes through both, a version 2 save only through the second.
+	let mut version_1 = sections.clone();
+	save::migrate(&mut version_1, 1, &migrations).unwrap();
+	assert_eq!(version_1.names().collect::<Vec<_>>(), ["time"]);
+	assert_eq!(Reader::new(version_1.get("time").unwrap()).u64().unwrap(), 10);
+
+	let mut version_2 = Sections::new();
+	version_2.insert("time", 5u64.to_le_bytes().to_vec());
+	save::migrate(&mut version_2, 2, &migrations).unwrap();
+	assert_eq!(Reader::new(version_2.get("time").unwrap()).u64().unwrap(), 10);
+
+	assert!(matches!(save::migrate(&mut sections, 4, &migrations), Err(SaveError::UnsupportedVersion(4))));
+}
+
+#[test]
+fn autosave_follows_simulated_time() {
+	let mut autosave = Autosave::new(Duration::from_secs(60), Duration::from_secs(30));
+	assert!(!autosave.due(Duration::from_secs(89)));
+	assert!(autosave.due(Duration::from_secs(90)));
+	assert!(!autosave.due(Duration::from_secs(91)));
+	// A long stall saves once, not once for every interval missed.
+	assert!(autosave.due(Duration::from_secs(1000)));
+	assert!(!autosave.due(Duration::from_secs(1059)));
+}
+
+#[test]
+fn saves_are_written_to_disk() {
+	let path = std::env::temp_dir().join(format!("frontier-outpost-save-{}.sav", std::process::id()));
+	let game = sample_game();
+	save::save(&path, &game, &save::thumbnail(&game.tilemap, tiles::color)).unwrap();
+
+	assert_eq!(save::load_header(&path).unwrap().seed, 3);
+	assert_eq!(save::load(&path).unwrap().time, game.time);
+	std::fs::remove_file(&path).unwrap();
+	assert!(matches!(save::load(&path), Err(SaveError::Io(..))));
+}
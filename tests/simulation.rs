@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+use frontier_outpost::config::SimulationConfig;
+use frontier_outpost::simulation::{self, FixedTimestep, Simulation};
+
+/// Counts its ticks and adds up the time they covered.
+#[derive(Default)]
+struct Counter {
+	ticks: u64,
+	elapsed: Duration,
+}
+
+impl Simulation for Counter {
+	fn tick(&mut self, dt: Duration) {
+		self.ticks += 1;
+		self.elapsed += dt;
+	}
+}
+
+/// A hundred ticks a second, so a tick is exactly ten milliseconds.
+fn config() -> SimulationConfig {
+	SimulationConfig {
+		ticks_per_second: 100,
+		max_ticks_per_frame: 5,
+	}
+}
+
+fn millis(ms: u64) -> Duration {
+	Duration::from_millis(ms)
+}
+
+#[test]
+fn frames_run_every_tick_that_is_due() {
+	let mut timestep = FixedTimestep::new(&config());
+	let mut counter = Counter::default();
+	assert_eq!(timestep.tick_length(), millis(10));
+
+	let alpha = timestep.update(millis(25), &mut counter);
+	assert_eq!((counter.ticks, timestep.ticks()), (2, 2));
+	assert!((alpha - 0.5).abs() < 1e-6, "alpha {alpha}");
+
+	// The leftover half tick is carried into the next frame.
+	let alpha = timestep.update(millis(5), &mut counter);
+	assert_eq!(counter.ticks, 3);
+	assert!(alpha.abs() < 1e-6, "alpha {alpha}");
+	timestep.update(millis(4), &mut counter);
+	assert_eq!(counter.ticks, 3);
+	assert_eq!(counter.elapsed, millis(30));
+}
+
+#[test]
+fn alpha_stays_within_a_tick() {
+	let mut timestep = FixedTimestep::new(&config());
+	let mut counter = Counter::default();
+	for frame in [1, 7, 16, 3, 9, 33, 12, 10, 49, 2, 17] {
+		let alpha = timestep.update(millis(frame), &mut counter);
+		assert!((0.0..=1.0).contains(&alpha), "alpha {alpha} after a {frame} ms frame");
+		assert_eq!(alpha, timestep.alpha());
+	}
+	// No frame was long enough to hit the cap, so all 159 ms were simulated but the last 9.
+	assert_eq!(counter.ticks, 15);
+	assert!((timestep.alpha() - 0.9).abs() < 1e-6);
+}
+
+#[test]
+fn stalls_drop_the_backlog_past_the_cap() {
+	let mut timestep = FixedTimestep::new(&config());
+	let mut counter = Counter::default();
+
+	// A second long hitch runs five ticks, not a hundred.
+	let alpha = timestep.update(Duration::from_secs(1), &mut counter);
+	assert_eq!(counter.ticks, 5);
+	assert_eq!(alpha, 0.0);
+
+	// Afterwards the simulation carries on at its normal pace.
+	timestep.update(millis(10), &mut counter);
+	assert_eq!(counter.ticks, 6);
+
+	// A cap of zero still runs a tick.
+	let mut stingy = FixedTimestep::new(&SimulationConfig { max_ticks_per_frame: 0, ..config() });
+	stingy.update(millis(30), &mut counter);
+	assert_eq!(stingy.ticks(), 1);
+}
+
+#[test]
+fn headless_runs_advance_by_whole_ticks() {
+	let mut first = Counter::default();
+	let mut second = Counter::default();
+	simulation::run_ticks(&mut first, &config(), 250);
+	simulation::run_ticks(&mut second, &config(), 250);
+	assert_eq!((first.ticks, first.elapsed), (250, Duration::from_millis(2500)));
+	assert_eq!((second.ticks, second.elapsed), (first.ticks, first.elapsed));
+
+	let mut realtime = Counter::default();
+	let mut seen = 0;
+	simulation::run_headless(&mut realtime, &config(), |counter, timestep| {
+		assert_eq!(counter.ticks, timestep.ticks());
+		seen = timestep.ticks();
+		seen < 3
+	});
+	assert!(seen >= 3);
+	assert_eq!(realtime.ticks, seen);
+	assert_eq!(realtime.elapsed, millis(10) * seen as u32);
+}
@@ -0,0 +1,58 @@
+use frontier_outpost::game::Game;
+use frontier_outpost::rng::Rng;
+use frontier_outpost::worldgen::{Biome, WorldGenerator};
+
+/// 64-bit FNV-1a, to pin generated maps without storing them.
+fn checksum(bytes: &[u8]) -> u64 {
+	bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x100_0000_01b3))
+}
+
+#[test]
+fn same_seed_gives_identical_maps() {
+	let first = WorldGenerator::new(42).generate(128, 96).to_bytes();
+	let second = WorldGenerator::new(42).generate(128, 96).to_bytes();
+	assert_eq!(first, second);
+
+	let other = WorldGenerator::new(43).generate(128, 96).to_bytes();
+	assert_ne!(first, other);
+}
+
+#[test]
+fn maps_match_on_every_platform() {
+	// A change here means existing seeds now give different maps, which breaks shared seeds.
+	let map = WorldGenerator::new(0x5eed).generate(64, 64);
+	assert_eq!(checksum(&map.to_bytes()), 0xa4d9_f905_b7a4_5044);
+}
+
+#[test]
+fn maps_have_land_water_and_ore() {
+	let map = WorldGenerator::new(7).generate(256, 256);
+	let count = |biome| map.biomes.iter().filter(|&&existing| existing == biome).count();
+	let tiles = map.biomes.len();
+
+	assert!(count(Biome::Water) > tiles / 20 && count(Biome::Water) < tiles / 2);
+	assert!(count(Biome::Grassland) + count(Biome::Forest) > tiles / 4);
+	assert!(map.ores.iter().any(Option::is_some));
+	assert!(map.ores.iter().zip(&map.biomes).all(|(ore, biome)| ore.is_none() || *biome != Biome::Water));
+}
+
+#[test]
+fn generated_game_uses_the_map() {
+	let map = WorldGenerator::new(9).generate(80, 80);
+	let game = Game::generate(9, 80);
+	assert_eq!(game.seed, 9);
+	assert_eq!((game.tilemap.width(), game.tilemap.height()), (80, 80));
+	for (x, y) in [(0, 0), (79, 0), (40, 40), (3, 77)] {
+		assert_eq!(game.tilemap.get(x, y), Some(map.tile(x as u32, y as u32)));
+	}
+}
+
+#[test]
+fn rng_sequences_are_fixed() {
+	let mut rng = Rng::new(1);
+	let first = [rng.next_u64(), rng.next_u64()];
+
+	let mut restored = Rng::from_state(Rng::new(1).state());
+	assert_eq!([restored.next_u64(), restored.next_u64()], first);
+	assert!((0..1000).all(|_| rng.below(10) < 10));
+}